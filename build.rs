@@ -0,0 +1,126 @@
+//! Generates the error code table shown in the diagnostics window.
+//!
+//! Every error enum tags its variants as `#[error("[XXX-NNN] message")]`. The
+//! table is scraped from those attributes so that it can never drift from the
+//! enums themselves.
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// The files holding the thiserror enums
+const ERROR_SOURCES: [&str; 3] = [
+    "src/command/error.rs",
+    "src/envoy/error.rs",
+    "src/ui/error.rs",
+];
+
+fn main() {
+    let mut table = String::from("&[\n");
+    for source in ERROR_SOURCES {
+        println!("cargo:rerun-if-changed={source}");
+        let text = std::fs::read_to_string(source)
+            .unwrap_or_else(|e| panic!("Could not read {source}: {e}"));
+        for (kind, variant, code, message) in scrape_error_codes(source, &text) {
+            writeln!(
+                table,
+                "    ErrorCode {{ code: {code:?}, kind: {kind:?}, variant: {variant:?}, message: {message:?} }},"
+            )
+            .unwrap();
+        }
+    }
+    table.push(']');
+    let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR is not set");
+    std::fs::write(Path::new(&out_dir).join("error_codes.rs"), table)
+        .expect("Could not write the error code table");
+}
+
+/// Collect (enum, variant, code, message) for every tagged variant in a source file. Fails the
+/// build on an error attribute which is not a string literal starting with a [XXX-NNN] code.
+fn scrape_error_codes(source: &str, text: &str) -> Vec<(String, String, String, String)> {
+    let mut codes = vec![];
+    let mut kind = String::new();
+    let mut pending: Option<(String, String)> = None;
+    let mut offset = 0;
+    for (index, raw_line) in text.split_inclusive('\n').enumerate() {
+        let line_start = offset;
+        offset += raw_line.len();
+        let line = raw_line.trim();
+        if let Some(rest) = line.strip_prefix("pub enum ") {
+            kind = rest.trim_end_matches('{').trim().to_string();
+        } else if line.starts_with("#[error(") {
+            // The literal may continue past this line, so parse it from the rest of the file
+            let literal = line_start + raw_line.find("#[error(").unwrap_or(0) + "#[error(".len();
+            let message = parse_string_literal(&text[literal..])
+                .unwrap_or_else(|reason| fail(source, index, line, reason));
+            let (code, message) = split_error_code(&message)
+                .unwrap_or_else(|| fail(source, index, line, "no [XXX-NNN] error code"));
+            pending = Some((code, message));
+        } else if line.starts_with("#[") || line.starts_with("//") || line.is_empty() {
+            continue;
+        } else if let Some((code, message)) = pending.take() {
+            let variant: String = line
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_')
+                .collect();
+            codes.push((kind.clone(), variant, code, message));
+        }
+    }
+    codes
+}
+
+/// Stop the build at a bad error attribute
+fn fail(source: &str, index: usize, line: &str, reason: &str) -> ! {
+    panic!(
+        "{source}:{}: {reason} in the error attribute `{line}`",
+        index + 1
+    )
+}
+
+/// Parse the string literal at the start of the text, which may be preceded by whitespace,
+/// returning its value
+fn parse_string_literal(text: &str) -> Result<String, &'static str> {
+    let text = text.trim_start();
+    if let Some(raw) = text.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let body = raw[hashes..]
+            .strip_prefix('"')
+            .ok_or("the message is not a string literal")?;
+        let end = body
+            .find(&format!("\"{}", "#".repeat(hashes)))
+            .ok_or("unterminated string literal")?;
+        return Ok(body[..end].to_string());
+    }
+    let mut chars = text
+        .strip_prefix('"')
+        .ok_or("the message is not a string literal")?
+        .chars()
+        .peekable();
+    let mut value = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Ok(value),
+            '\\' => match chars.next().ok_or("unterminated string literal")? {
+                'n' => value.push('\n'),
+                'r' => value.push('\r'),
+                't' => value.push('\t'),
+                '0' => value.push('\0'),
+                escaped @ ('\\' | '"' | '\'') => value.push(escaped),
+                // A line continuation skips the line break and the indentation after it
+                '\n' => while chars.next_if(|c| c.is_whitespace()).is_some() {},
+                _ => return Err("unsupported escape in string literal"),
+            },
+            c => value.push(c),
+        }
+    }
+    Err("unterminated string literal")
+}
+
+/// Split a message into its [XXX-NNN] code and the rest of the message
+fn split_error_code(message: &str) -> Option<(String, String)> {
+    let (code, rest) = message.strip_prefix('[')?.split_once(']')?;
+    let (prefix, number) = code.split_once('-')?;
+    let is_code = prefix.len() == 3
+        && prefix.chars().all(|c| c.is_ascii_uppercase())
+        && number.len() == 3
+        && number.chars().all(|c| c.is_ascii_digit());
+    is_code.then(|| (code.to_string(), rest.trim_start().to_string()))
+}
@@ -2,143 +2,807 @@
 //! There are some aspects of the GET DAQ system that result in hard edges when running
 //! an experiment. This scripting command system aims to eliminate this flaws by allowing
 //! users to extend the functionality of the DAQ.This is done by writting a shell script in the
-//! `scripts` directory and then tying the scrip to to a function here, which then can be called using
-//! the `execute` function with the appropriate CommandName.
+//! `scripts` directory and then tying the scrip to to a function here, which is then called by the UI.
+//! Where it is simple enough the work is done natively instead, with the script kept as a fallback.
 //!
 //! ## Examples
 //! By default, there are extensions for backing up the ECC configuration files, moving the .graw
 //! files to an experiment specific directory with run subdirectories, and checking if directories exist on the ECC machines.
+use super::config_archive::{archive_path, verify_archive, write_archive, BackupFormat};
 use super::constants::{BACKUP_CONFIG_DIR, CONFIG_DIR, SCRIPT_DIR};
+use super::error::CommandError;
 use crate::envoy::surveyor_envoy::SurveyorResponse;
-use std::process::Command;
+use crate::envoy::surveyor_status::SurveyorStatus;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-/// The status of a command which was executed
-#[derive(Debug, Clone)]
-pub enum CommandStatus {
-    Success,
-    Failure,
-    CouldNotExecute,
-}
+/// The exit code of ssh when the remote machine could not be reached
+const SSH_CONNECTION_FAILED: i32 = 255;
 
-/// CommandNames are tied to one of the functions which is callable by the execute function in
-/// this module. All commands must have the same function signature. This allows for relatively straightforward
-/// command sending from the UI. Typically these commands wrap the std::process::Command object which is used to
-/// run a shell script on a remote machine. Think of this like a *really* primitive scripting engine.
-#[derive(Debug, Clone)]
+/// How often a running command is checked for exit
+const COMMAND_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The external commands, each with its own timeout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum CommandName {
     MoveGrawFiles,
-    BackupConfig,
     CheckRunExists,
+    ListRuns,
+    Shell,
+}
+
+impl CommandName {
+    /// How long the command may run before it is killed, unless the config overrides it
+    pub fn default_timeout(&self) -> Duration {
+        match self {
+            Self::MoveGrawFiles => Duration::from_secs(300),
+            Self::CheckRunExists => Duration::from_secs(30),
+            Self::ListRuns => Duration::from_secs(30),
+            Self::Shell => Duration::from_secs(300),
+        }
+    }
 }
 
 impl std::fmt::Display for CommandName {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::MoveGrawFiles => write!(f, "MoveGrawFiles"),
-            Self::BackupConfig => write!(f, "BackupConfig"),
-            Self::CheckRunExists => write!(f, "CheckRunExists"),
+            Self::MoveGrawFiles => write!(f, "Move .graw files"),
+            Self::CheckRunExists => write!(f, "Check run exists"),
+            Self::ListRuns => write!(f, "List runs"),
+            Self::Shell => write!(f, "Shell"),
         }
     }
 }
 
-impl CommandName {
-    /// Retrieve the function associated with this CommandName
-    pub fn get_function(
-        &self,
-    ) -> impl Fn(&[SurveyorResponse], &str, &i32) -> Result<CommandStatus, std::io::Error> {
-        match self {
-            Self::MoveGrawFiles => move_graw_files,
-            Self::BackupConfig => backup_config,
-            Self::CheckRunExists => check_run_exists,
+/// The shell the scripts are run with, and the directory they are in
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShellConfig {
+    /// The shell, either a path or a name found on the PATH
+    pub shell: String,
+    pub script_dir: PathBuf,
+}
+
+impl Default for ShellConfig {
+    fn default() -> Self {
+        Self {
+            shell: String::from("zsh"),
+            script_dir: PathBuf::from(SCRIPT_DIR),
         }
     }
 }
 
-/// This is the function used by the rest of the crate. Pass in a CommandName with the required data and recieve a command status
-/// based on the behavior of the command.
-pub fn execute(
-    command: CommandName,
+impl ShellConfig {
+    /// The path of the shell, searching the PATH if it is only a name
+    pub fn shell_path(&self) -> Option<PathBuf> {
+        let shell = Path::new(&self.shell);
+        if shell.components().count() > 1 {
+            return shell.is_file().then(|| shell.to_path_buf());
+        }
+        std::env::var_os("PATH").and_then(|paths| {
+            std::env::split_paths(&paths)
+                .map(|dir| dir.join(shell))
+                .find(|path| path.is_file())
+        })
+    }
+
+    /// The path of a script
+    pub fn script_path(&self, script: &str) -> PathBuf {
+        self.script_dir.join(script)
+    }
+
+    /// A command running the shell. Fails naming the shell if it can't be found, rather than
+    /// failing opaquely when the command is spawned.
+    fn command(&self) -> Result<Command, std::io::Error> {
+        match self.shell_path() {
+            Some(path) => Ok(Command::new(path)),
+            None => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("could not execute, the shell {} was not found", self.shell),
+            )),
+        }
+    }
+
+    /// A command running a script with the shell. Fails naming the shell or the script if
+    /// either can't be found.
+    fn script(&self, script: &str) -> Result<Command, std::io::Error> {
+        let path = self.script_path(script);
+        if !path.is_file() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!(
+                    "could not execute, the script {} was not found",
+                    path.display()
+                ),
+            ));
+        }
+        let mut command = self.command()?;
+        command.arg(path);
+        Ok(command)
+    }
+}
+
+/// The captured result of an executed command
+#[derive(Debug, Clone)]
+pub struct CommandOutput {
+    /// The exit code, None if the command was killed by a signal
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// Set if the command was killed for running longer than this. The output is what it
+    /// printed until then.
+    pub timed_out: Option<Duration>,
+}
+
+impl CommandOutput {
+    /// Did the command exit successfully
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+
+    /// Why the command failed, for the operator: its stderr, or its exit code if it printed nothing
+    pub fn failure_detail(&self) -> String {
+        let stderr = self.stderr.trim();
+        match self.code {
+            _ if self.timed_out.is_some() => format!(
+                "timed out after {} s{}",
+                self.timed_out.unwrap_or_default().as_secs(),
+                if stderr.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ({stderr})")
+                }
+            ),
+            _ if !stderr.is_empty() => stderr.to_string(),
+            Some(code) => format!("exited with code {code}"),
+            None => String::from("killed by a signal"),
+        }
+    }
+
+    /// Log the failure of the command with everything it printed
+    fn log_failure(&self, description: &str) {
+        tracing::error!(
+            "{description} failed (exit code {:?}): stderr: {} stdout: {}",
+            self.code,
+            self.stderr.trim(),
+            self.stdout.trim()
+        );
+    }
+}
+
+/// Read a pipe of a child on a thread, into a buffer which can be taken at any time
+fn capture<R: Read + Send + 'static>(
+    pipe: Option<R>,
+) -> (Arc<Mutex<Vec<u8>>>, Option<std::thread::JoinHandle<()>>) {
+    let buffer = Arc::new(Mutex::new(vec![]));
+    let handle = pipe.map(|mut pipe| {
+        let buffer = buffer.clone();
+        std::thread::spawn(move || {
+            let mut chunk = [0u8; 4096];
+            while let Ok(count) = pipe.read(&mut chunk) {
+                if count == 0 {
+                    break;
+                }
+                if let Ok(mut buffer) = buffer.lock() {
+                    buffer.extend_from_slice(&chunk[..count]);
+                }
+            }
+        })
+    });
+    (buffer, handle)
+}
+
+/// Kill a child which ran out of time, along with anything it started (i.e. the ssh of a
+/// script), which shares its process group
+fn kill_child(child: &mut std::process::Child) {
+    #[cfg(unix)]
+    {
+        let _ = Command::new("kill")
+            .args(["-KILL", "--", &format!("-{}", child.id())])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Run a command, capturing its output. If it has not exited within the timeout it is killed,
+/// and whatever it printed until then is returned.
+fn run(command: &mut Command, timeout: Duration) -> Result<CommandOutput, std::io::Error> {
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(command, 0);
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let (stdout, stdout_reader) = capture(child.stdout.take());
+    let (stderr, stderr_reader) = capture(child.stderr.take());
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if Instant::now() >= deadline {
+            kill_child(&mut child);
+            break None;
+        }
+        std::thread::sleep(COMMAND_POLL_INTERVAL);
+    };
+    // After a timeout the readers are not waited for, as something may still hold the pipes
+    if status.is_some() {
+        for reader in [stdout_reader, stderr_reader].into_iter().flatten() {
+            let _ = reader.join();
+        }
+    }
+    let text = |buffer: &Arc<Mutex<Vec<u8>>>| {
+        buffer
+            .lock()
+            .map(|buffer| String::from_utf8_lossy(&buffer).into_owned())
+            .unwrap_or_default()
+    };
+    Ok(CommandOutput {
+        code: status.and_then(|status| status.code()),
+        stdout: text(&stdout),
+        stderr: text(&stderr),
+        timed_out: status.is_none().then_some(timeout),
+    })
+}
+
+/// The data routers which are online, with their IDs. Offline data routers report N/A for their
+/// address and location, which must never be passed to the scripts. Fails if no data router is online.
+fn online_routers(
     surveyor_data: &[SurveyorResponse],
-    experiment: &str,
-    run_number: &i32,
-) -> CommandStatus {
-    match command.get_function()(surveyor_data, experiment, run_number) {
-        Ok(stat) => stat,
+) -> Result<Vec<(usize, &SurveyorResponse)>, std::io::Error> {
+    let online: Vec<(usize, &SurveyorResponse)> = surveyor_data
+        .iter()
+        .enumerate()
+        .filter(|(id, data)| {
+            let is_online = matches!(SurveyorStatus::from(data.state), SurveyorStatus::Online);
+            if !is_online {
+                tracing::warn!("Data Router {id} is not online, skipping it");
+            }
+            is_online
+        })
+        .collect();
+    if online.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no data routers are online",
+        ));
+    }
+    Ok(online)
+}
+
+/// Copy a file or directory into a directory. Every file which can't be copied is added to the
+/// failures, and the rest are still copied.
+fn copy_into(source: &Path, destination: &Path, failures: &mut Vec<(PathBuf, std::io::Error)>) {
+    let target = match source.file_name() {
+        Some(name) => destination.join(name),
+        None => {
+            failures.push((
+                source.to_path_buf(),
+                std::io::ErrorKind::InvalidInput.into(),
+            ));
+            return;
+        }
+    };
+    if !source.is_dir() {
+        if let Err(e) = std::fs::copy(source, &target) {
+            failures.push((source.to_path_buf(), e));
+        }
+        return;
+    }
+    let entries = match std::fs::create_dir_all(&target).and_then(|_| std::fs::read_dir(source)) {
+        Ok(entries) => entries,
         Err(e) => {
-            tracing::error!("Could not execute command {}: {}", command, e);
-            CommandStatus::CouldNotExecute
+            failures.push((source.to_path_buf(), e));
+            return;
+        }
+    };
+    for entry in entries {
+        match entry {
+            Ok(entry) => copy_into(&entry.path(), &target, failures),
+            Err(e) => failures.push((source.to_path_buf(), e)),
         }
     }
 }
 
-/// Move the graw data files after a run is stopped
-pub fn move_graw_files(
+/// Back up the ECC configuration files of a run to configs_backup/<experiment>/run_<NNNN>,
+/// returning the backup directory. The prepare, describe and configure files of the experiment
+/// must exist. The per-CoBo describe files (describe-cobo*), or directories, are copied as well.
+/// A file which can't be copied does not stop the backup; all of the files which were not
+/// backed up are returned in the error.
+///
+/// If the format archives, the copy is then written to run_<NNNN>_configs.tar.gz and verified.
+/// For the Archive format the directory is removed once the archive is verified, and the archive
+/// is returned instead.
+pub fn backup_configs(
+    experiment: &str,
+    run_number: i32,
+    format: BackupFormat,
+) -> Result<PathBuf, CommandError> {
+    let config_dir = Path::new(CONFIG_DIR);
+    let run_path = Path::new(BACKUP_CONFIG_DIR)
+        .join(experiment)
+        .join(format!("run_{run_number:04}"));
+    if let Err(e) = std::fs::create_dir_all(&run_path) {
+        return Err(CommandError::BackupFailed(
+            run_path.clone(),
+            vec![(run_path, e)],
+        ));
+    }
+    let mut failures = vec![];
+    for stage in ["describe", "prepare", "configure"] {
+        copy_into(
+            &config_dir.join(format!("{stage}-{experiment}.xcfg")),
+            &run_path,
+            &mut failures,
+        );
+    }
+    match std::fs::read_dir(config_dir) {
+        Ok(entries) => {
+            for entry in entries {
+                let path = match entry {
+                    Ok(entry) => entry.path(),
+                    Err(e) => {
+                        failures.push((config_dir.to_path_buf(), e));
+                        continue;
+                    }
+                };
+                let is_cobo_describe = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("describe-cobo"));
+                if is_cobo_describe {
+                    copy_into(&path, &run_path, &mut failures);
+                }
+            }
+        }
+        Err(e) => failures.push((config_dir.to_path_buf(), e)),
+    }
+    let mut backup_path = run_path.clone();
+    if format.archives() {
+        let archive = archive_path(experiment, run_number);
+        let archived = write_archive(&run_path, &archive).and_then(|_| {
+            verify_archive(&archive)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+        });
+        match archived {
+            Ok(()) if format == BackupFormat::Archive && failures.is_empty() => {
+                // The directory is kept if it can't be removed, the archive is still good
+                if let Err(e) = std::fs::remove_dir_all(&run_path) {
+                    tracing::warn!("Could not remove {}: {e}", run_path.display());
+                }
+                backup_path = archive;
+            }
+            Ok(()) => (),
+            Err(e) => failures.push((archive, e)),
+        }
+    }
+    if failures.is_empty() {
+        Ok(backup_path)
+    } else {
+        Err(CommandError::BackupFailed(run_path, failures))
+    }
+}
+
+/// Check to see if a run number was already used before starting a run. Returns the run
+/// directories for this run number which already exist on the data routers, naming the data
+/// router each was found on. Every online data router is checked, not only the first.
+///
+/// The directories are tested over ssh directly, unless use_script is set, in which case the
+/// test_graw.sh script is used as before.
+pub fn find_existing_run_dirs(
     surveyor_data: &[SurveyorResponse],
     experiment: &str,
     run_number: &i32,
-) -> Result<CommandStatus, std::io::Error> {
-    let sub_command = format!("{SCRIPT_DIR}move_graw.sh");
-    let mut ret_stat = CommandStatus::Success;
-    for data in surveyor_data {
-        let output = Command::new("zsh")
-            .args([
-                &sub_command,
-                &data.address,
-                &data.location,
-                experiment,
-                &(run_number.to_string()),
-            ])
-            .output()?;
-        if !output.status.success() {
-            ret_stat = CommandStatus::Failure;
+    use_script: bool,
+    shell: &ShellConfig,
+    timeout: Duration,
+) -> Result<Vec<String>, std::io::Error> {
+    let mut existing = vec![];
+    for (id, data) in online_routers(surveyor_data)? {
+        let run_dir = format!("{}/{experiment}/run_{run_number:04}", data.location);
+        let output = if use_script {
+            run(
+                shell.script("test_graw.sh")?.args([
+                    &data.address,
+                    &data.location,
+                    experiment,
+                    &(run_number.to_string()),
+                ]),
+                timeout,
+            )?
+        } else {
+            run(
+                Command::new("ssh").args([&data.address, "test", "-d", &shell_quote(&run_dir)]),
+                timeout,
+            )?
+        };
+        if output.timed_out.is_some() {
+            output.log_failure(&format!("Checking {run_dir} on {}", data.address));
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("checking {} {}", data.address, output.failure_detail()),
+            ));
+        }
+        if !use_script && output.code == Some(SSH_CONNECTION_FAILED) {
+            output.log_failure(&format!("Checking {run_dir} on {}", data.address));
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotConnected,
+                format!(
+                    "could not reach {}: {}",
+                    data.address,
+                    output.failure_detail()
+                ),
+            ));
+        }
+        if output.success() {
+            existing.push(format!("Data Router {id} ({}): {run_dir}", data.address));
         }
     }
-    Ok(ret_stat)
+    Ok(existing)
 }
 
-/// Back up the ECC configuration files after a run is stopped
-pub fn backup_config(
-    _: &[SurveyorResponse],
+/// The run numbers of an experiment which already have data on the data routers
+#[derive(Debug, Clone, Default)]
+pub struct UsedRunNumbers {
+    pub numbers: BTreeSet<i32>,
+    /// The number of data routers which answered
+    pub answered: usize,
+}
+
+/// List the run directories (run_NNNN) of an experiment on the data routers. Like the run number
+/// check, this blocks for up to the timeout on each data router, so the app calls it on a
+/// background thread. Offline data routers are skipped, as are data routers which could not be
+/// reached.
+pub fn find_used_run_numbers(
+    surveyor_data: &[SurveyorResponse],
     experiment: &str,
-    run_number: &i32,
-) -> Result<CommandStatus, std::io::Error> {
-    let sub_command = format!("{SCRIPT_DIR}backup_configs.sh");
-    let output = Command::new("zsh")
-        .args([
-            &sub_command,
-            CONFIG_DIR,
-            BACKUP_CONFIG_DIR,
-            experiment,
-            &(run_number.to_string()),
-        ])
-        .output()?;
-    if output.status.success() {
-        Ok(CommandStatus::Success)
-    } else {
-        Ok(CommandStatus::Failure)
+    shell: &ShellConfig,
+    timeout: Duration,
+) -> Result<UsedRunNumbers, std::io::Error> {
+    let mut used = UsedRunNumbers::default();
+    for (_, data) in online_routers(surveyor_data)? {
+        let output = run(
+            shell
+                .script("list_runs.sh")?
+                .args([&data.address, &data.location, experiment]),
+            timeout,
+        )?;
+        if output.code == Some(SSH_CONNECTION_FAILED) || output.timed_out.is_some() {
+            tracing::warn!(
+                "Could not list the runs on {}: {}",
+                data.address,
+                output.failure_detail()
+            );
+            continue;
+        }
+        used.answered += 1;
+        // A failed listing means there is no experiment directory yet
+        if !output.success() {
+            continue;
+        }
+        used.numbers.extend(
+            output
+                .stdout
+                .lines()
+                .filter_map(|line| line.trim().strip_prefix("run_")?.parse::<i32>().ok()),
+        );
     }
+    Ok(used)
+}
+
+/// Run a one-off shell command, i.e. from the expert console, capturing its output
+pub fn run_shell(
+    command: &str,
+    shell: &ShellConfig,
+    timeout: Duration,
+) -> Result<CommandOutput, std::io::Error> {
+    run(shell.command()?.args(["-c", command]), timeout)
 }
 
-/// Check to see if a run number was already used before starting a run
-pub fn check_run_exists(
+/// A data router whose .graw files could not be moved
+#[derive(Debug, Clone)]
+pub struct MoveFailure {
+    pub id: usize,
+    pub address: String,
+    pub detail: String,
+}
+
+/// Move the .graw files of a run into the run directory on every online data router. The data
+/// routers are moved concurrently, and the ones which failed are returned. The files are moved
+/// over ssh directly, unless use_script is set, in which case the move_graw.sh script is used.
+pub fn move_run_files(
     surveyor_data: &[SurveyorResponse],
     experiment: &str,
     run_number: &i32,
-) -> Result<CommandStatus, std::io::Error> {
-    let sub_command = format!("{SCRIPT_DIR}test_graw.sh");
-    let output = Command::new("zsh")
-        .args([
-            &sub_command,
-            &surveyor_data[0].address,
-            &surveyor_data[0].location,
-            experiment,
-            &(run_number.to_string()),
-        ])
-        .output()?;
-
-    if output.status.success() {
-        Ok(CommandStatus::Success)
-    } else {
-        Ok(CommandStatus::Failure)
+    use_script: bool,
+    shell: &ShellConfig,
+    timeout: Duration,
+) -> Result<Vec<MoveFailure>, std::io::Error> {
+    let routers = online_routers(surveyor_data)?;
+    let results: Vec<(usize, &SurveyorResponse, std::io::Result<CommandOutput>)> =
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = routers
+                .into_iter()
+                .map(|(id, data)| {
+                    let handle = scope.spawn(move || {
+                        let script = use_script.then_some(shell);
+                        move_router_files(data, experiment, run_number, script, timeout)
+                    });
+                    (id, data, handle)
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|(id, data, handle)| {
+                    let result = handle
+                        .join()
+                        .unwrap_or_else(|_| Err(std::io::Error::other("the move thread panicked")));
+                    (id, data, result)
+                })
+                .collect()
+        });
+    Ok(results
+        .into_iter()
+        .filter_map(|(id, data, result)| {
+            let detail = match result {
+                Ok(output) if output.success() => return None,
+                Ok(output) => {
+                    output.log_failure(&format!("Moving the .graw files on {}", data.address));
+                    output.failure_detail()
+                }
+                Err(e) => e.to_string(),
+            };
+            Some(MoveFailure {
+                id,
+                address: data.address.clone(),
+                detail,
+            })
+        })
+        .collect())
+}
+
+/// Move the .graw files of a run on one data router, with the move_graw.sh script if a shell
+/// is given
+fn move_router_files(
+    data: &SurveyorResponse,
+    experiment: &str,
+    run_number: &i32,
+    script: Option<&ShellConfig>,
+    timeout: Duration,
+) -> std::io::Result<CommandOutput> {
+    if let Some(shell) = script {
+        return run(
+            shell.script("move_graw.sh")?.args([
+                &data.address,
+                &data.location,
+                experiment,
+                &(run_number.to_string()),
+            ]),
+            timeout,
+        );
+    }
+    let remote = move_graw_command(&data.location, experiment, run_number);
+    run(Command::new("ssh").args([&data.address, &remote]), timeout)
+}
+
+/// The shell command which moves the .graw files at a location into the run directory
+fn move_graw_command(location: &str, experiment: &str, run_number: &i32) -> String {
+    let graw = format!("{}/*.graw", shell_quote(location));
+    let run_path = shell_quote(&format!("{location}/{experiment}/run_{run_number:04}"));
+    format!("mkdir -p {run_path} && if ls {graw} >/dev/null 2>&1; then mv -f {graw} {run_path}; fi")
+}
+
+/// Quote a value for a POSIX shell, so that it is passed as a single word with no expansion.
+/// ssh joins its arguments into one remote command line, so every path interpolated into a
+/// remote command must be quoted.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A test_graw.sh which checks the run directory locally instead of over ssh
+    const TEST_GRAW: &str = "test -d \"$2/$3/run_$(printf %04d \"$4\")\"\n";
+
+    fn online_router(location: &Path) -> SurveyorResponse {
+        SurveyorResponse {
+            state: 1,
+            address: String::from("localhost"),
+            location: location.display().to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn shell_with_script(dir: &Path, script: &str) -> ShellConfig {
+        std::fs::write(dir.join("test_graw.sh"), script).unwrap();
+        ShellConfig {
+            shell: String::from("sh"),
+            script_dir: dir.to_path_buf(),
+        }
+    }
+
+    fn check(
+        routers: &[SurveyorResponse],
+        shell: &ShellConfig,
+        timeout: Duration,
+    ) -> Result<Vec<String>, std::io::Error> {
+        find_existing_run_dirs(routers, "e20009", &7, true, shell, timeout)
+    }
+
+    #[test]
+    fn run_number_is_free_without_a_run_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let shell = shell_with_script(dir.path(), TEST_GRAW);
+        let routers = [online_router(dir.path())];
+        let existing = check(&routers, &shell, Duration::from_secs(5)).unwrap();
+        assert!(existing.is_empty());
+    }
+
+    #[test]
+    fn run_number_is_in_use_with_a_run_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("e20009/run_0007")).unwrap();
+        let shell = shell_with_script(dir.path(), TEST_GRAW);
+        let routers = [online_router(dir.path()), online_router(dir.path())];
+        let existing = check(&routers, &shell, Duration::from_secs(5)).unwrap();
+        assert_eq!(existing.len(), 2);
+        assert!(existing[0].starts_with("Data Router 0 (localhost)"));
+        assert!(existing[1].ends_with("e20009/run_0007"));
+    }
+
+    #[test]
+    fn run_number_check_fails_on_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+        let shell = shell_with_script(dir.path(), "sleep 5\n");
+        let routers = [online_router(dir.path())];
+        let error = check(&routers, &shell, Duration::from_millis(100)).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn run_number_check_fails_without_the_script() {
+        let dir = tempfile::tempdir().unwrap();
+        let shell = ShellConfig {
+            shell: String::from("sh"),
+            script_dir: dir.path().to_path_buf(),
+        };
+        let routers = [online_router(dir.path())];
+        let error = check(&routers, &shell, Duration::from_secs(5)).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+        assert!(error.to_string().contains("test_graw.sh"));
+    }
+
+    fn ids(routers: &[SurveyorResponse]) -> Vec<usize> {
+        online_routers(routers)
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    #[test]
+    fn no_online_routers_is_an_error() {
+        let routers = [SurveyorResponse::default(), SurveyorResponse::default()];
+        let error = online_routers(&routers).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::NotFound);
+        assert!(online_routers(&[]).is_err());
+    }
+
+    #[test]
+    fn offline_routers_are_skipped_keeping_their_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let online = online_router(dir.path());
+        let offline = SurveyorResponse::default();
+        assert_eq!(
+            ids(&[online.clone(), offline.clone(), online.clone()]),
+            vec![0, 2]
+        );
+        assert_eq!(ids(&[offline.clone(), online.clone()]), vec![1]);
+    }
+
+    #[test]
+    fn run_check_skips_an_offline_first_router() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("e20009/run_0007")).unwrap();
+        // The offline router reports N/A for its location, which must not reach the script
+        let shell = shell_with_script(dir.path(), TEST_GRAW);
+        let routers = [SurveyorResponse::default(), online_router(dir.path())];
+        let existing = check(&routers, &shell, Duration::from_secs(5)).unwrap();
+        assert_eq!(existing.len(), 1);
+        assert!(existing[0].starts_with("Data Router 1 (localhost)"));
+    }
+
+    #[test]
+    fn quoted_values_are_a_single_word() {
+        for value in ["plain", "with space", "it's", "$HOME/*.graw", ""] {
+            let output = Command::new("sh")
+                .args(["-c", &format!("printf %s {}", shell_quote(value))])
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8(output.stdout).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn graw_files_are_moved_from_a_location_with_spaces() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = dir.path().join("data router's disk");
+        std::fs::create_dir_all(&location).unwrap();
+        std::fs::write(location.join("run.graw"), "").unwrap();
+        let command = move_graw_command(&location.display().to_string(), "e20009", &7);
+        let status = Command::new("sh").args(["-c", &command]).status().unwrap();
+        assert!(status.success());
+        assert!(location.join("e20009/run_0007/run.graw").exists());
+        assert!(!location.join("run.graw").exists());
+    }
+
+    #[test]
+    fn missing_source_is_a_failure_and_the_rest_are_copied() {
+        let source = tempfile::tempdir().unwrap();
+        let destination = tempfile::tempdir().unwrap();
+        std::fs::write(source.path().join("prepare-e20009.xcfg"), "prepare").unwrap();
+        let mut failures = vec![];
+        for name in ["describe-e20009.xcfg", "prepare-e20009.xcfg"] {
+            copy_into(&source.path().join(name), destination.path(), &mut failures);
+        }
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, source.path().join("describe-e20009.xcfg"));
+        assert_eq!(failures[0].1.kind(), std::io::ErrorKind::NotFound);
+        assert_eq!(
+            std::fs::read_to_string(destination.path().join("prepare-e20009.xcfg")).unwrap(),
+            "prepare"
+        );
+    }
+
+    #[test]
+    fn unwritable_destination_is_a_failure_for_every_file() {
+        let source = tempfile::tempdir().unwrap();
+        let cobos = source.path().join("describe-cobos");
+        std::fs::create_dir(&cobos).unwrap();
+        std::fs::write(source.path().join("configure-e20009.xcfg"), "configure").unwrap();
+        // A file where the destination directory should be, so nothing can be written into it
+        let blocker = tempfile::NamedTempFile::new().unwrap();
+        let mut failures = vec![];
+        copy_into(
+            &source.path().join("configure-e20009.xcfg"),
+            blocker.path(),
+            &mut failures,
+        );
+        copy_into(&cobos, blocker.path(), &mut failures);
+        let failed: Vec<&Path> = failures.iter().map(|(path, _)| path.as_path()).collect();
+        assert_eq!(
+            failed,
+            vec![
+                source.path().join("configure-e20009.xcfg").as_path(),
+                &cobos
+            ]
+        );
+    }
+
+    #[test]
+    fn directories_are_copied_recursively() {
+        let source = tempfile::tempdir().unwrap();
+        let destination = tempfile::tempdir().unwrap();
+        let cobos = source.path().join("describe-cobos");
+        std::fs::create_dir_all(cobos.join("cobo0")).unwrap();
+        std::fs::write(cobos.join("cobo0/describe.xcfg"), "cobo0").unwrap();
+        let mut failures = vec![];
+        copy_into(&cobos, destination.path(), &mut failures);
+        assert!(failures.is_empty());
+        assert!(destination
+            .path()
+            .join("describe-cobos/cobo0/describe.xcfg")
+            .is_file());
     }
 }
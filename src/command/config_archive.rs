@@ -0,0 +1,329 @@
+//! Compressed archives of the per-run ECC configuration backups. Over a campaign the loose
+//! backup directories grow into thousands of small files, so the backup of a run can instead (or
+//! as well) be written as a single run_<NNNN>_configs.tar.gz with a SHA-256 manifest. The files
+//! are streamed into the archive and hashed as they are read, and the archive is re-read and
+//! checked against the manifest before the loose copy is removed.
+use super::constants::BACKUP_CONFIG_DIR;
+use super::error::CommandError;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// How the configuration of each run is backed up
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum BackupFormat {
+    /// A directory with a copy of each file
+    #[default]
+    Directory,
+    /// A compressed archive with a SHA-256 manifest
+    Archive,
+    /// Both the directory and the archive
+    Both,
+}
+
+impl BackupFormat {
+    /// Is an archive written
+    pub fn archives(&self) -> bool {
+        matches!(self, Self::Archive | Self::Both)
+    }
+}
+
+impl std::fmt::Display for BackupFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Directory => write!(f, "Directory"),
+            Self::Archive => write!(f, "Archive"),
+            Self::Both => write!(f, "Directory and Archive"),
+        }
+    }
+}
+
+/// The archive of the configuration backup of a run
+pub fn archive_path(experiment: &str, run_number: i32) -> PathBuf {
+    Path::new(BACKUP_CONFIG_DIR)
+        .join(experiment)
+        .join(format!("run_{run_number:04}_configs.tar.gz"))
+}
+
+/// The SHA-256 manifest which accompanies an archive
+pub fn manifest_path(archive: &Path) -> PathBuf {
+    let name = archive
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+        .trim_end_matches(".tar.gz");
+    archive.with_file_name(format!("{name}.sha256"))
+}
+
+/// A reader which hashes everything read through it
+struct HashingReader<R: Read> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> HashingReader<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// The hex digest of everything read so far
+    fn digest(self) -> String {
+        format!("{:x}", self.hasher.finalize())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let count = self.inner.read(buf)?;
+        self.hasher.update(&buf[..count]);
+        Ok(count)
+    }
+}
+
+/// The hex digest of a file
+fn hash_file(path: &Path) -> std::io::Result<String> {
+    let mut reader = HashingReader::new(BufReader::new(File::open(path)?));
+    std::io::copy(&mut reader, &mut std::io::sink())?;
+    Ok(reader.digest())
+}
+
+/// Add the files of a directory to the archive, recursively, recording the hash of each
+fn append_dir<W: Write>(
+    builder: &mut tar::Builder<W>,
+    root: &Path,
+    dir: &Path,
+    hashes: &mut BTreeMap<String, String>,
+) -> std::io::Result<()> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<_>>()?;
+    paths.sort();
+    for path in paths {
+        if path.is_dir() {
+            append_dir(builder, root, &path, hashes)?;
+            continue;
+        }
+        let name = path
+            .strip_prefix(root)
+            .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))?
+            .to_string_lossy()
+            .to_string();
+        let file = File::open(&path)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_metadata(&file.metadata()?);
+        let mut reader = HashingReader::new(BufReader::new(file));
+        builder.append_data(&mut header, &name, &mut reader)?;
+        hashes.insert(name, reader.digest());
+    }
+    Ok(())
+}
+
+/// Write the files of a backup directory to a compressed archive, and the manifest of the
+/// archive beside it. The manifest has the sha256sum format, with the archive itself on the
+/// first line and then each file in the archive.
+pub fn write_archive(source: &Path, archive: &Path) -> std::io::Result<()> {
+    let encoder = GzEncoder::new(
+        BufWriter::new(File::create(archive)?),
+        Compression::default(),
+    );
+    let mut builder = tar::Builder::new(encoder);
+    let mut hashes = BTreeMap::new();
+    append_dir(&mut builder, source, source, &mut hashes)?;
+    builder.into_inner()?.finish()?.flush()?;
+
+    let archive_name = archive
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut manifest = format!("{}  {archive_name}\n", hash_file(archive)?);
+    for (name, hash) in hashes {
+        manifest += &format!("{hash}  {name}\n");
+    }
+    std::fs::write(manifest_path(archive), manifest)
+}
+
+/// Read a manifest as the archive hash and the hash of each file
+fn read_manifest(archive: &Path) -> Result<(String, BTreeMap<String, String>), CommandError> {
+    let path = manifest_path(archive);
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| CommandError::ArchiveUnreadable(path.clone(), e))?;
+    let mut lines = text.lines().filter_map(|line| line.split_once("  "));
+    let archive_hash = match lines.next() {
+        Some((hash, _)) => hash.to_string(),
+        None => {
+            return Err(CommandError::ArchiveMismatch(
+                archive.to_path_buf(),
+                String::from("the manifest is empty"),
+            ))
+        }
+    };
+    let files = lines
+        .map(|(hash, name)| (name.to_string(), hash.to_string()))
+        .collect();
+    Ok((archive_hash, files))
+}
+
+/// Re-read an archive and check it, and every file in it, against its manifest
+pub fn verify_archive(archive: &Path) -> Result<(), CommandError> {
+    let unreadable = |e| CommandError::ArchiveUnreadable(archive.to_path_buf(), e);
+    let mismatch = |detail| CommandError::ArchiveMismatch(archive.to_path_buf(), detail);
+    let (archive_hash, mut expected) = read_manifest(archive)?;
+    if hash_file(archive).map_err(unreadable)? != archive_hash {
+        return Err(mismatch(String::from("the archive hash differs")));
+    }
+    let file = File::open(archive).map_err(unreadable)?;
+    let mut reader = tar::Archive::new(GzDecoder::new(BufReader::new(file)));
+    for entry in reader.entries().map_err(unreadable)? {
+        let entry = entry.map_err(unreadable)?;
+        let name = entry
+            .path()
+            .map_err(unreadable)?
+            .to_string_lossy()
+            .to_string();
+        let mut hashing = HashingReader::new(entry);
+        std::io::copy(&mut hashing, &mut std::io::sink()).map_err(unreadable)?;
+        match expected.remove(&name) {
+            Some(hash) if hash == hashing.digest() => (),
+            Some(_) => return Err(mismatch(format!("{name} differs"))),
+            None => return Err(mismatch(format!("{name} is not in the manifest"))),
+        }
+    }
+    match expected.keys().next() {
+        Some(name) => Err(mismatch(format!("{name} is missing from the archive"))),
+        None => Ok(()),
+    }
+}
+
+/// Verify an archive and unpack it into a directory, overwriting the files there. Returns the
+/// number of files restored.
+pub fn restore_archive(archive: &Path, destination: &Path) -> Result<usize, CommandError> {
+    verify_archive(archive)?;
+    let unreadable = |e| CommandError::ArchiveUnreadable(archive.to_path_buf(), e);
+    let file = File::open(archive).map_err(unreadable)?;
+    let mut reader = tar::Archive::new(GzDecoder::new(BufReader::new(file)));
+    let mut restored = 0;
+    for entry in reader.entries().map_err(unreadable)? {
+        let mut entry = entry.map_err(unreadable)?;
+        // unpack_in refuses paths which would leave the destination
+        if entry
+            .unpack_in(destination)
+            .map_err(|e| CommandError::RestoreFailed(destination.to_path_buf(), e))?
+        {
+            restored += 1;
+        }
+    }
+    Ok(restored)
+}
+
+/// The configuration archives of an experiment, as run number and path, in run order
+pub fn list_archives(experiment: &str) -> Vec<(i32, PathBuf)> {
+    let dir = Path::new(BACKUP_CONFIG_DIR).join(experiment);
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    let mut archives: Vec<(i32, PathBuf)> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter_map(|path| {
+            let run_number = path
+                .file_name()?
+                .to_str()?
+                .strip_prefix("run_")?
+                .strip_suffix("_configs.tar.gz")?
+                .parse()
+                .ok()?;
+            Some((run_number, path))
+        })
+        .collect();
+    archives.sort();
+    archives
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A backup directory with experiment files and a per-CoBo subdirectory
+    fn backup_fixture(dir: &Path) -> PathBuf {
+        let source = dir.join("run_0007");
+        std::fs::create_dir_all(source.join("describe-cobos")).unwrap();
+        std::fs::write(source.join("prepare-e20009.xcfg"), "<Setup/>").unwrap();
+        std::fs::write(source.join("configure-e20009.xcfg"), "<Setup id=\"c\"/>").unwrap();
+        std::fs::write(source.join("describe-cobos/cobo0.xcfg"), "<Node/>").unwrap();
+        source
+    }
+
+    #[test]
+    fn archive_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = backup_fixture(dir.path());
+        let archive = dir.path().join("run_0007_configs.tar.gz");
+        write_archive(&source, &archive).unwrap();
+        assert!(manifest_path(&archive).is_file());
+        verify_archive(&archive).unwrap();
+
+        let destination = dir.path().join("restored");
+        std::fs::create_dir(&destination).unwrap();
+        assert_eq!(restore_archive(&archive, &destination).unwrap(), 3);
+        for name in [
+            "prepare-e20009.xcfg",
+            "configure-e20009.xcfg",
+            "describe-cobos/cobo0.xcfg",
+        ] {
+            assert_eq!(
+                std::fs::read(destination.join(name)).unwrap(),
+                std::fs::read(source.join(name)).unwrap(),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn tampered_archive_fails_verification() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = backup_fixture(dir.path());
+        let archive = dir.path().join("run_0007_configs.tar.gz");
+        write_archive(&source, &archive).unwrap();
+        let mut bytes = std::fs::read(&archive).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        std::fs::write(&archive, bytes).unwrap();
+        assert!(matches!(
+            verify_archive(&archive),
+            Err(CommandError::ArchiveMismatch(..))
+        ));
+        let destination = dir.path().join("restored");
+        assert!(restore_archive(&archive, &destination).is_err());
+        assert!(!destination.exists());
+    }
+
+    #[test]
+    fn missing_manifest_is_unreadable() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = backup_fixture(dir.path());
+        let archive = dir.path().join("run_0007_configs.tar.gz");
+        write_archive(&source, &archive).unwrap();
+        std::fs::remove_file(manifest_path(&archive)).unwrap();
+        assert!(matches!(
+            verify_archive(&archive),
+            Err(CommandError::ArchiveUnreadable(..))
+        ));
+    }
+
+    #[test]
+    fn manifest_sits_beside_the_archive() {
+        assert_eq!(
+            manifest_path(Path::new("configs_backup/e20009/run_0007_configs.tar.gz")),
+            PathBuf::from("configs_backup/e20009/run_0007_configs.sha256")
+        );
+    }
+}
@@ -0,0 +1,185 @@
+//! Pre-flight validation of the ECC configuration files of an experiment. A failed copy can leave
+//! a configuration file empty or cut off mid-tag, and the ECC servers then fail Describe, Prepare,
+//! or Configure with an error code which does not say which file is at fault. The files are only
+//! parsed shallowly, to check that they are well formed XML.
+use super::constants::CONFIG_DIR;
+use quick_xml::events::Event;
+use std::path::{Path, PathBuf};
+
+/// What is wrong with a configuration file
+#[derive(Debug, Clone, PartialEq)]
+pub enum XcfgProblem {
+    Missing,
+    Unreadable(String),
+    Empty,
+    /// The file ends part way through the XML
+    Truncated(String),
+    NotXml(String),
+}
+
+impl std::fmt::Display for XcfgProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing => write!(f, "missing"),
+            Self::Unreadable(e) => write!(f, "unreadable ({e})"),
+            Self::Empty => write!(f, "empty"),
+            Self::Truncated(e) => write!(f, "truncated ({e})"),
+            Self::NotXml(e) => write!(f, "not XML ({e})"),
+        }
+    }
+}
+
+/// A configuration file with a problem
+#[derive(Debug, Clone, PartialEq)]
+pub struct XcfgIssue {
+    pub path: PathBuf,
+    pub problem: XcfgProblem,
+}
+
+impl std::fmt::Display for XcfgIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} is {}", self.path.display(), self.problem)
+    }
+}
+
+/// Check that the text of a configuration file is well formed XML
+fn check_xml(text: &str) -> Option<XcfgProblem> {
+    if text.trim().is_empty() {
+        return Some(XcfgProblem::Empty);
+    }
+    // A file cut off mid-tag does not end with the closing bracket of a tag
+    let ends_in_tag = text.trim_end().ends_with('>');
+    let mut reader = quick_xml::Reader::from_str(text);
+    let mut depth: usize = 0;
+    let mut elements: usize = 0;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(_)) => {
+                depth += 1;
+                elements += 1;
+            }
+            Ok(Event::Empty(_)) => elements += 1,
+            Ok(Event::End(_)) => depth = depth.saturating_sub(1),
+            Ok(Event::Eof) => break,
+            Ok(_) => (),
+            Err(e) if ends_in_tag => return Some(XcfgProblem::NotXml(e.to_string())),
+            Err(e) => return Some(XcfgProblem::Truncated(e.to_string())),
+        }
+    }
+    if elements == 0 {
+        Some(XcfgProblem::NotXml(String::from("no XML elements")))
+    } else if depth > 0 {
+        Some(XcfgProblem::Truncated(format!(
+            "{depth} elements are not closed"
+        )))
+    } else {
+        None
+    }
+}
+
+/// Check a single configuration file
+pub fn check_xcfg(path: &Path) -> Option<XcfgProblem> {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Some(XcfgProblem::Missing),
+        Err(e) => return Some(XcfgProblem::Unreadable(e.to_string())),
+    };
+    match String::from_utf8(bytes) {
+        Ok(text) => check_xml(&text),
+        Err(e) => Some(XcfgProblem::NotXml(e.to_string())),
+    }
+}
+
+/// Check the describe, prepare, and configure files of an experiment, and the per-CoBo describe
+/// files, returning the files with problems
+pub fn check_experiment_configs(experiment: &str) -> Vec<XcfgIssue> {
+    let config_dir = Path::new(CONFIG_DIR);
+    let mut paths: Vec<PathBuf> = ["describe", "prepare", "configure"]
+        .iter()
+        .map(|stage| config_dir.join(format!("{stage}-{experiment}.xcfg")))
+        .collect();
+    if let Ok(entries) = std::fs::read_dir(config_dir) {
+        let mut cobo_describes: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| {
+                        name.starts_with("describe-cobo") && name.ends_with(".xcfg")
+                    })
+            })
+            .collect();
+        cobo_describes.sort();
+        paths.extend(cobo_describes);
+    }
+    paths
+        .into_iter()
+        .filter_map(|path| check_xcfg(&path).map(|problem| XcfgIssue { path, problem }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID_XCFG: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Setup id="e20009">
+  <Node id="CoBo">
+    <Instance id="0"><Module id="AsAd"/></Instance>
+  </Node>
+</Setup>
+"#;
+
+    /// Write a fixture into a directory, returning its path
+    fn fixture(dir: &Path, name: &str, text: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn valid_file_has_no_problem() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = fixture(dir.path(), "configure-e20009.xcfg", VALID_XCFG);
+        assert_eq!(check_xcfg(&path), None);
+    }
+
+    #[test]
+    fn empty_files_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, text) in [("empty.xcfg", ""), ("blank.xcfg", " \n\t\n")] {
+            let path = fixture(dir.path(), name, text);
+            assert_eq!(check_xcfg(&path), Some(XcfgProblem::Empty), "{name}");
+        }
+    }
+
+    #[test]
+    fn truncated_files_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        // Cut off mid-tag, and cut off between tags
+        let mid_tag = &VALID_XCFG[..VALID_XCFG.find("<Module").unwrap() + 4];
+        let between_tags = &VALID_XCFG[..VALID_XCFG.find("</Node>").unwrap()];
+        for (name, text) in [("mid_tag.xcfg", mid_tag), ("between.xcfg", between_tags)] {
+            let path = fixture(dir.path(), name, text);
+            assert!(
+                matches!(check_xcfg(&path), Some(XcfgProblem::Truncated(_))),
+                "{name}: {:?}",
+                check_xcfg(&path)
+            );
+        }
+    }
+
+    #[test]
+    fn other_problems_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = fixture(dir.path(), "text.xcfg", "not a configuration");
+        assert!(matches!(check_xcfg(&path), Some(XcfgProblem::NotXml(_))));
+        assert_eq!(
+            check_xcfg(&dir.path().join("missing.xcfg")),
+            Some(XcfgProblem::Missing)
+        );
+        let path = dir.path().join("binary.xcfg");
+        std::fs::write(&path, [0xff, 0xfe, 0x00]).unwrap();
+        assert!(matches!(check_xcfg(&path), Some(XcfgProblem::NotXml(_))));
+    }
+}
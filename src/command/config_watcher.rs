@@ -0,0 +1,71 @@
+//! Watches the ECC configuration directory so that the operator can be told when the
+//! configuration files of a described system have been edited.
+use super::constants::CONFIG_DIR;
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+
+/// Time to wait for file events to settle before reporting them
+const DEBOUNCE_TIME_MS: u64 = 1000;
+
+/// A debounced watcher on the ECC configuration directory
+pub struct ConfigWatcher {
+    _debouncer: Debouncer<RecommendedWatcher>,
+    events: Receiver<DebounceEventResult>,
+    experiment: String,
+}
+
+impl std::fmt::Debug for ConfigWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ConfigWatcher({CONFIG_DIR})")
+    }
+}
+
+impl ConfigWatcher {
+    /// Start watching the configuration directory for changes to the files of an experiment
+    pub fn new(experiment: &str) -> Result<Self, notify_debouncer_mini::notify::Error> {
+        let (tx, rx) = channel();
+        let mut debouncer = new_debouncer(Duration::from_millis(DEBOUNCE_TIME_MS), tx)?;
+        debouncer
+            .watcher()
+            .watch(Path::new(CONFIG_DIR), RecursiveMode::NonRecursive)?;
+        Ok(Self {
+            _debouncer: debouncer,
+            events: rx,
+            experiment: experiment.to_string(),
+        })
+    }
+
+    /// Is this one of the configuration files used to describe, prepare, or configure the experiment
+    fn is_relevant(&self, path: &Path) -> bool {
+        let name = match path.file_name().and_then(|n| n.to_str()) {
+            Some(n) => n,
+            None => return false,
+        };
+        let exp = &self.experiment;
+        name == format!("describe-{exp}.xcfg")
+            || name == format!("prepare-{exp}.xcfg")
+            || name == format!("configure-{exp}.xcfg")
+            || (name.starts_with("describe-cobo") && name.ends_with(".xcfg"))
+    }
+
+    /// Get the relevant configuration files which changed since the last call
+    pub fn changed_files(&self) -> Vec<PathBuf> {
+        let mut changed: Vec<PathBuf> = vec![];
+        while let Ok(result) = self.events.try_recv() {
+            match result {
+                Ok(events) => {
+                    for event in events {
+                        if self.is_relevant(&event.path) && !changed.contains(&event.path) {
+                            changed.push(event.path);
+                        }
+                    }
+                }
+                Err(e) => tracing::error!("Config directory watcher had an error: {e}"),
+            }
+        }
+        changed
+    }
+}
@@ -0,0 +1,24 @@
+//! Error types for the scripting extensions
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Describe each file which failed, as path: error
+fn describe_failures(failures: &[(PathBuf, std::io::Error)]) -> String {
+    failures
+        .iter()
+        .map(|(path, e)| format!("{}: {e}", path.display()))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[derive(Debug, Error)]
+pub enum CommandError {
+    #[error("[CMD-001] {} files were not backed up to {}: {}", .1.len(), .0.display(), describe_failures(.1))]
+    BackupFailed(PathBuf, Vec<(PathBuf, std::io::Error)>),
+    #[error("[CMD-002] Could not read the config archive {}: {}", .0.display(), .1)]
+    ArchiveUnreadable(PathBuf, std::io::Error),
+    #[error("[CMD-003] The config archive {} does not match its manifest: {}", .0.display(), .1)]
+    ArchiveMismatch(PathBuf, String),
+    #[error("[CMD-004] Could not restore the config archive into {}: {}", .0.display(), .1)]
+    RestoreFailed(PathBuf, std::io::Error),
+}
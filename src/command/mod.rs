@@ -1,3 +1,7 @@
 //! Scripting extension to the envoy system
 pub mod bash_command;
-mod constants;
+pub mod config_archive;
+pub mod config_check;
+pub mod config_watcher;
+pub mod constants;
+pub mod error;
@@ -0,0 +1,85 @@
+//! The AsAd-level summary of a module, parsed from the text of its Describe response. The text
+//! is free-form and differs between ECC versions, so the parser is tolerant: it only looks for
+//! lines naming an AsAd by index (i.e. "AsAd 2", "asad[2]", "AsAd_2") and for masked channel
+//! counts, and ignores everything else.
+use std::collections::BTreeSet;
+
+/// Words which mark an AsAd as masked by the configuration
+const MASKED_WORDS: [&str; 5] = ["masked", "mask", "disabled", "inactive", "off"];
+
+/// The AsAds of a module as reported by its last Describe
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AsAdSummary {
+    pub active: BTreeSet<usize>,
+    pub masked: BTreeSet<usize>,
+    /// The total number of masked channels, if the text reports any
+    pub masked_channels: Option<u32>,
+}
+
+/// The index following the first mention of an AsAd in a (lowercase) line
+fn asad_index(line: &str) -> Option<usize> {
+    let start = line.find("asad")? + "asad".len();
+    let rest = line[start..].trim_start_matches(|c: char| !c.is_ascii_alphanumeric());
+    let digits: String = rest.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+impl AsAdSummary {
+    /// Parse the text of a Describe response. Returns None if the text has no AsAd information.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut summary = Self::default();
+        let mut seen = BTreeSet::new();
+        for line in text.lines() {
+            let line = line.to_lowercase();
+            let words: Vec<&str> = line
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .collect();
+            let about_channels = words.iter().any(|word| word.starts_with("channel"));
+            if about_channels && words.iter().any(|word| word.starts_with("mask")) {
+                // The count is the first number which is not the AsAd index
+                let index = asad_index(&line).map(|index| index.to_string());
+                if let Some(count) = words
+                    .iter()
+                    .filter(|word| Some(word.to_string()) != index)
+                    .find_map(|word| word.parse::<u32>().ok())
+                {
+                    *summary.masked_channels.get_or_insert(0) += count;
+                }
+            }
+            let index = match asad_index(&line) {
+                Some(index) => index,
+                None => continue,
+            };
+            seen.insert(index);
+            // On a line about channels the mask words refer to the channels, not the AsAd
+            let masked_words: &[&str] = if about_channels {
+                &MASKED_WORDS[2..]
+            } else {
+                &MASKED_WORDS
+            };
+            if words.iter().any(|word| masked_words.contains(word)) {
+                summary.masked.insert(index);
+            }
+        }
+        if seen.is_empty() && summary.masked_channels.is_none() {
+            return None;
+        }
+        summary.active = seen.difference(&summary.masked).copied().collect();
+        Some(summary)
+    }
+
+    /// Describe the discrepancy with the expected number of active AsAds, if there is one
+    pub fn discrepancy(&self, expected: usize) -> Option<String> {
+        let active = self.active.len();
+        if active == expected {
+            return None;
+        }
+        let mut detail = format!("{active} of {expected} AsAds active");
+        if !self.masked.is_empty() {
+            let masked: Vec<String> = self.masked.iter().map(|id| id.to_string()).collect();
+            detail += &format!(" (masked: {})", masked.join(", "));
+        }
+        Some(detail)
+    }
+}
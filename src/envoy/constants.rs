@@ -1,7 +1,27 @@
 //! Constants used across the envoy system
-pub const NUMBER_OF_MODULES: usize = 12;
-pub const MUTANT_ID: usize = 11;
-pub const PROTOCOL: &str = "TCP";
+pub const DEFAULT_NUMBER_OF_COBOS: usize = 11;
+pub const DEFAULT_POLL_INTERVAL_SEC: u64 = 2;
 pub const DATA_ROUTER_LISTENER_PORT: i32 = 46005;
 pub const DATA_EXPORTER_LISTENER_PORT: i32 = 46007;
 pub const ADDRESS_START: &str = "192.168.41";
+/// How long stopping a run waits for the MuTaNT to stop before stopping the CoBos anyway
+pub const MUTANT_STOP_TIMEOUT_SEC: u64 = 30;
+/// How long re-configuring the system may take before it is abandoned
+pub const RECONFIGURE_TIMEOUT_SEC: u64 = 300;
+/// How long progressing the system may go without any module changing status before it is abandoned
+pub const PROGRESS_STALL_TIMEOUT_SEC: u64 = 300;
+
+/// The most messages a single module can have in flight to the embassy at once: an ECC status,
+/// an ECC operation response, and a data router status.
+pub const MESSAGES_IN_FLIGHT_PER_MODULE: usize = 3;
+/// Capacity of the channel from the embassy to each ECC envoy. Operations are submitted one at
+/// a time per module, the extra room allows retries to queue behind a slow operation.
+pub const ECC_ENVOY_CHANNEL_CAPACITY: usize = 10;
+/// Capacity of the cancel broadcast channel. Only a single cancel is ever sent.
+pub const CANCEL_CHANNEL_CAPACITY: usize = 1;
+
+/// Capacity of the channel from the envoys to the embassy, enough for every module (the CoBos
+/// and the MuTaNT) to have all of its messages in flight without an envoy blocking.
+pub fn embassy_channel_capacity(number_of_cobos: usize) -> usize {
+    (number_of_cobos + 1) * MESSAGES_IN_FLIGHT_PER_MODULE
+}
@@ -1,24 +1,38 @@
 use super::constants::{
-    ADDRESS_START, DATA_EXPORTER_LISTENER_PORT, DATA_ROUTER_LISTENER_PORT, MUTANT_ID,
-    NUMBER_OF_MODULES, PROTOCOL,
+    DATA_EXPORTER_LISTENER_PORT, DATA_ROUTER_LISTENER_PORT, ECC_ENVOY_CHANNEL_CAPACITY,
 };
-use super::ecc_operation::ECCOperation;
+use super::ecc_operation::{ECCOperation, ECCStatus};
 use super::error::EnvoyError;
 use super::message::EmbassyMessage;
+use super::network::{DataLinkProtocol, NetworkMap};
+use quick_xml::events::Event;
 use reqwest::{Client, Response};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::net::{TcpStream, ToSocketAddrs};
 use std::time::Duration;
 use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 
-/// Amount of time to wait to check status
-const STATUS_WAIT_TIME_SEC: u64 = 2;
-
 /// Connection timeout
 const CONNECTION_TIMEOUT_SEC: u64 = 120;
 
+/// Error code reported when the ECC server responds with a SOAP Fault
+const SOAP_FAULT_ERROR_CODE: i32 = -1;
+
+/// Error code reported when an operation could not be delivered to the ECC server
+const OPERATION_FAILED_ERROR_CODE: i32 = -2;
+
+/// Error code reported when the ECC server answered a status query, but the answer could not be read
+const STATUS_UNREADABLE_ERROR_CODE: i32 = -3;
+
+/// Number of attempts made to submit an operation before giving up
+const OPERATION_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry of an operation. Doubles with each retry.
+const OPERATION_RETRY_DELAY_MS: u64 = 500;
+
 /// The default port for ECC
 const ECC_URL_PORT: i32 = 8083;
 
@@ -61,24 +75,34 @@ pub struct ECCStatusResponse {
 #[derive(Debug, Clone)]
 pub struct ECCConfig {
     id: usize,
+    is_mutant: bool,
     experiment: String,
     address: String,
+    protocol: DataLinkProtocol,
     url: String,
+    poll_interval: Duration,
 }
 
 impl ECCConfig {
-    /// Create a ECC config from an experiment name and module ID
-    pub fn new(id: usize, experiment: &str) -> ECCConfig {
-        let address = match id {
-            MUTANT_ID => format!("{ADDRESS_START}.1"),
-            _ => format!("{ADDRESS_START}.{}", 60 + id),
-        };
-        let url = Self::url(&address);
+    /// Create a ECC config from an experiment name, module ID, module address, data link protocol, and status polling interval.
+    /// The MuTaNT is always the module after the last CoBo.
+    pub fn new(
+        id: usize,
+        mutant_id: usize,
+        experiment: &str,
+        address: &str,
+        protocol: DataLinkProtocol,
+        poll_interval: Duration,
+    ) -> ECCConfig {
+        let url = Self::url(address);
         ECCConfig {
             id,
+            is_mutant: id == mutant_id,
             experiment: experiment.to_string(),
-            address,
+            address: address.to_string(),
+            protocol,
             url,
+            poll_interval,
         }
     }
 
@@ -111,16 +135,17 @@ impl ECCConfig {
         let ip = self.address.clone();
         let router = self.data_router();
         let exporter = self.data_exporter();
+        let protocol = self.protocol;
         format!(
             r#"<table>
                         <DataLinkSet>
                             <DataLink>
                                 <DataSender id="{source}" />
-                                <DataRouter ipAddress="{ip}" name="{router}" port="{DATA_ROUTER_LISTENER_PORT}" type="{PROTOCOL}" />
+                                <DataRouter ipAddress="{ip}" name="{router}" port="{DATA_ROUTER_LISTENER_PORT}" type="{protocol}" />
                             </DataLink>
                             <DataLink>
                                 <DataSender id="{source}" />
-                                <DataRouter ipAddress="{ip}" name="{exporter}" port="{DATA_EXPORTER_LISTENER_PORT}" type="{PROTOCOL}" />
+                                <DataRouter ipAddress="{ip}" name="{exporter}" port="{DATA_EXPORTER_LISTENER_PORT}" type="{protocol}" />
                             </DataLink>
                         </DataLinkSet>
                     </table>"#
@@ -129,17 +154,19 @@ impl ECCConfig {
 
     /// Comopose the string defining the describe ID
     fn describe(&self) -> String {
-        match self.id {
-            MUTANT_ID => self.experiment.clone(),
-            _ => format!("cobo{}", self.id),
+        if self.is_mutant {
+            self.experiment.clone()
+        } else {
+            format!("cobo{}", self.id)
         }
     }
 
     /// Compose the string defining the data source (module)
     fn source(&self) -> String {
-        match self.id {
-            MUTANT_ID => String::from("Mutant[master]"),
-            _ => format!("CoBo[{}]", self.id),
+        if self.is_mutant {
+            String::from("Mutant[master]")
+        } else {
+            format!("CoBo[{}]", self.id)
         }
     }
 
@@ -154,11 +181,27 @@ impl ECCConfig {
     }
 
     /// Compose the associated getECCServer URL
-    fn url(address: &str) -> String {
+    pub fn url(address: &str) -> String {
         format!("http://{}:{}", address, ECC_URL_PORT)
     }
 }
 
+/// Check that an ECC server is listening at an address by opening a connection to the ECC
+/// port. Blocks for up to the timeout per resolved address, so don't call from the UI thread.
+pub fn probe_ecc_server(address: &str, timeout: Duration) -> Result<(), std::io::Error> {
+    let mut result = Err(std::io::Error::new(
+        std::io::ErrorKind::NotFound,
+        "address did not resolve",
+    ));
+    for socket in (address, ECC_URL_PORT as u16).to_socket_addrs()? {
+        result = TcpStream::connect_timeout(&socket, timeout).map(|_| ());
+        if result.is_ok() {
+            break;
+        }
+    }
+    result
+}
+
 /// Run an ECC envoy, communicating with the ECCServer
 async fn run_ecc_envoy(
     config: ECCConfig,
@@ -177,7 +220,7 @@ async fn run_ecc_envoy(
     // This is the core loop of the envoy. Wait for one of three conditions.
     // 1. A cancel message. This stops the envoy and ends the task
     // 2. A operation (ECCOperation) has been requested. Submit the request to the module
-    // 3. 2 seconds pass. Every 2 sec query the status of the server.
+    // 3. The poll interval passes. Every interval query the status of the server.
     loop {
         tokio::select! {
             _ = cancel.recv() => {
@@ -186,20 +229,23 @@ async fn run_ecc_envoy(
 
             data = incoming.recv() => {
                 if let Some(message) = data {
-                    let response = submit_operation(&config, &client, message).await?;
-                    outgoing.send(response).await?;
+                    match submit_operation_with_retry(&config, &client, message, &mut cancel).await? {
+                        Some(response) => outgoing.send(response).await?,
+                        None => return Ok(()),
+                    }
                 } else {
                     return Ok(())
                 }
             }
 
-            _ = tokio::time::sleep(Duration::from_secs(STATUS_WAIT_TIME_SEC)) => {
-                if let Ok(response) = submit_check_status(&config, &client).await {
-                    outgoing.send(response).await?
-                } else {
-                    let response = ECCStatusResponse { error_code: 0, error_message: String::from(""), state: 0, transition: 0 };
-                    let message = EmbassyMessage::compose_ecc_status(serde_yaml::to_string(&response)?, config.id);
-                    outgoing.send(message).await?
+            _ = tokio::time::sleep(config.poll_interval) => {
+                match submit_check_status(&config, &client).await {
+                    Ok(response) => outgoing.send(response).await?,
+                    Err(e) => {
+                        let response = status_error_response(&e);
+                        let message = EmbassyMessage::compose_ecc_status(serde_yaml::to_string(&response)?, config.id);
+                        outgoing.send(message).await?
+                    }
                 }
             }
         }
@@ -223,6 +269,53 @@ async fn submit_operation(
     Ok(parsed_response)
 }
 
+/// Submit an operation, retrying with exponential backoff if the request fails to reach the server.
+/// If every attempt fails, an operation response with a nonzero error code is returned so that the
+/// failure is reported rather than dropped. Returns None if the envoy was cancelled while waiting to retry.
+async fn submit_operation_with_retry(
+    config: &ECCConfig,
+    cxn: &Client,
+    message: EmbassyMessage,
+    cancel: &mut broadcast::Receiver<EmbassyMessage>,
+) -> Result<Option<EmbassyMessage>, EnvoyError> {
+    let mut delay = Duration::from_millis(OPERATION_RETRY_DELAY_MS);
+    let mut attempt = 1;
+    let error = loop {
+        match submit_operation(config, cxn, message.clone()).await {
+            Ok(response) => return Ok(Some(response)),
+            Err(EnvoyError::BadRequest(e)) if attempt < OPERATION_ATTEMPTS => {
+                tracing::warn!(
+                    "ECC operation {} for module {} failed (attempt {attempt} of {OPERATION_ATTEMPTS}): {e}",
+                    message.operation,
+                    config.id
+                );
+            }
+            Err(e) => break e,
+        }
+        tokio::select! {
+            _ = cancel.recv() => return Ok(None),
+            _ = tokio::time::sleep(delay) => (),
+        }
+        delay *= 2;
+        attempt += 1;
+    };
+
+    tracing::error!(
+        "ECC operation {} for module {} failed: {error}",
+        message.operation,
+        config.id
+    );
+    let failure = ECCOperationResponse {
+        error_code: OPERATION_FAILED_ERROR_CODE,
+        error_message: error.to_string(),
+        text: String::default(),
+    };
+    Ok(Some(EmbassyMessage::compose_ecc_response(
+        serde_yaml::to_string(&failure)?,
+        config.id,
+    )))
+}
+
 /// Sumbit a status check
 async fn submit_check_status(
     config: &ECCConfig,
@@ -239,6 +332,26 @@ async fn submit_check_status(
     Ok(parsed_response)
 }
 
+/// The status reported when a status query fails. Only a server which could not be reached
+/// (or did not answer in time) is Unreachable; a server whose answer could not be read is in an
+/// error state, with an error code saying so.
+fn status_error_response(error: &EnvoyError) -> ECCStatusResponse {
+    match error {
+        EnvoyError::BadRequest(e) if e.is_connect() || e.is_timeout() => ECCStatusResponse {
+            error_code: 0,
+            error_message: error.to_string(),
+            state: ECCStatus::Unreachable.into(),
+            transition: 0,
+        },
+        _ => ECCStatusResponse {
+            error_code: STATUS_UNREADABLE_ERROR_CODE,
+            error_message: error.to_string(),
+            state: ECCStatus::ErrorStat.into(),
+            transition: 0,
+        },
+    }
+}
+
 /// Compose the operation request (text)
 fn compose_operation_request(
     config: &ECCConfig,
@@ -252,47 +365,102 @@ fn compose_operation_request(
     ))
 }
 
+/// Read the text of every leaf element in an ECC SOAP response, keyed by local element name
+/// (namespace prefixes are ignored). Element order, whitespace, and unknown elements do not matter.
+/// Empty elements are recorded with empty text.
+fn read_soap_elements(text: &str) -> Result<HashMap<String, String>, EnvoyError> {
+    let mut reader = quick_xml::Reader::from_str(text);
+    reader.config_mut().trim_text(true);
+    let mut elements = HashMap::new();
+    let mut current: Option<String> = None;
+    loop {
+        match reader.read_event()? {
+            Event::Start(tag) => {
+                let name = String::from_utf8(tag.local_name().as_ref().to_vec())?;
+                elements.entry(name.clone()).or_insert_with(String::new);
+                current = Some(name);
+            }
+            Event::Empty(tag) => {
+                let name = String::from_utf8(tag.local_name().as_ref().to_vec())?;
+                elements.entry(name).or_insert_with(String::new);
+            }
+            Event::Text(t) => {
+                if let Some(name) = &current {
+                    elements.insert(name.clone(), t.unescape()?.into_owned());
+                }
+            }
+            Event::End(_) => current = None,
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+    Ok(elements)
+}
+
+/// Retrieve a required integer element from the parsed SOAP elements
+fn required_int(elements: &HashMap<String, String>, name: &str) -> Result<i32, EnvoyError> {
+    match elements.get(name) {
+        Some(value) => Ok(value.trim().parse()?),
+        None => Err(EnvoyError::FailedXMLConvert),
+    }
+}
+
+/// Check the SOAP elements for a fault. The ECC server reports faults using the standard SOAP Fault
+/// element rather than the ECC ErrorCode, so we convert it to a generic error code and message.
+fn soap_fault(elements: &HashMap<String, String>) -> Option<(i32, String)> {
+    if !elements.contains_key("Fault") {
+        return None;
+    }
+    let message = elements
+        .get("faultstring")
+        .or(elements.get("faultcode"))
+        .cloned()
+        .unwrap_or_default();
+    Some((SOAP_FAULT_ERROR_CODE, message))
+}
+
+/// Parse the text of an ECC operation response
+fn parse_operation_text(text: &str) -> Result<ECCOperationResponse, EnvoyError> {
+    let elements = read_soap_elements(text)?;
+    if let Some((error_code, error_message)) = soap_fault(&elements) {
+        return Ok(ECCOperationResponse {
+            error_code,
+            error_message,
+            text: String::default(),
+        });
+    }
+    Ok(ECCOperationResponse {
+        error_code: required_int(&elements, "ErrorCode")?,
+        error_message: elements.get("ErrorMessage").cloned().unwrap_or_default(),
+        text: elements.get("Text").cloned().unwrap_or_default(),
+    })
+}
+
+/// Parse the text of an ECC status response
+fn parse_status_text(text: &str) -> Result<ECCStatusResponse, EnvoyError> {
+    let elements = read_soap_elements(text)?;
+    if let Some((error_code, error_message)) = soap_fault(&elements) {
+        return Ok(ECCStatusResponse {
+            error_code,
+            error_message,
+            ..Default::default()
+        });
+    }
+    Ok(ECCStatusResponse {
+        error_code: required_int(&elements, "ErrorCode")?,
+        error_message: elements.get("ErrorMessage").cloned().unwrap_or_default(),
+        state: required_int(&elements, "State")?,
+        transition: required_int(&elements, "Transition")?,
+    })
+}
+
 /// Parse the response from the server after an operation
 async fn parse_operation_response(
     config: &ECCConfig,
     response: Response,
 ) -> Result<EmbassyMessage, EnvoyError> {
     let text = response.text().await?;
-    let mut reader = quick_xml::Reader::from_str(&text);
-    let mut parsed = ECCOperationResponse::default();
-
-    reader.read_event()?; //Opening
-    reader.read_event()?; //Junk
-    reader.read_event()?; //SOAP Decl
-    reader.read_event()?; //SOAP Body
-    reader.read_event()?; //ECC
-    reader.read_event()?; //ErrorCode start tag
-    let event = reader.read_event()?; //ErrorCode payload
-    parsed.error_code = match event {
-        quick_xml::events::Event::Text(t) => String::from_utf8(t.to_vec())?.parse()?,
-        _ => return Err(EnvoyError::FailedXMLConvert),
-    };
-    reader.read_event()?; //ErrorCode end tag
-    reader.read_event()?; //ErrorMesage start tag
-    let event = reader.read_event()?; //ErrorMessage payload or end tag
-    let mut is_msg = true;
-    parsed.error_message = match event {
-        quick_xml::events::Event::Text(t) => String::from_utf8(t.to_vec())?,
-        _ => {
-            is_msg = false;
-            String::from("")
-        }
-    };
-    if is_msg {
-        reader.read_event()?; //ErrorMessage end tag
-    }
-    reader.read_event()?; //Text start tag
-    let event = reader.read_event()?; //Text payload
-    parsed.text = match event {
-        quick_xml::events::Event::Text(t) => String::from_utf8(t.to_vec())?,
-        _ => String::from(""),
-    };
-
+    let parsed = parse_operation_text(&text)?;
     Ok(EmbassyMessage::compose_ecc_response(
         serde_yaml::to_string(&parsed)?,
         config.id,
@@ -305,59 +473,22 @@ async fn parse_status_response(
     response: Response,
 ) -> Result<EmbassyMessage, EnvoyError> {
     let text = response.text().await?;
-    let mut reader = quick_xml::Reader::from_str(&text);
-    let mut parsed: ECCStatusResponse = ECCStatusResponse::default();
-
-    reader.read_event()?; //Opening
-    reader.read_event()?; //Junk
-    reader.read_event()?; //SOAP Decl
-    reader.read_event()?; //SOAP Body
-    reader.read_event()?; //ECC
-    reader.read_event()?; //ErrorCode start tag
-    let event = reader.read_event()?; //ErrorCode payload
-    parsed.error_code = match event {
-        quick_xml::events::Event::Text(t) => String::from_utf8(t.to_vec())?.parse()?,
-        _ => return Err(EnvoyError::FailedXMLConvert),
-    };
-    reader.read_event()?; //ErrorCode end tag
-    reader.read_event()?; //ErrorMesage start tag
-    let event = reader.read_event()?; //ErrorMessage payload or end tag
-    let mut is_msg = true;
-    parsed.error_message = match event {
-        quick_xml::events::Event::Text(t) => String::from_utf8(t.to_vec())?,
-        _ => {
-            is_msg = false;
-            String::from("")
-        }
-    };
-    if is_msg {
-        reader.read_event()?; //ErrorMessage end tag
-    }
-    reader.read_event()?; //State start tag
-    let event = reader.read_event()?; //State payload
-    parsed.state = match event {
-        quick_xml::events::Event::Text(t) => String::from_utf8(t.to_vec())?.parse()?,
-        _ => return Err(EnvoyError::FailedXMLConvert),
-    };
-    reader.read_event()?; //State end tag
-    reader.read_event()?; //Transition start tag
-    let event = reader.read_event()?; //Transition payload
-    parsed.transition = match event {
-        quick_xml::events::Event::Text(t) => String::from_utf8(t.to_vec())?.parse()?,
-        _ => return Err(EnvoyError::FailedXMLConvert),
-    };
-
+    let parsed = parse_status_text(&text)?;
     let status_response =
         EmbassyMessage::compose_ecc_status(serde_yaml::to_string(&parsed)?, config.id);
     Ok(status_response)
 }
 
 /// Startup the ECC communication system
-/// Takes in a runtime reference, experiment name, and a channel to send data to the embassy. Spawns the ECCEnvoys with tasks to wait for
-/// a command to operation that ECC DAQ and to periodically check the status of that particular ECC DAQ.
+/// Takes in a runtime reference, experiment name, CoBo and MuTaNT addresses, status polling interval, and a channel to send data to the embassy. Spawns the ECCEnvoys
+/// (one per CoBo plus the MuTaNT) with tasks to wait for a command to operation that ECC DAQ and to periodically check the status
+/// of that particular ECC DAQ.
 pub fn startup_ecc_envoys(
     runtime: &mut tokio::runtime::Runtime,
     experiment: &str,
+    cobo_addresses: &[String],
+    network: &NetworkMap,
+    poll_interval: Duration,
     ecc_tx: &mpsc::Sender<EmbassyMessage>,
     cancel: &broadcast::Sender<EmbassyMessage>,
 ) -> (
@@ -368,9 +499,14 @@ pub fn startup_ecc_envoys(
     let mut handles: Vec<JoinHandle<()>> = vec![];
 
     //spin up the envoys
-    for id in 0..NUMBER_OF_MODULES {
-        let config = ECCConfig::new(id, experiment);
-        let (embassy_tx, ecc_rx) = mpsc::channel::<EmbassyMessage>(10);
+    let mutant_id = cobo_addresses.len();
+    for id in 0..=mutant_id {
+        let (address, protocol) = match cobo_addresses.get(id) {
+            Some(address) => (address.as_str(), network.cobo_protocol(id)),
+            None => (network.mutant.as_str(), network.protocol),
+        };
+        let config = ECCConfig::new(id, mutant_id, experiment, address, protocol, poll_interval);
+        let (embassy_tx, ecc_rx) = mpsc::channel::<EmbassyMessage>(ECC_ENVOY_CHANNEL_CAPACITY);
         let this_ecc_tx = ecc_tx.clone();
         let this_cancel = cancel.subscribe();
         let handle = runtime.spawn(async move {
@@ -386,3 +522,102 @@ pub fn startup_ecc_envoys(
 
     (handles, switchboard)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn envelope(body: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<SOAP-ENV:Envelope xmlns:SOAP-ENV="http://schemas.xmlsoap.org/soap/envelope/" xmlns:ns="urn:ecc">
+<SOAP-ENV:Body>{body}</SOAP-ENV:Body>
+</SOAP-ENV:Envelope>"#
+        )
+    }
+
+    #[test]
+    fn status_is_read_by_element_name() {
+        let text = envelope(
+            "<ns:GetStateResponse><ns:Transition>0</ns:Transition><ns:State> 3 </ns:State>\
+             <ns:Unknown>x</ns:Unknown><ns:ErrorMessage/><ns:ErrorCode>0</ns:ErrorCode>\
+             </ns:GetStateResponse>",
+        );
+        let status = parse_status_text(&text).unwrap();
+        assert_eq!(status.error_code, 0);
+        assert_eq!(status.error_message, "");
+        assert_eq!(status.state, 3);
+        assert_eq!(status.transition, 0);
+    }
+
+    #[test]
+    fn operation_text_is_unescaped() {
+        let text = envelope(
+            "<ns:Response><ns:ErrorCode>2</ns:ErrorCode>\
+             <ns:ErrorMessage>a &lt; b</ns:ErrorMessage><ns:Text>done</ns:Text></ns:Response>",
+        );
+        let response = parse_operation_text(&text).unwrap();
+        assert_eq!(response.error_code, 2);
+        assert_eq!(response.error_message, "a < b");
+        assert_eq!(response.text, "done");
+    }
+
+    #[test]
+    fn soap_fault_becomes_an_error_code() {
+        let text = envelope(
+            "<SOAP-ENV:Fault><faultcode>SOAP-ENV:Server</faultcode>\
+             <faultstring>No such state</faultstring></SOAP-ENV:Fault>",
+        );
+        let status = parse_status_text(&text).unwrap();
+        assert_eq!(status.error_code, SOAP_FAULT_ERROR_CODE);
+        assert_eq!(status.error_message, "No such state");
+        let operation = parse_operation_text(&text).unwrap();
+        assert_eq!(operation.error_code, SOAP_FAULT_ERROR_CODE);
+    }
+
+    #[test]
+    fn missing_or_bad_elements_are_errors() {
+        let missing = envelope("<ns:R><ns:ErrorCode>0</ns:ErrorCode><ns:State>1</ns:State></ns:R>");
+        assert!(matches!(
+            parse_status_text(&missing),
+            Err(EnvoyError::FailedXMLConvert)
+        ));
+        let bad = envelope(
+            "<ns:R><ns:ErrorCode>0</ns:ErrorCode><ns:State>one</ns:State>\
+             <ns:Transition>0</ns:Transition></ns:R>",
+        );
+        assert!(matches!(
+            parse_status_text(&bad),
+            Err(EnvoyError::InvalidStringToInt(_))
+        ));
+        assert!(parse_status_text("<a><b></a>").is_err());
+    }
+
+    #[test]
+    fn refused_connection_is_unreachable() {
+        let runtime = crate::envoy::runtime::build_runtime(&Default::default()).unwrap();
+        let error = runtime.block_on(async {
+            Client::new()
+                .post("http://127.0.0.1:1/")
+                .send()
+                .await
+                .unwrap_err()
+        });
+        let response = status_error_response(&EnvoyError::BadRequest(error));
+        assert_eq!(ECCStatus::from(response.state), ECCStatus::Unreachable);
+        assert_eq!(response.error_code, 0);
+    }
+
+    #[test]
+    fn unreadable_status_is_an_error() {
+        for error in [
+            parse_status_text("<a><b></a>").unwrap_err(),
+            EnvoyError::FailedXMLConvert,
+        ] {
+            let response = status_error_response(&error);
+            assert_eq!(ECCStatus::from(response.state), ECCStatus::ErrorStat);
+            assert_eq!(response.error_code, STATUS_UNREADABLE_ERROR_CODE);
+            assert_eq!(response.error_message, error.to_string());
+        }
+    }
+}
@@ -9,6 +9,12 @@ const ECC_READY_STATUS: &str = "Ready";
 const ECC_RUNNING_STATUS: &str = "Running";
 const ECC_INCONSISTENT_STATUS: &str = "Inconsistent";
 const ECC_ERROR_STATUS: &str = "Error";
+const ECC_NOT_MONITORED_STATUS: &str = "Not Monitored";
+const ECC_UNREACHABLE_STATUS: &str = "Unreachable";
+
+/// State value used by the envoys when the ECC server could not be reached. This is not
+/// a state reported by ECC itself.
+const ECC_UNREACHABLE_STATE: i32 = -2;
 
 const ECC_DESCRIBE_OP: &str = "Describe";
 const ECC_PREPARE_OP: &str = "Prepare";
@@ -32,6 +38,8 @@ pub enum ECCStatus {
     Running,
     ErrorStat,
     Inconsistent,
+    NotMonitored,
+    Unreachable,
 }
 
 impl std::fmt::Display for ECCStatus {
@@ -46,6 +54,8 @@ impl std::fmt::Display for ECCStatus {
             Self::Running => write!(f, "{ECC_RUNNING_STATUS}"),
             Self::ErrorStat => write!(f, "{ECC_ERROR_STATUS}"),
             Self::Inconsistent => write!(f, "{ECC_INCONSISTENT_STATUS}"),
+            Self::NotMonitored => write!(f, "{ECC_NOT_MONITORED_STATUS}"),
+            Self::Unreachable => write!(f, "{ECC_UNREACHABLE_STATUS}"),
         }
     }
 }
@@ -62,20 +72,25 @@ impl From<ECCStatus> for String {
             ECCStatus::Running => ECC_RUNNING_STATUS,
             ECCStatus::ErrorStat => ECC_ERROR_STATUS,
             ECCStatus::Inconsistent => ECC_INCONSISTENT_STATUS,
+            ECCStatus::NotMonitored => ECC_NOT_MONITORED_STATUS,
+            ECCStatus::Unreachable => ECC_UNREACHABLE_STATUS,
         })
     }
 }
 
+/// Matches the getECCServer state numbering (and the From<i32> conversion below).
+/// Inconsistent and NotMonitored are never reported by a server and map to the error value.
 impl From<ECCStatus> for i32 {
     fn from(value: ECCStatus) -> i32 {
         match value {
             ECCStatus::Offline => 0,
             ECCStatus::Idle => 1,
-            ECCStatus::Prepared => 2,
-            ECCStatus::Described => 3,
+            ECCStatus::Described => 2,
+            ECCStatus::Prepared => 3,
             ECCStatus::Ready => 4,
             ECCStatus::Running => 5,
             ECCStatus::Busy => 6,
+            ECCStatus::Unreachable => ECC_UNREACHABLE_STATE,
             _ => -1,
         }
     }
@@ -94,6 +109,8 @@ impl TryFrom<String> for ECCStatus {
             ECC_RUNNING_STATUS => Ok(Self::Running),
             ECC_ERROR_STATUS => Ok(Self::ErrorStat),
             ECC_INCONSISTENT_STATUS => Ok(Self::Inconsistent),
+            ECC_NOT_MONITORED_STATUS => Ok(Self::NotMonitored),
+            ECC_UNREACHABLE_STATUS => Ok(Self::Unreachable),
             _ => Err(Self::Error::BadString(value)),
         }
     }
@@ -109,12 +126,23 @@ impl From<i32> for ECCStatus {
             4 => ECCStatus::Ready,
             5 => ECCStatus::Running,
             6 => ECCStatus::Busy,
+            ECC_UNREACHABLE_STATE => ECCStatus::Unreachable,
             _ => ECCStatus::ErrorStat,
         }
     }
 }
 
 impl ECCStatus {
+    /// The states reported by a getECCServer, in the order they are progressed through
+    pub const SERVER_STATES: [ECCStatus; 6] = [
+        ECCStatus::Offline,
+        ECCStatus::Idle,
+        ECCStatus::Described,
+        ECCStatus::Prepared,
+        ECCStatus::Ready,
+        ECCStatus::Running,
+    ];
+
     /// Get the operation associated with progressing the system from this
     /// status
     pub fn get_forward_operation(&self) -> ECCOperation {
@@ -154,9 +182,27 @@ impl ECCStatus {
     }
 }
 
+/// The order in which the MuTaNT and the CoBos must perform a system wide operation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ModuleOrder {
+    Any,
+    MutantFirst,
+    CobosFirst,
+}
+
+impl std::fmt::Display for ModuleOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Any => write!(f, "Any order"),
+            Self::MutantFirst => write!(f, "MuTaNT first, then CoBos"),
+            Self::CobosFirst => write!(f, "CoBos first, then MuTaNT"),
+        }
+    }
+}
+
 /// An operation to be performed on
 /// a getECCServer. Can be converted to String.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ECCOperation {
     Describe,
     Prepare,
@@ -183,6 +229,44 @@ impl std::fmt::Display for ECCOperation {
     }
 }
 
+impl ECCOperation {
+    /// The operations which transition a getECCServer between states
+    pub const TRANSITIONS: [ECCOperation; 5] = [
+        ECCOperation::Describe,
+        ECCOperation::Prepare,
+        ECCOperation::Configure,
+        ECCOperation::Breakup,
+        ECCOperation::Undo,
+    ];
+
+    /// The order in which the modules must perform this operation
+    pub fn module_order(&self) -> ModuleOrder {
+        match self {
+            ECCOperation::Prepare => ModuleOrder::MutantFirst,
+            ECCOperation::Configure => ModuleOrder::CobosFirst,
+            _ => ModuleOrder::Any,
+        }
+    }
+
+    /// A short description of what the operation does
+    pub fn description(&self) -> &'static str {
+        match self {
+            ECCOperation::Describe => "Load the description of the hardware (describe config)",
+            ECCOperation::Prepare => {
+                "Prepare the hardware and the clock distribution (prepare config)"
+            }
+            ECCOperation::Configure => {
+                "Load the configuration and data links, ready to take data (configure config)"
+            }
+            ECCOperation::Start => "Start taking data",
+            ECCOperation::Stop => "Stop taking data",
+            ECCOperation::Breakup => "Undo the Configure, returning to Prepared",
+            ECCOperation::Undo => "Undo the Describe or Prepare, returning to the previous state",
+            ECCOperation::Invalid => "Not a valid operation",
+        }
+    }
+}
+
 impl TryFrom<String> for ECCOperation {
     type Error = ECCOperationError;
     fn try_from(value: String) -> Result<Self, Self::Error> {
@@ -214,3 +298,32 @@ impl From<ECCOperation> for String {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_integers_round_trip() {
+        for status in ECCStatus::SERVER_STATES
+            .into_iter()
+            .chain([ECCStatus::Busy, ECCStatus::Unreachable])
+        {
+            let value: i32 = status.clone().into();
+            assert_eq!(ECCStatus::from(value), status);
+        }
+    }
+
+    #[test]
+    fn statuses_without_an_integer_are_errors() {
+        for status in [
+            ECCStatus::ErrorStat,
+            ECCStatus::Inconsistent,
+            ECCStatus::NotMonitored,
+        ] {
+            let value: i32 = status.into();
+            assert_eq!(ECCStatus::from(value), ECCStatus::ErrorStat);
+        }
+        assert_eq!(ECCStatus::from(42), ECCStatus::ErrorStat);
+    }
+}
@@ -1,13 +1,89 @@
+use super::constants::{embassy_channel_capacity, CANCEL_CHANNEL_CAPACITY};
 use super::ecc_envoy::startup_ecc_envoys;
 use super::error::EmbassyError;
 use super::message::{EmbassyMessage, MessageKind};
+use super::network::NetworkMap;
+use super::runtime::{build_runtime, runtime_stats, RuntimeConfig, RuntimeStats};
 use super::surveyor_envoy::startup_surveyor_envoys;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::runtime::Runtime;
 use tokio::sync::broadcast;
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 
+/// Maximum number of messages read by a single poll. Any remaining messages are left,
+/// in order, in the channel for the next poll so that one frame can't be stalled by a backlog.
+const MAX_MESSAGES_PER_POLL: usize = 64;
+
+/// Counters describing how the embassy is keeping up with the envoys
+#[derive(Debug, Clone, Default)]
+pub struct PollStats {
+    /// Total number of times messages were left for a later poll
+    pub deferred_polls: u64,
+    /// Number of messages waiting after the last poll
+    pub backlog: usize,
+    /// Longest time taken to handle the messages of a single poll
+    pub worst_handle_time: Duration,
+}
+
+/// Called by the embassy whenever a message arrives from an envoy, i.e. to wake the UI
+#[derive(Clone)]
+pub struct ArrivalHook(Arc<dyn Fn() + Send + Sync>);
+
+impl ArrivalHook {
+    pub fn new(hook: impl Fn() + Send + Sync + 'static) -> Self {
+        Self(Arc::new(hook))
+    }
+}
+
+impl std::fmt::Debug for ArrivalHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ArrivalHook")
+    }
+}
+
+/// Forward the messages from the envoys to the embassy, calling the hook after each one.
+/// Stops once the envoys are gone or the embassy stops recieving.
+async fn relay_messages(
+    mut incoming: mpsc::Receiver<EmbassyMessage>,
+    outgoing: mpsc::Sender<EmbassyMessage>,
+    hook: ArrivalHook,
+) {
+    while let Some(message) = incoming.recv().await {
+        if outgoing.send(message).await.is_err() {
+            break;
+        }
+        (hook.0)();
+    }
+}
+
+/// The families of envoys which can be started by the embassy. Families which
+/// are not active are not spawned and are considered "not monitored".
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EnvoyFamilies {
+    pub ecc: bool,
+    pub surveyor: bool,
+}
+
+impl Default for EnvoyFamilies {
+    fn default() -> Self {
+        Self {
+            ecc: true,
+            surveyor: true,
+        }
+    }
+}
+
+impl EnvoyFamilies {
+    /// Is at least one family of envoys active
+    pub fn any(&self) -> bool {
+        self.ecc || self.surveyor
+    }
+}
+
 /// The embassy is the bridge between the async envoys and
 /// the synchronous UI-application. The embassy is essentially a
 /// container of channels used to communicate back-and-forth between these
@@ -19,57 +95,153 @@ pub struct Embassy {
     cancel: Option<broadcast::Sender<EmbassyMessage>>,
     handles: Option<Vec<JoinHandle<()>>>,
     runtime: Runtime,
+    runtime_config: RuntimeConfig,
     is_connected: bool,
+    families: EnvoyFamilies,
+    poll_stats: PollStats,
+    arrival_hook: Option<ArrivalHook>,
 }
 
 impl Embassy {
-    /// Create an Embassy with a tokio Runtime
-    pub fn new(rt: Runtime) -> Self {
+    /// Create an Embassy with a tokio Runtime built from the given config
+    pub fn new(rt: Runtime, runtime_config: RuntimeConfig) -> Self {
         Embassy {
             ecc_senders: HashMap::new(),
             envoy_reciever: None,
             cancel: None,
             handles: None,
             runtime: rt,
+            runtime_config,
             is_connected: false,
+            families: EnvoyFamilies::default(),
+            poll_stats: PollStats::default(),
+            arrival_hook: None,
         }
     }
 
-    /// Start the embassy service, connecting it to the various envoys
-    pub fn startup(&mut self, experiment: &str) {
-        let (envoy_tx, embassy_rx) = mpsc::channel::<EmbassyMessage>(33);
-        let (cancel_tx, _) = broadcast::channel::<EmbassyMessage>(10);
+    /// Rebuild the async runtime if the config differs from the one currently in use.
+    /// The runtime can only be replaced while the embassy is disconnected.
+    pub fn apply_runtime_config(&mut self, config: &RuntimeConfig) -> Result<(), EmbassyError> {
+        if *config == self.runtime_config {
+            return Ok(());
+        }
+        if self.is_connected {
+            return Err(EmbassyError::RuntimeInUse);
+        }
+        self.runtime = build_runtime(config)?;
+        self.runtime_config = config.clone();
+        tracing::info!(
+            "Rebuilt the async runtime with {} worker threads",
+            config.worker_threads
+        );
+        Ok(())
+    }
 
-        let (mut handles, ecc_switchboard) =
-            startup_ecc_envoys(&mut self.runtime, experiment, &envoy_tx, &cancel_tx);
-        let mut sur_handles = startup_surveyor_envoys(&mut self.runtime, &envoy_tx, &cancel_tx);
-        handles.append(&mut sur_handles);
-        self.ecc_senders = ecc_switchboard;
+    /// Get a snapshot of the async runtime statistics
+    pub fn runtime_stats(&self) -> RuntimeStats {
+        runtime_stats(&self.runtime)
+    }
+
+    /// Set the hook called whenever a message arrives. Takes effect at the next connect.
+    pub fn set_arrival_hook(&mut self, hook: ArrivalHook) {
+        self.arrival_hook = Some(hook);
+    }
+
+    /// Start the embassy service, connecting it to the requested families of envoys
+    /// for the given number of CoBos, polling status at the given interval. Fails without
+    /// spawning any envoys if a module is missing from the network map.
+    pub fn startup(
+        &mut self,
+        experiment: &str,
+        number_of_cobos: usize,
+        network: &NetworkMap,
+        families: &EnvoyFamilies,
+        poll_interval: Duration,
+    ) -> Result<(), EmbassyError> {
+        let cobo_addresses = network.cobo_addresses(number_of_cobos)?;
+        let (envoy_tx, envoy_rx) =
+            mpsc::channel::<EmbassyMessage>(embassy_channel_capacity(number_of_cobos));
+        let (cancel_tx, _) = broadcast::channel::<EmbassyMessage>(CANCEL_CHANNEL_CAPACITY);
+
+        let mut handles: Vec<JoinHandle<()>> = vec![];
+        let embassy_rx = match &self.arrival_hook {
+            Some(hook) => {
+                let (relay_tx, relay_rx) =
+                    mpsc::channel::<EmbassyMessage>(embassy_channel_capacity(number_of_cobos));
+                handles.push(
+                    self.runtime
+                        .spawn(relay_messages(envoy_rx, relay_tx, hook.clone())),
+                );
+                relay_rx
+            }
+            None => envoy_rx,
+        };
+        if families.ecc {
+            let (mut ecc_handles, ecc_switchboard) = startup_ecc_envoys(
+                &mut self.runtime,
+                experiment,
+                &cobo_addresses,
+                network,
+                poll_interval,
+                &envoy_tx,
+                &cancel_tx,
+            );
+            handles.append(&mut ecc_handles);
+            self.ecc_senders = ecc_switchboard;
+        }
+        if families.surveyor {
+            let mut sur_handles = startup_surveyor_envoys(
+                &mut self.runtime,
+                &cobo_addresses,
+                poll_interval,
+                &envoy_tx,
+                &cancel_tx,
+            );
+            handles.append(&mut sur_handles);
+        }
         self.envoy_reciever = Some(embassy_rx);
         self.cancel = Some(cancel_tx);
         self.is_connected = true;
+        self.families = *families;
         self.handles = Some(handles);
+        Ok(())
     }
 
-    /// Shutdown the Embassy and cancel any tasks
+    /// Shutdown the Embassy and cancel any tasks. The embassy stops accepting messages first,
+    /// then any messages still in flight are discarded, and finally the tasks are joined.
+    /// Closing the channel before joining ensures no envoy is left blocked on a full channel.
     pub fn shutdown(&mut self) -> Result<(), EmbassyError> {
+        self.is_connected = false;
         let cancel_message = EmbassyMessage::compose_cancel();
-        if let Some(tx) = &self.cancel {
+        if let Some(tx) = self.cancel.take() {
             tx.send(cancel_message)
                 .expect("Some how all of the envoys were already dead!");
         }
+        if let Some(mut rx) = self.envoy_reciever.take() {
+            rx.close();
+            let mut discarded = 0;
+            while rx.try_recv().is_ok() {
+                discarded += 1;
+            }
+            if discarded > 0 {
+                tracing::info!("Discarded {discarded} messages recieved during shutdown");
+            }
+        }
         if let Some(handles) = self.handles.take() {
             for handle in handles {
                 self.runtime.block_on(handle)?
             }
         }
-        self.is_connected = false;
+        self.ecc_senders.clear();
         Ok(())
     }
 
     /// Submit an EmbassyMessage. Currently only communicates with ECCEnvoys.
     pub fn submit_message(&mut self, message: EmbassyMessage) -> Result<(), EmbassyError> {
         if message.kind == MessageKind::ECCOperation {
+            if !self.families.ecc {
+                return Err(EmbassyError::InactiveFamily(message.kind));
+            }
             if let Some(sender) = self.ecc_senders.get_mut(&message.id) {
                 sender.blocking_send(message)?;
             }
@@ -77,11 +249,12 @@ impl Embassy {
         Ok(())
     }
 
-    /// Poll the Embassy to see if any messages were recieved from the envoys
+    /// Poll the Embassy to see if any messages were recieved from the envoys. At most
+    /// MAX_MESSAGES_PER_POLL messages are returned; the rest are deferred to the next poll.
     pub fn poll_messages(&mut self) -> Result<Vec<EmbassyMessage>, EmbassyError> {
         let mut messages: Vec<EmbassyMessage> = vec![];
         if let Some(rx) = &mut self.envoy_reciever {
-            loop {
+            while messages.len() < MAX_MESSAGES_PER_POLL {
                 match rx.try_recv() {
                     Ok(message) => messages.push(message),
                     Err(mpsc::error::TryRecvError::Empty) => break,
@@ -90,10 +263,26 @@ impl Embassy {
                     }
                 }
             }
+            self.poll_stats.backlog = rx.len();
+            if self.poll_stats.backlog > 0 {
+                self.poll_stats.deferred_polls += 1;
+            }
         }
         Ok(messages)
     }
 
+    /// Record how long it took to handle the messages of a poll
+    pub fn record_handle_time(&mut self, time: Duration) {
+        if time > self.poll_stats.worst_handle_time {
+            self.poll_stats.worst_handle_time = time;
+        }
+    }
+
+    /// Get the polling counters
+    pub fn poll_stats(&self) -> &PollStats {
+        &self.poll_stats
+    }
+
     /// Is the embassy connected to the envoys
     pub fn is_connected(&self) -> bool {
         self.is_connected
@@ -108,3 +297,242 @@ impl Embassy {
         }
     }
 }
+
+/// The far end of a loopback embassy: the operations submitted to each ECC module, and a channel
+/// on which the test plays the envoys
+#[cfg(test)]
+pub struct Loopback {
+    pub operations: Vec<mpsc::Receiver<EmbassyMessage>>,
+    pub envoys: mpsc::Sender<EmbassyMessage>,
+    pub cancelled: broadcast::Receiver<EmbassyMessage>,
+}
+
+#[cfg(test)]
+impl Loopback {
+    /// The operations submitted to each module since the last call, as (module id, operation)
+    pub fn take_operations(&mut self) -> Vec<(usize, String)> {
+        let mut operations = vec![];
+        for (id, rx) in self.operations.iter_mut().enumerate() {
+            while let Ok(message) = rx.try_recv() {
+                operations.push((id, message.operation));
+            }
+        }
+        operations
+    }
+}
+
+#[cfg(test)]
+impl Embassy {
+    /// A connected embassy with no envoys behind it, for the given number of ECC modules
+    pub fn loopback(number_of_modules: usize, capacity: usize) -> (Self, Loopback) {
+        let runtime = build_runtime(&RuntimeConfig::default()).unwrap();
+        let mut embassy = Self::new(runtime, RuntimeConfig::default());
+        let mut operations = vec![];
+        for id in 0..number_of_modules {
+            let (tx, rx) = mpsc::channel(capacity);
+            embassy.ecc_senders.insert(id, tx);
+            operations.push(rx);
+        }
+        let (envoys, rx) = mpsc::channel(capacity);
+        let (cancel, cancelled) = broadcast::channel(CANCEL_CHANNEL_CAPACITY);
+        embassy.envoy_reciever = Some(rx);
+        embassy.cancel = Some(cancel);
+        embassy.handles = Some(vec![]);
+        embassy.is_connected = true;
+        (
+            embassy,
+            Loopback {
+                operations,
+                envoys,
+                cancelled,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envoy::ecc_operation::ECCStatus;
+    use crate::envoy::testing::ecc_status;
+
+    #[test]
+    fn shutdown_discards_messages_in_flight() {
+        let (mut embassy, loopback) = Embassy::loopback(2, 8);
+        loopback
+            .envoys
+            .try_send(ecc_status(0, ECCStatus::Idle))
+            .unwrap();
+        loopback
+            .envoys
+            .try_send(ecc_status(1, ECCStatus::Idle))
+            .unwrap();
+        embassy.shutdown().unwrap();
+        assert!(!embassy.is_connected());
+        assert!(embassy.poll_messages().unwrap().is_empty());
+        assert!(loopback
+            .envoys
+            .try_send(ecc_status(0, ECCStatus::Idle))
+            .is_err());
+    }
+
+    #[test]
+    fn backlog_is_deferred_in_order() {
+        let total = MAX_MESSAGES_PER_POLL + 10;
+        let (mut embassy, loopback) = Embassy::loopback(1, total);
+        for id in 0..total {
+            loopback
+                .envoys
+                .try_send(ecc_status(id, ECCStatus::Idle))
+                .unwrap();
+        }
+        let first = embassy.poll_messages().unwrap();
+        assert_eq!(first.len(), MAX_MESSAGES_PER_POLL);
+        assert_eq!(embassy.poll_stats().backlog, 10);
+        assert_eq!(embassy.poll_stats().deferred_polls, 1);
+
+        let second = embassy.poll_messages().unwrap();
+        let ids: Vec<usize> = first.iter().chain(second.iter()).map(|m| m.id).collect();
+        assert_eq!(ids, (0..total).collect::<Vec<usize>>());
+        assert_eq!(embassy.poll_stats().backlog, 0);
+        assert_eq!(embassy.poll_stats().deferred_polls, 1);
+    }
+
+    #[test]
+    fn worst_handle_time_is_kept() {
+        let (mut embassy, _loopback) = Embassy::loopback(1, 1);
+        embassy.record_handle_time(Duration::from_millis(5));
+        embassy.record_handle_time(Duration::from_millis(2));
+        assert_eq!(
+            embassy.poll_stats().worst_handle_time,
+            Duration::from_millis(5)
+        );
+    }
+
+    #[test]
+    fn operations_are_dropped_after_shutdown() {
+        let (mut embassy, mut loopback) = Embassy::loopback(2, 8);
+        let prepare = || EmbassyMessage::compose_ecc_op(String::from("Prepare"), 1);
+        embassy.submit_message(prepare()).unwrap();
+        assert_eq!(
+            loopback.take_operations(),
+            vec![(1, String::from("Prepare"))]
+        );
+        embassy.shutdown().unwrap();
+        embassy.submit_message(prepare()).unwrap();
+        assert!(loopback.take_operations().is_empty());
+    }
+
+    #[test]
+    fn shutdown_cancels_before_joining() {
+        let (mut embassy, loopback) = Embassy::loopback(1, 1);
+        let mut cancel = loopback.cancelled.resubscribe();
+        let envoys = loopback.envoys.clone();
+        // An envoy blocked on the full channel, which only stops when cancelled
+        envoys.try_send(ecc_status(0, ECCStatus::Idle)).unwrap();
+        let handle = embassy.runtime.spawn(async move {
+            tokio::select! {
+                _ = cancel.recv() => (),
+                _ = envoys.send(ecc_status(0, ECCStatus::Ready)) => (),
+            }
+        });
+        embassy.handles = Some(vec![handle]);
+        embassy.shutdown().unwrap();
+        assert_eq!(embassy.number_of_tasks(), 0);
+    }
+
+    /// Read a stress test parameter from the environment
+    fn stress_parameter(name: &str, default: u64) -> u64 {
+        std::env::var(name)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default)
+    }
+
+    /// Stress harness for the message path. Fake envoys on their own threads send statuses at a
+    /// fixed rate into a channel sized like the real one, while the test polls and handles them
+    /// at the UI frame rate. Prints a report of the backpressure and the poll and handling times.
+    /// Run with `cargo test stress_message_path -- --ignored --nocapture`, and set
+    /// ENVOY_STRESS_COBOS, ENVOY_STRESS_RATE_HZ, and ENVOY_STRESS_SEC to change the load.
+    #[test]
+    #[ignore]
+    fn stress_message_path() {
+        use crate::envoy::testing::status_manager;
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        let cobos = stress_parameter("ENVOY_STRESS_COBOS", 44) as usize;
+        let rate = stress_parameter("ENVOY_STRESS_RATE_HZ", 20);
+        let duration = Duration::from_secs(stress_parameter("ENVOY_STRESS_SEC", 5));
+        let frame = Duration::from_millis(16);
+
+        let (mut embassy, loopback) = Embassy::loopback(cobos + 1, embassy_channel_capacity(cobos));
+        let mut status = status_manager(&vec![ECCStatus::Running; cobos + 1]);
+        let blocked = Arc::new(AtomicU64::new(0));
+        let worst_block = Arc::new(AtomicU64::new(0));
+        let envoys: Vec<std::thread::JoinHandle<u64>> = (0..=cobos)
+            .map(|id| {
+                let tx = loopback.envoys.clone();
+                let blocked = blocked.clone();
+                let worst_block = worst_block.clone();
+                std::thread::spawn(move || {
+                    let interval = Duration::from_secs_f64(1.0 / rate as f64);
+                    let start = std::time::Instant::now();
+                    let mut sent = 0;
+                    while start.elapsed() < duration {
+                        let message = ecc_status(id, ECCStatus::Running);
+                        if let Err(mpsc::error::TrySendError::Full(message)) = tx.try_send(message)
+                        {
+                            // Real envoys await the send, so they are held up here
+                            blocked.fetch_add(1, Ordering::Relaxed);
+                            let waited = std::time::Instant::now();
+                            if tx.blocking_send(message).is_err() {
+                                break;
+                            }
+                            worst_block
+                                .fetch_max(waited.elapsed().as_micros() as u64, Ordering::Relaxed);
+                        }
+                        sent += 1;
+                        std::thread::sleep(interval);
+                    }
+                    sent
+                })
+            })
+            .collect();
+
+        let mut received = 0;
+        let mut worst_poll = Duration::ZERO;
+        let mut worst_backlog = 0;
+        let start = std::time::Instant::now();
+        while start.elapsed() < duration || envoys.iter().any(|envoy| !envoy.is_finished()) {
+            let polled = std::time::Instant::now();
+            let messages = embassy.poll_messages().unwrap();
+            worst_poll = worst_poll.max(polled.elapsed());
+            worst_backlog = worst_backlog.max(embassy.poll_stats().backlog);
+            let handled = std::time::Instant::now();
+            status.handle_messages(&messages).unwrap();
+            embassy.record_handle_time(handled.elapsed());
+            received += messages.len() as u64;
+            std::thread::sleep(frame);
+        }
+        let sent: u64 = envoys.into_iter().map(|envoy| envoy.join().unwrap()).sum();
+        received += embassy.poll_messages().unwrap().len() as u64;
+
+        let stats = embassy.poll_stats();
+        println!(
+            "Embassy stress: {} modules at {rate} Hz for {duration:?}",
+            cobos + 1
+        );
+        println!("  channel capacity:    {}", embassy_channel_capacity(cobos));
+        println!("  sent / received:     {sent} / {received}");
+        println!(
+            "  blocked sends:       {} (worst {} us)",
+            blocked.load(Ordering::Relaxed),
+            worst_block.load(Ordering::Relaxed)
+        );
+        println!("  deferred polls:      {}", stats.deferred_polls);
+        println!("  worst backlog:       {worst_backlog}");
+        println!("  worst poll time:     {worst_poll:?}");
+        println!("  worst handling time: {:?}", stats.worst_handle_time);
+        assert_eq!(sent, received, "messages were lost");
+    }
+}
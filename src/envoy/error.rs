@@ -1,180 +1,82 @@
+//! Error types for the envoy system. Each operator-visible failure carries a short, stable
+//! code (i.e. ENV-001) in its message so that it can be referenced in operator documentation.
+//! Codes are never reused; if a variant is removed its code is retired.
 use super::{
-    ecc_operation::ECCOperation,
+    ecc_operation::{ECCOperation, ECCStatus},
     message::{EmbassyMessage, MessageKind},
+    transition::{ReconfigurePhase, RunStartPhase},
 };
+use thiserror::Error;
 use tokio::sync::mpsc::error::SendError;
 
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum ECCOperationError {
+    #[error("[OPR-001] Could not convert string {0} to ECCOperation!")]
     BadString(String),
 }
 
-impl std::fmt::Display for ECCOperationError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::BadString(s) => write!(f, "Could not convert string {s} to ECCOperation!"),
-        }
-    }
-}
-
-impl std::error::Error for ECCOperationError {}
-
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum ECCStatusError {
+    #[error("[STA-001] Could not convert string {0} to ECCStatus!")]
     BadString(String),
 }
 
-impl std::fmt::Display for ECCStatusError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::BadString(s) => write!(f, "Could not convert string {s} to ECCStatus!"),
-        }
-    }
-}
-
-impl std::error::Error for ECCStatusError {}
-
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum EnvoyError {
-    BadRequest(reqwest::Error),
-    SendError(SendError<EmbassyMessage>),
-    InvalidStatus(ECCStatusError),
-    BadOperation(ECCOperationError),
-    FailedMessageParse(serde_yaml::Error),
-    InvalidStringToInt(std::num::ParseIntError),
-    InvalidStringToFloat(std::num::ParseFloatError),
-    FailedXMLParse(quick_xml::Error),
-    FailedXMLUtf8(std::string::FromUtf8Error),
+    #[error("[ENV-001] Envoy recieved an error while making a request: {0}")]
+    BadRequest(#[from] reqwest::Error),
+    #[error("[ENV-002] Envoy failed to send a message: {0}")]
+    SendError(#[from] SendError<EmbassyMessage>),
+    #[error("[ENV-004] Envoy recieved operation error: {0}")]
+    BadOperation(#[from] ECCOperationError),
+    #[error("[ENV-005] Envoy failed to (de)serialize a message as YAML: {0}")]
+    FailedMessageParse(#[from] serde_yaml::Error),
+    #[error("[ENV-006] Envoy failed to parse string to integer: {0}")]
+    InvalidStringToInt(#[from] std::num::ParseIntError),
+    #[error("[ENV-008] Envoy failed to parse XML body: {0}")]
+    FailedXMLParse(#[from] quick_xml::Error),
+    #[error("[ENV-009] Envoy failed to convert XML to String: {0}")]
+    FailedXMLUtf8(#[from] std::string::FromUtf8Error),
+    #[error("[ENV-010] Envoy failed to convert XML data!")]
     FailedXMLConvert,
 }
 
-impl From<reqwest::Error> for EnvoyError {
-    fn from(value: reqwest::Error) -> Self {
-        Self::BadRequest(value)
-    }
-}
-
-impl From<SendError<EmbassyMessage>> for EnvoyError {
-    fn from(value: SendError<EmbassyMessage>) -> Self {
-        Self::SendError(value)
-    }
-}
-
-impl From<ECCStatusError> for EnvoyError {
-    fn from(value: ECCStatusError) -> Self {
-        Self::InvalidStatus(value)
-    }
-}
-
-impl From<ECCOperationError> for EnvoyError {
-    fn from(value: ECCOperationError) -> Self {
-        Self::BadOperation(value)
-    }
-}
-
-impl From<serde_yaml::Error> for EnvoyError {
-    fn from(value: serde_yaml::Error) -> Self {
-        Self::FailedMessageParse(value)
-    }
-}
-
-impl From<std::num::ParseIntError> for EnvoyError {
-    fn from(value: std::num::ParseIntError) -> Self {
-        Self::InvalidStringToInt(value)
-    }
-}
-
-impl From<std::num::ParseFloatError> for EnvoyError {
-    fn from(value: std::num::ParseFloatError) -> Self {
-        Self::InvalidStringToFloat(value)
-    }
-}
-
-impl From<quick_xml::Error> for EnvoyError {
-    fn from(value: quick_xml::Error) -> Self {
-        Self::FailedXMLParse(value)
-    }
-}
-
-impl From<std::string::FromUtf8Error> for EnvoyError {
-    fn from(value: std::string::FromUtf8Error) -> Self {
-        Self::FailedXMLUtf8(value)
-    }
-}
-
-impl std::fmt::Display for EnvoyError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::BadRequest(e) => {
-                write!(f, "Envoy recieved an error while making a request: {e}")
-            }
-            Self::FailedMessageParse(e) => {
-                write!(f, "Envoy failed to parse a message to yaml: {e}")
-            }
-            Self::BadOperation(e) => write!(f, "Envoy recieved operation error: {e}"),
-            Self::InvalidStatus(e) => write!(f, "Envoy recieved status error: {e}"),
-            Self::SendError(e) => write!(f, "Envoy failed to send a message: {e}"),
-            Self::InvalidStringToInt(e) => {
-                write!(f, "Envoy failed to parse string to integer: {e}")
-            }
-            Self::InvalidStringToFloat(e) => {
-                write!(f, "Envoy failed to parse string to float: {e}")
-            }
-            Self::FailedXMLParse(e) => write!(f, "Envoy failed to parse XML body: {e}"),
-            Self::FailedXMLUtf8(e) => write!(f, "Envoy failed to convert XML to String: {e}"),
-            Self::FailedXMLConvert => write!(f, "Envoy failed to convert XML data!"),
-        }
-    }
-}
-
-impl std::error::Error for EnvoyError {}
-
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum EmbassyError {
-    FailedSend(SendError<EmbassyMessage>),
+    #[error("[EMB-001] Embassy had an error sending the following message: {0}")]
+    FailedSend(#[from] SendError<EmbassyMessage>),
+    #[error("[EMB-002] Embassy expected {0} message, recieved {1} message!")]
     InvalidKind(MessageKind, MessageKind),
-    FailedParse(serde_yaml::Error),
+    #[error("[EMB-003] Embassy had an error parsing a YAML message: {0}")]
+    FailedParse(#[from] serde_yaml::Error),
+    #[error("[EMB-004] Embassy communication lines were disconnected!")]
     FailedRecieve,
-    FailedJoin(tokio::task::JoinError),
+    #[error("[EMB-005] Embassy failed to join a task: {0}")]
+    FailedJoin(#[from] tokio::task::JoinError),
+    #[error("[EMB-006] Attempted invalid transition: {0}")]
     InvalidTransition(ECCOperation),
+    #[error("[EMB-007] Embassy cannot deliver a {0} message, those envoys were not connected!")]
+    InactiveFamily(MessageKind),
+    #[error("[EMB-008] Embassy failed to build the async runtime: {0}")]
+    FailedRuntime(#[from] std::io::Error),
+    #[error("[EMB-009] Embassy cannot rebuild the async runtime while connected!")]
+    RuntimeInUse,
+    #[error("[EMB-010] No network address was configured for module {0}!")]
+    MissingAddress(usize),
+    #[error("[EMB-011] Run start failed while {0}, module {1} reported: {2}")]
+    RunStartFailed(RunStartPhase, usize, String),
+    #[error("[EMB-012] Module {0} cannot be progressed from {1} to {2}!")]
+    UnreachableTarget(usize, ECCStatus, ECCStatus),
+    #[error("[EMB-013] The MuTaNT could not be stopped: {0}")]
+    MutantStopFailed(String),
+    #[error("[EMB-014] The MuTaNT did not stop within {0} s!")]
+    MutantStopTimedOut(u64),
+    #[error("[EMB-015] Re-configuring failed while {0}, module {1} reported: {2}")]
+    ReconfigureFailed(ReconfigurePhase, usize, String),
+    #[error("[EMB-016] Re-configuring timed out after {1} s while {0}!")]
+    ReconfigureTimedOut(ReconfigurePhase, u64),
+    #[error("[EMB-017] Progressing the system failed in stage {0}, module {1} reported: {2}")]
+    ProgressFailed(usize, usize, String),
+    #[error("[EMB-018] Progressing the system stalled in stage {0}, no module changed status for {1} s!")]
+    ProgressStalled(usize, u64),
 }
-
-impl From<SendError<EmbassyMessage>> for EmbassyError {
-    fn from(value: SendError<EmbassyMessage>) -> Self {
-        Self::FailedSend(value)
-    }
-}
-
-impl From<serde_yaml::Error> for EmbassyError {
-    fn from(value: serde_yaml::Error) -> Self {
-        Self::FailedParse(value)
-    }
-}
-
-impl From<tokio::task::JoinError> for EmbassyError {
-    fn from(value: tokio::task::JoinError) -> Self {
-        Self::FailedJoin(value)
-    }
-}
-
-impl std::fmt::Display for EmbassyError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::InvalidKind(expected, recieved) => write!(
-                f,
-                "Embassy expected {expected} message, recieved {recieved} message!"
-            ),
-            Self::FailedSend(e) => {
-                write!(f, "Embassy had an error sending the following message: {e}")
-            }
-            Self::FailedParse(e) => write!(f, "Embassy had an error parsing a message: {e}"),
-            Self::FailedRecieve => {
-                write!(f, "Embassy communication lines were disconnected!")
-            }
-            Self::FailedJoin(e) => write!(f, "Embassy failed to join a task: {e}"),
-            Self::InvalidTransition(op) => write!(f, "Attempted invalid transition: {op}"),
-        }
-    }
-}
-
-impl std::error::Error for EmbassyError {}
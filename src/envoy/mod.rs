@@ -1,11 +1,17 @@
 //! Envoys and embassy code
+pub mod asad_summary;
 pub mod constants;
 pub mod ecc_envoy;
 pub mod ecc_operation;
 pub mod embassy;
 pub mod error;
 pub mod message;
+pub mod network;
+pub mod reconcile;
+pub mod runtime;
 pub mod status_manager;
 pub mod surveyor_envoy;
 pub mod surveyor_status;
+#[cfg(test)]
+pub mod testing;
 pub mod transition;
@@ -0,0 +1,96 @@
+use super::constants::{ADDRESS_START, DEFAULT_NUMBER_OF_COBOS};
+use super::error::EmbassyError;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The transport a module uses to send data to its DataRouter and DataExporter. Only
+/// these types are accepted by the GET DataRouter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum DataLinkProtocol {
+    #[default]
+    #[serde(rename = "TCP")]
+    Tcp,
+    #[serde(rename = "ZBUF")]
+    Zbuf,
+    #[serde(rename = "FDT")]
+    Fdt,
+}
+
+impl std::fmt::Display for DataLinkProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp => write!(f, "TCP"),
+            Self::Zbuf => write!(f, "ZBUF"),
+            Self::Fdt => write!(f, "FDT"),
+        }
+    }
+}
+
+/// The network addresses (IP or hostname) of the AT-TPC modules. CoBos are
+/// mapped by module id, the MuTaNT has its own entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkMap {
+    pub mutant: String,
+    pub cobos: BTreeMap<usize, String>,
+    /// The data link protocol of the MuTaNT, and of any CoBo without its own
+    #[serde(default)]
+    pub protocol: DataLinkProtocol,
+    /// Data link protocols of CoBos which differ from the default, by module id
+    #[serde(default)]
+    pub cobo_protocols: BTreeMap<usize, DataLinkProtocol>,
+}
+
+impl Default for NetworkMap {
+    fn default() -> Self {
+        let cobos = (0..DEFAULT_NUMBER_OF_COBOS)
+            .map(|id| (id, Self::default_cobo_address(id)))
+            .collect();
+        Self {
+            mutant: format!("{ADDRESS_START}.1"),
+            cobos,
+            protocol: DataLinkProtocol::default(),
+            cobo_protocols: BTreeMap::new(),
+        }
+    }
+}
+
+impl NetworkMap {
+    /// The address of a CoBo on the standard AT-TPC subnet
+    pub fn default_cobo_address(id: usize) -> String {
+        format!("{ADDRESS_START}.{}", 60 + id)
+    }
+
+    /// Make sure the first number_of_cobos CoBos have an entry, using the standard
+    /// subnet address for any which are missing
+    pub fn ensure_cobos(&mut self, number_of_cobos: usize) {
+        for id in 0..number_of_cobos {
+            self.cobos
+                .entry(id)
+                .or_insert_with(|| Self::default_cobo_address(id));
+        }
+    }
+
+    /// Get the data link protocol of a CoBo
+    pub fn cobo_protocol(&self, id: usize) -> DataLinkProtocol {
+        self.cobo_protocols
+            .get(&id)
+            .copied()
+            .unwrap_or(self.protocol)
+    }
+
+    /// Get the address of a CoBo. Fails if the CoBo is not in the map.
+    pub fn cobo_address(&self, id: usize) -> Result<&str, EmbassyError> {
+        self.cobos
+            .get(&id)
+            .map(|address| address.as_str())
+            .ok_or(EmbassyError::MissingAddress(id))
+    }
+
+    /// Get the addresses of the first number_of_cobos CoBos, ordered by module id.
+    /// Fails if any of the CoBos are not in the map.
+    pub fn cobo_addresses(&self, number_of_cobos: usize) -> Result<Vec<String>, EmbassyError> {
+        (0..number_of_cobos)
+            .map(|id| self.cobo_address(id).map(String::from))
+            .collect()
+    }
+}
@@ -0,0 +1,239 @@
+//! Recognition of module state combinations the app never creates itself, i.e. when someone
+//! starts or stops modules from a console on the ECC machine, and the actions which are safe
+//! to take from them.
+use super::ecc_operation::{ECCOperation, ECCStatus};
+use super::embassy::Embassy;
+use super::error::EmbassyError;
+use super::message::EmbassyMessage;
+use super::status_manager::StatusManager;
+
+/// A recognized state combination which was induced outside of the app
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExternalState {
+    /// The MuTaNT is Running but the CoBos were never started
+    MutantRunningCobosReady,
+    /// All of the CoBos are Running but the MuTaNT was never started
+    CobosRunningMutantReady,
+    /// Some of the CoBos are Running and the rest are Ready
+    CobosPartiallyRunning,
+}
+
+/// An action which is safe to take from an externally induced state
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SafeAction {
+    /// Stop the MuTaNT, returning the system to Ready
+    StopMutant,
+    /// Start the CoBos which are Ready
+    StartCobos,
+    /// Stop every Running module, the MuTaNT first
+    FullStop,
+}
+
+impl std::fmt::Display for SafeAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::StopMutant => write!(f, "Stop MuTaNT"),
+            Self::StartCobos => write!(f, "Start CoBos"),
+            Self::FullStop => write!(f, "Full Stop"),
+        }
+    }
+}
+
+impl ExternalState {
+    /// Explain what the system looks like to the operator
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::MutantRunningCobosReady => "The MuTaNT is Running but the CoBos are Ready. It was probably started from the ECC console. No data is being taken, and the run is not known to the envoy.",
+            Self::CobosRunningMutantReady => "The CoBos are Running but the MuTaNT is Ready. They were probably started from the ECC console. No triggers are being sent, and the run is not known to the envoy.",
+            Self::CobosPartiallyRunning => "Only some of the CoBos are Running. They were probably started or stopped from the ECC console.",
+        }
+    }
+
+    /// The actions which are safe to take from this state
+    pub fn safe_actions(&self) -> &'static [SafeAction] {
+        match self {
+            Self::MutantRunningCobosReady => &[SafeAction::StopMutant, SafeAction::StartCobos],
+            Self::CobosRunningMutantReady => &[SafeAction::FullStop],
+            Self::CobosPartiallyRunning => &[SafeAction::StartCobos, SafeAction::FullStop],
+        }
+    }
+}
+
+/// Recognize an externally induced state from the statuses of the MuTaNT and the CoBos
+pub fn classify_external_state(mutant: ECCStatus, cobos: &[ECCStatus]) -> Option<ExternalState> {
+    if cobos.is_empty() {
+        return None;
+    }
+    let running = cobos
+        .iter()
+        .filter(|s| matches!(s, ECCStatus::Running))
+        .count();
+    let ready = cobos
+        .iter()
+        .filter(|s| matches!(s, ECCStatus::Ready))
+        .count();
+    if running + ready != cobos.len() {
+        return None;
+    }
+    match mutant {
+        ECCStatus::Running if ready == cobos.len() => Some(ExternalState::MutantRunningCobosReady),
+        ECCStatus::Ready if running == cobos.len() => Some(ExternalState::CobosRunningMutantReady),
+        ECCStatus::Running | ECCStatus::Ready if running > 0 && ready > 0 => {
+            Some(ExternalState::CobosPartiallyRunning)
+        }
+        _ => None,
+    }
+}
+
+/// Recognize an externally induced state of the system. Only meaningful when the app is not
+/// itself starting or stopping a run, as those pass through the same combinations. CoBos
+/// waived by the quorum policy (now, or in the given list, i.e. when the run started) are
+/// expected to disagree, so only the other modules are compared.
+pub fn detect_external_state(status: &StatusManager, waived: &[usize]) -> Option<ExternalState> {
+    if !status.get_monitored_families().ecc || status.is_any_held() {
+        return None;
+    }
+    let waived_now = status.waived_cobos();
+    let cobos: Vec<ECCStatus> = status
+        .cobo_ids()
+        .into_iter()
+        .filter(|id| !waived.contains(id) && !waived_now.contains(id))
+        .map(|id| status.get_ecc_status(id))
+        .collect();
+    classify_external_state(status.get_ecc_status(status.mutant_id()), &cobos)
+}
+
+/// Submit an operation to a module, recording it so that the response is tracked
+fn submit(
+    embassy: &mut Embassy,
+    status: &mut StatusManager,
+    id: usize,
+    operation: ECCOperation,
+) -> Result<(), EmbassyError> {
+    status.record_operation(id, operation.clone());
+    embassy.submit_message(EmbassyMessage::compose_ecc_op(operation.into(), id))
+}
+
+/// Take a safe action. Only the modules in the matching state are operated on.
+pub fn take_safe_action(
+    embassy: &mut Embassy,
+    status: &mut StatusManager,
+    action: SafeAction,
+) -> Result<(), EmbassyError> {
+    let mutant = status.mutant_id();
+    let is_running =
+        |status: &StatusManager, id: usize| matches!(status.get_ecc_status(id), ECCStatus::Running);
+    match action {
+        SafeAction::StopMutant => submit(embassy, status, mutant, ECCOperation::Stop)?,
+        SafeAction::StartCobos => {
+            for id in status.cobo_ids() {
+                if matches!(status.get_ecc_status(id), ECCStatus::Ready) {
+                    submit(embassy, status, id, ECCOperation::Start)?;
+                }
+            }
+        }
+        SafeAction::FullStop => {
+            if is_running(status, mutant) {
+                submit(embassy, status, mutant, ECCOperation::Stop)?;
+            }
+            for id in status.cobo_ids() {
+                if is_running(status, id) {
+                    submit(embassy, status, id, ECCOperation::Stop)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envoy::testing::status_manager;
+    use ECCStatus::{Idle, Ready, Running};
+
+    fn op(operation: ECCOperation) -> String {
+        operation.into()
+    }
+
+    #[test]
+    fn external_states_are_classified() {
+        assert_eq!(
+            classify_external_state(Running, &[Ready, Ready]),
+            Some(ExternalState::MutantRunningCobosReady)
+        );
+        assert_eq!(
+            classify_external_state(Ready, &[Running, Running]),
+            Some(ExternalState::CobosRunningMutantReady)
+        );
+        assert_eq!(
+            classify_external_state(Ready, &[Running, Ready]),
+            Some(ExternalState::CobosPartiallyRunning)
+        );
+        assert_eq!(
+            classify_external_state(Running, &[Ready, Running]),
+            Some(ExternalState::CobosPartiallyRunning)
+        );
+    }
+
+    #[test]
+    fn normal_states_are_not_external() {
+        assert_eq!(classify_external_state(Ready, &[Ready, Ready]), None);
+        assert_eq!(classify_external_state(Running, &[Running, Running]), None);
+        assert_eq!(classify_external_state(Running, &[Ready, Idle]), None);
+        assert_eq!(classify_external_state(Running, &[]), None);
+    }
+
+    #[test]
+    fn detection_uses_the_module_statuses() {
+        let status = status_manager(&[Ready, Ready, Running]);
+        assert_eq!(
+            detect_external_state(&status, &[]),
+            Some(ExternalState::MutantRunningCobosReady)
+        );
+    }
+
+    #[test]
+    fn full_stop_stops_every_running_module() {
+        let (mut embassy, mut loopback) = Embassy::loopback(4, 8);
+        let mut status = status_manager(&[Running, Ready, Running, Running]);
+        take_safe_action(&mut embassy, &mut status, SafeAction::FullStop).unwrap();
+        assert_eq!(
+            loopback.take_operations(),
+            vec![
+                (0, op(ECCOperation::Stop)),
+                (2, op(ECCOperation::Stop)),
+                (3, op(ECCOperation::Stop)),
+            ]
+        );
+        assert_eq!(status.get_last_operation(1), None);
+    }
+
+    #[test]
+    fn start_cobos_only_starts_the_ready_cobos() {
+        let (mut embassy, mut loopback) = Embassy::loopback(3, 8);
+        let mut status = status_manager(&[Running, Ready, Ready]);
+        take_safe_action(&mut embassy, &mut status, SafeAction::StartCobos).unwrap();
+        assert_eq!(
+            loopback.take_operations(),
+            vec![(1, op(ECCOperation::Start))]
+        );
+    }
+
+    #[test]
+    fn cobos_waived_at_the_run_start_are_skipped() {
+        use crate::envoy::status_manager::{ReadinessMode, ReadinessPolicy};
+        let mut status = status_manager(&[Running, Running, Idle, Ready]);
+        status.set_readiness_policy(ReadinessPolicy {
+            mode: ReadinessMode::Quorum,
+            quorum_cobos: 2,
+        });
+        // The MuTaNT was stopped from the console during a run which waived CoBo 2
+        assert_eq!(
+            detect_external_state(&status, &[2]),
+            Some(ExternalState::CobosRunningMutantReady)
+        );
+        // Without the waiver the straggler hides the drift
+        assert_eq!(detect_external_state(&status, &[]), None);
+    }
+}
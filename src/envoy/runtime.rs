@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+use tokio::runtime::{Builder, Runtime};
+
+const DEFAULT_WORKER_THREADS: usize = 5;
+const DEFAULT_THREAD_NAME: &str = "envoy-worker";
+
+/// Configuration of the tokio runtime which drives the envoys
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuntimeConfig {
+    pub worker_threads: usize,
+    pub thread_name: String,
+}
+
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self {
+            worker_threads: DEFAULT_WORKER_THREADS,
+            thread_name: String::from(DEFAULT_THREAD_NAME),
+        }
+    }
+}
+
+/// A snapshot of some basic tokio runtime statistics
+#[derive(Debug, Clone, Default)]
+pub struct RuntimeStats {
+    pub workers: usize,
+    pub alive_tasks: usize,
+    pub injection_queue_depth: usize,
+}
+
+/// Build the multi-threaded tokio runtime used by the envoys
+pub fn build_runtime(config: &RuntimeConfig) -> Result<Runtime, std::io::Error> {
+    Builder::new_multi_thread()
+        .worker_threads(config.worker_threads.max(1))
+        .thread_name(config.thread_name.clone())
+        .enable_time()
+        .enable_io()
+        .build()
+}
+
+/// Take a snapshot of the statistics of a runtime
+pub fn runtime_stats(runtime: &Runtime) -> RuntimeStats {
+    let metrics = runtime.metrics();
+    RuntimeStats {
+        workers: metrics.num_workers(),
+        alive_tasks: metrics.num_alive_tasks(),
+        injection_queue_depth: metrics.global_queue_depth(),
+    }
+}
@@ -1,10 +1,122 @@
-use crate::envoy::constants::{MUTANT_ID, NUMBER_OF_MODULES};
+use crate::envoy::asad_summary::AsAdSummary;
+use crate::envoy::constants::DEFAULT_NUMBER_OF_COBOS;
 use crate::envoy::ecc_envoy::{ECCOperationResponse, ECCStatusResponse};
-use crate::envoy::ecc_operation::ECCStatus;
+use crate::envoy::ecc_operation::{ECCOperation, ECCStatus, ModuleOrder};
+use crate::envoy::embassy::EnvoyFamilies;
 use crate::envoy::error::EmbassyError;
 use crate::envoy::message::{EmbassyMessage, MessageKind};
 use crate::envoy::surveyor_envoy::SurveyorResponse;
-use crate::envoy::surveyor_status::SurveyorStatus;
+use crate::envoy::surveyor_status::{DiskHealth, DiskHealthState, SurveyorStatus};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// The most recent operation response from an ECCEnvoy and when it was recieved
+#[derive(Debug, Clone)]
+pub struct ECCOperationRecord {
+    pub response: ECCOperationResponse,
+    pub time: Instant,
+}
+
+impl ECCOperationRecord {
+    /// Did the operation fail
+    pub fn is_error(&self) -> bool {
+        self.response.error_code != 0
+    }
+}
+
+/// An error reported by an ECCEnvoy, from either an operation or a status query
+#[derive(Debug, Clone)]
+pub struct ECCErrorReport {
+    pub id: usize,
+    /// The ECC error code, zero if the error did not come from the server
+    pub code: i32,
+    pub message: String,
+    pub is_operation: bool,
+}
+
+/// The ECC status of every module at a point in time
+#[derive(Debug, Clone)]
+pub struct ECCSnapshot {
+    pub statuses: Vec<ECCStatus>,
+    pub time: Instant,
+}
+
+/// Counts of the messages received from an envoy, for the diagnostics
+#[derive(Debug, Clone, Default)]
+pub struct EnvoyMessageStats {
+    pub messages: u64,
+    pub last_response: Option<Instant>,
+    /// The number of error responses since the last good one
+    pub consecutive_failures: u32,
+}
+
+impl EnvoyMessageStats {
+    /// Count a response from the envoy
+    fn record(&mut self, failed: bool) {
+        self.messages += 1;
+        self.last_response = Some(Instant::now());
+        if failed {
+            self.consecutive_failures += 1;
+        } else {
+            self.consecutive_failures = 0;
+        }
+    }
+}
+
+/// How many CoBos must agree with the MuTaNT for the system to have a status
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum ReadinessMode {
+    /// Every module must agree
+    #[default]
+    Strict,
+    /// The MuTaNT and a quorum of the CoBos must agree. The other CoBos are waived.
+    Quorum,
+}
+
+impl std::fmt::Display for ReadinessMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Strict => write!(f, "Strict"),
+            Self::Quorum => write!(f, "Quorum"),
+        }
+    }
+}
+
+/// The readiness policy, for commissioning large systems where a few CoBos may lag behind
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ReadinessPolicy {
+    pub mode: ReadinessMode,
+    /// The number of CoBos which make a quorum
+    pub quorum_cobos: usize,
+}
+
+impl ReadinessPolicy {
+    /// The policy with the quorum limited to 1..=number_of_cobos
+    pub fn clamped(self, number_of_cobos: usize) -> Self {
+        Self {
+            quorum_cobos: self.quorum_cobos.clamp(1, number_of_cobos.max(1)),
+            ..self
+        }
+    }
+}
+
+impl Default for ReadinessPolicy {
+    fn default() -> Self {
+        Self {
+            mode: ReadinessMode::Strict,
+            quorum_cobos: DEFAULT_NUMBER_OF_COBOS - 1,
+        }
+    }
+}
+
+/// A change in whether the control network appears to be lost, i.e. whether every module
+/// stopped reporting status at once
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NetworkChange {
+    Lost,
+    Restored,
+}
 
 /// Structure used to manage the status of all of the envoys. We need a centralized location
 /// because we also want to express the status of the entire system, not just the individuals.
@@ -14,24 +126,64 @@ use crate::envoy::surveyor_status::SurveyorStatus;
 pub struct StatusManager {
     ecc_status: Vec<ECCStatusResponse>,
     surveyor_status: Vec<SurveyorResponse>,
+    last_disk_health: Vec<Option<DiskHealthState>>,
+    disk_health_changes: Vec<(usize, DiskHealth)>,
     ecc_holds: Vec<bool>,
+    ecc_op_records: Vec<Option<ECCOperationRecord>>,
+    ecc_last_ops: Vec<Option<(ECCOperation, Instant)>>,
+    ecc_asads: Vec<Option<AsAdSummary>>,
+    ecc_retries: Vec<u32>,
+    new_errors: Vec<ECCErrorReport>,
+    ecc_last_status_errors: Vec<Option<String>>,
+    ecc_last_updates: Vec<Instant>,
+    ecc_stale: Vec<bool>,
+    ecc_message_stats: Vec<EnvoyMessageStats>,
+    surveyor_message_stats: Vec<EnvoyMessageStats>,
+    network_lost: bool,
+    ecc_targets: BTreeMap<usize, ECCStatus>,
+    system_target: Option<ECCStatus>,
+    families: EnvoyFamilies,
+    readiness: ReadinessPolicy,
+    is_active: bool,
 }
 
 impl StatusManager {
-    /// Create a new manager with space for the statuses of all envoys
-    pub fn new() -> Self {
-        let eccs = vec![ECCStatusResponse::default(); NUMBER_OF_MODULES];
-        let surs = vec![SurveyorResponse::default(); NUMBER_OF_MODULES - 1];
-        let holds = vec![false; NUMBER_OF_MODULES];
+    /// Create a new manager with space for the statuses of all envoys.
+    /// There is an ECCEnvoy for each CoBo plus the MuTaNT, and a SurveyorEnvoy for each CoBo.
+    pub fn new(number_of_cobos: usize) -> Self {
+        let eccs = vec![ECCStatusResponse::default(); number_of_cobos + 1];
+        let surs = vec![SurveyorResponse::default(); number_of_cobos];
+        let holds = vec![false; number_of_cobos + 1];
+        let op_records = vec![None; number_of_cobos + 1];
         Self {
             ecc_status: eccs,
             surveyor_status: surs,
+            last_disk_health: vec![None; number_of_cobos],
+            disk_health_changes: vec![],
             ecc_holds: holds,
+            ecc_op_records: op_records,
+            ecc_last_ops: vec![None; number_of_cobos + 1],
+            ecc_asads: vec![None; number_of_cobos + 1],
+            ecc_retries: vec![0; number_of_cobos + 1],
+            new_errors: vec![],
+            ecc_last_status_errors: vec![None; number_of_cobos + 1],
+            ecc_last_updates: vec![Instant::now(); number_of_cobos + 1],
+            ecc_stale: vec![false; number_of_cobos + 1],
+            ecc_message_stats: vec![EnvoyMessageStats::default(); number_of_cobos + 1],
+            surveyor_message_stats: vec![EnvoyMessageStats::default(); number_of_cobos],
+            network_lost: false,
+            ecc_targets: BTreeMap::new(),
+            system_target: None,
+            families: EnvoyFamilies::default(),
+            readiness: ReadinessPolicy::default(),
+            is_active: false,
         }
     }
 
-    /// Reset the data of all the envoys
+    /// Reset the data of all the envoys. The manager will not accept messages
+    /// again until the monitored families are set (i.e. on connect).
     pub fn reset(&mut self) {
+        self.is_active = false;
         for eccs in self.ecc_status.iter_mut() {
             *eccs = ECCStatusResponse::default();
         }
@@ -39,16 +191,88 @@ impl StatusManager {
         for surs in self.surveyor_status.iter_mut() {
             *surs = SurveyorResponse::default();
         }
+
+        for health in self.last_disk_health.iter_mut() {
+            *health = None;
+        }
+        self.disk_health_changes.clear();
+
+        for hold in self.ecc_holds.iter_mut() {
+            *hold = false;
+        }
+
+        for stale in self.ecc_stale.iter_mut() {
+            *stale = false;
+        }
+        self.ecc_asads.fill(None);
+        self.network_lost = false;
+
+        self.ecc_message_stats.fill(EnvoyMessageStats::default());
+        self.surveyor_message_stats
+            .fill(EnvoyMessageStats::default());
+
+        self.clear_operation_errors();
+        self.clear_ecc_targets();
+    }
+
+    /// The module ID of the MuTaNT, which is always the module after the last CoBo
+    pub fn mutant_id(&self) -> usize {
+        self.ecc_status.len() - 1
+    }
+
+    /// The module IDs of all of the CoBos
+    pub fn cobo_ids(&self) -> Vec<usize> {
+        (0..self.mutant_id()).collect()
+    }
+
+    /// The module IDs of all of the ECC modules (CoBos and MuTaNT)
+    pub fn module_ids(&self) -> Vec<usize> {
+        (0..self.ecc_status.len()).collect()
+    }
+
+    /// Set which families of envoys are being monitored. Families which are not monitored
+    /// report a NotMonitored status rather than Offline.
+    pub fn set_monitored_families(&mut self, families: &EnvoyFamilies) {
+        self.families = *families;
+        self.is_active = true;
+        // Staleness is measured from the connection
+        let now = Instant::now();
+        for update in self.ecc_last_updates.iter_mut() {
+            *update = now;
+        }
+    }
+
+    /// Set the readiness policy used for the system status
+    pub fn set_readiness_policy(&mut self, policy: ReadinessPolicy) {
+        self.readiness = policy.clamped(self.mutant_id());
+    }
+
+    /// The readiness policy used for the system status
+    pub fn get_readiness_policy(&self) -> &ReadinessPolicy {
+        &self.readiness
+    }
+
+    /// Which families of envoys are being monitored
+    pub fn get_monitored_families(&self) -> &EnvoyFamilies {
+        &self.families
     }
 
     /// Read messages from the embassy and look for ECC or Surveyor status respsonses.
     /// Set the status of the given module to match the message.
     pub fn handle_messages(&mut self, messages: &[EmbassyMessage]) -> Result<(), EmbassyError> {
+        debug_assert!(
+            self.is_active || messages.is_empty(),
+            "StatusManager handled messages while disconnected"
+        );
+        if !self.is_active {
+            return Ok(());
+        }
         for message in messages {
             let module_id = message.id;
             match message.kind {
                 MessageKind::ECCOperation => {
                     let resp: ECCOperationResponse = message.try_into()?;
+                    self.ecc_message_stats[module_id].record(resp.error_code != 0);
                     if resp.error_code != 0 {
                         tracing::error!(
                             "ECC Operation failed with error code {} for module id {}: {}",
@@ -56,20 +280,61 @@ impl StatusManager {
                             module_id,
                             resp.error_message
                         );
+                        self.new_errors.push(ECCErrorReport {
+                            id: module_id,
+                            code: resp.error_code,
+                            message: format!(
+                                "Error code {}: {}",
+                                resp.error_code, resp.error_message
+                            ),
+                            is_operation: true,
+                        });
                     } else {
                         tracing::info!("ECC Operation completed for module id {}", module_id);
+                        self.ecc_retries[module_id] = 0;
+                        if matches!(
+                            self.get_last_operation(module_id),
+                            Some(ECCOperation::Describe)
+                        ) {
+                            self.ecc_asads[module_id] = AsAdSummary::parse(&resp.text);
+                            if self.ecc_asads[module_id].is_none() {
+                                tracing::warn!(
+                                    "The Describe response of module {module_id} has no AsAd information"
+                                );
+                            }
+                        }
                     }
+                    self.ecc_op_records[module_id] = Some(ECCOperationRecord {
+                        response: resp,
+                        time: Instant::now(),
+                    });
                     self.ecc_holds[module_id] = false;
                 }
                 MessageKind::ECCStatus => {
                     let resp: ECCStatusResponse = message.try_into()?;
+                    self.ecc_message_stats[module_id].record(resp.error_code != 0);
+                    self.ecc_last_updates[module_id] = Instant::now();
+                    self.ecc_stale[module_id] = false;
                     if resp.error_code != 0 {
                         tracing::error!(
                             "ECC Status failed with error code {} for module id {}: {}",
                             resp.error_code,
                             module_id,
                             resp.error_message
-                        )
+                        );
+                        let error =
+                            format!("Error code {}: {}", resp.error_code, resp.error_message);
+                        if self.ecc_last_status_errors[module_id].as_ref() != Some(&error) {
+                            self.new_errors.push(ECCErrorReport {
+                                id: module_id,
+                                code: resp.error_code,
+                                message: error.clone(),
+                                is_operation: false,
+                            });
+                            self.ecc_last_status_errors[module_id] = Some(error);
+                        }
+                    } else {
+                        self.ecc_last_status_errors[module_id] = None;
                     }
 
                     if !self.ecc_holds[module_id] {
@@ -78,6 +343,21 @@ impl StatusManager {
                 }
                 MessageKind::Surveyor => {
                     let resp: SurveyorResponse = message.try_into()?;
+                    self.surveyor_message_stats[module_id].record(!matches!(
+                        SurveyorStatus::from(resp.state),
+                        SurveyorStatus::Online
+                    ));
+                    // Only the last reported health is remembered, so that an offline
+                    // surveyor coming back does not repeat the alert
+                    if let Some(health) = &resp.disk_health {
+                        let previous = self.last_disk_health[module_id].replace(health.state);
+                        let changed = previous != Some(health.state);
+                        if changed
+                            && (health.state != DiskHealthState::Healthy || previous.is_some())
+                        {
+                            self.disk_health_changes.push((module_id, health.clone()));
+                        }
+                    }
                     self.surveyor_status[module_id] = resp;
                 }
                 _ => {
@@ -93,9 +373,56 @@ impl StatusManager {
         &self.ecc_status
     }
 
+    /// Do the CoBos agree on a status. Under the quorum policy, only a quorum of them must agree.
+    pub fn cobos_agree(&self, state: &ECCStatus) -> bool {
+        let cobos = &self.ecc_status[..self.mutant_id()];
+        let agreeing = cobos
+            .iter()
+            .filter(|s| ECCStatus::from(s.state) == *state)
+            .count();
+        match self.readiness.mode {
+            ReadinessMode::Strict => agreeing == cobos.len(),
+            ReadinessMode::Quorum => agreeing >= self.readiness.quorum_cobos.min(cobos.len()),
+        }
+    }
+
+    /// The CoBos which disagree with the system status and are waived by the quorum policy.
+    /// Empty under the strict policy, or when the system has no status.
+    pub fn waived_cobos(&self) -> Vec<usize> {
+        if self.readiness.mode == ReadinessMode::Strict {
+            return vec![];
+        }
+        let system = self.get_system_ecc_status();
+        if matches!(
+            system,
+            ECCStatus::NotMonitored | ECCStatus::Unreachable | ECCStatus::Inconsistent
+        ) {
+            return vec![];
+        }
+        self.cobo_ids()
+            .into_iter()
+            .filter(|id| ECCStatus::from(self.ecc_status[*id].state) != system)
+            .collect()
+    }
+
     /// Retrieve the system ECC status. System status matches the envoy status if all
-    /// envoys have the same status. If not, the system status is Inconsistent.
+    /// envoys have the same status. If not, the system status is Inconsistent. If any
+    /// envoy cannot reach its server, the system status is Unreachable. Under the quorum
+    /// policy, only the MuTaNT and a quorum of the CoBos must agree (see waived_cobos).
     pub fn get_system_ecc_status(&self) -> ECCStatus {
+        if !self.families.ecc {
+            return ECCStatus::NotMonitored;
+        }
+        if self.readiness.mode == ReadinessMode::Quorum {
+            let mutant = ECCStatus::from(self.ecc_status[self.mutant_id()].state);
+            if mutant != ECCStatus::Unreachable && self.cobos_agree(&mutant) {
+                return mutant;
+            }
+        }
+        let unreachable: i32 = ECCStatus::Unreachable.into();
+        if self.ecc_status.iter().any(|s| s.state == unreachable) {
+            return ECCStatus::Unreachable;
+        }
         let sys_status = self.ecc_status[0].state;
         for status in self.ecc_status.iter() {
             if sys_status != status.state {
@@ -116,43 +443,29 @@ impl StatusManager {
         matches!(self.get_system_ecc_status(), ECCStatus::Running)
     }
 
-    /// Are all of the CoBos running, waiting for the MuTaNT
+    /// Are all of the CoBos (or a quorum of them) running, waiting for the MuTaNT
     pub fn is_all_but_mutant_running(&self) -> bool {
-        let sys_status = self.ecc_status[0].state;
-        for status in self.ecc_status[..(NUMBER_OF_MODULES - 1)].iter() {
-            if sys_status != status.state {
-                return false;
-            }
-        }
-
-        matches!(ECCStatus::from(sys_status), ECCStatus::Running)
+        self.cobos_agree(&ECCStatus::Running)
     }
 
-    /// Is everyone but the MuTaNT at the Ready status
+    /// Is everyone but the MuTaNT (or a quorum of the CoBos) at the Ready status
     pub fn is_all_but_mutant_ready(&self) -> bool {
-        let sys_status = self.ecc_status[0].state;
-        for status in self.ecc_status[..(NUMBER_OF_MODULES - 1)].iter() {
-            if sys_status != status.state {
-                return false;
-            }
-        }
-
-        matches!(ECCStatus::from(sys_status), ECCStatus::Ready)
+        self.cobos_agree(&ECCStatus::Ready)
     }
 
-    /// Is the MuTaNT stopped (not running)
+    /// Is the MuTaNT stopped, i.e. back at Ready
     pub fn is_mutant_stopped(&self) -> bool {
-        matches!(self.get_ecc_status(MUTANT_ID), ECCStatus::Running)
+        matches!(self.get_ecc_status(self.mutant_id()), ECCStatus::Ready)
     }
 
     /// Is the MuTaNT at the Prepared status
     pub fn is_mutant_prepared(&self) -> bool {
-        matches!(self.get_ecc_status(MUTANT_ID), ECCStatus::Prepared)
+        matches!(self.get_ecc_status(self.mutant_id()), ECCStatus::Prepared)
     }
 
     /// Is the MuTaNT at the Ready status
     pub fn is_mutant_ready(&self) -> bool {
-        matches!(self.get_ecc_status(MUTANT_ID), ECCStatus::Ready)
+        matches!(self.get_ecc_status(self.mutant_id()), ECCStatus::Ready)
     }
 
     /// Returns a slice of all SurveyorResponses (SurveyorEnvoy statuses)
@@ -162,12 +475,233 @@ impl StatusManager {
 
     /// Get the status of a specific ECCEnvoy
     pub fn get_ecc_status(&self, id: usize) -> ECCStatus {
+        if !self.families.ecc {
+            return ECCStatus::NotMonitored;
+        }
         ECCStatus::from(self.ecc_status[id].state)
     }
 
+    /// Get the AsAd summary from the last Describe of a specific ECCEnvoy, if it had one
+    pub fn get_asad_summary(&self, id: usize) -> Option<&AsAdSummary> {
+        self.ecc_asads.get(id).and_then(|summary| summary.as_ref())
+    }
+
+    /// Get the response to the last operation of a specific ECCEnvoy
+    pub fn get_operation_record(&self, id: usize) -> Option<&ECCOperationRecord> {
+        self.ecc_op_records[id].as_ref()
+    }
+
+    /// Get the error from the last operation of a specific ECCEnvoy, if it failed
+    pub fn get_operation_error(&self, id: usize) -> Option<String> {
+        match &self.ecc_op_records[id] {
+            Some(record) if record.is_error() => Some(format!(
+                "Error code {}: {}",
+                record.response.error_code, record.response.error_message
+            )),
+            _ => None,
+        }
+    }
+
+    /// Forget the errors from all previous operations
+    pub fn clear_operation_errors(&mut self) {
+        for id in 0..self.ecc_op_records.len() {
+            self.dismiss_operation_error(id);
+        }
+    }
+
+    /// Forget the error from the last operation of a specific ECCEnvoy
+    pub fn dismiss_operation_error(&mut self, id: usize) {
+        self.ecc_op_records[id] = None;
+        self.ecc_retries[id] = 0;
+    }
+
+    /// Record the operation submitted to a specific ECCEnvoy
+    pub fn record_operation(&mut self, id: usize, operation: ECCOperation) {
+        self.ecc_last_ops[id] = Some((operation, Instant::now()));
+    }
+
+    /// Check for modules which have not reported status within the threshold. If every
+    /// module is stale at once the control network is most likely lost, so a single change
+    /// is returned instead of an error for each module; the per-module errors are suppressed
+    /// until a module reports again, which clears the condition.
+    pub fn check_staleness(&mut self, threshold: Duration) -> Option<NetworkChange> {
+        if !self.is_active || !self.families.ecc {
+            return None;
+        }
+        let now = Instant::now();
+        let stale: Vec<bool> = self
+            .ecc_last_updates
+            .iter()
+            .map(|update| now - *update > threshold)
+            .collect();
+
+        if stale.iter().all(|s| *s) {
+            if self.network_lost {
+                return None;
+            }
+            self.network_lost = true;
+            // Mark every module as already reported, so that no per-module errors follow
+            self.ecc_stale.fill(true);
+            return Some(NetworkChange::Lost);
+        }
+
+        // Once restored, the modules which are still stale are reported individually
+        let restored = std::mem::take(&mut self.network_lost);
+        if restored {
+            self.ecc_stale.fill(false);
+        }
+        for (id, is_stale) in stale.into_iter().enumerate() {
+            if is_stale && !self.ecc_stale[id] {
+                self.ecc_stale[id] = true;
+                self.new_errors.push(ECCErrorReport {
+                    id,
+                    code: 0,
+                    message: format!(
+                        "No status recieved for {:.0} s",
+                        (now - self.ecc_last_updates[id]).as_secs_f64()
+                    ),
+                    is_operation: false,
+                });
+            }
+        }
+
+        restored.then_some(NetworkChange::Restored)
+    }
+
+    /// Is the control network most likely lost (see check_staleness)
+    pub fn is_network_lost(&self) -> bool {
+        self.network_lost
+    }
+
+    /// Take the errors recieved since the last call. Repeated status errors are only reported
+    /// when the error changes.
+    pub fn take_new_errors(&mut self) -> Vec<ECCErrorReport> {
+        std::mem::take(&mut self.new_errors)
+    }
+
+    /// Take the disk health changes recieved since the last call, as (data router id, health)
+    pub fn take_disk_health_changes(&mut self) -> Vec<(usize, DiskHealth)> {
+        std::mem::take(&mut self.disk_health_changes)
+    }
+
+    /// The data routers currently reporting a failing disk
+    pub fn get_failing_disks(&self) -> Vec<usize> {
+        self.surveyor_status
+            .iter()
+            .enumerate()
+            .filter(|(_, status)| {
+                status
+                    .disk_health
+                    .as_ref()
+                    .is_some_and(|health| health.state == DiskHealthState::Failing)
+            })
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Get the last operation submitted to a specific ECCEnvoy
+    pub fn get_last_operation(&self, id: usize) -> Option<&ECCOperation> {
+        self.ecc_last_ops[id].as_ref().map(|(op, _)| op)
+    }
+
+    /// Time between submitting the last operation to a specific ECCEnvoy and recieving the response
+    pub fn get_operation_latency(&self, id: usize) -> Option<std::time::Duration> {
+        match (&self.ecc_last_ops[id], &self.ecc_op_records[id]) {
+            (Some((_, sent)), Some(record)) if record.time >= *sent => Some(record.time - *sent),
+            _ => None,
+        }
+    }
+
+    /// Get the last operation submitted to a specific ECCEnvoy, if it failed
+    pub fn get_failed_operation(&self, id: usize) -> Option<&ECCOperation> {
+        match &self.ecc_op_records[id] {
+            Some(record) if record.is_error() => self.get_last_operation(id),
+            _ => None,
+        }
+    }
+
+    /// Record that the failed operation of a specific ECCEnvoy was retried
+    pub fn mark_retry(&mut self, id: usize) {
+        self.ecc_retries[id] += 1;
+    }
+
+    /// The number of times the failed operation of a specific ECCEnvoy was retried
+    pub fn get_retry_count(&self, id: usize) -> u32 {
+        self.ecc_retries[id]
+    }
+
+    /// Set a target status for a specific ECCEnvoy. The envoy will be progressed
+    /// until it reaches the target (see transition::advance_targets). Any previous
+    /// operation error is dismissed.
+    pub fn set_ecc_target(&mut self, id: usize, target: ECCStatus) {
+        self.dismiss_operation_error(id);
+        self.ecc_targets.insert(id, target);
+    }
+
+    /// Get the target status of a specific ECCEnvoy, if it has one
+    pub fn get_ecc_target(&self, id: usize) -> Option<&ECCStatus> {
+        self.ecc_targets.get(&id)
+    }
+
+    /// Remove the target status of a specific ECCEnvoy
+    pub fn clear_ecc_target(&mut self, id: usize) {
+        self.ecc_targets.remove(&id);
+    }
+
+    /// Remove the target status of all ECCEnvoys, including the system target
+    pub fn clear_ecc_targets(&mut self) {
+        self.ecc_targets.clear();
+        self.system_target = None;
+    }
+
+    /// Set the target status of the entire system
+    pub fn set_system_target(&mut self, target: ECCStatus) {
+        self.system_target = Some(target);
+    }
+
+    /// Get the target status of the entire system, if it has one
+    pub fn get_system_target(&self) -> Option<&ECCStatus> {
+        self.system_target.as_ref()
+    }
+
+    /// Get all of the ECCEnvoys with a target status
+    pub fn get_ecc_targets(&self) -> Vec<(usize, ECCStatus)> {
+        self.ecc_targets
+            .iter()
+            .map(|(id, target)| (*id, target.clone()))
+            .collect()
+    }
+
+    /// Take a snapshot of the current ECC status of every module
+    pub fn ecc_snapshot(&self) -> ECCSnapshot {
+        ECCSnapshot {
+            statuses: self
+                .module_ids()
+                .into_iter()
+                .map(|id| self.get_ecc_status(id))
+                .collect(),
+            time: Instant::now(),
+        }
+    }
+
+    /// How many ECCEnvoys are waiting on an operation
+    pub fn number_held(&self) -> usize {
+        self.ecc_holds.iter().filter(|hold| **hold).count()
+    }
+
+    /// Is any ECCEnvoy waiting on an operation
+    pub fn is_any_held(&self) -> bool {
+        self.ecc_holds.iter().any(|hold| *hold)
+    }
+
+    /// Is a specific ECCEnvoy waiting on an operation
+    pub fn is_ecc_held(&self, id: usize) -> bool {
+        self.ecc_holds[id]
+    }
+
     /// Set a specific ECCEnvoy as Busy
     pub fn set_ecc_busy(&mut self, id: usize) {
-        if id > MUTANT_ID {
+        if id > self.mutant_id() {
             return;
         }
 
@@ -175,24 +709,27 @@ impl StatusManager {
         self.ecc_holds[id] = true;
     }
 
-    /// Check if an ECCEnvoy can go forward (progress)
+    /// Check if an ECCEnvoy can go forward (progress), respecting the order
+    /// in which the MuTaNT and CoBos must perform the operation
     pub fn can_ecc_go_forward(&self, id: usize) -> bool {
         let status = self.get_ecc_status(id);
-        if status == ECCStatus::Described && id != MUTANT_ID {
-            matches!(
-                self.get_ecc_status(MUTANT_ID),
+        let mutant_id = self.mutant_id();
+        match status.get_forward_operation().module_order() {
+            ModuleOrder::MutantFirst if id != mutant_id => matches!(
+                self.get_ecc_status(mutant_id),
                 ECCStatus::Prepared | ECCStatus::Ready
-            )
-        } else if status == ECCStatus::Prepared && id == MUTANT_ID {
-            self.is_all_but_mutant_ready()
-        } else {
-            status.can_go_forward()
+            ),
+            ModuleOrder::CobosFirst if id == mutant_id => self.is_all_but_mutant_ready(),
+            _ => status.can_go_forward(),
         }
     }
 
     /// Retrieve the Surveyor/DataRouter system status. System status matches the envoy status if all
     /// envoys have the same status. If not, the system status is Inconsistent.
     pub fn get_surveyor_system_status(&self) -> SurveyorStatus {
+        if !self.families.surveyor {
+            return SurveyorStatus::NotMonitored;
+        }
         let sys_status = self.surveyor_status[0].state;
         for status in self.surveyor_status.iter() {
             if sys_status != status.state {
@@ -202,9 +739,157 @@ impl StatusManager {
         SurveyorStatus::from(sys_status)
     }
 
+    /// The message counts of every ECCEnvoy
+    pub fn get_ecc_message_stats(&self) -> &[EnvoyMessageStats] {
+        &self.ecc_message_stats
+    }
+
+    /// The message counts of every SurveyorEnvoy
+    pub fn get_surveyor_message_stats(&self) -> &[EnvoyMessageStats] {
+        &self.surveyor_message_stats
+    }
+
     /// Get the status of a specific SurveyorEnvoy
-    #[allow(dead_code)]
     pub fn get_surveyor_status(&self, id: usize) -> SurveyorStatus {
+        if !self.families.surveyor {
+            return SurveyorStatus::NotMonitored;
+        }
         SurveyorStatus::from(self.surveyor_status[id].state)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envoy::testing::{ecc_status, status_manager, surveyor_health};
+
+    #[test]
+    fn messages_are_handled_once_connected() {
+        let mut manager = StatusManager::new(2);
+        manager.set_monitored_families(&EnvoyFamilies::default());
+        manager
+            .handle_messages(&[ecc_status(1, ECCStatus::Ready)])
+            .unwrap();
+        assert_eq!(manager.get_ecc_status(1), ECCStatus::Ready);
+    }
+
+    #[test]
+    fn reset_clears_statuses_and_holds() {
+        let mut manager = status_manager(&[ECCStatus::Ready, ECCStatus::Ready]);
+        manager.set_ecc_busy(0);
+        manager.reset();
+        assert!(!manager.is_any_held());
+        assert_eq!(manager.get_ecc_status(0), ECCStatus::Offline);
+        assert_eq!(manager.get_ecc_status(1), ECCStatus::Offline);
+        // Nothing is read until the next connect
+        manager.handle_messages(&[]).unwrap();
+        assert_eq!(manager.check_staleness(Duration::ZERO), None);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "handled messages while disconnected")]
+    fn messages_after_reset_are_a_bug() {
+        let mut manager = status_manager(&[ECCStatus::Ready, ECCStatus::Ready]);
+        manager.reset();
+        let _ = manager.handle_messages(&[ecc_status(0, ECCStatus::Ready)]);
+    }
+
+    const STALE_THRESHOLD: Duration = Duration::from_millis(20);
+
+    fn stale_ids(manager: &mut StatusManager) -> Vec<usize> {
+        manager
+            .take_new_errors()
+            .iter()
+            .map(|error| error.id)
+            .collect()
+    }
+
+    #[test]
+    fn stale_modules_are_reported_once() {
+        let mut manager = status_manager(&[ECCStatus::Ready, ECCStatus::Ready]);
+        std::thread::sleep(2 * STALE_THRESHOLD);
+        manager
+            .handle_messages(&[ecc_status(1, ECCStatus::Ready)])
+            .unwrap();
+        assert_eq!(manager.check_staleness(STALE_THRESHOLD), None);
+        assert_eq!(stale_ids(&mut manager), vec![0]);
+        assert_eq!(manager.check_staleness(STALE_THRESHOLD), None);
+        assert!(stale_ids(&mut manager).is_empty());
+    }
+
+    #[test]
+    fn network_loss_is_entered_and_left() {
+        let mut manager = status_manager(&[ECCStatus::Ready, ECCStatus::Ready, ECCStatus::Ready]);
+        std::thread::sleep(2 * STALE_THRESHOLD);
+        assert_eq!(
+            manager.check_staleness(STALE_THRESHOLD),
+            Some(NetworkChange::Lost)
+        );
+        assert!(manager.is_network_lost());
+        assert!(stale_ids(&mut manager).is_empty());
+        assert_eq!(manager.check_staleness(STALE_THRESHOLD), None);
+
+        manager
+            .handle_messages(&[ecc_status(1, ECCStatus::Ready)])
+            .unwrap();
+        assert_eq!(
+            manager.check_staleness(STALE_THRESHOLD),
+            Some(NetworkChange::Restored)
+        );
+        assert!(!manager.is_network_lost());
+        // The modules which did not come back are reported individually
+        assert_eq!(stale_ids(&mut manager), vec![0, 2]);
+        assert_eq!(manager.check_staleness(STALE_THRESHOLD), None);
+        assert!(stale_ids(&mut manager).is_empty());
+    }
+
+    #[test]
+    fn disk_health_changes_are_reported_once() {
+        use DiskHealthState::{Failing, Healthy, Warning};
+        let mut manager = status_manager(&[ECCStatus::Ready, ECCStatus::Ready, ECCStatus::Ready]);
+        let mut report = |messages: &[EmbassyMessage]| {
+            manager.handle_messages(messages).unwrap();
+            manager
+                .take_disk_health_changes()
+                .into_iter()
+                .map(|(id, health)| (id, health.state))
+                .collect::<Vec<(usize, DiskHealthState)>>()
+        };
+        // A healthy disk is not news, but a disk which is unhealthy from the start is
+        assert_eq!(
+            report(&[
+                surveyor_health(0, Some(Healthy)),
+                surveyor_health(1, Some(Warning))
+            ]),
+            vec![(1, Warning)]
+        );
+        assert!(report(&[surveyor_health(1, Some(Warning))]).is_empty());
+        // A report without health keeps the last known health
+        assert!(report(&[surveyor_health(1, None)]).is_empty());
+        assert_eq!(
+            report(&[
+                surveyor_health(0, Some(Failing)),
+                surveyor_health(1, Some(Healthy))
+            ]),
+            vec![(0, Failing), (1, Healthy)]
+        );
+        assert_eq!(manager.get_failing_disks(), vec![0]);
+    }
+
+    #[test]
+    fn quorum_is_clamped_to_the_cobos() {
+        let policy = |quorum_cobos| ReadinessPolicy {
+            mode: ReadinessMode::Quorum,
+            quorum_cobos,
+        };
+        assert_eq!(policy(0).clamped(11).quorum_cobos, 1);
+        assert_eq!(policy(20).clamped(11).quorum_cobos, 11);
+        assert_eq!(policy(7).clamped(11).quorum_cobos, 7);
+        assert_eq!(policy(3).clamped(0).quorum_cobos, 1);
+
+        let mut manager = status_manager(&[ECCStatus::Ready, ECCStatus::Ready, ECCStatus::Ready]);
+        manager.set_readiness_policy(policy(10));
+        assert_eq!(manager.get_readiness_policy().quorum_cobos, 2);
+    }
+}
@@ -1,6 +1,6 @@
-use super::constants::{ADDRESS_START, NUMBER_OF_MODULES};
 use super::error::EnvoyError;
 use super::message::EmbassyMessage;
+use super::surveyor_status::DiskHealth;
 use reqwest::{Client, Response};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -10,8 +10,6 @@ use tokio::task::JoinHandle;
 
 const SURVEYOR_URL_PORT: i32 = 8081;
 
-const STATUS_WAIT_TIME_SEC: u64 = 2;
-
 const CONNECTION_TIMEOUT_SEC: u64 = 120;
 
 /// The message delivered from the SurveyorEnvoy (the status of a DataRouter and its machine)
@@ -28,6 +26,9 @@ pub struct SurveyorResponse {
     pub files: i32,
     pub bytes_used: u64,
     pub data_rate: f64,
+    /// Absent for surveyors which do not report the disk health
+    #[serde(default)]
+    pub disk_health: Option<DiskHealth>,
 }
 
 impl Default for SurveyorResponse {
@@ -42,31 +43,47 @@ impl Default for SurveyorResponse {
             files: 0,
             bytes_used: 0,
             data_rate: 0.0,
+            disk_health: None,
         }
     }
 }
 
+impl SurveyorResponse {
+    /// The disk usage in percent, if the surveyor reported it
+    pub fn percent_used_value(&self) -> Option<f64> {
+        self.percent_used.trim().trim_end_matches('%').parse().ok()
+    }
+
+    /// The free space on the disk in bytes, if the surveyor reported the disk usage
+    pub fn free_bytes(&self) -> Option<u64> {
+        self.percent_used_value()
+            .map(|used| (self.disk_space as f64 * (1.0 - used / 100.0)).max(0.0) as u64)
+    }
+}
+
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct SurveyorConfig {
     id: usize,
     address: String,
     url: String,
+    poll_interval: Duration,
 }
 
 impl SurveyorConfig {
-    pub fn new(id: usize) -> Self {
-        let address = Self::address(&id);
-        let url = Self::url(&address);
-
-        Self { id, address, url }
-    }
+    pub fn new(id: usize, address: &str, poll_interval: Duration) -> Self {
+        let url = Self::url(address);
 
-    fn address(id: &usize) -> String {
-        format!("{ADDRESS_START}.{}", 60 + id)
+        Self {
+            id,
+            address: address.to_string(),
+            url,
+            poll_interval,
+        }
     }
 
-    fn url(address: &str) -> String {
+    /// Compose the associated surveyor page URL
+    pub fn url(address: &str) -> String {
         format!("http://{address}:{SURVEYOR_URL_PORT}/~attpc/surveyor.html")
     }
 }
@@ -91,11 +108,10 @@ async fn run_surveyor_envoy(
                 return Ok(());
             }
 
-            _ = tokio::time::sleep(Duration::from_secs(STATUS_WAIT_TIME_SEC)) => {
+            _ = tokio::time::sleep(config.poll_interval) => {
                 if let Ok(maybe) = submit_check_status(&config, &client, &mut previous_bytes).await {
-                    match maybe {
-                        Some(response) => outgoing.send(response).await?,
-                        None => ()
+                    if let Some(response) = maybe {
+                        outgoing.send(response).await?
                     }
                 } else {
                     let message = EmbassyMessage::compose_surveyor_response(serde_yaml::to_string(&SurveyorResponse::default())?, config.id);
@@ -144,7 +160,9 @@ async fn parse_response(
     let mut bytes: u64 = 0;
     let mut n_files = 0;
     for line in lines[4..].iter() {
-        if line.contains("graw") {
+        if line.starts_with("health:") {
+            status.disk_health = DiskHealth::from_line(line);
+        } else if line.contains("graw") {
             let line_entries: Vec<&str> = line.split_whitespace().collect();
             bytes += line_entries[4].parse::<u64>()?;
             n_files += 1;
@@ -161,7 +179,8 @@ async fn parse_response(
     status.bytes_used = bytes;
     let bytes_float = bytes as f64;
 
-    status.data_rate = (bytes_float - *previous_bytes) * 1.0e-6 / (STATUS_WAIT_TIME_SEC as f64); //MB/s
+    status.data_rate =
+        (bytes_float - *previous_bytes) * 1.0e-6 / config.poll_interval.as_secs_f64(); //MB/s
 
     *previous_bytes = bytes_float;
 
@@ -174,14 +193,16 @@ async fn parse_response(
 /// Function to create all of the SurveyorEnvoys and spawn their tasks. Returns handles to the tasks.
 pub fn startup_surveyor_envoys(
     runtime: &mut tokio::runtime::Runtime,
+    cobo_addresses: &[String],
+    poll_interval: Duration,
     surveyor_tx: &mpsc::Sender<EmbassyMessage>,
     cancel: &broadcast::Sender<EmbassyMessage>,
 ) -> Vec<JoinHandle<()>> {
     let mut handles: Vec<JoinHandle<()>> = vec![];
 
     //spin up the surveyor envoys, Mutant does not get a data router/surveyor
-    for id in 0..(NUMBER_OF_MODULES - 1) {
-        let config = SurveyorConfig::new(id);
+    for (id, address) in cobo_addresses.iter().enumerate() {
+        let config = SurveyorConfig::new(id, address, poll_interval);
         let this_surveyor_tx = surveyor_tx.clone();
         let this_cancel = cancel.subscribe();
         let handle = runtime.spawn(async move {
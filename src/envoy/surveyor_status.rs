@@ -1,7 +1,10 @@
+use serde::{Deserialize, Serialize};
+
 const SURVEYOR_ONLINE_STATE_TEXT: &str = "Online";
 const SURVEYOR_OFFLINE_STATE_TEXT: &str = "Offline";
 const SURVEYOR_INVALID_STATE_TEXT: &str = "Invalid";
 const SURVEYOR_INCONSISTENT_STATE_TEXT: &str = "Invalid";
+const SURVEYOR_NOT_MONITORED_STATE_TEXT: &str = "Not Monitored";
 
 const SURVEYOR_DISK_FILLED_TEXT: &str = "Filled";
 const SURVEYOR_DISK_EMPTY_TEXT: &str = "Empty";
@@ -14,6 +17,7 @@ pub enum SurveyorStatus {
     Offline,
     Invalid,
     Inconsistent,
+    NotMonitored,
 }
 
 impl From<i32> for SurveyorStatus {
@@ -33,6 +37,7 @@ impl std::fmt::Display for SurveyorStatus {
             Self::Offline => write!(f, "{SURVEYOR_OFFLINE_STATE_TEXT}"),
             Self::Invalid => write!(f, "{SURVEYOR_INVALID_STATE_TEXT}"),
             Self::Inconsistent => write!(f, "{SURVEYOR_INCONSISTENT_STATE_TEXT}"),
+            Self::NotMonitored => write!(f, "{SURVEYOR_NOT_MONITORED_STATE_TEXT}"),
         }
     }
 }
@@ -64,3 +69,97 @@ impl std::fmt::Display for SurveyorDiskStatus {
         }
     }
 }
+
+/// The SMART health of the disk being written to by the DataRouter. Only reported by
+/// surveyors which support it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DiskHealthState {
+    Healthy,
+    Warning,
+    Failing,
+}
+
+impl DiskHealthState {
+    /// Parse the health flag reported by the surveyor
+    fn from_flag(flag: &str) -> Option<Self> {
+        match flag.to_ascii_lowercase().as_str() {
+            "healthy" | "ok" | "passed" => Some(Self::Healthy),
+            "warning" => Some(Self::Warning),
+            "failing" | "failed" => Some(Self::Failing),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for DiskHealthState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Healthy => write!(f, "Healthy"),
+            Self::Warning => write!(f, "Warning"),
+            Self::Failing => write!(f, "Failing"),
+        }
+    }
+}
+
+/// The disk health reported by the surveyor, with the SMART summary as details
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiskHealth {
+    pub state: DiskHealthState,
+    pub details: String,
+}
+
+impl DiskHealth {
+    /// Parse a surveyor health line of the form `health: <flag> <details>`. Returns None if
+    /// the line is not a health line or the flag is not recognized.
+    pub fn from_line(line: &str) -> Option<Self> {
+        let rest = line.trim().strip_prefix("health:")?.trim();
+        let (flag, details) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        match DiskHealthState::from_flag(flag) {
+            Some(state) => Some(Self {
+                state,
+                details: details.trim().to_string(),
+            }),
+            None => {
+                tracing::warn!("Surveyor reported an unknown disk health: {flag}");
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn health_lines_are_parsed() {
+        assert_eq!(
+            DiskHealth::from_line("health: OK"),
+            Some(DiskHealth {
+                state: DiskHealthState::Healthy,
+                details: String::new()
+            })
+        );
+        assert_eq!(
+            DiskHealth::from_line("  health: warning 12 reallocated sectors "),
+            Some(DiskHealth {
+                state: DiskHealthState::Warning,
+                details: String::from("12 reallocated sectors")
+            })
+        );
+        assert_eq!(
+            DiskHealth::from_line("health: FAILED").map(|health| health.state),
+            Some(DiskHealthState::Failing)
+        );
+    }
+
+    #[test]
+    fn other_lines_are_not_health() {
+        assert_eq!(DiskHealth::from_line("health: degraded"), None);
+        assert_eq!(DiskHealth::from_line("health:"), None);
+        assert_eq!(
+            DiskHealth::from_line("-rw-r--r-- 1 daq daq 1024 run.graw"),
+            None
+        );
+    }
+}
@@ -0,0 +1,59 @@
+//! Helpers shared by the tests of the envoy modules
+
+use super::ecc_envoy::{ECCOperationResponse, ECCStatusResponse};
+use super::ecc_operation::ECCStatus;
+use super::embassy::EnvoyFamilies;
+use super::message::EmbassyMessage;
+use super::status_manager::StatusManager;
+use super::surveyor_envoy::SurveyorResponse;
+use super::surveyor_status::{DiskHealth, DiskHealthState};
+
+/// A status message from an ECCEnvoy reporting the given status
+pub fn ecc_status(id: usize, status: ECCStatus) -> EmbassyMessage {
+    let response = ECCStatusResponse {
+        state: status.into(),
+        ..Default::default()
+    };
+    EmbassyMessage::compose_ecc_status(serde_yaml::to_string(&response).unwrap(), id)
+}
+
+/// An operation response from an ECCEnvoy with the given error code
+pub fn ecc_response(id: usize, error_code: i32) -> EmbassyMessage {
+    let response = ECCOperationResponse {
+        error_code,
+        error_message: String::from(if error_code == 0 { "" } else { "failed" }),
+        text: String::new(),
+    };
+    EmbassyMessage::compose_ecc_response(serde_yaml::to_string(&response).unwrap(), id)
+}
+
+/// A status message from an online data router reporting the given disk health
+pub fn surveyor_health(id: usize, state: Option<DiskHealthState>) -> EmbassyMessage {
+    let response = SurveyorResponse {
+        state: 1,
+        disk_health: state.map(|state| DiskHealth {
+            state,
+            details: String::new(),
+        }),
+        ..Default::default()
+    };
+    EmbassyMessage::compose_surveyor_response(serde_yaml::to_string(&response).unwrap(), id)
+}
+
+/// A connected status manager with every ECC module (CoBos then the MuTaNT) at the given statuses
+pub fn status_manager(statuses: &[ECCStatus]) -> StatusManager {
+    let mut manager = StatusManager::new(statuses.len() - 1);
+    manager.set_monitored_families(&EnvoyFamilies::default());
+    set_statuses(&mut manager, statuses);
+    manager
+}
+
+/// Report new statuses for every ECC module
+pub fn set_statuses(manager: &mut StatusManager, statuses: &[ECCStatus]) {
+    let messages: Vec<EmbassyMessage> = statuses
+        .iter()
+        .enumerate()
+        .map(|(id, status)| ecc_status(id, status.clone()))
+        .collect();
+    manager.handle_messages(&messages).unwrap();
+}
@@ -1,10 +1,15 @@
-use super::constants::{MUTANT_ID, NUMBER_OF_MODULES};
-use super::ecc_operation::ECCOperation;
+use super::ecc_operation::{ECCOperation, ECCStatus, ModuleOrder};
 use super::embassy::Embassy;
 use super::error::EmbassyError;
 use super::message::EmbassyMessage;
-use super::status_manager::StatusManager;
+use super::status_manager::{ECCSnapshot, StatusManager};
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
 
+/// Drain the messages recieved by the embassy into the status manager, and advance any targets.
+/// This is the only path which drains messages. It takes both the embassy and the status manager
+/// mutably, so the blocking helpers below and the per-frame poll in the app can never interleave,
+/// and each message is taken from the channel (and handled) exactly once.
 pub fn poll_embassy(
     embassy: &mut Embassy,
     status_manager: &mut StatusManager,
@@ -14,10 +19,89 @@ pub fn poll_embassy(
     }
     match embassy.poll_messages() {
         Ok(messages) => {
+            let start = Instant::now();
             status_manager.handle_messages(&messages)?;
+            embassy.record_handle_time(start.elapsed());
         }
         Err(e) => tracing::error!("Embassy ran into an error polling the envoys: {}", e),
     };
+    advance_targets(embassy, status_manager);
+    Ok(())
+}
+
+/// Progress any ECC envoys which have a target status, one operation at a time. Each
+/// envoy is only sent its next operation once the previous one has completed and the ordering
+/// constraints between the MuTaNT and CoBos allow it. Targets are dropped if reached, or
+/// if an operation fails or the envoy enters an error state.
+/// If a system target is set, a failure of any module aborts the entire plan.
+pub fn advance_targets(embassy: &mut Embassy, status_manager: &mut StatusManager) {
+    for (id, target) in status_manager.get_ecc_targets() {
+        if status_manager.is_ecc_held(id) {
+            continue;
+        }
+        let status = status_manager.get_ecc_status(id);
+        let failure = if let Some(error) = status_manager.get_operation_error(id) {
+            Some(error)
+        } else if matches!(status, ECCStatus::ErrorStat | ECCStatus::Unreachable) {
+            Some(format!("status is {status}"))
+        } else {
+            None
+        };
+
+        if let Some(failure) = failure {
+            tracing::error!("Module id {id} could not be brought to {target}: {failure}");
+            if let Some(system_target) = status_manager.get_system_target() {
+                tracing::error!("Aborted bringing the system to {system_target}");
+                status_manager.clear_ecc_targets();
+                return;
+            }
+            status_manager.clear_ecc_target(id);
+        } else if status == target {
+            tracing::info!("Module id {id} reached {target}");
+            status_manager.clear_ecc_target(id);
+        } else if status_manager.can_ecc_go_forward(id) {
+            transition_ecc(embassy, status_manager, vec![id], true);
+        }
+    }
+
+    if status_manager.get_ecc_targets().is_empty() {
+        if let Some(system_target) = status_manager.get_system_target() {
+            tracing::info!("System reached {system_target}");
+            status_manager.clear_ecc_targets();
+        }
+    }
+}
+
+/// Plan bringing the entire system to a target status. Every module which is not already at
+/// the target is given it as a target, and is then driven there by advance_targets as statuses
+/// come in. The ordering of the MuTaNT and CoBos is enforced by StatusManager::can_ecc_go_forward.
+/// Fails without planning anything if any module cannot be progressed to the target.
+pub fn plan_system_target(
+    status_manager: &mut StatusManager,
+    target: ECCStatus,
+) -> Result<(), EmbassyError> {
+    let position = |status: &ECCStatus| ECCStatus::SERVER_STATES.iter().position(|s| s == status);
+    let target_position = match position(&target) {
+        Some(p) => p,
+        None => return Err(EmbassyError::UnreachableTarget(0, target.clone(), target)),
+    };
+    let mut planned: Vec<usize> = vec![];
+    for id in status_manager.module_ids() {
+        let status = status_manager.get_ecc_status(id);
+        if status == target {
+            continue;
+        }
+        match position(&status) {
+            Some(p) if p < target_position && status.can_go_forward() => planned.push(id),
+            _ => return Err(EmbassyError::UnreachableTarget(id, status, target)),
+        }
+    }
+
+    tracing::info!("Bringing the system to {target}...");
+    for id in planned {
+        status_manager.set_ecc_target(id, target.clone());
+    }
+    status_manager.set_system_target(target);
     Ok(())
 }
 
@@ -45,171 +129,1165 @@ pub fn transition_ecc(
             status.get_backward_operation()
         };
         match operation {
-            ECCOperation::Invalid => (),
+            ECCOperation::Invalid => status_manager.set_ecc_busy(id),
             _ => {
-                match embassy.submit_message(EmbassyMessage::compose_ecc_op(operation.into(), id)) {
-                    Ok(()) => (),
-                    Err(e) => tracing::error!("Embassy had an error sending a message: {}", e),
+                if let Err(e) = send_operation(embassy, status_manager, id, operation) {
+                    tracing::error!("Embassy had an error sending a message: {}", e);
                 }
             }
         }
-        status_manager.set_ecc_busy(id);
     }
 }
 
-/// Send the mutant forward from described to prepared and block on waiting
-/// until that transition is complete
-pub fn forward_mutant_prepared_blocking(
+/// Record and send an operation to an ECC envoy, marking it busy. Every operation goes through
+/// here, so that the last operation of each envoy can be retried if it fails.
+fn send_operation(
     embassy: &mut Embassy,
     status_manager: &mut StatusManager,
+    id: usize,
+    operation: ECCOperation,
 ) -> Result<(), EmbassyError> {
-    transition_ecc(embassy, status_manager, vec![MUTANT_ID], true);
-    loop {
-        poll_embassy(embassy, status_manager)?;
-        if status_manager.is_mutant_prepared() {
-            break;
+    status_manager.record_operation(id, operation.clone());
+    embassy.submit_message(EmbassyMessage::compose_ecc_op(operation.into(), id))?;
+    status_manager.set_ecc_busy(id);
+    Ok(())
+}
+
+/// Transition a selection of ECC envoys. Envoys which cannot make the requested transition
+/// from their current status are skipped with a warning, rather than sent an invalid operation.
+pub fn transition_selected(
+    embassy: &mut Embassy,
+    status_manager: &mut StatusManager,
+    ids: &[usize],
+    is_forward: bool,
+) {
+    let mut valid: Vec<usize> = vec![];
+    for id in ids.iter().copied() {
+        let can_go = if is_forward {
+            status_manager.can_ecc_go_forward(id)
+        } else {
+            status_manager.get_ecc_status(id).can_go_backward()
+        };
+        if can_go {
+            valid.push(id);
+        } else {
+            tracing::warn!(
+                "Skipping module id {id}, it cannot go {} from status {}",
+                if is_forward { "forward" } else { "backward" },
+                status_manager.get_ecc_status(id)
+            );
         }
     }
-    Ok(())
+    transition_ecc(embassy, status_manager, valid, is_forward);
 }
 
-/// Send all of the CoBos forward from prepared to Ready (Configure transition) and
-/// block on waiting until all of those transitions are complete
-pub fn forward_cobos_ready_blocking(
+/// Re-submit the last operation of an ECC envoy if it failed. Nothing is sent
+/// if the envoy is still waiting on an operation.
+pub fn retry_failed_operation(
     embassy: &mut Embassy,
     status_manager: &mut StatusManager,
+    id: usize,
 ) -> Result<(), EmbassyError> {
-    let all_ids_but_mutant: Vec<usize> = (0..(NUMBER_OF_MODULES - 1)).collect();
-    transition_ecc(embassy, status_manager, all_ids_but_mutant, true);
-    loop {
-        poll_embassy(embassy, status_manager)?;
-        if status_manager.is_all_but_mutant_ready() {
-            break;
+    if status_manager.is_ecc_held(id) {
+        return Ok(());
+    }
+    let operation = match status_manager.get_failed_operation(id) {
+        Some(op) => op.clone(),
+        None => return Ok(()),
+    };
+    tracing::info!("Retrying {operation} for module id {id}");
+    send_operation(embassy, status_manager, id, operation)?;
+    status_manager.mark_retry(id);
+    Ok(())
+}
+
+/// An operation the forward transition sends to one module
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedOperation {
+    pub module: usize,
+    pub operation: ECCOperation,
+    /// The status the module is expected to be in when the operation is sent
+    pub from: ECCStatus,
+    /// The status the operation brings the module to
+    pub to: ECCStatus,
+    /// The operations of a stage are only sent once the previous stage reached its status
+    pub stage: usize,
+}
+
+/// The operations a forward transition of the whole system will send, in the order they are sent
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransitionPlan {
+    pub system: ECCStatus,
+    pub operation: ECCOperation,
+    /// The status the system reaches. Each stage waits for the previous one to reach it.
+    pub target: ECCStatus,
+    pub order: ModuleOrder,
+    pub mutant: usize,
+    pub operations: Vec<PlannedOperation>,
+}
+
+impl TransitionPlan {
+    /// The number of stages in the plan
+    pub fn stages(&self) -> usize {
+        self.operations
+            .iter()
+            .map(|planned| planned.stage + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The operations sent in a stage
+    pub fn stage(&self, stage: usize) -> Vec<&PlannedOperation> {
+        self.operations
+            .iter()
+            .filter(|planned| planned.stage == stage)
+            .collect()
+    }
+
+    /// The plan as JSON, for tools outside of the app. Each operation lists the modules and the
+    /// status it waits for before it is sent.
+    pub fn to_json(&self) -> serde_json::Value {
+        let operations: Vec<serde_json::Value> = self
+            .operations
+            .iter()
+            .map(|planned| {
+                let waits_for = planned.stage.checked_sub(1).map(|previous| {
+                    let modules: Vec<usize> =
+                        self.stage(previous).iter().map(|p| p.module).collect();
+                    serde_json::json!({
+                        "modules": modules,
+                        "status": self.target.to_string(),
+                    })
+                });
+                serde_json::json!({
+                    "module": planned.module,
+                    "role": if planned.module == self.mutant { "MuTaNT" } else { "CoBo" },
+                    "operation": planned.operation.to_string(),
+                    "from": planned.from.to_string(),
+                    "to": planned.to.to_string(),
+                    "stage": planned.stage,
+                    "waits_for": waits_for,
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "system": self.system.to_string(),
+            "operation": self.operation.to_string(),
+            "target": self.target.to_string(),
+            "order": self.order.to_string(),
+            "operations": operations,
+        })
+    }
+}
+
+impl std::fmt::Display for TransitionPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.operation, self.order)?;
+        for stage in 0..self.stages() {
+            let modules: Vec<String> = self
+                .stage(stage)
+                .iter()
+                .map(|planned| planned.module.to_string())
+                .collect();
+            write!(f, "; stage {}: modules {}", stage + 1, modules.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Plan the forward transition of the whole system from a snapshot of the module statuses. The
+/// MuTaNT is the last module. Modules which cannot go forward from their status (CoBos waived by
+/// the quorum policy) are left out. Fails if the system status has no forward operation.
+pub fn plan_forward_transition(
+    system: &ECCStatus,
+    snapshot: &ECCSnapshot,
+) -> Result<TransitionPlan, EmbassyError> {
+    let operation = system.get_forward_operation();
+    if matches!(operation, ECCOperation::Invalid) {
+        return Err(EmbassyError::InvalidTransition(operation));
+    }
+    let target =
+        forward_status(system).ok_or(EmbassyError::InvalidTransition(operation.clone()))?;
+    let order = operation.module_order();
+    let mutant = snapshot.statuses.len().saturating_sub(1);
+    let stage = |module: usize| match order {
+        ModuleOrder::Any => 0,
+        ModuleOrder::MutantFirst => usize::from(module != mutant),
+        ModuleOrder::CobosFirst => usize::from(module == mutant),
+    };
+    let mut operations: Vec<PlannedOperation> = snapshot
+        .statuses
+        .iter()
+        .enumerate()
+        .filter_map(|(module, status)| {
+            let operation = status.get_forward_operation();
+            if matches!(operation, ECCOperation::Invalid) {
+                return None;
+            }
+            Some(PlannedOperation {
+                module,
+                operation,
+                from: status.clone(),
+                to: forward_status(status)?,
+                stage: stage(module),
+            })
+        })
+        .collect();
+    operations.sort_by_key(|planned| (planned.stage, planned.module));
+    Ok(TransitionPlan {
+        system: system.clone(),
+        operation,
+        target,
+        order,
+        mutant,
+        operations,
+    })
+}
+
+/// The status a forward operation brings a module to from a status
+fn forward_status(status: &ECCStatus) -> Option<ECCStatus> {
+    let position = ECCStatus::SERVER_STATES.iter().position(|s| s == status)?;
+    ECCStatus::SERVER_STATES.get(position + 1).cloned()
+}
+
+/// Has a stage of a plan reached the target status. Under the quorum policy only a quorum of the
+/// CoBos must reach it.
+fn is_stage_reached(status_manager: &StatusManager, plan: &TransitionPlan, stage: usize) -> bool {
+    plan.stage(stage).iter().all(|planned| {
+        if planned.module == plan.mutant {
+            status_manager.get_ecc_status(planned.module) == plan.target
+        } else {
+            status_manager.cobos_agree(&plan.target)
         }
+    })
+}
+
+/// A forward transition of the whole system in progress. Like re-configuring, it is advanced
+/// each frame rather than blocking, so that the UI keeps rendering between the stages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForwardTransition {
+    pub plan: TransitionPlan,
+    /// The stage whose operations were sent last
+    pub stage: usize,
+    /// The module statuses when one last changed, for the stall timeout
+    last_statuses: Vec<ECCStatus>,
+    last_progress: Instant,
+}
+
+impl ForwardTransition {
+    /// The number of planned modules which reached the status the plan brings them to
+    pub fn completed(&self, status_manager: &StatusManager) -> usize {
+        self.plan
+            .operations
+            .iter()
+            .filter(|planned| {
+                !status_manager.is_ecc_held(planned.module)
+                    && status_manager.get_ecc_status(planned.module) == planned.to
+            })
+            .count()
+    }
+}
+
+/// Send the operations of a stage of a plan
+fn send_stage(
+    embassy: &mut Embassy,
+    status_manager: &mut StatusManager,
+    plan: &TransitionPlan,
+    stage: usize,
+) -> Result<(), EmbassyError> {
+    for planned in plan.stage(stage) {
+        send_operation(
+            embassy,
+            status_manager,
+            planned.module,
+            planned.operation.clone(),
+        )?;
     }
     Ok(())
 }
 
 /// Transition all of the envoys forward (Progress)
-/// This is slightly more complicated as order matters for two of the phases (Prepare and Configure)
+/// This is slightly more complicated as order matters for two of the phases (Prepare and Configure).
+/// The transition is planned up front and the first stage of the plan is sent. The later stages
+/// are sent by advance_forward_transition, so exactly the planned operations are sent.
 pub fn forward_transition_all(
     embassy: &mut Embassy,
     status_manager: &mut StatusManager,
-) -> Result<(), EmbassyError> {
+) -> Result<ForwardTransition, EmbassyError> {
     let system = status_manager.get_system_ecc_status();
-    let all_ids_but_mutant: Vec<usize> = (0..(NUMBER_OF_MODULES - 1)).collect();
-    let ids: Vec<usize> = (0..NUMBER_OF_MODULES).collect();
-    match system.get_forward_operation() {
-        //Describe operation: order doesn't matter
-        ECCOperation::Describe => {
-            transition_ecc(embassy, status_manager, ids, true);
-            Ok(())
-        }
-        //Prepare operation: mutant first, then cobos
-        ECCOperation::Prepare => {
-            forward_mutant_prepared_blocking(embassy, status_manager)?;
-            transition_ecc(embassy, status_manager, all_ids_but_mutant, true);
-            Ok(())
+    let plan = plan_forward_transition(&system, &status_manager.ecc_snapshot())?;
+    tracing::info!("Progressing the system: {plan}");
+    status_manager.clear_operation_errors();
+    send_stage(embassy, status_manager, &plan, 0)?;
+    Ok(ForwardTransition {
+        plan,
+        stage: 0,
+        last_statuses: status_manager.ecc_snapshot().statuses,
+        last_progress: Instant::now(),
+    })
+}
+
+/// Advance a forward transition of the system: once the current stage has reached its status
+/// the next stage is sent. Returns true once the last stage has reached its status.
+/// Fails if a module sent an operation reports an operation error or enters an error state, or if
+/// no module changes status within the stall timeout.
+pub fn advance_forward_transition(
+    transition: &mut ForwardTransition,
+    embassy: &mut Embassy,
+    status_manager: &mut StatusManager,
+    stall_timeout: Duration,
+) -> Result<bool, EmbassyError> {
+    for planned in transition
+        .plan
+        .operations
+        .iter()
+        .filter(|planned| planned.stage <= transition.stage)
+    {
+        let id = planned.module;
+        if let Some(error) = status_manager.get_operation_error(id) {
+            return Err(EmbassyError::ProgressFailed(
+                transition.stage + 1,
+                id,
+                error,
+            ));
         }
-        //Configure operation: cobos first, then mutant
-        ECCOperation::Configure => {
-            forward_cobos_ready_blocking(embassy, status_manager)?;
-            transition_ecc(embassy, status_manager, vec![MUTANT_ID], true);
-            Ok(())
+        let status = status_manager.get_ecc_status(id);
+        if matches!(status, ECCStatus::ErrorStat | ECCStatus::Unreachable) {
+            return Err(EmbassyError::ProgressFailed(
+                transition.stage + 1,
+                id,
+                status.to_string(),
+            ));
         }
-        e => Err(EmbassyError::InvalidTransition(e)),
     }
+    let statuses = status_manager.ecc_snapshot().statuses;
+    if statuses != transition.last_statuses {
+        transition.last_statuses = statuses;
+        transition.last_progress = Instant::now();
+    } else if transition.last_progress.elapsed() > stall_timeout {
+        return Err(EmbassyError::ProgressStalled(
+            transition.stage + 1,
+            stall_timeout.as_secs(),
+        ));
+    }
+    if !is_stage_reached(status_manager, &transition.plan, transition.stage) {
+        return Ok(false);
+    }
+    if transition.stage + 1 >= transition.plan.stages() {
+        return Ok(true);
+    }
+    transition.stage += 1;
+    send_stage(embassy, status_manager, &transition.plan, transition.stage)?;
+    transition.last_progress = Instant::now();
+    Ok(false)
 }
 
 /// Transition all of the envoys backwards (Regresss)
 pub fn backward_transition_all(embassy: &mut Embassy, status_manager: &mut StatusManager) {
-    let ids: Vec<usize> = (0..(NUMBER_OF_MODULES)).collect();
+    let ids = status_manager.module_ids();
     transition_ecc(embassy, status_manager, ids, false);
 }
 
-/// Start the MuTaNT
-pub fn start_mutant(embassy: &mut Embassy) -> Result<(), EmbassyError> {
-    embassy.submit_message(EmbassyMessage::compose_ecc_op(
-        ECCOperation::Start.into(),
-        MUTANT_ID,
-    ))
+/// The phases of re-configuring the system to pick up changed config files. Like the run
+/// start sequence, it is advanced each frame rather than blocking.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReconfigurePhase {
+    /// Waiting for the system to Breakup to Prepared
+    Breakup,
+    /// Waiting for the system to Configure back to Ready
+    Configure,
+    /// The system is Ready with the new configuration
+    Complete,
+}
+
+impl std::fmt::Display for ReconfigurePhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Breakup => write!(f, "Re-configuring system (Breakup)"),
+            Self::Configure => write!(f, "Re-configuring system (Configure)"),
+            Self::Complete => write!(f, "Re-configured"),
+        }
+    }
 }
 
-/// Reconfigure the MuTaNT (Regress once, and then Configure again) to
-/// restart the event numbers and timestamps. This is used when starting
-/// a new run.
-pub fn reconfigure_mutant_blocking(
+/// Begin re-configuring the system by sending it back from Ready to Prepared
+pub fn begin_reconfigure(
     embassy: &mut Embassy,
     status_manager: &mut StatusManager,
-) -> Result<(), EmbassyError> {
-    let mutant = vec![MUTANT_ID];
-    transition_ecc(embassy, status_manager, mutant.clone(), false);
-    loop {
-        poll_embassy(embassy, status_manager)?;
-        if status_manager.is_mutant_prepared() {
-            break;
+) -> Result<ReconfigurePhase, EmbassyError> {
+    if !status_manager.is_system_ready() {
+        return Err(EmbassyError::InvalidTransition(ECCOperation::Breakup));
+    }
+    status_manager.clear_operation_errors();
+    backward_transition_all(embassy, status_manager);
+    Ok(ReconfigurePhase::Breakup)
+}
+
+/// Advance re-configuring the system if the current phase is finished, returning the new phase.
+/// Once the system is Prepared it is brought back to Ready by the planner (see plan_system_target).
+/// Fails if any module reports an operation error or enters an error state.
+pub fn advance_reconfigure(
+    phase: ReconfigurePhase,
+    status_manager: &mut StatusManager,
+) -> Result<ReconfigurePhase, EmbassyError> {
+    if phase == ReconfigurePhase::Complete {
+        return Ok(phase);
+    }
+    for id in status_manager.module_ids() {
+        if let Some(error) = status_manager.get_operation_error(id) {
+            return Err(EmbassyError::ReconfigureFailed(phase, id, error));
+        }
+        let status = status_manager.get_ecc_status(id);
+        if matches!(status, ECCStatus::ErrorStat | ECCStatus::Unreachable) {
+            return Err(EmbassyError::ReconfigureFailed(
+                phase,
+                id,
+                status.to_string(),
+            ));
         }
     }
-    transition_ecc(embassy, status_manager, mutant, true);
-    loop {
-        poll_embassy(embassy, status_manager)?;
-        if status_manager.is_mutant_ready() {
-            break;
+
+    let system = status_manager.get_system_ecc_status();
+    match phase {
+        ReconfigurePhase::Breakup if system == ECCStatus::Prepared => {
+            plan_system_target(status_manager, ECCStatus::Ready)?;
+            Ok(ReconfigurePhase::Configure)
         }
+        ReconfigurePhase::Configure
+            if system == ECCStatus::Ready && status_manager.get_system_target().is_none() =>
+        {
+            Ok(ReconfigurePhase::Complete)
+        }
+        _ => Ok(phase),
+    }
+}
+
+/// How often the modules are polled by the blocking helpers
+const BLOCKING_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Regressing every module to Idle, before disconnecting. Like re-configuring, it is advanced
+/// each frame rather than blocking.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IdleRegression {
+    /// The modules which could not be regressed, and are no longer sent operations
+    failed: BTreeSet<usize>,
+    /// The module statuses when one last changed, for the step timeout
+    last_statuses: Vec<ECCStatus>,
+    last_progress: Instant,
+    pub start_time: Instant,
+}
+
+impl IdleRegression {
+    /// The number of modules which are Idle
+    pub fn idle(&self, status_manager: &StatusManager) -> usize {
+        status_manager
+            .module_ids()
+            .into_iter()
+            .filter(|id| status_manager.get_ecc_status(*id) == ECCStatus::Idle)
+            .count()
     }
-    Ok(())
 }
 
-/// Stop the MuTaNT and wait until that is completed
+/// Begin regressing every module to Idle. Any targets and old operation errors are dropped.
+pub fn begin_regress_to_idle(status_manager: &mut StatusManager) -> IdleRegression {
+    status_manager.clear_ecc_targets();
+    status_manager.clear_operation_errors();
+    IdleRegression {
+        failed: BTreeSet::new(),
+        last_statuses: status_manager.ecc_snapshot().statuses,
+        last_progress: Instant::now(),
+        start_time: Instant::now(),
+    }
+}
+
+/// Advance regressing every module to Idle, one backward operation at a time. The regression is
+/// bounded: if no module changes status within the step timeout it is abandoned. Modules whose
+/// operation fails are not retried. Once finished, returns the modules which were not regressed,
+/// with their last status.
+pub fn advance_regress_to_idle(
+    regression: &mut IdleRegression,
+    embassy: &mut Embassy,
+    status_manager: &mut StatusManager,
+    step_timeout: Duration,
+) -> Option<Vec<(usize, ECCStatus)>> {
+    let mut pending = false;
+    for id in status_manager.module_ids() {
+        if regression.failed.contains(&id) {
+            continue;
+        }
+        if let Some(error) = status_manager.get_operation_error(id) {
+            tracing::error!("Module id {id} could not be regressed to Idle: {error}");
+            regression.failed.insert(id);
+            continue;
+        }
+        let status = status_manager.get_ecc_status(id);
+        if status_manager.is_ecc_held(id) || status == ECCStatus::Busy {
+            pending = true;
+        } else if status.can_go_backward() {
+            transition_ecc(embassy, status_manager, vec![id], false);
+            pending = true;
+        } else if status != ECCStatus::Idle {
+            tracing::error!("Module id {id} cannot be regressed to Idle from {status}");
+            regression.failed.insert(id);
+        }
+    }
+    if pending {
+        let statuses = status_manager.ecc_snapshot().statuses;
+        if statuses != regression.last_statuses {
+            regression.last_statuses = statuses;
+            regression.last_progress = Instant::now();
+            return None;
+        } else if regression.last_progress.elapsed() <= step_timeout {
+            return None;
+        }
+        tracing::error!(
+            "Timed out after {} s waiting for the modules to regress to Idle",
+            step_timeout.as_secs()
+        );
+    }
+    Some(
+        status_manager
+            .module_ids()
+            .into_iter()
+            .map(|id| (id, status_manager.get_ecc_status(id)))
+            .filter(|(_, status)| *status != ECCStatus::Idle)
+            .collect(),
+    )
+}
+
+/// Start the MuTaNT
+pub fn start_mutant(
+    embassy: &mut Embassy,
+    status_manager: &mut StatusManager,
+) -> Result<(), EmbassyError> {
+    let mutant = status_manager.mutant_id();
+    send_operation(embassy, status_manager, mutant, ECCOperation::Start)
+}
+
+/// Stop the MuTaNT and wait until it is back at Ready. Fails if the Stop operation fails, the
+/// MuTaNT enters an error state, or it is not stopped within the timeout.
 pub fn stop_mutant_blocking(
     embassy: &mut Embassy,
     status_manager: &mut StatusManager,
+    timeout: Duration,
 ) -> Result<(), EmbassyError> {
-    embassy.submit_message(EmbassyMessage::compose_ecc_op(
-        ECCOperation::Stop.into(),
-        MUTANT_ID,
-    ))?;
+    let mutant = status_manager.mutant_id();
+    status_manager.dismiss_operation_error(mutant);
+    send_operation(embassy, status_manager, mutant, ECCOperation::Stop)?;
 
     //Wait for mutant to stop
+    let start = Instant::now();
     loop {
         poll_embassy(embassy, status_manager)?;
-        if status_manager.is_mutant_stopped() {
-            break;
+        if let Some(error) = status_manager.get_operation_error(mutant) {
+            return Err(EmbassyError::MutantStopFailed(error));
         }
+        let status = status_manager.get_ecc_status(mutant);
+        if matches!(status, ECCStatus::ErrorStat | ECCStatus::Unreachable) {
+            return Err(EmbassyError::MutantStopFailed(format!(
+                "status is {status}"
+            )));
+        }
+        if !status_manager.is_ecc_held(mutant) && status_manager.is_mutant_stopped() {
+            return Ok(());
+        }
+        if start.elapsed() > timeout {
+            return Err(EmbassyError::MutantStopTimedOut(timeout.as_secs()));
+        }
+        std::thread::sleep(BLOCKING_POLL_INTERVAL);
     }
+}
 
+/// Start all of the CoBos
+pub fn start_cobos(
+    embassy: &mut Embassy,
+    status_manager: &mut StatusManager,
+) -> Result<(), EmbassyError> {
+    for id in status_manager.cobo_ids() {
+        send_operation(embassy, status_manager, id, ECCOperation::Start)?;
+    }
     Ok(())
 }
 
-/// Start all of the CoBos and wait until that is completed
-pub fn start_cobos_blocking(
+/// Stop all of the CoBos
+pub fn stop_cobos(
     embassy: &mut Embassy,
     status_manager: &mut StatusManager,
 ) -> Result<(), EmbassyError> {
-    for id in 0..(NUMBER_OF_MODULES - 1) {
-        embassy.submit_message(EmbassyMessage::compose_ecc_op(
-            ECCOperation::Start.into(),
-            id,
-        ))?;
+    for id in status_manager.cobo_ids() {
+        send_operation(embassy, status_manager, id, ECCOperation::Stop)?;
     }
+    Ok(())
+}
 
-    //Wait for good CoBo status
-    loop {
-        poll_embassy(embassy, status_manager)?;
-        if status_manager.is_all_but_mutant_running() {
-            break;
+/// The phases of the run start sequence. The sequence is advanced each frame
+/// rather than blocking, so that the UI stays responsive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunStartPhase {
+    /// Waiting for the MuTaNT to Breakup, which resets the event numbers and timestamps
+    BreakupMutant,
+    /// Waiting for the MuTaNT to Configure back to Ready
+    ConfigureMutant,
+    /// Waiting for all of the CoBos to be Running
+    StartCobos,
+    /// The MuTaNT was started, the run is started
+    Complete,
+}
+
+impl std::fmt::Display for RunStartPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BreakupMutant => write!(f, "Re-configuring MuTaNT (Breakup)"),
+            Self::ConfigureMutant => write!(f, "Re-configuring MuTaNT (Configure)"),
+            Self::StartCobos => write!(f, "Starting CoBos"),
+            Self::Complete => write!(f, "Started"),
         }
     }
-    Ok(())
 }
 
-/// Stop all of the CoBos
-pub fn stop_cobos(embassy: &mut Embassy) -> Result<(), EmbassyError> {
-    for id in 0..(NUMBER_OF_MODULES - 1) {
-        embassy.submit_message(EmbassyMessage::compose_ecc_op(
-            ECCOperation::Stop.into(),
-            id,
-        ))?;
+/// Begin the run start sequence. If reset_mutant is true the MuTaNT is sent backwards, so that it can be
+/// re-configured to reset the timestamps. Otherwise the sequence goes straight to starting the CoBos.
+pub fn begin_run_start(
+    embassy: &mut Embassy,
+    status_manager: &mut StatusManager,
+    reset_mutant: bool,
+) -> Result<RunStartPhase, EmbassyError> {
+    status_manager.clear_operation_errors();
+    if reset_mutant {
+        let mutant = vec![status_manager.mutant_id()];
+        transition_ecc(embassy, status_manager, mutant, false);
+        Ok(RunStartPhase::BreakupMutant)
+    } else {
+        start_cobos(embassy, status_manager)?;
+        Ok(RunStartPhase::StartCobos)
+    }
+}
+
+/// Advance the run start sequence if the current phase is finished, returning the new phase.
+/// Fails if any module in the current phase reports an operation error or enters an error state.
+pub fn advance_run_start(
+    phase: RunStartPhase,
+    embassy: &mut Embassy,
+    status_manager: &mut StatusManager,
+) -> Result<RunStartPhase, EmbassyError> {
+    let mutant_id = status_manager.mutant_id();
+    let ids = match phase {
+        RunStartPhase::BreakupMutant | RunStartPhase::ConfigureMutant => vec![mutant_id],
+        RunStartPhase::StartCobos => status_manager.cobo_ids(),
+        RunStartPhase::Complete => return Ok(phase),
+    };
+    for id in ids {
+        if let Some(error) = status_manager.get_operation_error(id) {
+            return Err(EmbassyError::RunStartFailed(phase, id, error));
+        }
+        let status = status_manager.get_ecc_status(id);
+        if matches!(status, ECCStatus::ErrorStat | ECCStatus::Unreachable) {
+            return Err(EmbassyError::RunStartFailed(phase, id, status.to_string()));
+        }
+    }
+
+    match phase {
+        RunStartPhase::BreakupMutant if status_manager.is_mutant_prepared() => {
+            transition_ecc(embassy, status_manager, vec![mutant_id], true);
+            Ok(RunStartPhase::ConfigureMutant)
+        }
+        RunStartPhase::ConfigureMutant if status_manager.is_mutant_ready() => {
+            start_cobos(embassy, status_manager)?;
+            Ok(RunStartPhase::StartCobos)
+        }
+        RunStartPhase::StartCobos if status_manager.is_all_but_mutant_running() => {
+            start_mutant(embassy, status_manager)?;
+            Ok(RunStartPhase::Complete)
+        }
+        _ => Ok(phase),
+    }
+}
+
+/// The outcome of a bulk transition for a single module
+#[derive(Debug, Clone)]
+pub struct TransitionReportRow {
+    pub id: usize,
+    pub before: ECCStatus,
+    pub after: ECCStatus,
+    /// Time from the start of the transition to the module's operation response
+    pub duration: Option<Duration>,
+    pub error: Option<String>,
+}
+
+/// Before and after comparison of the ECC state for a bulk transition
+#[derive(Debug, Clone)]
+pub struct TransitionReport {
+    pub rows: Vec<TransitionReportRow>,
+}
+
+impl TransitionReport {
+    /// Compare a snapshot taken before the transition with the current state. Only operation
+    /// responses recieved after the snapshot are attributed to the transition.
+    pub fn new(before: &ECCSnapshot, status_manager: &StatusManager) -> Self {
+        let rows = before
+            .statuses
+            .iter()
+            .enumerate()
+            .map(|(id, status)| {
+                let record = status_manager
+                    .get_operation_record(id)
+                    .filter(|record| record.time >= before.time);
+                TransitionReportRow {
+                    id,
+                    before: status.clone(),
+                    after: status_manager.get_ecc_status(id),
+                    duration: record.map(|record| record.time - before.time),
+                    error: record.filter(|record| record.is_error()).map(|record| {
+                        format!(
+                            "Error code {}: {}",
+                            record.response.error_code, record.response.error_message
+                        )
+                    }),
+                }
+            })
+            .collect();
+        Self { rows }
+    }
+
+    /// The time the latest operation response was recieved, relative to the start of the transition
+    pub fn latest_response(&self) -> Option<Duration> {
+        self.rows.iter().filter_map(|row| row.duration).max()
+    }
+
+    /// Log the modules which changed state or failed
+    pub fn log(&self) {
+        for row in self.rows.iter() {
+            match &row.error {
+                Some(e) => tracing::warn!(
+                    "Module {}: {} -> {} failed: {e}",
+                    row.id,
+                    row.before,
+                    row.after
+                ),
+                None if row.before != row.after => {
+                    tracing::info!("Module {}: {} -> {}", row.id, row.before, row.after)
+                }
+                None => (),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envoy::embassy::Loopback;
+    use crate::envoy::testing::{ecc_response, ecc_status, status_manager};
+    use ECCStatus::{Described, ErrorStat, Idle, Prepared, Ready, Running};
+
+    const STALL: Duration = Duration::from_secs(60);
+
+    fn op(operation: ECCOperation) -> String {
+        operation.into()
+    }
+
+    #[test]
+    fn start_and_stop_are_recorded() {
+        let (mut embassy, mut loopback) = Embassy::loopback(3, 8);
+        let mut status = status_manager(&[Ready, Ready, Ready]);
+        start_cobos(&mut embassy, &mut status).unwrap();
+        start_mutant(&mut embassy, &mut status).unwrap();
+        assert_eq!(
+            loopback.take_operations(),
+            vec![
+                (0, op(ECCOperation::Start)),
+                (1, op(ECCOperation::Start)),
+                (2, op(ECCOperation::Start)),
+            ]
+        );
+        stop_cobos(&mut embassy, &mut status).unwrap();
+        for id in status.module_ids() {
+            assert!(status.is_ecc_held(id));
+        }
+        assert_eq!(status.get_last_operation(0), Some(&ECCOperation::Stop));
+        assert_eq!(status.get_last_operation(2), Some(&ECCOperation::Start));
+    }
+
+    #[test]
+    fn failed_start_can_be_retried() {
+        let (mut embassy, mut loopback) = Embassy::loopback(2, 8);
+        let mut status = status_manager(&[Ready, Ready]);
+        start_mutant(&mut embassy, &mut status).unwrap();
+        loopback.envoys.try_send(ecc_response(1, 7)).unwrap();
+        poll_embassy(&mut embassy, &mut status).unwrap();
+        assert_eq!(status.get_failed_operation(1), Some(&ECCOperation::Start));
+        loopback.take_operations();
+
+        retry_failed_operation(&mut embassy, &mut status, 1).unwrap();
+        assert_eq!(
+            loopback.take_operations(),
+            vec![(1, op(ECCOperation::Start))]
+        );
+        assert_eq!(status.get_retry_count(1), 1);
+    }
+
+    #[test]
+    fn stop_mutant_waits_for_ready() {
+        let (mut embassy, mut loopback) = Embassy::loopback(2, 8);
+        let mut status = status_manager(&[Running, Running]);
+        loopback.envoys.try_send(ecc_response(1, 0)).unwrap();
+        loopback.envoys.try_send(ecc_status(1, Ready)).unwrap();
+        stop_mutant_blocking(&mut embassy, &mut status, Duration::from_secs(5)).unwrap();
+        assert_eq!(
+            loopback.take_operations(),
+            vec![(1, op(ECCOperation::Stop))]
+        );
+        assert!(status.is_mutant_stopped());
+    }
+
+    #[test]
+    fn stop_mutant_fails_on_operation_error() {
+        let (mut embassy, loopback) = Embassy::loopback(2, 8);
+        let mut status = status_manager(&[Running, Running]);
+        loopback.envoys.try_send(ecc_response(1, 3)).unwrap();
+        let result = stop_mutant_blocking(&mut embassy, &mut status, Duration::from_secs(5));
+        assert!(matches!(result, Err(EmbassyError::MutantStopFailed(_))));
+    }
+
+    #[test]
+    fn stop_mutant_fails_on_error_status() {
+        let (mut embassy, loopback) = Embassy::loopback(2, 8);
+        let mut status = status_manager(&[Running, Running]);
+        loopback.envoys.try_send(ecc_response(1, 0)).unwrap();
+        loopback.envoys.try_send(ecc_status(1, ErrorStat)).unwrap();
+        let result = stop_mutant_blocking(&mut embassy, &mut status, Duration::from_secs(5));
+        assert!(matches!(result, Err(EmbassyError::MutantStopFailed(_))));
+    }
+
+    /// Play the envoys: answer every submitted operation successfully and report the given status
+    fn answer_operations(
+        loopback: &mut Loopback,
+        status: impl Fn(&str) -> ECCStatus,
+    ) -> Vec<(usize, String)> {
+        let operations = loopback.take_operations();
+        for (id, operation) in operations.iter() {
+            loopback.envoys.try_send(ecc_response(*id, 0)).unwrap();
+            loopback
+                .envoys
+                .try_send(ecc_status(*id, status(operation)))
+                .unwrap();
+        }
+        operations
+    }
+
+    #[test]
+    fn reconfigure_breaks_up_and_configures() {
+        let (mut embassy, mut loopback) = Embassy::loopback(3, 8);
+        let mut status = status_manager(&[Ready, Ready, Ready]);
+        let mut phase = begin_reconfigure(&mut embassy, &mut status).unwrap();
+        assert_eq!(phase, ReconfigurePhase::Breakup);
+        let sent = answer_operations(&mut loopback, |_| Prepared);
+        assert!(sent
+            .iter()
+            .all(|(_, operation)| *operation == op(ECCOperation::Breakup)));
+        assert_eq!(sent.len(), 3);
+
+        let mut configured = vec![];
+        for _ in 0..10 {
+            poll_embassy(&mut embassy, &mut status).unwrap();
+            phase = advance_reconfigure(phase, &mut status).unwrap();
+            if phase == ReconfigurePhase::Complete {
+                break;
+            }
+            configured.extend(answer_operations(&mut loopback, |_| Ready));
+        }
+        assert_eq!(phase, ReconfigurePhase::Complete);
+        assert!(status.is_system_ready());
+        // The CoBos are configured before the MuTaNT
+        assert_eq!(configured.last(), Some(&(2, op(ECCOperation::Configure))));
+        assert_eq!(configured.len(), 3);
+    }
+
+    #[test]
+    fn reconfigure_requires_ready() {
+        let (mut embassy, _loopback) = Embassy::loopback(2, 8);
+        let mut status = status_manager(&[Prepared, Prepared]);
+        assert!(begin_reconfigure(&mut embassy, &mut status).is_err());
+    }
+
+    #[test]
+    fn reconfigure_fails_on_operation_error() {
+        let (mut embassy, loopback) = Embassy::loopback(2, 8);
+        let mut status = status_manager(&[Ready, Ready]);
+        let phase = begin_reconfigure(&mut embassy, &mut status).unwrap();
+        loopback.envoys.try_send(ecc_response(0, 4)).unwrap();
+        poll_embassy(&mut embassy, &mut status).unwrap();
+        assert!(matches!(
+            advance_reconfigure(phase, &mut status),
+            Err(EmbassyError::ReconfigureFailed(
+                ReconfigurePhase::Breakup,
+                0,
+                _
+            ))
+        ));
+    }
+
+    #[test]
+    fn stop_mutant_times_out() {
+        let (mut embassy, _loopback) = Embassy::loopback(2, 8);
+        let mut status = status_manager(&[Running, Running]);
+        let result = stop_mutant_blocking(&mut embassy, &mut status, Duration::from_millis(50));
+        assert!(matches!(result, Err(EmbassyError::MutantStopTimedOut(_))));
+    }
+
+    #[test]
+    fn report_compares_mixed_outcomes() {
+        let mut status = status_manager(&[Ready, Ready, Ready]);
+        // A response from before the transition is not attributed to it
+        status.handle_messages(&[ecc_response(2, 0)]).unwrap();
+        let before = status.ecc_snapshot();
+        status
+            .handle_messages(&[
+                ecc_response(0, 0),
+                ecc_response(1, 4),
+                ecc_status(0, Running),
+                ecc_status(1, ErrorStat),
+            ])
+            .unwrap();
+        let report = TransitionReport::new(&before, &status);
+        let outcomes: Vec<(ECCStatus, ECCStatus, bool, bool)> = report
+            .rows
+            .iter()
+            .map(|row| {
+                (
+                    row.before.clone(),
+                    row.after.clone(),
+                    row.duration.is_some(),
+                    row.error.is_some(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                (Ready, Running, true, false),
+                (Ready, ErrorStat, true, true),
+                (Ready, Ready, false, false),
+            ]
+        );
+        assert!(report.rows[1]
+            .error
+            .as_ref()
+            .unwrap()
+            .contains("Error code 4"));
+        let latest = report.latest_response().unwrap();
+        assert!(latest >= report.rows[0].duration.unwrap());
+        assert!(latest >= report.rows[1].duration.unwrap());
+    }
+
+    #[test]
+    fn report_without_responses_has_no_latest_response() {
+        let status = status_manager(&[Ready, Ready]);
+        let report = TransitionReport::new(&status.ecc_snapshot(), &status);
+        assert!(report.rows.iter().all(|row| row.before == row.after));
+        assert_eq!(report.latest_response(), None);
+    }
+
+    #[test]
+    fn each_message_is_handled_once_across_polls() {
+        let total = 100;
+        let (mut embassy, loopback) = Embassy::loopback(2, total);
+        let mut status = status_manager(&[Ready, Ready]);
+        let counted = |status: &StatusManager| -> u64 {
+            status
+                .get_ecc_message_stats()
+                .iter()
+                .map(|stats| stats.messages)
+                .sum()
+        };
+        let before = counted(&status);
+        for index in 0..total {
+            loopback
+                .envoys
+                .try_send(ecc_status(index % 2, Ready))
+                .unwrap();
+        }
+        // More messages than a single poll takes, then polls with nothing left
+        for _ in 0..4 {
+            poll_embassy(&mut embassy, &mut status).unwrap();
+        }
+        assert_eq!(counted(&status) - before, total as u64);
+    }
+
+    /// Progress the whole system frame by frame while playing the envoys, answering each
+    /// operation with the target status. Returns the transition and the operations sent in
+    /// each frame which sent any.
+    fn execute_forward(statuses: &[ECCStatus]) -> (ForwardTransition, Vec<Vec<(usize, String)>>) {
+        let (mut embassy, mut loopback) = Embassy::loopback(statuses.len(), 8);
+        let mut status = status_manager(statuses);
+        let mut transition = forward_transition_all(&mut embassy, &mut status).unwrap();
+        let target = transition.plan.target.clone();
+        let mut frames = vec![];
+        for _ in 0..10 {
+            let sent = answer_operations(&mut loopback, |_| target.clone());
+            if !sent.is_empty() {
+                frames.push(sent);
+            }
+            poll_embassy(&mut embassy, &mut status).unwrap();
+            if advance_forward_transition(&mut transition, &mut embassy, &mut status, STALL)
+                .unwrap()
+            {
+                return (transition, frames);
+            }
+        }
+        panic!("The transition did not complete");
+    }
+
+    /// Each stage of the plan is sent in its own frame, exactly as planned
+    fn assert_follows_plan(plan: &TransitionPlan, frames: &[Vec<(usize, String)>]) {
+        assert_eq!(frames.len(), plan.stages());
+        for (stage, sent) in frames.iter().enumerate() {
+            let planned: Vec<(usize, String)> = plan
+                .stage(stage)
+                .iter()
+                .map(|planned| (planned.module, op(planned.operation.clone())))
+                .collect();
+            assert_eq!(*sent, planned);
+        }
+    }
+
+    #[test]
+    fn describe_executes_the_plan() {
+        let (transition, frames) = execute_forward(&[Idle, Idle, Idle]);
+        assert_eq!(transition.plan.stages(), 1);
+        assert_follows_plan(&transition.plan, &frames);
+    }
+
+    #[test]
+    fn prepare_executes_the_plan_mutant_first() {
+        let (transition, frames) = execute_forward(&[Described, Described, Described]);
+        assert_eq!(transition.plan.stages(), 2);
+        assert_follows_plan(&transition.plan, &frames);
+        assert_eq!(frames[0], vec![(2, op(ECCOperation::Prepare))]);
+    }
+
+    #[test]
+    fn configure_executes_the_plan_cobos_first() {
+        let (transition, frames) = execute_forward(&[Prepared, Prepared, Prepared]);
+        assert_eq!(transition.plan.stages(), 2);
+        assert_follows_plan(&transition.plan, &frames);
+        assert_eq!(frames[1], vec![(2, op(ECCOperation::Configure))]);
+    }
+
+    #[test]
+    fn progress_waits_for_the_stage_without_blocking() {
+        let (mut embassy, mut loopback) = Embassy::loopback(3, 8);
+        let mut status = status_manager(&[Described, Described, Described]);
+        let mut transition = forward_transition_all(&mut embassy, &mut status).unwrap();
+        assert_eq!(
+            loopback.take_operations(),
+            vec![(2, op(ECCOperation::Prepare))]
+        );
+        for _ in 0..3 {
+            poll_embassy(&mut embassy, &mut status).unwrap();
+            assert!(
+                !advance_forward_transition(&mut transition, &mut embassy, &mut status, STALL)
+                    .unwrap()
+            );
+        }
+        assert_eq!(transition.stage, 0);
+        assert!(loopback.take_operations().is_empty());
+        assert_eq!(transition.completed(&status), 0);
+    }
+
+    #[test]
+    fn progress_aborts_when_a_module_fails_mid_stage() {
+        let (mut embassy, mut loopback) = Embassy::loopback(3, 8);
+        let mut status = status_manager(&[Prepared, Prepared, Prepared]);
+        let mut transition = forward_transition_all(&mut embassy, &mut status).unwrap();
+        assert_eq!(
+            loopback.take_operations(),
+            vec![
+                (0, op(ECCOperation::Configure)),
+                (1, op(ECCOperation::Configure)),
+            ]
+        );
+        loopback.envoys.try_send(ecc_response(0, 0)).unwrap();
+        loopback.envoys.try_send(ecc_status(0, Ready)).unwrap();
+        loopback.envoys.try_send(ecc_response(1, 4)).unwrap();
+        poll_embassy(&mut embassy, &mut status).unwrap();
+        let result = advance_forward_transition(&mut transition, &mut embassy, &mut status, STALL);
+        assert!(matches!(result, Err(EmbassyError::ProgressFailed(1, 1, _))));
+        // The MuTaNT is never configured
+        assert!(loopback.take_operations().is_empty());
+    }
+
+    #[test]
+    fn progress_aborts_on_an_error_status() {
+        let (mut embassy, mut loopback) = Embassy::loopback(3, 8);
+        let mut status = status_manager(&[Described, Described, Described]);
+        let mut transition = forward_transition_all(&mut embassy, &mut status).unwrap();
+        loopback.take_operations();
+        loopback.envoys.try_send(ecc_response(2, 0)).unwrap();
+        loopback.envoys.try_send(ecc_status(2, ErrorStat)).unwrap();
+        poll_embassy(&mut embassy, &mut status).unwrap();
+        let result = advance_forward_transition(&mut transition, &mut embassy, &mut status, STALL);
+        assert!(matches!(result, Err(EmbassyError::ProgressFailed(1, 2, _))));
+        assert!(loopback.take_operations().is_empty());
+    }
+
+    #[test]
+    fn progress_gives_up_when_stalled() {
+        let (mut embassy, _loopback) = Embassy::loopback(3, 8);
+        let mut status = status_manager(&[Described, Described, Described]);
+        let mut transition = forward_transition_all(&mut embassy, &mut status).unwrap();
+        let stall = Duration::from_millis(200);
+        poll_embassy(&mut embassy, &mut status).unwrap();
+        assert!(
+            !advance_forward_transition(&mut transition, &mut embassy, &mut status, stall).unwrap()
+        );
+        std::thread::sleep(stall * 2);
+        let result = advance_forward_transition(&mut transition, &mut embassy, &mut status, stall);
+        assert!(matches!(result, Err(EmbassyError::ProgressStalled(1, 0))));
+    }
+
+    #[test]
+    fn regression_steps_every_module_to_idle() {
+        let (mut embassy, mut loopback) = Embassy::loopback(2, 8);
+        let mut status = status_manager(&[Prepared, Prepared]);
+        let mut regression = begin_regress_to_idle(&mut status);
+        let mut remaining = None;
+        let mut sent = vec![];
+        for reported in [Described, Idle, Idle] {
+            remaining = advance_regress_to_idle(&mut regression, &mut embassy, &mut status, STALL);
+            if remaining.is_some() {
+                break;
+            }
+            sent.extend(answer_operations(&mut loopback, |_| reported.clone()));
+            poll_embassy(&mut embassy, &mut status).unwrap();
+        }
+        assert_eq!(remaining, Some(vec![]));
+        assert_eq!(regression.idle(&status), 2);
+        assert_eq!(
+            sent,
+            vec![
+                (0, op(ECCOperation::Undo)),
+                (1, op(ECCOperation::Undo)),
+                (0, op(ECCOperation::Undo)),
+                (1, op(ECCOperation::Undo)),
+            ]
+        );
+    }
+
+    #[test]
+    fn regression_gives_up_when_stalled() {
+        let (mut embassy, _loopback) = Embassy::loopback(2, 8);
+        let mut status = status_manager(&[Ready, Ready]);
+        let mut regression = begin_regress_to_idle(&mut status);
+        let step = Duration::from_millis(200);
+        assert_eq!(
+            advance_regress_to_idle(&mut regression, &mut embassy, &mut status, step),
+            None
+        );
+        std::thread::sleep(step * 2);
+        assert_eq!(
+            advance_regress_to_idle(&mut regression, &mut embassy, &mut status, step),
+            Some(vec![(0, ECCStatus::Busy), (1, ECCStatus::Busy)])
+        );
     }
-    Ok(())
 }
@@ -21,6 +21,7 @@
 //! - Experiment Name: this is a unqiue identifier for this experiment. This name should match the name used to identify the ECC configuration files given to the CoBo/Mutant ECC servers.
 //! - Description: Currently unused. Potentially used in a automatic experiment log feature in the future.
 //! - Run Number: The number associated with the current data-taking run. This number *must* be unique for each run.
+//! - Network: The address (IP or hostname) of each CoBo by module id, and of the MuTaNT. Defaults to the standard AT-TPC subnet. Every CoBo must have an address to connect.
 //!
 //! Configurations can be saved using the File->Save menu. Configurations can then be loaded using File->Open. Configurations are serialized to YAML
 //! files using the [serde](https://serde.rs) library.
@@ -53,26 +54,29 @@
 //! it can be difficult to express this in a clear way. To help with this, the [tracing](https://tokio.rs/tokio/topics/tracing) library is used; tracing allows logging of async type systems
 //! in a way that aims to be expressive about where information is coming from. Tracing logs data to the terminal, so if things seem to not be working, check the terminal from which you spawned
 //! attpc_envoy and see if anything was reported.
+//! Each run also has its own log file, logs/<experiment>/run_<NNNN>.log, and anything logged between runs goes to logs/<experiment>/idle.log.
+//!
+//! ### Headless Planning
+//!
+//! `attpc_envoy plan [--json] [--config <path>]` connects to the ECC servers without opening the user interface, waits for every module to report its status,
+//! and prints the operations the next system Progress would send. Nothing is sent to the servers. Without `--config` the config saved by the setup wizard is used.
 
 mod command;
 mod envoy;
 mod ui;
 
+use envoy::runtime::build_runtime;
 use std::path::PathBuf;
-use tokio::runtime::Builder;
 use tracing_subscriber::fmt::writer::MakeWriterExt;
+use tracing_subscriber::layer::SubscriberExt;
 use ui::app::EnvoyApp;
+use ui::log_console::LogConsole;
+use ui::plan_cli::run_plan_command;
+use ui::run_log_file::RunLogFile;
+use ui::setup_wizard::load_saved_config;
 
 /// Program entry point
 fn main() {
-    //Create the async runtime
-    let runtime: tokio::runtime::Runtime = Builder::new_multi_thread()
-        .worker_threads(5)
-        .enable_time()
-        .enable_io()
-        .build()
-        .expect("Could not startup async runtime!");
-
     let log_path = PathBuf::from("logs/");
     if !log_path.exists() {
         match std::fs::create_dir(&log_path) {
@@ -86,7 +90,10 @@ fn main() {
     let rolling_log = tracing_appender::rolling::daily(log_path, "attpc_envoy_log");
     let stderr = std::io::stderr.with_max_level(tracing::Level::ERROR);
 
-    //Create our logging/tracing system.
+    //Create our logging/tracing system. Events also go to the in-app log console and the
+    //per-run log file
+    let (log_console, console_layer) = LogConsole::new();
+    let run_log = RunLogFile::default();
     let subscriber = tracing_subscriber::fmt()
         .compact()
         .with_file(true)
@@ -94,12 +101,31 @@ fn main() {
         .with_thread_ids(true)
         .with_target(false)
         .with_writer(stderr.and(rolling_log))
-        .finish();
+        .finish()
+        .with(console_layer)
+        .with(
+            tracing_subscriber::fmt::layer()
+                .compact()
+                .with_ansi(false)
+                .with_target(false)
+                .with_writer(run_log.clone()),
+        );
     tracing::subscriber::set_global_default(subscriber)
         .expect("Could not initialize the tracing system!");
 
     tracing::info!("Tracing initialized!");
 
+    //Create the async runtime from the saved config, so both modes share its settings
+    let config = load_saved_config();
+    let runtime_config = config.runtime.clone();
+    let runtime = build_runtime(&runtime_config).expect("Could not startup async runtime!");
+
+    //The plan subcommand runs headless, without the user interface
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "plan") {
+        std::process::exit(run_plan_command(&args[1..], runtime, runtime_config));
+    }
+
     //Start our application
     let native_options = eframe::NativeOptions {
         viewport: eframe::egui::ViewportBuilder::default()
@@ -110,7 +136,15 @@ fn main() {
     match eframe::run_native(
         "ATTPC Envoy",
         native_options,
-        Box::new(|cc| Ok(Box::new(EnvoyApp::new(cc, runtime)))),
+        Box::new(|cc| {
+            Ok(Box::new(EnvoyApp::new(
+                cc,
+                config,
+                runtime,
+                log_console,
+                run_log,
+            )))
+        }),
     ) {
         Ok(()) => (),
         Err(e) => tracing::error!("Eframe error: {}", e),
@@ -0,0 +1,9 @@
+#!/usr/bin/zsh
+
+IP=$1
+LOC=$2
+EXP=$3
+
+COMMAND="ls ${LOC}/${EXP}"
+
+ssh $IP "${COMMAND}"
@@ -1,19 +1,135 @@
-use super::config::Config;
+use super::config::{Config, DisconnectPolicy};
 use super::config_panel::render_config_panel;
-use super::ecc_panel::render_ecc_panel;
-use super::graph_manager::GraphManager;
+use super::config_restore::{render_config_restore, ConfigRestore};
+use super::confirm_panel::{
+    render_confirm_panel, render_run_number_issue, ConfirmAction, RunNumberIssue,
+};
+use super::deferred_tasks::{render_deferred_tasks, DeferredTask, DeferredTaskKind};
+use super::diagnostics_panel::render_diagnostics_panel;
+use super::ecc_panel::{render_ecc_panel, render_transition_report};
+use super::effective_config::{effective_config_entries, render_effective_config, EffectiveEntry};
+use super::error_log::{render_error_log_panel, ErrorLog};
+use super::expert_console::{render_expert_console, ExpertConsole};
+use super::features::FeatureMatrix;
+use super::file_check::{render_file_count_warning, FileCountWarning};
+use super::graph_manager::{router_problems, GraphManager};
 use super::graph_panel::render_graph_panel;
+use super::help_panel::render_help_panel;
+use super::livetime::RunLivetime;
+use super::log_console::{render_log_panel, LogConsole};
+use super::module_panel::render_module_windows;
+use super::notifications::{render_notifications, Notifications, ToastLevel};
+use super::pre_run_checklist::render_pre_run_checklist;
+use super::rate_alarm::{RateAlarm, RateAlarmChange};
+use super::repaint::{repaint_hook, RepaintScheduler, REPAINT_COALESCE_WINDOW};
 use super::router_panel::render_data_router_panel;
+use super::run_log_file::RunLogFile;
 use super::run_log_panel::render_run_log_panel;
-use crate::command::bash_command::{execute, CommandName, CommandStatus};
-use crate::envoy::embassy::Embassy;
-use crate::envoy::status_manager::StatusManager;
+use super::run_log_store::{RunRecord, RunTag};
+use super::run_metadata::{average_rates, write_effective_config, QuorumMetadata, RunMetadata};
+use super::run_sequence::{RunSequence, SequencePhase, StopReason};
+use super::run_summary::write_run_summary;
+use super::run_table_panel::{render_run_table_panel, RunTableView};
+use super::sanitize::is_valid_experiment;
+use super::setup_wizard::{is_first_launch, render_setup_wizard, SetupWizard};
+use super::start_attempts::StartAttempts;
+use super::start_countdown::{render_start_countdown, StartCountdown, COUNTDOWN_REPAINT_INTERVAL};
+use crate::command::bash_command::{
+    backup_configs, find_existing_run_dirs, find_used_run_numbers, move_run_files, CommandName,
+    MoveFailure, UsedRunNumbers,
+};
+use crate::command::config_check::{check_experiment_configs, XcfgIssue};
+use crate::command::config_watcher::ConfigWatcher;
+use crate::command::error::CommandError;
+use crate::envoy::constants::{
+    DEFAULT_NUMBER_OF_COBOS, MUTANT_STOP_TIMEOUT_SEC, PROGRESS_STALL_TIMEOUT_SEC,
+    RECONFIGURE_TIMEOUT_SEC,
+};
+use crate::envoy::ecc_operation::ECCStatus;
+use crate::envoy::embassy::{ArrivalHook, Embassy};
+use crate::envoy::error::EmbassyError;
+use crate::envoy::reconcile::{detect_external_state, ExternalState};
+use crate::envoy::status_manager::{ECCSnapshot, NetworkChange, ReadinessMode, StatusManager};
+use crate::envoy::surveyor_status::DiskHealthState;
 use crate::envoy::transition::*;
 
-use eframe::egui::Color32;
-use std::time::Instant;
+use eframe::egui::{Color32, Key, KeyboardShortcut, Modifiers};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+use uuid::Uuid;
 
 const DEFAULT_TEXT_COLOR: Color32 = Color32::LIGHT_GRAY;
+const WINDOW_TITLE: &str = "AT-TPC Envoy";
+
+pub const START_RUN_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::R);
+pub const STOP_RUN_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::T);
+pub const DISCONNECT_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::D);
+
+/// A step of stopping a run done on the background thread, with its result
+#[derive(Debug)]
+enum StopStep {
+    Moved(std::io::Result<Vec<MoveFailure>>),
+    BackedUp(Result<PathBuf, CommandError>),
+}
+
+/// A stopped run whose .graw files are being moved, and then its configuration backed up, on a
+/// background thread. The run is recorded once both complete, as the DAQ was when it stopped.
+#[derive(Debug)]
+pub struct StoppingRun {
+    pub run_number: i32,
+    run: RunRecord,
+    metadata: RunMetadata,
+    pub since: Instant,
+    /// Set once the move completed and the configuration is being backed up
+    pub backing_up: bool,
+    steps: Receiver<StopStep>,
+}
+
+/// A data router command run on a background thread, as it blocks for up to its timeout on each
+/// data router. The result is taken by polling each frame.
+#[derive(Debug)]
+pub struct BackgroundCommand<T> {
+    pub since: Instant,
+    result: Receiver<T>,
+}
+
+impl<T: Send + 'static> BackgroundCommand<T> {
+    /// Run a command on a new background thread
+    fn spawn(command: impl FnOnce() -> T + Send + 'static) -> Self {
+        let (tx, rx) = channel();
+        std::thread::spawn(move || {
+            let _ = tx.send(command());
+        });
+        Self {
+            since: Instant::now(),
+            result: rx,
+        }
+    }
+
+    /// Take the result of the command, if it finished. The command panicked if the channel is
+    /// disconnected.
+    fn poll(&self) -> Option<Result<T, TryRecvError>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(Ok(result)),
+            Err(TryRecvError::Empty) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// The tasks deferred by a quick stop, being run in order on the same kind of background thread
+/// as the stop of a run. Each task is removed from the config once it succeeds.
+#[derive(Debug)]
+pub struct DeferredTasksRun {
+    /// Continue starting the run once the tasks are done, as a run start ran them
+    then_start: bool,
+    pub since: Instant,
+    steps: Receiver<(DeferredTask, StopStep)>,
+}
 
 /// EnvoyApp implements the eframe::App trait,
 /// and holds the tokio runtime and the embassy hub.
@@ -24,179 +140,1856 @@ pub struct EnvoyApp {
     pub status: StatusManager,
     pub graphs: GraphManager,
     pub run_start_time: Instant,
+    run_start_wall_time: OffsetDateTime,
+    pub run_id: Uuid,
     pub new_field_name: String,
+    pub new_tag_name: String,
+    pub pending_confirmation: Option<ConfirmAction>,
+    pub skip_confirmation: bool,
+    pub run_number_issue: Option<RunNumberIssue>,
+    pub file_count_warning: Option<FileCountWarning>,
+    pub auto_stop_armed: bool,
+    /// Set once a run has been stopped for a full disk, so that the stop is not repeated
+    pub disk_full_stopped: bool,
+    pub transition_snapshot: Option<ECCSnapshot>,
+    pub transition_report: Option<TransitionReport>,
+    pub sanitize_notice: Option<String>,
+    pub runs_started: usize,
+    /// The bytes used on each data router when the current run started
+    pub run_start_bytes: Vec<u64>,
+    pub restart_pending: bool,
+    /// The run being stopped, while its .graw files are moved
+    pub stopping_run: Option<StoppingRun>,
+    /// The deferred tasks being run, if any
+    pub deferred_run: Option<DeferredTasksRun>,
+    /// The check that the run number is free, before the run starts
+    pub run_number_check: Option<BackgroundCommand<std::io::Result<Vec<String>>>>,
+    /// The listing of the used run numbers, for suggesting the next one
+    pub run_number_suggestion: Option<BackgroundCommand<std::io::Result<UsedRunNumbers>>>,
+    pub window_title: String,
+    pub notifications: Notifications,
+    pub error_log: ErrorLog,
+    pub livetime: Option<RunLivetime>,
+    /// The quorum the current run was started under, if any
+    pub run_quorum: Option<QuorumMetadata>,
+    pub rate_alarm: Option<RateAlarm>,
+    pub log_console: LogConsole,
+    run_log_file: RunLogFile,
+    /// The entries shown by the effective config viewer, None when it is closed
+    pub effective_config: Option<Vec<EffectiveEntry>>,
+    /// The run table viewer, None when it is closed
+    pub run_table: Option<RunTableView>,
+    /// The config restore window, None when it is closed
+    pub config_restore: Option<ConfigRestore>,
+    /// An externally induced state of the system, and when it was first seen
+    pub external_state: Option<(ExternalState, Instant)>,
+    /// The pre-run checklist, if it is open, with whether the soft checks are overridden
+    pub pre_run_checklist: Option<bool>,
+    /// A run start scheduled by the operator
+    pub start_countdown: Option<StartCountdown>,
+    /// When the current run was scheduled to start, if it was started by a countdown
+    pub scheduled_start_time: Option<OffsetDateTime>,
+    pub countdown_seconds: u64,
+    pub countdown_time: String,
+    /// The setup wizard, if it is open
+    pub setup_wizard: Option<SetupWizard>,
+    pub repaints: Arc<Mutex<RepaintScheduler>>,
+    pub show_log: bool,
+    pub run_sequence: Option<RunSequence>,
+    pub sequence_runs: usize,
+    pub sequence_minutes: u64,
+    pub sequence_alert: Option<String>,
+    pub show_diagnostics: bool,
+    pub show_help: bool,
+    pub show_expert_console: bool,
+    pub expert_console: ExpertConsole,
+    pub show_deferred_tasks: bool,
+    pub config_watcher: Option<ConfigWatcher>,
+    pub changed_configs: Vec<PathBuf>,
+    pub reconfigure_phase: Option<ReconfigurePhase>,
+    pub reconfigure_start_time: Instant,
+    /// The forward transition of the system (Progress) in progress, if any
+    pub forward_transition: Option<ForwardTransition>,
+    /// Regressing the modules to Idle before disconnecting, if in progress
+    pub idle_regression: Option<IdleRegression>,
+    /// The ECC configuration files of the experiment with problems, from the last check
+    pub config_file_issues: Vec<XcfgIssue>,
+    pub run_start_phase: Option<RunStartPhase>,
+    pub run_start_phase_time: Instant,
+    pub run_start_request_time: Instant,
+    /// The attempts made to start the current run number
+    pub start_attempts: StartAttempts,
+    pub mutant_reconfigure_time: Option<Duration>,
+    pub ecc_selection: BTreeSet<usize>,
+    pub module_windows: BTreeSet<usize>,
 }
 
 //*************//
 // STATE LOGIC //
 //*************//
 impl EnvoyApp {
-    /// Create an app from a tokio runtime and eframe context
-    pub fn new(cc: &eframe::CreationContext<'_>, runtime: tokio::runtime::Runtime) -> Self {
+    /// Create an app from the loaded config, the tokio runtime built from it, and eframe context
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        config: Config,
+        runtime: tokio::runtime::Runtime,
+        log_console: LogConsole,
+        run_log_file: RunLogFile,
+    ) -> Self {
         let mut visuals = eframe::egui::Visuals::dark();
         visuals.override_text_color = Some(DEFAULT_TEXT_COLOR);
         cc.egui_ctx.set_visuals(visuals);
         cc.egui_ctx.set_theme(eframe::egui::Theme::Dark);
+        let first_launch = is_first_launch();
+        let runtime_config = config.runtime.clone();
+        let setup_wizard = first_launch.then(|| SetupWizard::new(&config));
+        run_log_file.open_idle(&config.experiment);
+        tracing::info!("Features: {}", FeatureMatrix::from_config(&config));
+        //Repaint when messages arrive rather than waiting for the next idle repaint
+        let repaints = Arc::new(Mutex::new(RepaintScheduler::new(REPAINT_COALESCE_WINDOW)));
+        let mut embassy = Embassy::new(runtime, runtime_config);
+        embassy.set_arrival_hook(ArrivalHook::new(repaint_hook(
+            repaints.clone(),
+            cc.egui_ctx.clone(),
+        )));
         EnvoyApp {
-            config: Config::new(),
-            embassy: Embassy::new(runtime),
-            status: StatusManager::new(),
-            graphs: GraphManager::new(10, 2),
+            config,
+            embassy,
+            repaints,
+            status: StatusManager::new(DEFAULT_NUMBER_OF_COBOS),
+            graphs: GraphManager::new(10, 2, DEFAULT_NUMBER_OF_COBOS),
             run_start_time: Instant::now(),
+            run_start_wall_time: OffsetDateTime::now_utc(),
+            run_id: Uuid::nil(),
             new_field_name: String::default(),
+            new_tag_name: String::default(),
+            pending_confirmation: None,
+            skip_confirmation: false,
+            run_number_issue: None,
+            file_count_warning: None,
+            auto_stop_armed: false,
+            disk_full_stopped: false,
+            transition_snapshot: None,
+            transition_report: None,
+            sanitize_notice: None,
+            runs_started: 0,
+            run_start_bytes: vec![],
+            restart_pending: false,
+            stopping_run: None,
+            deferred_run: None,
+            run_number_check: None,
+            run_number_suggestion: None,
+            window_title: String::from(WINDOW_TITLE),
+            notifications: Notifications::default(),
+            error_log: ErrorLog::default(),
+            livetime: None,
+            run_quorum: None,
+            rate_alarm: None,
+            log_console,
+            run_log_file,
+            effective_config: None,
+            run_table: None,
+            config_restore: None,
+            external_state: None,
+            pre_run_checklist: None,
+            start_countdown: None,
+            scheduled_start_time: None,
+            countdown_seconds: 90,
+            countdown_time: String::default(),
+            setup_wizard,
+            show_log: true,
+            run_sequence: None,
+            sequence_runs: 10,
+            sequence_minutes: 60,
+            sequence_alert: None,
+            show_diagnostics: false,
+            show_help: false,
+            show_expert_console: false,
+            expert_console: ExpertConsole::default(),
+            show_deferred_tasks: false,
+            config_watcher: None,
+            changed_configs: vec![],
+            reconfigure_phase: None,
+            reconfigure_start_time: Instant::now(),
+            forward_transition: None,
+            idle_regression: None,
+            config_file_issues: vec![],
+            run_start_phase: None,
+            run_start_phase_time: Instant::now(),
+            run_start_request_time: Instant::now(),
+            start_attempts: StartAttempts::default(),
+            mutant_reconfigure_time: None,
+            ecc_selection: BTreeSet::new(),
+            module_windows: BTreeSet::new(),
         }
     }
 
     /// Create all of the envoys, the embassy, and start the async tasks
     pub fn connect(&mut self) {
         if !self.embassy.is_connected() {
-            self.embassy.startup(&self.config.experiment);
+            match self.embassy.apply_runtime_config(&self.config.runtime) {
+                Ok(()) => (),
+                Err(e) => {
+                    self.notify_error(format!("Could not apply the runtime config: {e}"));
+                    return;
+                }
+            }
+            self.config.num_cobos = self.config.num_cobos.max(1);
+            self.config.poll_interval_sec = self.config.poll_interval_sec.max(1);
+            match self.embassy.startup(
+                &self.config.experiment,
+                self.config.num_cobos,
+                &self.config.network,
+                &self.config.families,
+                Duration::from_secs(self.config.poll_interval_sec),
+            ) {
+                Ok(()) => (),
+                Err(e) => {
+                    self.notify_error(format!("Could not connect the embassy: {e}"));
+                    return;
+                }
+            }
+            self.status = StatusManager::new(self.config.num_cobos);
+            self.status.set_monitored_families(&self.config.families);
+            self.status.set_readiness_policy(self.config.readiness);
+            self.graphs.set_number_of_cobos(self.config.num_cobos);
+            self.graphs.set_rate_ceiling(self.config.rate_ceiling);
+            self.changed_configs.clear();
+            self.ecc_selection.clear();
+            if self.config.families.ecc {
+                self.check_config_files();
+                if !self.config_file_issues.is_empty() {
+                    self.notify_warning(format!(
+                        "ECC config files have problems: {}",
+                        self.config_file_issues
+                            .iter()
+                            .map(|issue| issue.to_string())
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    ));
+                }
+            }
+            if self.config.watch_configs && self.config.families.ecc {
+                match ConfigWatcher::new(&self.config.experiment) {
+                    Ok(watcher) => self.config_watcher = Some(watcher),
+                    Err(e) => tracing::warn!("Could not watch the ECC config directory: {e}"),
+                }
+            }
             tracing::info!(
                 "Connnected with {} tasks spawned",
                 self.embassy.number_of_tasks()
             );
+            tracing::info!("Features: {}", FeatureMatrix::from_config(&self.config));
         }
     }
 
-    /// Emit a cancel signal to all of the envoys and destroy the envoys and the embassy
-    /// This can cause a small blocking period while waiting for all of the tasks to join back.
+    /// Ask the operator to confirm a disconnect which ignores a run in progress, for when
+    /// the DAQ is wedged
+    pub fn request_force_disconnect(&mut self) {
+        self.pending_confirmation = Some(ConfirmAction::ForceDisconnect);
+    }
+
+    /// Can the envoys be disconnected. Disconnecting during a run would leave the data routers
+    /// writing with no way to stop the CoBos.
+    pub fn can_disconnect(&self) -> bool {
+        self.embassy.is_connected()
+            && !self.status.is_system_running()
+            && !self.is_run_stopping()
+            && self.idle_regression.is_none()
+    }
+
+    /// Disconnect the envoys, refusing while a run is in progress. If the disconnect policy
+    /// regresses to Idle, the operator is asked to confirm that first, unless every module is
+    /// already Idle.
     pub fn disconnect(&mut self) {
+        if self.status.is_system_running() {
+            self.notify_error(String::from(
+                "Stop the run before disconnecting, or use File > Force Disconnect if the DAQ is stuck",
+            ));
+            return;
+        }
+        let configured = self
+            .status
+            .module_ids()
+            .into_iter()
+            .any(|id| self.status.get_ecc_status(id).can_go_backward());
+        if self.config.disconnect_policy == DisconnectPolicy::RegressToIdle
+            && self.status.get_monitored_families().ecc
+            && configured
+        {
+            self.pending_confirmation = Some(ConfirmAction::RegressAndDisconnect);
+            return;
+        }
+        self.force_disconnect();
+    }
+
+    /// Regress every module to Idle and then disconnect. The modules are regressed each frame
+    /// (see advance_idle_regression), and the envoys are disconnected once that finishes. Never
+    /// used during a run.
+    pub fn regress_and_disconnect(&mut self) {
+        self.pending_confirmation = None;
+        if self.status.is_system_running() {
+            self.disconnect();
+            return;
+        }
+        tracing::info!("Regressing the modules to Idle before disconnecting...");
+        self.idle_regression = Some(begin_regress_to_idle(&mut self.status));
+    }
+
+    /// Advance regressing the modules to Idle, if in progress. Once finished the modules which
+    /// could not be regressed are reported and the envoys are disconnected.
+    fn advance_idle_regression(&mut self) {
+        let regression = match self.idle_regression.as_mut() {
+            Some(r) => r,
+            None => return,
+        };
+        let remaining = match advance_regress_to_idle(
+            regression,
+            &mut self.embassy,
+            &mut self.status,
+            Duration::from_secs(self.config.disconnect_timeout_sec),
+        ) {
+            Some(remaining) => remaining,
+            None => return,
+        };
+        self.idle_regression = None;
+        if remaining.is_empty() {
+            tracing::info!("Every module is Idle.");
+        } else {
+            let modules: Vec<String> = remaining
+                .iter()
+                .map(|(id, status)| format!("{id} ({status})"))
+                .collect();
+            self.notify_error(format!(
+                "Some modules could not be regressed to Idle before disconnecting: {}",
+                modules.join(", ")
+            ));
+        }
+        self.force_disconnect();
+    }
+
+    /// Emit a cancel signal to all of the envoys and destroy the envoys and the embassy, even
+    /// during a run. This can cause a small blocking period while waiting for all of the tasks to join back.
+    pub fn force_disconnect(&mut self) {
+        self.pending_confirmation = None;
+        if self.status.is_system_running() {
+            tracing::warn!(
+                "Force disconnecting during run {}, the run will not be stopped or cataloged",
+                self.config.run_number
+            );
+        }
         if self.embassy.is_connected() {
             match self.embassy.shutdown() {
                 Ok(()) => (),
                 Err(e) => tracing::error!("Failed to stop the embassy: {e}"),
             }
             self.status.reset();
+            if let Some(phase) = self.run_start_phase.take() {
+                self.start_attempts
+                    .fail(&phase.to_string(), "Disconnected from the system");
+            }
+            self.config_watcher = None;
+            self.changed_configs.clear();
+            self.reconfigure_phase = None;
+            self.forward_transition = None;
+            self.idle_regression = None;
+            self.transition_snapshot = None;
+            self.restart_pending = false;
+            self.livetime = None;
+            self.rate_alarm = None;
+            if self.run_sequence.is_some() {
+                self.halt_run_sequence(Some(String::from("Disconnected from the system")));
+            }
             tracing::info!("Disconnected the embassy");
             tracing::info!("Status manager reset.")
         }
     }
 
-    /// Send a start run command to all of the envoys.
-    /// Note that several important things must happen here. First a command is sent to make sure that
-    /// the run number was not already used. Then, the CoBos must start, and only once all CoBos are running,
-    /// does the Mutant start. The rate graphs are also reset.
+    /// Check the ECC config directory for changes. Changes only matter once the
+    /// system has been described, as the description is then stale.
+    fn check_config_changes(&mut self) {
+        let watcher = match &self.config_watcher {
+            Some(w) => w,
+            None => return,
+        };
+        let changed = watcher.changed_files();
+        if changed.is_empty() {
+            return;
+        }
+        self.check_config_files();
+        if !matches!(
+            self.status.get_system_ecc_status(),
+            ECCStatus::Described | ECCStatus::Prepared | ECCStatus::Ready
+        ) {
+            return;
+        }
+        for path in changed {
+            tracing::info!("ECC config file {} changed", path.display());
+            if !self.changed_configs.contains(&path) {
+                self.changed_configs.push(path);
+            }
+        }
+    }
+
+    /// Check the ECC configuration files of the experiment for empty, truncated, or malformed files
+    pub fn check_config_files(&mut self) {
+        self.config_file_issues = check_experiment_configs(&self.config.experiment);
+        for issue in self.config_file_issues.iter() {
+            tracing::warn!("ECC config file {issue}");
+        }
+    }
+
+    /// Breakup and re-configure the system to pick up changed config files. The system is
+    /// driven back to Ready each frame (see advance_reconfigure).
+    pub fn reconfigure_system(&mut self) {
+        tracing::info!("Re-configuring the system to apply changed config files...");
+        match begin_reconfigure(&mut self.embassy, &mut self.status) {
+            Ok(phase) => {
+                self.reconfigure_phase = Some(phase);
+                self.reconfigure_start_time = Instant::now();
+            }
+            Err(e) => self.notify_error(format!("An error occured re-configuring the system: {e}")),
+        }
+    }
+
+    /// Advance re-configuring the system, if it is in progress. Gives up, abandoning the plan,
+    /// if the system is not re-configured within the timeout.
+    fn advance_reconfigure(&mut self) {
+        let phase = match self.reconfigure_phase {
+            Some(p) => p,
+            None => return,
+        };
+        let timeout = Duration::from_secs(RECONFIGURE_TIMEOUT_SEC);
+        let result = if self.reconfigure_start_time.elapsed() > timeout {
+            Err(EmbassyError::ReconfigureTimedOut(phase, timeout.as_secs()))
+        } else {
+            advance_reconfigure(phase, &mut self.status)
+        };
+        match result {
+            Ok(ReconfigurePhase::Complete) => {
+                self.reconfigure_phase = None;
+                self.changed_configs.clear();
+                self.notify_info(format!(
+                    "System re-configured in {} s.",
+                    self.reconfigure_start_time.elapsed().as_secs()
+                ));
+            }
+            Ok(next) => {
+                if next != phase {
+                    tracing::info!("{next}...");
+                }
+                self.reconfigure_phase = Some(next);
+            }
+            Err(e) => {
+                self.reconfigure_phase = None;
+                self.status.clear_ecc_targets();
+                self.notify_error(format!("An error occured re-configuring the system: {e}"));
+            }
+        }
+    }
+
+    /// Progress the whole system. The stages of the transition are sent each frame as the
+    /// previous stage completes (see advance_forward_transition).
+    pub fn progress_system(&mut self) {
+        self.begin_transition_report();
+        match forward_transition_all(&mut self.embassy, &mut self.status) {
+            Ok(transition) => self.forward_transition = Some(transition),
+            Err(e) => tracing::error!(
+                "An error occurred attempting to transition the system state: {}",
+                e
+            ),
+        }
+    }
+
+    /// Are the transition buttons locked. Holds clear between the steps of the target planner,
+    /// so bringing the system to a target locks them as well as operations in flight.
+    pub fn is_transition_locked(&self) -> bool {
+        self.status.is_any_held()
+            || self.forward_transition.is_some()
+            || self.status.get_system_target().is_some()
+            || self.idle_regression.is_some()
+    }
+
+    /// Advance the forward transition of the system, if one is in progress
+    fn advance_forward_transition(&mut self) {
+        let transition = match self.forward_transition.as_mut() {
+            Some(t) => t,
+            None => return,
+        };
+        match advance_forward_transition(
+            transition,
+            &mut self.embassy,
+            &mut self.status,
+            Duration::from_secs(PROGRESS_STALL_TIMEOUT_SEC),
+        ) {
+            Ok(true) => {
+                tracing::info!("System progressed to {}", transition.plan.target);
+                self.forward_transition = None;
+            }
+            Ok(false) => (),
+            Err(e) => {
+                self.forward_transition = None;
+                self.notify_error(format!(
+                    "An error occurred attempting to transition the system state: {e}"
+                ));
+            }
+        }
+    }
+
+    /// How long re-configuring the system has been in progress
+    pub fn reconfigure_elapsed(&self) -> Duration {
+        self.reconfigure_start_time.elapsed()
+    }
+
+    /// Start a run from the operator, showing the pre-run checklist first unless an expert
+    /// chose to skip it
+    pub fn request_start_run(&mut self) {
+        self.scheduled_start_time = None;
+        self.check_config_files();
+        if self.config.expert_mode && self.config.skip_pre_run_checklist {
+            self.start_run();
+        } else {
+            self.pre_run_checklist = Some(false);
+        }
+    }
+
+    /// Schedule a run start. At the end of the countdown the run is started through start_run.
+    pub fn schedule_start_run(&mut self, countdown: StartCountdown) {
+        if !self.can_start_run() {
+            return;
+        }
+        self.notify_info(format!(
+            "Run {} will start at {:02}:{:02}:{:02} UTC",
+            self.config.run_number,
+            countdown.target.hour(),
+            countdown.target.minute(),
+            countdown.target.second()
+        ));
+        self.start_countdown = Some(countdown);
+    }
+
+    /// Abort the scheduled run start, if there is one
+    pub fn abort_start_countdown(&mut self) {
+        if self.start_countdown.take().is_some() {
+            self.notify_info(format!(
+                "Scheduled start of run {} aborted",
+                self.config.run_number
+            ));
+        }
+    }
+
+    /// Start the run once the countdown reaches zero. The run is only started if the system
+    /// can still start one.
+    fn check_start_countdown(&mut self) {
+        let countdown = match self.start_countdown {
+            Some(countdown) if countdown.is_due() => countdown,
+            _ => return,
+        };
+        self.start_countdown = None;
+        if !self.can_start_run() {
+            self.notify_error(format!(
+                "Scheduled start of run {} skipped: the system is not ready",
+                self.config.run_number
+            ));
+            return;
+        }
+        tracing::info!(
+            "Countdown complete, starting run {} (scheduled {})",
+            self.config.run_number,
+            countdown.target
+        );
+        self.scheduled_start_time = Some(countdown.target);
+        self.start_run();
+    }
+
+    /// Begin the run start sequence.
+    /// Note that several important things must happen here. First the data router disk health and free space are checked, and a command is sent to make sure that
+    /// the run number was not already used. Then, the MuTaNT is re-configured, the CoBos must start, and only once all CoBos are running,
+    /// does the Mutant start. The sequence is advanced each frame (see advance_run_start) so the UI does not freeze.
     pub fn start_run(&mut self) {
-        //Order is all cobos, then mutant
+        if !self.config.deferred_tasks.is_empty() {
+            // The run start continues once the tasks are done (see poll_deferred_tasks)
+            tracing::info!(
+                "Running the tasks deferred by the last quick stop before starting run {}...",
+                self.config.run_number
+            );
+            let tasks = self.config.deferred_tasks.clone();
+            self.begin_deferred_tasks(tasks, true);
+            return;
+        }
+        let failing = self.status.get_failing_disks();
+        if !failing.is_empty() {
+            let issue = RunNumberIssue::FailingDisks(failing);
+            self.notify_error(format!(
+                "Run {} was not started: {issue}",
+                self.config.run_number
+            ));
+            self.run_number_issue = Some(issue);
+            return;
+        }
+        if self.status.get_monitored_families().surveyor {
+            let low = self
+                .config
+                .disk_space_issues(self.status.get_surveyor_status_response());
+            if !low.is_empty() {
+                let issue = RunNumberIssue::LowDiskSpace(low);
+                self.notify_error(format!(
+                    "Run {} was not started: {issue}",
+                    self.config.run_number
+                ));
+                self.run_number_issue = Some(issue);
+                return;
+            }
+        }
+        self.start_run_skipping_disk_checks();
+    }
 
-        //Check the run number status using the shell scripting engine
-        tracing::info!("Starting run {} ...", self.config.run_number);
+    /// Start the run after checking the run number, ignoring the disk health and free space.
+    /// Only used directly when an expert chooses to proceed despite a disk problem. The run
+    /// number is checked over ssh on a background thread, and the run is started by
+    /// poll_run_number_check once it is found to be free.
+    pub fn start_run_skipping_disk_checks(&mut self) {
+        self.run_number_issue = None;
+        if !self.status.get_monitored_families().surveyor {
+            tracing::warn!(
+                "Data routers are not monitored, the run number could not be validated!"
+            );
+            self.start_run_unchecked();
+            return;
+        }
         tracing::info!("Checking if run number is ok...");
-        match execute(
-            CommandName::CheckRunExists,
-            self.status.get_surveyor_status_response(),
-            &self.config.experiment,
-            &self.config.run_number,
-        ) {
-            CommandStatus::Success => {
-                tracing::warn!("Tried to start a run with a run number that was already used! Either delete the extant data or change the run number!");
+        let routers = self.status.get_surveyor_status_response().to_vec();
+        let experiment = self.config.experiment.clone();
+        let run_number = self.config.run_number;
+        let use_script = self.config.run_check_script;
+        let shell = self.config.shell.clone();
+        let timeout = self.config.command_timeout(CommandName::CheckRunExists);
+        self.run_number_check = Some(BackgroundCommand::spawn(move || {
+            find_existing_run_dirs(
+                &routers,
+                &experiment,
+                &run_number,
+                use_script,
+                &shell,
+                timeout,
+            )
+        }));
+    }
+
+    /// Start the run once the run number check finds the run number free. The run number is
+    /// free if none of the data routers have a directory for it.
+    fn poll_run_number_check(&mut self) {
+        let checked = match self
+            .run_number_check
+            .as_ref()
+            .and_then(|check| check.poll())
+        {
+            Some(checked) => checked,
+            None => return,
+        };
+        self.run_number_check = None;
+        let issue = match checked {
+            Ok(Ok(paths)) if paths.is_empty() => {
+                tracing::info!("Run number validated.");
+                if self.can_start_run() {
+                    self.start_run_unchecked();
+                } else {
+                    self.notify_error(format!(
+                        "Run {} was not started: the system is no longer ready",
+                        self.config.run_number
+                    ));
+                }
                 return;
             }
-            CommandStatus::Failure => (),
-            CommandStatus::CouldNotExecute => return,
+            Ok(Ok(paths)) => RunNumberIssue::InUse(paths),
+            Ok(Err(e)) => RunNumberIssue::CheckFailed(e.to_string()),
+            Err(_) => RunNumberIssue::CheckFailed(String::from("the check exited unexpectedly")),
+        };
+        self.notify_error(format!(
+            "Run {} was not started: {issue}",
+            self.config.run_number
+        ));
+        self.run_number_issue = Some(issue);
+    }
+
+    /// Set the run number to the one after the last run found on the data routers. The runs are
+    /// listed over ssh on a background thread (see poll_run_number_suggestion).
+    pub fn suggest_run_number(&mut self) {
+        if !self.status.get_monitored_families().surveyor {
+            self.notify_error(String::from(
+                "Data routers are not monitored, no run number could be suggested",
+            ));
+            return;
+        }
+        if self.run_number_suggestion.is_some() {
+            return;
         }
-        tracing::info!("Run number validated.");
+        let routers = self.status.get_surveyor_status_response().to_vec();
+        let experiment = self.config.experiment.clone();
+        let shell = self.config.shell.clone();
+        let timeout = self.config.command_timeout(CommandName::ListRuns);
+        self.run_number_suggestion = Some(BackgroundCommand::spawn(move || {
+            find_used_run_numbers(&routers, &experiment, &shell, timeout)
+        }));
+    }
 
-        tracing::info!("Re-configuring MuTaNT to reset timestamps...");
-        match reconfigure_mutant_blocking(&mut self.embassy, &mut self.status) {
-            Ok(()) => (),
-            Err(e) => tracing::error!("An error occured reconfiguring MuTaNT: {}", e),
+    /// Set the suggested run number once the runs are listed. Nothing is changed if a run was
+    /// started in the meantime.
+    fn poll_run_number_suggestion(&mut self) {
+        let listed = match self
+            .run_number_suggestion
+            .as_ref()
+            .and_then(|suggestion| suggestion.poll())
+        {
+            Some(listed) => listed,
+            None => return,
+        };
+        self.run_number_suggestion = None;
+        let used = match listed {
+            Ok(Ok(used)) => used,
+            Ok(Err(e)) => {
+                self.notify_error(format!("Could not list the existing runs: {e}"));
+                return;
+            }
+            Err(_) => {
+                self.notify_error(String::from(
+                    "Listing the existing runs exited unexpectedly",
+                ));
+                return;
+            }
+        };
+        if self.is_run_locked() {
+            tracing::warn!("A run was started while the run number was suggested, ignoring it");
+            return;
+        }
+        if used.answered == 0 {
+            self.notify_error(String::from(
+                "No data router answered, no run number could be suggested",
+            ));
+            return;
+        }
+        match used.numbers.last() {
+            Some(last) => {
+                self.config.run_number = last + 1;
+                self.notify_info(format!(
+                    "Run number set to {} ({} of {} data routers answered)",
+                    self.config.run_number,
+                    used.answered,
+                    self.status.get_surveyor_status_response().len()
+                ));
+            }
+            None => self.notify_info(format!(
+                "No runs of {} were found, the run number was not changed",
+                self.config.experiment
+            )),
         }
-        tracing::info!("MuTaNT is re-configured. Proceeding.");
+    }
 
-        tracing::info!("Starting CoBos...");
-        //Start CoBos
-        match start_cobos_blocking(&mut self.embassy, &mut self.status) {
-            Ok(()) => (),
-            Err(e) => tracing::error!("An error occured starting the CoBos: {}", e),
+    /// Start the run without checking the run number. Only used directly when an
+    /// expert chooses to proceed after the run number check could not be executed.
+    pub fn start_run_unchecked(&mut self) {
+        //Order is all cobos, then mutant
+        self.run_number_issue = None;
+        self.start_attempts.begin(self.config.run_number);
+        if self.config.operator.trim().is_empty() {
+            self.notify_warning(format!(
+                "Run {} is starting without an operator name",
+                self.config.run_number
+            ));
+        }
+        self.run_id = Uuid::new_v4();
+        let policy = *self.status.get_readiness_policy();
+        self.run_quorum = (policy.mode == ReadinessMode::Quorum).then(|| {
+            let waived_cobos = self.status.waived_cobos();
+            if !waived_cobos.is_empty() {
+                tracing::warn!(
+                    "Starting run {} under quorum, waiving CoBos {waived_cobos:?}",
+                    self.config.run_number
+                );
+            }
+            QuorumMetadata {
+                quorum_cobos: policy.quorum_cobos,
+                waived_cobos,
+            }
+        });
+        self.run_log_file
+            .open_run(&self.config.experiment, self.config.run_number);
+        match write_effective_config(&self.config) {
+            Ok(path) => tracing::info!("Effective config written to {}", path.display()),
+            Err(e) => self.notify_error(format!("Could not write the effective config: {e}")),
+        }
+        tracing::info!(
+            "Starting run {} (UUID {}) ...",
+            self.config.run_number,
+            self.run_id
+        );
+        if self.config.reset_mutant_timestamps {
+            tracing::info!("Re-configuring MuTaNT to reset timestamps...");
+        } else {
+            tracing::info!("Skipping the MuTaNT re-configure, timestamps will not be reset.");
+        }
+        self.mutant_reconfigure_time = None;
+        self.run_start_phase_time = Instant::now();
+        self.run_start_request_time = self.run_start_phase_time;
+        match begin_run_start(
+            &mut self.embassy,
+            &mut self.status,
+            self.config.reset_mutant_timestamps,
+        ) {
+            Ok(phase) => self.run_start_phase = Some(phase),
+            Err(e) => {
+                let step = if self.config.reset_mutant_timestamps {
+                    RunStartPhase::BreakupMutant
+                } else {
+                    RunStartPhase::StartCobos
+                };
+                self.start_attempts.fail(&step.to_string(), &e.to_string());
+                self.notify_error(format!(
+                    "Aborted starting run {}: {e}",
+                    self.config.run_number
+                ));
+                self.open_idle_log();
+            }
         }
+    }
 
-        tracing::info!("CoBos started.");
+    /// Open (or refresh) the effective config viewer
+    pub fn show_effective_config(&mut self) {
+        match effective_config_entries(&self.config) {
+            Ok(entries) => self.effective_config = Some(entries),
+            Err(e) => self.notify_error(format!("Could not resolve the effective config: {e}")),
+        }
+    }
 
-        tracing::info!("Starting MuTaNT...");
-        match start_mutant(&mut self.embassy) {
-            Ok(()) => (),
-            Err(e) => tracing::error!("An error occured starting the MuTaNT: {}", e),
+    /// Direct the log to the between-runs file of the current experiment
+    pub fn open_idle_log(&self) {
+        self.run_log_file.open_idle(&self.config.experiment);
+    }
+
+    /// How long the current run start phase has been in progress
+    pub fn run_start_phase_elapsed(&self) -> Duration {
+        Instant::now() - self.run_start_phase_time
+    }
+
+    /// Is the run start sequence in progress
+    pub fn is_run_starting(&self) -> bool {
+        self.run_start_phase.is_some()
+    }
+
+    /// Is a stopped run waiting for its .graw files to be moved
+    pub fn is_run_stopping(&self) -> bool {
+        self.stopping_run.is_some()
+    }
+
+    /// Is a requested run start waiting on work in the background before the run start
+    /// sequence can begin
+    pub fn is_start_pending(&self) -> bool {
+        self.deferred_run.as_ref().is_some_and(|run| run.then_start)
+            || self.run_number_check.is_some()
+    }
+
+    /// Are the experiment and run number locked, as a run is in progress or being started,
+    /// stopped, or cycled. The stop commands use these values to move the data.
+    pub fn is_run_locked(&self) -> bool {
+        self.status.is_system_running()
+            || self.is_run_starting()
+            || self.is_start_pending()
+            || self.is_run_stopping()
+            || self.restart_pending
+    }
+
+    /// Advance the run start sequence, if one is in progress. Once the sequence completes
+    /// the run start time is set and the rate graphs are reset.
+    fn advance_run_start(&mut self) {
+        let phase = match self.run_start_phase {
+            Some(p) => p,
+            None => return,
+        };
+        match advance_run_start(phase, &mut self.embassy, &mut self.status) {
+            Ok(RunStartPhase::Complete) => {
+                self.run_start_phase = None;
+                self.start_attempts.started();
+                self.notify_info(format!(
+                    "Run {} (UUID {}) successfully started!",
+                    self.config.run_number, self.run_id
+                ));
+                //Update run start time
+                self.run_start_time = Instant::now();
+                self.run_start_wall_time = OffsetDateTime::now_utc();
+                if let Some(scheduled) = self.scheduled_start_time {
+                    tracing::info!(
+                        "Run {} was scheduled to start at {scheduled}, and started at {}",
+                        self.config.run_number,
+                        self.run_start_wall_time
+                    );
+                }
+                self.auto_stop_armed = true;
+                self.runs_started += 1;
+                self.run_start_bytes = self
+                    .status
+                    .get_surveyor_status_response()
+                    .iter()
+                    .map(|router| router.bytes_used)
+                    .collect();
+                self.livetime = Some(RunLivetime::new(self.status.module_ids().len()));
+                self.rate_alarm = Some(RateAlarm::new(Duration::from_secs(
+                    self.config.stalled_rate_grace_sec,
+                )));
+                self.graphs.reset();
+            }
+            Ok(next) => {
+                if next != phase {
+                    if phase == RunStartPhase::ConfigureMutant {
+                        let reconfigure_time = self.run_start_request_time.elapsed();
+                        tracing::info!(
+                            "MuTaNT re-configured in {:.1} s",
+                            reconfigure_time.as_secs_f64()
+                        );
+                        self.mutant_reconfigure_time = Some(reconfigure_time);
+                    }
+                    tracing::info!("{next}...");
+                    self.run_start_phase_time = Instant::now();
+                }
+                self.run_start_phase = Some(next);
+            }
+            Err(e) => {
+                self.run_start_phase = None;
+                self.start_attempts.fail(&phase.to_string(), &e.to_string());
+                self.notify_error(format!(
+                    "Aborted starting run {}: {e}",
+                    self.config.run_number
+                ));
+                self.open_idle_log();
+            }
+        }
+    }
+
+    /// Begin a sequence of runs of fixed length, using the operator entered count and length
+    pub fn start_run_sequence(&mut self) {
+        if self.sequence_runs == 0 || self.sequence_minutes == 0 {
+            return;
+        }
+        tracing::info!(
+            "Starting a sequence of {} runs of {} minutes",
+            self.sequence_runs,
+            self.sequence_minutes
+        );
+        self.sequence_alert = None;
+        self.run_sequence = Some(RunSequence::new(
+            self.sequence_runs,
+            Duration::from_secs(self.sequence_minutes * 60),
+        ));
+    }
+
+    /// Halt the run sequence. The current run (if any) is left running.
+    pub fn halt_run_sequence(&mut self, reason: Option<String>) {
+        if let Some(sequence) = self.run_sequence.take() {
+            match &reason {
+                Some(r) => self.notify_error(format!(
+                    "Run sequence halted after {} of {} runs: {r}",
+                    sequence.completed_runs, sequence.total_runs
+                )),
+                None => tracing::info!(
+                    "Run sequence stopped after {} of {} runs",
+                    sequence.completed_runs,
+                    sequence.total_runs
+                ),
+            }
+        }
+        self.sequence_alert = reason;
+    }
+
+    /// Drive the run sequence: start a run once the system is Ready, stop it once the run
+    /// length has passed, and repeat. Halts if a run fails to start or stops unexpectedly.
+    fn advance_run_sequence(&mut self) {
+        let mut sequence = match self.run_sequence.take() {
+            Some(s) => s,
+            None => return,
+        };
+        match sequence.phase {
+            SequencePhase::WaitingForReady => {
+                let system = self.status.get_system_ecc_status();
+                if matches!(system, ECCStatus::ErrorStat | ECCStatus::Unreachable) {
+                    self.halt_run_sequence(Some(format!(
+                        "The system is {system}, the next run could not be started"
+                    )));
+                    return;
+                }
+                if self.can_start_run() {
+                    let started = self.runs_started;
+                    self.start_run();
+                    if !self.is_run_starting() && !self.is_start_pending() {
+                        self.halt_run_sequence(Some(format!(
+                            "Run {} failed to start",
+                            self.config.run_number
+                        )));
+                        return;
+                    }
+                    sequence.phase = SequencePhase::Starting(started);
+                }
+            }
+            SequencePhase::Starting(started) => {
+                if self.runs_started > started {
+                    sequence.phase = SequencePhase::Running {
+                        since: Instant::now(),
+                        confirmed: false,
+                    };
+                } else if !self.is_run_starting() && !self.is_start_pending() {
+                    self.halt_run_sequence(Some(format!(
+                        "Run {} failed to start",
+                        self.config.run_number
+                    )));
+                    return;
+                }
+            }
+            SequencePhase::Running { since, confirmed } => {
+                let is_running = self.status.is_system_running();
+                if confirmed && !is_running {
+                    self.halt_run_sequence(Some(format!(
+                        "The system left the Running state during run {}",
+                        self.config.run_number
+                    )));
+                    return;
+                }
+                if since.elapsed() >= sequence.run_length {
+                    self.stop_run(StopReason::Sequence);
+                    sequence.completed_runs += 1;
+                    if sequence.completed_runs >= sequence.total_runs {
+                        tracing::info!("Run sequence of {} runs completed", sequence.total_runs);
+                        return;
+                    }
+                    sequence.phase = SequencePhase::WaitingForReady;
+                } else {
+                    sequence.phase = SequencePhase::Running {
+                        since,
+                        confirmed: confirmed || is_running,
+                    };
+                }
+            }
+        }
+        self.run_sequence = Some(sequence);
+    }
+
+    /// Stop the run, first asking the operator to confirm (and to tag the run if no tags were chosen)
+    pub fn request_stop_run(&mut self) {
+        if !self.skip_confirmation || self.config.run_tags.is_empty() {
+            self.pending_confirmation = Some(ConfirmAction::StopRun);
+        } else {
+            self.stop_run(StopReason::Manual);
         }
-        tracing::info!("MuTaNT started.");
-        tracing::info!("Run {} successfully started!", self.config.run_number);
+    }
+
+    /// Whether the auto-stop and auto-cycle limits apply to the current run
+    fn auto_limits_apply(&self) -> bool {
+        auto_limits_apply(
+            self.status.is_system_running(),
+            self.is_run_starting(),
+            self.auto_stop_armed,
+            self.run_sequence.is_some(),
+        )
+    }
+
+    /// Time left before the run is automatically stopped, if auto-stop is enabled and a run started
+    /// by this session is in progress. Run sequences take precedence over auto-stop.
+    pub fn auto_stop_remaining(&self) -> Option<Duration> {
+        if !self.auto_limits_apply() {
+            return None;
+        }
+        self.config
+            .auto_stop_duration()
+            .map(|limit| limit.saturating_sub(Instant::now() - self.run_start_time))
+    }
+
+    /// Stop the run if it has exceeded the auto-stop duration
+    fn check_auto_stop(&mut self) {
+        if self.auto_stop_remaining() == Some(Duration::ZERO) {
+            tracing::info!(
+                "Run {} reached the auto-stop duration of {} minutes",
+                self.config.run_number,
+                self.config.auto_stop_minutes.unwrap_or_default()
+            );
+            self.stop_run(StopReason::AutoStop);
+        }
+    }
+
+    /// Stop the run if any data router has less free space than the disk full floor. The stop
+    /// reason carries the router, which is noted in the run log and metadata.
+    fn check_disk_full(&mut self) {
+        if !self.status.is_system_running() {
+            self.disk_full_stopped = false;
+            return;
+        }
+        if self.disk_full_stopped
+            || self.is_run_starting()
+            || !self.status.get_monitored_families().surveyor
+        {
+            return;
+        }
+        let floor = self.config.disk_full_floor_gb;
+        let full = self
+            .status
+            .get_surveyor_status_response()
+            .iter()
+            .position(|router| {
+                router
+                    .free_bytes()
+                    .is_some_and(|free| (free as f64 * 1.0e-9) < floor)
+            });
+        let id = match full {
+            Some(id) => id,
+            None => return,
+        };
+        self.disk_full_stopped = true;
+        self.notify_error(format!(
+            "Data Router {id} has less than {floor} GB free, stopping run {}!",
+            self.config.run_number
+        ));
+        if self.run_sequence.is_some() {
+            self.halt_run_sequence(Some(format!("disk full on router {id}")));
+        }
+        self.stop_run(StopReason::DiskFull(id));
+    }
+
+    /// Raise an alarm for each data router which stopped writing while the others are writing,
+    /// and clear it once the data router recovers
+    fn check_data_rates(&mut self) {
+        if !self.status.is_system_running() || !self.status.get_monitored_families().surveyor {
+            return;
+        }
+        let changes = match self.rate_alarm.as_mut() {
+            Some(alarm) => alarm.sample(self.status.get_surveyor_status_response()),
+            None => return,
+        };
+        for change in changes {
+            match change {
+                RateAlarmChange::Raised(id) => self.notify_error(format!(
+                    "Data Router {id} has written no data for {} s while the others are writing!",
+                    self.config.stalled_rate_grace_sec
+                )),
+                RateAlarmChange::Cleared(id) => {
+                    self.notify_info(format!("Data Router {id} is writing data again"))
+                }
+            }
+        }
+    }
+
+    /// Which data routers the watchdogs flag, for the rate graph: those which are not online, and
+    /// those which stopped writing during the run
+    fn graph_problems(&self) -> Vec<bool> {
+        if !self.embassy.is_connected() || !self.status.get_monitored_families().surveyor {
+            return vec![];
+        }
+        router_problems(self.status.get_surveyor_status_response(), |id| {
+            self.rate_alarm
+                .as_ref()
+                .is_some_and(|alarm| alarm.is_alarmed(id))
+        })
+    }
+
+    /// The total bytes of .graw files on all of the data routers
+    fn total_bytes_used(&self) -> u64 {
+        self.status
+            .get_surveyor_status_response()
+            .iter()
+            .map(|resp| resp.bytes_used)
+            .sum()
+    }
+
+    /// The bytes written during the current run and the auto-cycle threshold, if auto-cycle is enabled
+    /// and a run started by this session is in progress. Run sequences take precedence over auto-cycle.
+    pub fn auto_cycle_progress(&self) -> Option<(u64, u64)> {
+        if !self.auto_limits_apply() {
+            return None;
+        }
+        self.config.auto_cycle_bytes().map(|limit| {
+            (
+                self.total_bytes_used()
+                    .saturating_sub(self.run_start_bytes.iter().sum()),
+                limit,
+            )
+        })
+    }
+
+    /// Stop the run if it has written more than the auto-cycle threshold, and start the next
+    /// run once the system is Ready again.
+    fn check_auto_cycle(&mut self) {
+        if let Some((written, limit)) = self.auto_cycle_progress() {
+            if written >= limit {
+                tracing::info!(
+                    "Run {} wrote {} bytes, exceeding the auto-cycle threshold of {} bytes. Cycling to the next run.",
+                    self.config.run_number,
+                    written,
+                    limit
+                );
+                self.stop_run(StopReason::AutoCycle);
+                self.restart_pending = true;
+            }
+        }
+        if !self.restart_pending {
+            return;
+        }
+        let system = self.status.get_system_ecc_status();
+        if matches!(system, ECCStatus::ErrorStat | ECCStatus::Unreachable) {
+            self.notify_error(format!(
+                "The system is {system}, the auto-cycled run could not be started!"
+            ));
+            self.restart_pending = false;
+        } else if self.can_start_run() {
+            self.restart_pending = false;
+            self.start_run();
+        }
+    }
+
+    /// Snapshot the ECC state before a bulk transition, so that a report can be made once it completes
+    pub fn begin_transition_report(&mut self) {
+        self.transition_report = None;
+        self.transition_snapshot = Some(self.status.ecc_snapshot());
+    }
+
+    /// Once a bulk transition has completed, compare the state with the snapshot taken before it.
+    /// Waits one poll interval after the last operation response so that the new states are reported.
+    fn check_transition_report(&mut self) {
+        let snapshot = match &self.transition_snapshot {
+            Some(s) => s,
+            None => return,
+        };
+        if self.status.is_any_held() || self.forward_transition.is_some() {
+            return;
+        }
+        let report = TransitionReport::new(snapshot, &self.status);
+        let settle_time = report.latest_response().unwrap_or_default()
+            + Duration::from_secs(self.config.poll_interval_sec);
+        if snapshot.time.elapsed() < settle_time {
+            return;
+        }
+        report.log();
+        self.transition_report = Some(report);
+        self.transition_snapshot = None;
+    }
 
-        //Update run start time
-        self.run_start_time = Instant::now();
-        self.graphs.reset();
+    /// Let the operator know that text they entered was changed to be safe for the run table and scripts
+    pub fn notify_sanitized(&mut self, name: &str, changed: bool) {
+        self.sanitize_notice = changed.then(|| {
+            format!("{name} was adjusted: control characters are removed, length is limited, and the experiment name may only contain letters, digits, '-', '_', and '.'")
+        });
+    }
+
+    /// Compose the window title from the experiment, run number, and connection/run state
+    fn compose_window_title(&self) -> String {
+        let state = if !self.embassy.is_connected() {
+            "Disconnected"
+        } else if self.status.is_system_running() {
+            "RUNNING"
+        } else {
+            "Connected"
+        };
+        format!(
+            "{WINDOW_TITLE} \u{2014} {} \u{2014} run {} [{state}]",
+            self.config.experiment, self.config.run_number
+        )
+    }
+
+    /// Update the native window title, only sending the command when it changes
+    fn update_window_title(&mut self, ctx: &eframe::egui::Context) {
+        let title = self.compose_window_title();
+        if title != self.window_title {
+            ctx.send_viewport_cmd(eframe::egui::ViewportCommand::Title(title.clone()));
+            self.window_title = title;
+        }
+    }
+
+    /// Log an error and show it to the operator until dismissed
+    pub fn notify_error(&mut self, message: String) {
+        tracing::error!("{message}");
+        self.error_log.push(None, message.clone());
+        self.notifications.push(ToastLevel::Error, message);
+    }
+
+    /// Log a warning and show it to the operator until dismissed
+    pub fn notify_warning(&mut self, message: String) {
+        tracing::warn!("{message}");
+        self.notifications.push(ToastLevel::Warning, message);
+    }
+
+    /// Log some information and briefly show it to the operator
+    pub fn notify_info(&mut self, message: String) {
+        tracing::info!("{message}");
+        self.notifications.push(ToastLevel::Info, message);
+    }
+
+    /// Alert the operator if every module stopped reporting status at once. A module is stale
+    /// when it has not reported for twice the poll interval.
+    fn check_staleness(&mut self) {
+        let threshold = Duration::from_secs(2 * self.config.poll_interval_sec);
+        match self.status.check_staleness(threshold) {
+            Some(NetworkChange::Lost) => self.notify_error(format!(
+                "Control network lost? No module has reported status for {} s",
+                threshold.as_secs()
+            )),
+            Some(NetworkChange::Restored) => self.notify_info(String::from(
+                "Control network restored, modules are reporting again",
+            )),
+            None => (),
+        }
+    }
+
+    /// Look for module states induced outside of the app. The run start passes through some
+    /// of the same combinations, so they are ignored while a run is starting.
+    fn check_external_state(&mut self) {
+        let detected = if self.is_run_starting() {
+            None
+        } else {
+            let waived = self
+                .run_quorum
+                .as_ref()
+                .map(|quorum| quorum.waived_cobos.as_slice())
+                .unwrap_or_default();
+            detect_external_state(&self.status, waived)
+        };
+        let previous = self.external_state.map(|(state, _)| state);
+        if detected != previous {
+            self.external_state = detected.map(|state| (state, Instant::now()));
+        }
+    }
+
+    /// The externally induced state of the system, once it has persisted for two status polls.
+    /// Transient combinations (i.e. while the CoBos are stopping) are not reported.
+    pub fn external_state(&self) -> Option<ExternalState> {
+        let grace = Duration::from_secs(2 * self.config.poll_interval_sec);
+        self.external_state
+            .filter(|(_, since)| since.elapsed() >= grace)
+            .map(|(state, _)| state)
+    }
+
+    /// Alert on the data router disks whose health changed
+    fn notify_disk_health(&mut self) {
+        for (id, health) in self.status.take_disk_health_changes() {
+            let message = format!(
+                "Data router {id} disk health is {}: {}",
+                health.state, health.details
+            );
+            match health.state {
+                DiskHealthState::Healthy => self.notify_info(message),
+                DiskHealthState::Warning | DiskHealthState::Failing => self.notify_error(message),
+            }
+        }
+    }
+
+    /// Record the ECC errors recieved by the status manager, showing the operation failures
+    fn notify_ecc_errors(&mut self) {
+        for report in self.status.take_new_errors() {
+            if report.is_operation {
+                let message = format!("ECC operation failed: {}", report.message);
+                self.error_log.push(Some(report.id), message.clone());
+                self.notifications.push_module_error(
+                    report.code,
+                    report.id,
+                    message,
+                    Duration::from_secs(self.config.error_group_window_sec),
+                );
+            } else {
+                self.error_log.push(
+                    Some(report.id),
+                    format!("ECC status failed: {}", report.message),
+                );
+            }
+        }
+    }
+
+    /// Can a run be started right now
+    pub fn can_start_run(&self) -> bool {
+        self.status.is_system_ready()
+            && !self.is_run_starting()
+            && !self.is_run_stopping()
+            && self.deferred_run.is_none()
+            && self.run_number_check.is_none()
+            && self.reconfigure_phase.is_none()
+            && self.start_countdown.is_none()
+            && is_valid_experiment(&self.config.experiment)
+    }
+
+    /// Can the current run be stopped right now
+    pub fn can_stop_run(&self) -> bool {
+        self.status.is_system_running() && !self.is_run_starting()
+    }
+
+    /// Handle the run control keyboard shortcuts. Shortcuts are ignored while a text
+    /// field has focus, and follow the same rules (and confirmations) as the buttons.
+    fn handle_shortcuts(&mut self, ctx: &eframe::egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&START_RUN_SHORTCUT)) && self.can_start_run() {
+            self.request_start_run();
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&STOP_RUN_SHORTCUT)) && self.can_stop_run() {
+            self.request_stop_run();
+        }
+        if ctx.input_mut(|i| i.consume_shortcut(&DISCONNECT_SHORTCUT))
+            && self.embassy.is_connected()
+        {
+            self.disconnect();
+        }
     }
 
     /// Send a stop run command to all of the envoys.
     /// Note that several important things must happen here. First the Mutant is stopped. Then, only after the Mutant has stopped,
     /// all of the Cobos are told to stop. After the stop command is issued, a command is sent to move all of the data to a run specific location,
     /// as well as a command to back up the ECC configuration files.
-    pub fn stop_run(&mut self) {
+    pub fn stop_run(&mut self, reason: StopReason) {
+        self.pending_confirmation = None;
+        self.auto_stop_armed = false;
         //Order is mutant, all cobos
         tracing::info!("Stopping run {} ...", self.config.run_number);
         tracing::info!("Stopping the MuTaNT...");
         //Stop the mutant
-        match stop_mutant_blocking(&mut self.embassy, &mut self.status) {
-            Ok(()) => (),
-            Err(e) => tracing::error!("Embassy had an error stopping the MuTaNT: {}", e),
+        match stop_mutant_blocking(
+            &mut self.embassy,
+            &mut self.status,
+            Duration::from_secs(MUTANT_STOP_TIMEOUT_SEC),
+        ) {
+            Ok(()) => tracing::info!("MuTaNT stopped."),
+            Err(e) => self.notify_error(format!("Embassy had an error stopping the MuTaNT: {e}")),
         }
 
-        tracing::info!("MuTaNT stopped.");
         tracing::info!("Stopping CoBos...");
 
         //Stop all of the CoBos
-        match stop_cobos(&mut self.embassy) {
+        match stop_cobos(&mut self.embassy, &mut self.status) {
             Ok(()) => (),
-            Err(e) => {
-                tracing::error!("Embassy had an error stoppging the CoBos: {}", e)
-            }
+            Err(e) => self.notify_error(format!("Embassy had an error stopping the CoBos: {e}")),
         }
 
         tracing::info!("CoBos stopped.");
-        tracing::info!("Moving .graw files...");
+        let mut file_check = String::new();
+        if self.status.get_monitored_families().surveyor {
+            // Check the file counts before the move clears them
+            self.file_count_warning = FileCountWarning::check(
+                self.config.run_number,
+                self.status.get_surveyor_status_response(),
+                self.config.file_count_factor,
+            );
+            file_check = match &self.file_count_warning {
+                Some(warning) => {
+                    tracing::warn!(
+                        "Run {} file counts are inconsistent: {}",
+                        self.config.run_number,
+                        warning.flag()
+                    );
+                    warning.flag()
+                }
+                None => String::from("OK"),
+            };
+        }
+        let experiment = self.config.experiment.clone();
+        let run_number = self.config.run_number;
+        self.rate_alarm = None;
+        let livetime_fraction = self.livetime.take().map(|livetime| {
+            for (id, degraded) in livetime.degraded_modules() {
+                tracing::info!(
+                    "Module {id} was not Running for {} s of run {run_number}",
+                    degraded.as_secs()
+                );
+            }
+            tracing::info!(
+                "Run {run_number} DAQ livetime: {:.2}%",
+                livetime.fraction() * 100.0
+            );
+            livetime.fraction()
+        });
 
-        match execute(
-            CommandName::MoveGrawFiles,
+        // The run is recorded as it was when the DAQ stopped, not once the files are moved
+        let run_duration = Instant::now() - self.run_start_time;
+        let average_rates = average_rates(
             self.status.get_surveyor_status_response(),
-            &self.config.experiment,
-            &self.config.run_number,
-        ) {
-            CommandStatus::Success => (),
-            CommandStatus::Failure => {
-                tracing::error!("Unable to move the graw files after the stop run signal!")
-            }
-            CommandStatus::CouldNotExecute => (),
+            &self.run_start_bytes,
+            run_duration,
+        );
+        let run = self.config.run_record(
+            &self.run_id,
+            run_duration,
+            livetime_fraction,
+            reason,
+            file_check,
+            &self.start_attempts,
+        );
+        let mut metadata = RunMetadata::new(
+            &self.config,
+            &self.run_id,
+            &self.run_start_wall_time,
+            self.scheduled_start_time.as_ref(),
+            livetime_fraction,
+            reason,
+            &self.status,
+        );
+        metadata.quorum = self.run_quorum.take();
+        for (router, rate) in metadata.routers.iter_mut().zip(average_rates.iter()) {
+            router.average_rate = *rate;
         }
+        let (start_attempts, start_failure) = self.start_attempts.for_run(run_number);
+        metadata.start_attempts = start_attempts;
+        metadata.last_start_failure = start_failure.map(|failure| failure.to_string());
+        metadata.mutant_reconfigure_sec = self
+            .mutant_reconfigure_time
+            .map(|reconfigure_time| reconfigure_time.as_secs_f64());
 
-        tracing::info!(".graw files moved.");
-        tracing::info!("Backing up GET configuration...");
+        if reason == StopReason::QuickStop {
+            tracing::warn!(
+                "Quick stop: deferring the .graw file move and the configuration backup of run {run_number}"
+            );
+            self.config
+                .deferred_tasks
+                .extend(DeferredTask::for_run(&experiment, run_number));
+            self.finish_stop_run(run, metadata, None);
+        } else if self.status.get_monitored_families().surveyor {
+            // The move can take minutes, so it runs on a background thread, followed by the
+            // backup, and the run is finished by poll_stopping_run once both complete
+            tracing::info!("Moving .graw files...");
+            let routers = self.status.get_surveyor_status_response().to_vec();
+            let use_script = self.config.move_graw_script;
+            let shell = self.config.shell.clone();
+            let timeout = self.config.command_timeout(CommandName::MoveGrawFiles);
+            let format = self.config.backup_format;
+            let (tx, rx) = channel();
+            std::thread::spawn(move || {
+                let moved = move_run_files(
+                    &routers,
+                    &experiment,
+                    &run_number,
+                    use_script,
+                    &shell,
+                    timeout,
+                );
+                if tx.send(StopStep::Moved(moved)).is_ok() {
+                    let backed_up = backup_configs(&experiment, run_number, format);
+                    let _ = tx.send(StopStep::BackedUp(backed_up));
+                }
+            });
+            self.stopping_run = Some(StoppingRun {
+                run_number,
+                run,
+                metadata,
+                since: Instant::now(),
+                backing_up: false,
+                steps: rx,
+            });
+        } else {
+            tracing::warn!("Data routers are not monitored, the .graw files were not moved!");
+            let config_backup = self.backup_config(&experiment, run_number);
+            self.finish_stop_run(run, metadata, config_backup);
+        }
+    }
 
-        match execute(
-            CommandName::BackupConfig,
-            self.status.get_surveyor_status_response(),
-            &self.config.experiment,
-            &self.config.run_number,
-        ) {
-            CommandStatus::Success => (),
-            CommandStatus::Failure => {
-                tracing::error!("Could not backup config files after the stop run signal")
+    /// Report the steps of the stopped run as they complete, and record the run once its
+    /// configuration is backed up
+    fn poll_stopping_run(&mut self) {
+        let step = match self
+            .stopping_run
+            .as_ref()
+            .map(|stopping| stopping.steps.try_recv())
+        {
+            Some(Ok(step)) => step,
+            Some(Err(TryRecvError::Disconnected)) => {
+                if let Some(stopping) = self.stopping_run.take() {
+                    self.notify_error(format!(
+                        "The stop of run {} exited before it finished, check the .graw files and the configuration backup!",
+                        stopping.run_number
+                    ));
+                    self.finish_stop_run(stopping.run, stopping.metadata, None);
+                }
+                return;
+            }
+            _ => return,
+        };
+        let run_number = match self.stopping_run.as_ref() {
+            Some(stopping) => stopping.run_number,
+            None => return,
+        };
+        match step {
+            StopStep::Moved(moved) => {
+                self.report_moved_files(run_number, moved);
+                tracing::info!("Backing up GET configuration...");
+                if let Some(stopping) = self.stopping_run.as_mut() {
+                    stopping.backing_up = true;
+                }
+            }
+            StopStep::BackedUp(backed_up) => {
+                let config_backup = self.report_backup(run_number, backed_up);
+                if let Some(stopping) = self.stopping_run.take() {
+                    self.finish_stop_run(stopping.run, stopping.metadata, config_backup);
+                }
             }
-            CommandStatus::CouldNotExecute => (),
         }
+    }
 
-        tracing::info!("GET configuration backed up.");
-        tracing::info!("Run {} stopped!", self.config.run_number);
+    /// Record a stopped run in the run table, summary, and metadata, and advance the run number
+    fn finish_stop_run(
+        &mut self,
+        run: RunRecord,
+        mut metadata: RunMetadata,
+        config_backup: Option<PathBuf>,
+    ) {
+        self.notify_info(format!(
+            "Run {} (UUID {}) stopped!",
+            run.run_number, run.run_uuid
+        ));
 
         tracing::info!("Saving config to table...");
-        self.config
-            .write_table(Instant::now() - self.run_start_time);
-        tracing::info!("Config saved to table.");
+        match self.config.write_table(&run) {
+            Ok(()) => tracing::info!("Config saved to table."),
+            Err(e) => {
+                self.notify_error(format!("Could not write the run to the config table: {e}"))
+            }
+        }
+        if self.run_table.is_some() {
+            self.run_table = Some(RunTableView::load(&self.config));
+        }
 
+        if self.config.write_run_summary {
+            let average_rates: Vec<Option<f64>> = metadata
+                .routers
+                .iter()
+                .map(|router| router.average_rate)
+                .collect();
+            match write_run_summary(
+                &self.config.experiment,
+                &run,
+                self.status.get_surveyor_status_response(),
+                &average_rates,
+            ) {
+                Ok(path) => tracing::info!("Run summary written to {}", path.display()),
+                Err(e) => self.notify_error(format!("Could not write the run summary: {e}")),
+            }
+        }
+
+        metadata.config_backup = config_backup.map(|path| path.display().to_string());
+        match metadata.write() {
+            Ok(path) => tracing::info!("Run metadata written to {}", path.display()),
+            Err(e) => self.notify_error(format!("Could not write the run metadata: {e}")),
+        }
+
+        self.scheduled_start_time = None;
+        self.open_idle_log();
         self.config.run_number += 1;
+        self.config.run_tags.clear();
+        self.config.run_tag = RunTag::Good;
         match self.config.save() {
             Ok(()) => tracing::info!("Config autosaved to {}", self.config.path.display()),
             Err(e) => tracing::error!("Could not autosave Config: {e}"),
         }
     }
+
+    /// Notify which data routers failed to move the .graw files of a run. Returns true if every
+    /// data router moved its files.
+    fn report_moved_files(
+        &mut self,
+        run_number: i32,
+        moved: std::io::Result<Vec<MoveFailure>>,
+    ) -> bool {
+        match moved {
+            Ok(failures) if failures.is_empty() => {
+                tracing::info!(".graw files moved.");
+                true
+            }
+            Ok(failures) => {
+                let routers: Vec<String> = failures
+                    .iter()
+                    .map(|failure| {
+                        tracing::error!(
+                            "Data Router {} ({}) failed to move its .graw files: {}",
+                            failure.id,
+                            failure.address,
+                            failure.detail
+                        );
+                        format!(
+                            "Data Router {} ({}): {}",
+                            failure.id, failure.address, failure.detail
+                        )
+                    })
+                    .collect();
+                self.notify_error(format!(
+                    "Unable to move the graw files of run {run_number} on {}!",
+                    routers.join(", ")
+                ));
+                false
+            }
+            Err(e) => {
+                self.notify_error(format!(
+                    "Could not execute the command to move the graw files: {e}"
+                ));
+                false
+            }
+        }
+    }
+
+    /// Back up the GET configuration of a run, returning the backup path if it succeeded
+    fn backup_config(&mut self, experiment: &str, run_number: i32) -> Option<PathBuf> {
+        tracing::info!("Backing up GET configuration...");
+        let backed_up = backup_configs(experiment, run_number, self.config.backup_format);
+        self.report_backup(run_number, backed_up)
+    }
+
+    /// Notify if the GET configuration of a run was not backed up, returning the backup path if
+    /// it was
+    fn report_backup(
+        &mut self,
+        run_number: i32,
+        backed_up: Result<PathBuf, CommandError>,
+    ) -> Option<PathBuf> {
+        match backed_up {
+            Ok(path) => {
+                tracing::info!("GET configuration backed up to {}.", path.display());
+                Some(path)
+            }
+            Err(e) => {
+                if let CommandError::BackupFailed(_, failures) = &e {
+                    for (path, error) in failures {
+                        tracing::error!("{} was not backed up: {error}", path.display());
+                    }
+                }
+                self.notify_error(format!(
+                    "Could not backup the config files of run {run_number}: {e}"
+                ));
+                None
+            }
+        }
+    }
+
+    /// Run some of the tasks deferred by a quick stop in the background. Never runs during a
+    /// run, since the files of the current run would be moved too.
+    pub fn run_deferred_tasks(&mut self, tasks: Vec<DeferredTask>) {
+        if self.is_run_locked() {
+            return;
+        }
+        self.begin_deferred_tasks(tasks, false);
+    }
+
+    /// Run tasks deferred by a quick stop, in order, on a background thread. The results are
+    /// reported by poll_deferred_tasks. If then_start is set, the run start continues once the
+    /// tasks are done.
+    fn begin_deferred_tasks(&mut self, tasks: Vec<DeferredTask>, then_start: bool) {
+        if self.deferred_run.is_some() {
+            return;
+        }
+        let surveyor = self.status.get_monitored_families().surveyor;
+        let mut runnable = vec![];
+        for task in tasks {
+            if task.kind == DeferredTaskKind::MoveGrawFiles && !surveyor {
+                self.notify_error(format!(
+                    "Could not run the deferred task ({task}): the data routers are not monitored"
+                ));
+            } else {
+                runnable.push(task);
+            }
+        }
+        let routers = self.status.get_surveyor_status_response().to_vec();
+        let use_script = self.config.move_graw_script;
+        let shell = self.config.shell.clone();
+        let timeout = self.config.command_timeout(CommandName::MoveGrawFiles);
+        let format = self.config.backup_format;
+        let (tx, rx) = channel();
+        std::thread::spawn(move || {
+            for task in runnable {
+                let step = match task.kind {
+                    DeferredTaskKind::MoveGrawFiles => StopStep::Moved(move_run_files(
+                        &routers,
+                        &task.experiment,
+                        &task.run_number,
+                        use_script,
+                        &shell,
+                        timeout,
+                    )),
+                    DeferredTaskKind::BackupConfig => StopStep::BackedUp(backup_configs(
+                        &task.experiment,
+                        task.run_number,
+                        format,
+                    )),
+                };
+                if tx.send((task, step)).is_err() {
+                    return;
+                }
+            }
+        });
+        self.deferred_run = Some(DeferredTasksRun {
+            then_start,
+            since: Instant::now(),
+            steps: rx,
+        });
+    }
+
+    /// Report the deferred tasks as they complete, removing those which succeeded. Once they
+    /// are all done, a run start which was waiting on them continues, unless any of them failed.
+    fn poll_deferred_tasks(&mut self) {
+        let step = match self.deferred_run.as_ref().map(|run| run.steps.try_recv()) {
+            Some(Ok(step)) => step,
+            Some(Err(TryRecvError::Disconnected)) => {
+                if let Some(run) = self.deferred_run.take() {
+                    self.finish_deferred_tasks(run.then_start);
+                }
+                return;
+            }
+            _ => return,
+        };
+        let (task, step) = step;
+        let done = match step {
+            StopStep::Moved(moved) => self.report_moved_files(task.run_number, moved),
+            StopStep::BackedUp(backed_up) => {
+                self.report_backup(task.run_number, backed_up).is_some()
+            }
+        };
+        if done {
+            tracing::info!("Deferred task done: {task}");
+            if let Some(index) = self.config.deferred_tasks.iter().position(|t| *t == task) {
+                self.config.deferred_tasks.remove(index);
+                self.save_deferred_tasks();
+            }
+        }
+    }
+
+    /// Continue a run start which ran the deferred tasks, refusing if any are left
+    fn finish_deferred_tasks(&mut self, then_start: bool) {
+        if !then_start {
+            return;
+        }
+        if !self.config.deferred_tasks.is_empty() {
+            let issue = RunNumberIssue::DeferredTasks(
+                self.config
+                    .deferred_tasks
+                    .iter()
+                    .map(|task| task.to_string())
+                    .collect(),
+            );
+            self.notify_error(format!(
+                "Run {} was not started: {issue}",
+                self.config.run_number
+            ));
+            self.run_number_issue = Some(issue);
+        } else if self.can_start_run() {
+            self.start_run();
+        } else {
+            self.notify_error(format!(
+                "Run {} was not started: the system is no longer ready",
+                self.config.run_number
+            ));
+        }
+    }
+
+    /// Discard a deferred task without running it
+    pub fn discard_deferred_task(&mut self, index: usize) {
+        if index >= self.config.deferred_tasks.len() {
+            return;
+        }
+        let task = self.config.deferred_tasks.remove(index);
+        tracing::warn!("Discarded deferred task: {task}");
+        self.save_deferred_tasks();
+    }
+
+    /// Save the config so that the deferred tasks survive a restart
+    fn save_deferred_tasks(&mut self) {
+        if let Err(e) = self.config.save() {
+            tracing::error!("Could not save the deferred tasks: {e}");
+        }
+    }
 }
 //*************//
 // STATE LOGIC //
@@ -207,11 +2000,40 @@ impl EnvoyApp {
 //*************//
 impl eframe::App for EnvoyApp {
     fn update(&mut self, ctx: &eframe::egui::Context, _frame: &mut eframe::Frame) {
+        if let Ok(mut repaints) = self.repaints.lock() {
+            repaints.frame(Instant::now());
+        }
         //Probably don't want to poll every frame, but as a test...
         match poll_embassy(&mut self.embassy, &mut self.status) {
             Ok(()) => (),
-            Err(e) => tracing::error!("An error occurred when polling the embassy: {}", e),
+            Err(e) => self.notify_error(format!("An error occurred when polling the embassy: {e}")),
         }
+        self.check_staleness();
+        self.notify_ecc_errors();
+        self.notify_disk_health();
+        self.check_external_state();
+        self.check_start_countdown();
+        self.advance_run_start();
+        self.advance_reconfigure();
+        self.advance_forward_transition();
+        self.advance_idle_regression();
+        self.poll_stopping_run();
+        self.poll_deferred_tasks();
+        self.poll_run_number_check();
+        self.poll_run_number_suggestion();
+        self.check_config_changes();
+        self.handle_shortcuts(ctx);
+        if let Some(livetime) = self.livetime.as_mut() {
+            livetime.sample(&self.status);
+        }
+        self.check_data_rates();
+        self.check_disk_full();
+        self.check_auto_stop();
+        self.check_auto_cycle();
+        self.check_transition_report();
+        self.advance_run_sequence();
+        self.update_window_title(ctx);
+        self.graphs.set_problems(&self.graph_problems());
         if self.graphs.should_update()
             && self.embassy.is_connected()
             && self.status.is_system_running()
@@ -219,14 +2041,63 @@ impl eframe::App for EnvoyApp {
             self.graphs
                 .update(self.status.get_surveyor_status_response());
         }
+        render_log_panel(&mut self.log_console, self.show_log, ctx);
         render_run_log_panel(self, ctx);
         render_config_panel(self, ctx);
+        render_error_log_panel(self, ctx);
         render_graph_panel(self, ctx);
         render_ecc_panel(self, ctx);
         render_data_router_panel(self, ctx);
-        ctx.request_repaint_after(std::time::Duration::from_secs(1));
+        render_diagnostics_panel(self, ctx);
+        render_help_panel(self, ctx);
+        render_expert_console(self, ctx);
+        render_deferred_tasks(self, ctx);
+        render_effective_config(self, ctx);
+        render_run_table_panel(self, ctx);
+        render_config_restore(self, ctx);
+        render_setup_wizard(self, ctx);
+        render_module_windows(self, ctx);
+        render_notifications(&mut self.notifications, ctx);
+        render_confirm_panel(self, ctx);
+        render_run_number_issue(self, ctx);
+        render_file_count_warning(self, ctx);
+        render_pre_run_checklist(self, ctx);
+        render_start_countdown(self, ctx);
+        render_transition_report(self, ctx);
+        if self.embassy.poll_stats().backlog > 0 {
+            //Handle the deferred messages on the next frame
+            ctx.request_repaint();
+        } else if self.start_countdown.is_some() {
+            ctx.request_repaint_after(COUNTDOWN_REPAINT_INTERVAL);
+        } else {
+            ctx.request_repaint_after(std::time::Duration::from_secs(1));
+        }
     }
 }
 //*************//
 //  APP IMPL  //
 //*************//
+
+/// The auto-stop and auto-cycle limits only apply to a run started by this session once it is
+/// fully running, and never to the runs of a sequence, which stops its own runs
+fn auto_limits_apply(running: bool, starting: bool, armed: bool, sequenced: bool) -> bool {
+    running && !starting && armed && !sequenced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_limits_wait_for_an_armed_running_run() {
+        assert!(auto_limits_apply(true, false, true, false));
+        assert!(!auto_limits_apply(false, false, true, false));
+        assert!(!auto_limits_apply(true, true, true, false));
+        assert!(!auto_limits_apply(true, false, false, false));
+    }
+
+    #[test]
+    fn auto_limits_skip_run_sequences() {
+        assert!(!auto_limits_apply(true, false, true, true));
+    }
+}
@@ -1,9 +1,27 @@
+use super::deferred_tasks::DeferredTask;
 use super::error::ConfigError;
+use super::graph_manager::DEFAULT_RATE_CEILING;
+use super::notifications::DEFAULT_ERROR_GROUP_WINDOW_SEC;
+use super::run_log_store::{
+    CsvRunLog, RunLogBackend, RunLogStore, RunRecord, RunTag, SqliteRunLog,
+};
+use super::run_sequence::StopReason;
+use super::sanitize::{sanitize_experiment, sanitize_text, TextLimits};
+use super::start_attempts::StartAttempts;
+use crate::command::bash_command::{CommandName, ShellConfig};
+use crate::command::config_archive::BackupFormat;
+use crate::envoy::constants::{DEFAULT_NUMBER_OF_COBOS, DEFAULT_POLL_INTERVAL_SEC};
+use crate::envoy::embassy::EnvoyFamilies;
+use crate::envoy::network::NetworkMap;
+use crate::envoy::runtime::RuntimeConfig;
+use crate::envoy::status_manager::ReadinessPolicy;
+use crate::envoy::surveyor_envoy::SurveyorResponse;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
-use std::io::{BufRead, Read, Write};
+use std::io::{Read, Write};
 use std::path::PathBuf;
+use uuid::Uuid;
 
 const DEFAULT_FIELDS: [&str; 11] = [
     "Target Gas",
@@ -19,16 +37,232 @@ const DEFAULT_FIELDS: [&str; 11] = [
     "GET Freq. (MHz)",
 ];
 
+/// The description of a new config, which should be replaced before a run
+pub const DEFAULT_DESCRIPTION: &str = "Write here";
+
+/// The run log fields which should be filled before a run
+const DEFAULT_REQUIRED_FIELDS: [&str; 2] = ["Target Gas", "Pressure (Torr)"];
+
+/// Data router disk usage above which runs are not started
+const DEFAULT_DISK_USAGE_LIMIT_PERCENT: f64 = 95.0;
+
+/// Data router free space below which runs are not started
+const DEFAULT_MIN_FREE_DISK_GB: f64 = 100.0;
+
+/// Data router free space below which the run is stopped
+const DEFAULT_DISK_FULL_FLOOR_GB: f64 = 20.0;
+
+/// How far a data router's file count may be from the median before the run is flagged
+const DEFAULT_FILE_COUNT_FACTOR: f64 = 2.0;
+
+/// How long a data router may write nothing during a run before the alarm is raised
+const DEFAULT_STALLED_RATE_GRACE_SEC: u64 = 30;
+
+/// How long the regression to Idle on disconnect may wait for the modules to make progress
+const DEFAULT_DISCONNECT_TIMEOUT_SEC: u64 = 30;
+
+const DEFAULT_TAGS: [&str; 4] = ["junk", "calibration", "physics", "pulser"];
+
+/// Separator used between tags in the table, as commas delimit the columns
+const TAG_SEPARATOR: &str = ";";
+
+/// What happens to the hardware when the envoys are disconnected
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum DisconnectPolicy {
+    /// Leave the modules in whatever state they are in
+    #[default]
+    LeaveAsIs,
+    /// Regress every module to Idle before disconnecting, after the operator confirms
+    RegressToIdle,
+}
+
+impl std::fmt::Display for DisconnectPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LeaveAsIs => write!(f, "Leave As Is"),
+            Self::RegressToIdle => write!(f, "Regress to Idle"),
+        }
+    }
+}
+
 /// (De)Serializable application configuration
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Config {
     #[serde(skip)]
     pub path: PathBuf,
+    #[serde(skip)]
+    csv_run_log: CsvRunLog,
+    #[serde(skip)]
+    sqlite_run_log: SqliteRunLog,
 
     pub experiment: String,
     pub run_number: i32,
     pub description: String,
+    /// Who was on shift, recorded with each run
+    #[serde(default)]
+    pub operator: String,
     pub fields: BTreeMap<String, String>,
+    #[serde(default)]
+    pub families: EnvoyFamilies,
+    #[serde(default = "default_num_cobos")]
+    pub num_cobos: usize,
+    #[serde(default)]
+    pub runtime: RuntimeConfig,
+    #[serde(default)]
+    pub network: NetworkMap,
+    #[serde(default = "default_watch_configs")]
+    pub watch_configs: bool,
+    #[serde(default = "default_poll_interval_sec")]
+    pub poll_interval_sec: u64,
+    /// How many modules must agree for the system to be Ready (or in any other state)
+    #[serde(default)]
+    pub readiness: ReadinessPolicy,
+    /// What happens to the hardware on disconnect
+    #[serde(default)]
+    pub disconnect_policy: DisconnectPolicy,
+    /// How long the regression to Idle on disconnect may wait for the modules to make progress
+    #[serde(default = "default_disconnect_timeout_sec")]
+    pub disconnect_timeout_sec: u64,
+    #[serde(default = "default_rate_ceiling")]
+    pub rate_ceiling: f64,
+    /// Re-configure the MuTaNT at the start of each run to reset the timestamps
+    #[serde(default = "default_reset_mutant_timestamps")]
+    pub reset_mutant_timestamps: bool,
+    /// Automatically stop runs after this many minutes. Disabled if unset or zero
+    #[serde(default)]
+    pub auto_stop_minutes: Option<u64>,
+    /// Automatically stop the run and start the next one after this many GB are written. Disabled if unset or zero
+    #[serde(default)]
+    pub auto_cycle_gb: Option<f64>,
+    #[serde(default)]
+    pub text_limits: TextLimits,
+    /// Allow overrides of safety checks (i.e. starting a run when the run number could not be checked)
+    #[serde(default)]
+    pub expert_mode: bool,
+    /// Skip the pre-run checklist. Only honored in expert mode
+    #[serde(default)]
+    pub skip_pre_run_checklist: bool,
+    /// Check the run number with the test_graw.sh script rather than the built-in check
+    #[serde(default)]
+    pub run_check_script: bool,
+    /// Move the .graw files with the move_graw.sh script rather than the built-in move
+    #[serde(default)]
+    pub move_graw_script: bool,
+    /// The shell and the directory of the scripts
+    #[serde(default)]
+    pub shell: ShellConfig,
+    /// The timeout (s) of each external command, overriding its default
+    #[serde(default)]
+    pub command_timeouts: BTreeMap<CommandName, u64>,
+    /// How the GET configuration of each run is backed up
+    #[serde(default)]
+    pub backup_format: BackupFormat,
+    /// The stop-run steps skipped by a quick stop, which must be done before the next run
+    #[serde(default)]
+    pub deferred_tasks: Vec<DeferredTask>,
+    /// The number of AsAds each module is expected to have active, by module ID. Modules which
+    /// are not listed are not checked.
+    #[serde(default)]
+    pub expected_asads: BTreeMap<usize, usize>,
+    /// The run log fields the pre-run checklist requires to be filled
+    #[serde(default = "default_required_fields")]
+    pub required_fields: Vec<String>,
+    /// The data router disk usage (%) at or above which runs are not started
+    #[serde(default = "default_disk_usage_limit_percent")]
+    pub disk_usage_limit_percent: f64,
+    /// The data router free space (GB) below which runs are not started
+    #[serde(default = "default_min_free_disk_gb")]
+    pub min_free_disk_gb: f64,
+    /// The data router free space (GB) below which a run in progress is stopped
+    #[serde(default = "default_disk_full_floor_gb")]
+    pub disk_full_floor_gb: f64,
+    /// How long (s) a data router may write nothing while the others are writing before the alarm is raised
+    #[serde(default = "default_stalled_rate_grace_sec")]
+    pub stalled_rate_grace_sec: u64,
+    /// A data router whose file count differs from the median by more than this factor is flagged when the run stops
+    #[serde(default = "default_file_count_factor")]
+    pub file_count_factor: f64,
+    /// The tags available for classifying runs
+    #[serde(default = "default_tags")]
+    pub tags: Vec<String>,
+    /// The tags chosen for the current run
+    #[serde(default)]
+    pub run_tags: BTreeSet<String>,
+    /// The verdict on the current run. Reset to Good when a run stops
+    #[serde(default)]
+    pub run_tag: RunTag,
+    /// Identical ECC error codes from different modules within this many seconds are shown as one notification
+    #[serde(default = "default_error_group_window_sec")]
+    pub error_group_window_sec: u64,
+    /// Where the run log is kept
+    #[serde(default)]
+    pub run_log_backend: RunLogBackend,
+    /// Write a markdown summary of each run when it stops
+    #[serde(default = "default_write_run_summary")]
+    pub write_run_summary: bool,
+}
+
+fn default_num_cobos() -> usize {
+    DEFAULT_NUMBER_OF_COBOS
+}
+
+fn default_watch_configs() -> bool {
+    true
+}
+
+fn default_poll_interval_sec() -> u64 {
+    DEFAULT_POLL_INTERVAL_SEC
+}
+
+fn default_rate_ceiling() -> f64 {
+    DEFAULT_RATE_CEILING
+}
+
+fn default_reset_mutant_timestamps() -> bool {
+    true
+}
+
+fn default_write_run_summary() -> bool {
+    true
+}
+
+fn default_error_group_window_sec() -> u64 {
+    DEFAULT_ERROR_GROUP_WINDOW_SEC
+}
+
+fn default_required_fields() -> Vec<String> {
+    DEFAULT_REQUIRED_FIELDS
+        .iter()
+        .map(|field| field.to_string())
+        .collect()
+}
+
+fn default_disk_usage_limit_percent() -> f64 {
+    DEFAULT_DISK_USAGE_LIMIT_PERCENT
+}
+
+fn default_min_free_disk_gb() -> f64 {
+    DEFAULT_MIN_FREE_DISK_GB
+}
+
+fn default_disk_full_floor_gb() -> f64 {
+    DEFAULT_DISK_FULL_FLOOR_GB
+}
+
+fn default_stalled_rate_grace_sec() -> u64 {
+    DEFAULT_STALLED_RATE_GRACE_SEC
+}
+
+fn default_disconnect_timeout_sec() -> u64 {
+    DEFAULT_DISCONNECT_TIMEOUT_SEC
+}
+
+fn default_file_count_factor() -> f64 {
+    DEFAULT_FILE_COUNT_FACTOR
+}
+
+fn default_tags() -> Vec<String> {
+    DEFAULT_TAGS.iter().map(|tag| tag.to_string()).collect()
 }
 
 impl Config {
@@ -39,10 +273,48 @@ impl Config {
         }
         Config {
             path: PathBuf::from("example.yml"),
+            csv_run_log: CsvRunLog::default(),
+            sqlite_run_log: SqliteRunLog::default(),
             experiment: String::from("Exp"),
             run_number: 0,
-            description: String::from("Write here"),
+            description: String::from(DEFAULT_DESCRIPTION),
+            operator: String::new(),
             fields,
+            families: EnvoyFamilies::default(),
+            num_cobos: DEFAULT_NUMBER_OF_COBOS,
+            runtime: RuntimeConfig::default(),
+            network: NetworkMap::default(),
+            watch_configs: true,
+            poll_interval_sec: DEFAULT_POLL_INTERVAL_SEC,
+            readiness: ReadinessPolicy::default(),
+            disconnect_policy: DisconnectPolicy::default(),
+            disconnect_timeout_sec: DEFAULT_DISCONNECT_TIMEOUT_SEC,
+            rate_ceiling: DEFAULT_RATE_CEILING,
+            reset_mutant_timestamps: true,
+            auto_stop_minutes: None,
+            auto_cycle_gb: None,
+            text_limits: TextLimits::default(),
+            expert_mode: false,
+            skip_pre_run_checklist: false,
+            run_check_script: false,
+            move_graw_script: false,
+            backup_format: BackupFormat::default(),
+            command_timeouts: BTreeMap::new(),
+            shell: ShellConfig::default(),
+            deferred_tasks: vec![],
+            expected_asads: BTreeMap::new(),
+            required_fields: default_required_fields(),
+            disk_usage_limit_percent: DEFAULT_DISK_USAGE_LIMIT_PERCENT,
+            min_free_disk_gb: DEFAULT_MIN_FREE_DISK_GB,
+            disk_full_floor_gb: DEFAULT_DISK_FULL_FLOOR_GB,
+            stalled_rate_grace_sec: DEFAULT_STALLED_RATE_GRACE_SEC,
+            file_count_factor: DEFAULT_FILE_COUNT_FACTOR,
+            tags: default_tags(),
+            run_tags: BTreeSet::new(),
+            run_tag: RunTag::Good,
+            error_group_window_sec: DEFAULT_ERROR_GROUP_WINDOW_SEC,
+            run_log_backend: RunLogBackend::Csv,
+            write_run_summary: true,
         }
     }
 
@@ -59,109 +331,219 @@ impl Config {
         file.read_to_string(&mut yaml_str)?;
         *self = serde_yaml::from_str::<Config>(&yaml_str)?;
         self.path = path;
+        let changed = self.sanitize();
+        if !changed.is_empty() {
+            tracing::warn!(
+                "Loaded config had invalid text which was sanitized: {}",
+                changed.join(", ")
+            );
+        }
+        let readiness = self.readiness.clamped(self.num_cobos);
+        if readiness != self.readiness {
+            tracing::warn!(
+                "Loaded config had a quorum of {} CoBos, limited to {}",
+                self.readiness.quorum_cobos,
+                readiness.quorum_cobos
+            );
+            self.readiness = readiness;
+        }
         Ok(())
     }
 
-    pub fn add_field(&mut self, field: String, value: String) {
-        self.fields.insert(field, value);
-    }
-
-    /// Get the path to a configuration table which we will log experiment data to
-    fn get_config_table(&self) -> PathBuf {
-        let mut header = String::from("Run,Note,Duration");
-        for key in self.fields.keys() {
-            header = format!("{header},{key}");
-        }
-        header = format!("{header}\n");
-        let table_dir = PathBuf::from("tables/");
-        if !table_dir.exists() {
-            match std::fs::create_dir(&table_dir) {
-                Ok(()) => (),
-                Err(e) => tracing::error!(
-                    "Could not create table directory due to: {}. The config table will not be saved!",
-                    e
-                ),
+    /// Set the experiment name, returning true if it had to be sanitized
+    pub fn set_experiment(&mut self, experiment: &str) -> bool {
+        let (experiment, changed) =
+            sanitize_experiment(experiment, self.text_limits.max_experiment_length);
+        self.experiment = experiment;
+        changed
+    }
+
+    /// Set the run description, returning true if it had to be sanitized
+    pub fn set_description(&mut self, description: &str) -> bool {
+        let (description, changed) =
+            sanitize_text(description, self.text_limits.max_description_length);
+        self.description = description;
+        changed
+    }
+
+    /// Set the operator name, returning true if it had to be sanitized
+    pub fn set_operator(&mut self, operator: &str) -> bool {
+        let (operator, changed) = sanitize_text(operator, self.text_limits.max_field_length);
+        self.operator = operator;
+        changed
+    }
+
+    /// Set the value of a run log field, returning true if it had to be sanitized
+    pub fn set_field(&mut self, field: &str, value: &str) -> bool {
+        let (value, changed) = sanitize_text(value, self.text_limits.max_field_length);
+        self.fields.insert(field.to_string(), value);
+        changed
+    }
+
+    /// Sanitize all of the operator entered text, returning the names of the values which were changed
+    pub fn sanitize(&mut self) -> Vec<String> {
+        let mut changed = vec![];
+        if self.set_experiment(&self.experiment.clone()) {
+            changed.push(String::from("Experiment"));
+        }
+        if self.set_description(&self.description.clone()) {
+            changed.push(String::from("Description"));
+        }
+        if self.set_operator(&self.operator.clone()) {
+            changed.push(String::from("Operator"));
+        }
+        for (field, value) in self.fields.clone() {
+            if self.set_field(&field, &value) {
+                changed.push(field);
             }
         }
+        changed
+    }
 
-        let table_path = table_dir.join(format!("{}.csv", self.experiment));
-        if !table_path.exists() {
-            if let Ok(mut file) = std::fs::File::create(&table_path) {
-                match file.write_all(header.as_bytes()) {
-                    Ok(_) => (),
-                    Err(e) => {
-                        tracing::error!("Could not write header to config table: {}", e);
-                    }
-                }
-            }
-        } else {
-            let mut lines = Vec::new();
-            if let Ok(file) = std::fs::File::open(&table_path) {
-                let mut reader = std::io::BufReader::new(file);
-                let mut header_line = String::new();
-                reader.read_line(&mut header_line).expect("No header line!");
-                if format!("{header_line}\n") == header {
-                    return table_path;
-                }
-                lines = match reader.lines().collect() {
-                    Ok(l) => l,
-                    Err(e) => {
-                        tracing::error!("Could not read existing table! Error: {}", e);
-                        return table_path;
-                    }
-                }
-            }
-            if let Ok(mut file) = std::fs::File::create(&table_path) {
-                match file.write_all(header.as_bytes()) {
-                    Ok(_) => (),
-                    Err(e) => {
-                        tracing::error!(
-                            "Could not write header to run log file at reformat: {}",
-                            e
-                        );
-                        return table_path;
-                    }
-                }
-                for line in lines {
-                    let line_end = format!("{line}\n");
-                    match file.write_all(line_end.as_bytes()) {
-                        Ok(_) => (),
-                        Err(e) => {
-                            tracing::error!(
-                                "Could not write line {} when reformating run log: {}",
-                                line,
-                                e
-                            );
-                            return table_path;
-                        }
-                    }
-                }
-            }
+    pub fn add_field(&mut self, field: String, value: String) {
+        let (field, _) = sanitize_text(&field, self.text_limits.max_field_length);
+        let field = field.replace(',', "");
+        if field.is_empty() {
+            return;
         }
+        self.set_field(&field, &value);
+    }
 
-        table_path
+    /// Describe each data router with too little free disk space to start a run. Routers which
+    /// did not report their disk usage are skipped.
+    pub fn disk_space_issues(&self, routers: &[SurveyorResponse]) -> Vec<String> {
+        routers
+            .iter()
+            .enumerate()
+            .filter_map(|(id, router)| {
+                let used = router.percent_used_value()?;
+                let free_gb = router.free_bytes()? as f64 * 1.0e-9;
+                (used >= self.disk_usage_limit_percent || free_gb < self.min_free_disk_gb)
+                    .then(|| format!("Data Router {id}: {free_gb:.1} GB free ({used}% used)"))
+            })
+            .collect()
     }
 
-    /// Write experiment data to a log table
-    pub fn write_table(&self, ellapsed_time: std::time::Duration) {
-        let path = self.get_config_table();
-        let mut row = format!(
-            "{},{},{}",
-            self.run_number,
-            self.description,
-            ellapsed_time.as_secs()
-        );
-        if let Ok(mut file) = std::fs::OpenOptions::new().append(true).open(path) {
-            for field in self.fields.values() {
-                row = format!("{row},{field}")
-            }
-            row = format!("{row}\n");
-            match file.write_all(row.as_bytes()) {
-                Ok(_) => (),
-                Err(e) => {
-                    tracing::error!("Could not write row to config table: {}", e);
-                }
-            }
+    /// The run length after which runs are automatically stopped, if enabled
+    pub fn auto_stop_duration(&self) -> Option<std::time::Duration> {
+        match self.auto_stop_minutes {
+            Some(minutes) if minutes > 0 => Some(std::time::Duration::from_secs(minutes * 60)),
+            _ => None,
+        }
+    }
+
+    /// The bytes written after which runs are automatically cycled, if enabled
+    pub fn auto_cycle_bytes(&self) -> Option<u64> {
+        match self.auto_cycle_gb {
+            Some(gb) if gb > 0.0 => Some((gb * 1.0e9) as u64),
+            _ => None,
+        }
+    }
+
+    /// Add a tag to the list of available tags. Tags are trimmed and may not contain
+    /// the table delimiters.
+    pub fn add_tag(&mut self, tag: &str) {
+        let tag = tag.trim().replace([',', ';'], "");
+        if !tag.is_empty() && !self.tags.contains(&tag) {
+            self.tags.push(tag);
         }
     }
+
+    /// Remove a tag from the list of available tags (and from the current run)
+    pub fn remove_tag(&mut self, tag: &str) {
+        self.tags.retain(|t| t != tag);
+        self.run_tags.remove(tag);
+    }
+
+    /// The store the run log is kept in, chosen by the run log backend
+    fn run_log_store(&self) -> &dyn RunLogStore {
+        match self.run_log_backend {
+            RunLogBackend::Csv => &self.csv_run_log,
+            RunLogBackend::Sqlite => &self.sqlite_run_log,
+        }
+    }
+
+    fn run_log_store_mut(&mut self) -> &mut dyn RunLogStore {
+        match self.run_log_backend {
+            RunLogBackend::Csv => &mut self.csv_run_log,
+            RunLogBackend::Sqlite => &mut self.sqlite_run_log,
+        }
+    }
+
+    /// The path of the run log of the experiment
+    pub fn table_path(&self) -> PathBuf {
+        self.run_log_store().path(&self.experiment)
+    }
+
+    /// Read the run log of the experiment. The first record is the header.
+    pub fn read_table(&self) -> Result<Vec<Vec<String>>, ConfigError> {
+        self.run_log_store().read_runs(&self.experiment)
+    }
+
+    /// Compose the run log record of the current run. The run log and the run summary are
+    /// both written from this record, so they can't disagree.
+    pub fn run_record(
+        &self,
+        run_id: &Uuid,
+        ellapsed_time: std::time::Duration,
+        livetime: Option<f64>,
+        stop_reason: StopReason,
+        file_check: String,
+        start_attempts: &StartAttempts,
+    ) -> RunRecord {
+        let (attempts, failure) = start_attempts.for_run(self.run_number);
+        RunRecord {
+            run_number: self.run_number,
+            run_uuid: run_id.to_string(),
+            note: stop_reason.annotate(&self.description),
+            operator: self.operator.clone(),
+            tags: self
+                .run_tags
+                .iter()
+                .cloned()
+                .collect::<Vec<String>>()
+                .join(TAG_SEPARATOR),
+            run_tag: self.run_tag,
+            duration_sec: ellapsed_time.as_secs(),
+            livetime,
+            stop_reason: stop_reason.to_string(),
+            file_check,
+            start_attempts: attempts,
+            start_failure: failure.map(|f| f.to_string()).unwrap_or_default(),
+            fields: self.fields.clone(),
+        }
+    }
+
+    /// How long an external command may run before it is killed
+    pub fn command_timeout(&self, name: CommandName) -> std::time::Duration {
+        self.command_timeouts
+            .get(&name)
+            .map(|seconds| std::time::Duration::from_secs(*seconds))
+            .unwrap_or(name.default_timeout())
+    }
+
+    /// Record the run in the run log
+    pub fn write_table(&mut self, run: &RunRecord) -> Result<(), ConfigError> {
+        let experiment = self.experiment.clone();
+        self.run_log_store_mut().write_run(&experiment, run)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loaded_quorum_is_clamped() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::new();
+        config.path = dir.path().join("config.yaml");
+        config.num_cobos = 3;
+        config.readiness.quorum_cobos = 50;
+        config.save().unwrap();
+
+        let mut loaded = Config::new();
+        loaded.load(config.path.clone()).unwrap();
+        assert_eq!(loaded.readiness.quorum_cobos, 3);
+    }
 }
@@ -1,7 +1,22 @@
-use super::app::EnvoyApp;
+use super::app::{EnvoyApp, DISCONNECT_SHORTCUT, START_RUN_SHORTCUT, STOP_RUN_SHORTCUT};
+use super::config::DisconnectPolicy;
+use super::config_restore::ConfigRestore;
+use super::confirm_panel::{render_tag_selector, tag_summary};
+use super::features::FeatureMatrix;
+use super::run_log_store::RunTag;
+use super::run_sequence::StopReason;
+use super::run_table_panel::RunTableView;
+use super::sanitize::is_valid_experiment;
+use super::setup_wizard::SetupWizard;
+use super::start_countdown::StartCountdown;
 use super::style::pretty_ellapsed_time;
-use eframe::egui::{Button, Color32, DragValue, RichText, TopBottomPanel};
+use crate::envoy::status_manager::ReadinessMode;
+use eframe::egui::{
+    Button, CollapsingHeader, Color32, ComboBox, DragValue, Grid, RichText, TextEdit,
+    TopBottomPanel,
+};
 use rfd::FileDialog;
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
 /// Render the configuration panel (top panel in the UI)
@@ -11,38 +26,196 @@ use std::time::{Duration, Instant};
 pub fn render_config_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
     TopBottomPanel::top("Config_Panel").show(ctx, |ui| {
         //Drop down menu
-        ui.menu_button(RichText::new("File").size(16.0), |ui| {
-            if ui.button(RichText::new("Save").size(14.0)).clicked() {
-                if let Some(path) = FileDialog::new()
-                    .set_directory(
-                        std::env::current_dir().expect("Couldn't access runtime directory"),
+        ui.horizontal(|ui| {
+            ui.menu_button(RichText::new("File").size(16.0), |ui| {
+                if ui.button(RichText::new("Save").size(14.0)).clicked() {
+                    if let Some(path) = FileDialog::new()
+                        .set_directory(
+                            std::env::current_dir().expect("Couldn't access runtime directory"),
+                        )
+                        .add_filter("YAML", &["yaml", "yml"])
+                        .save_file()
+                    {
+                        app.config.path = path;
+                        match app.config.save() {
+                            Ok(()) => (),
+                            Err(e) => tracing::error!("Could not save Config: {e}"),
+                        }
+                    }
+                    ui.close_menu();
+                }
+                if ui
+                    .add_enabled(
+                        !app.is_run_locked(),
+                        Button::new(RichText::new("Open").size(14.0)),
                     )
-                    .add_filter("YAML", &["yaml", "yml"])
-                    .save_file()
+                    .on_disabled_hover_text("The config cannot be changed during a run")
+                    .clicked()
                 {
-                    app.config.path = path;
-                    match app.config.save() {
-                        Ok(()) => (),
-                        Err(e) => tracing::error!("Could not save Config: {e}"),
+                    if let Some(path) = FileDialog::new()
+                        .set_directory(
+                            std::env::current_dir().expect("Couldn't access runtime directory"),
+                        )
+                        .add_filter("YAML", &["yaml", "yml"])
+                        .pick_file()
+                    {
+                        match app.config.load(path) {
+                            Ok(()) => app.open_idle_log(),
+                            Err(e) => tracing::error!("Could not load Config: {e}"),
+                        }
                     }
+                    ui.close_menu();
                 }
-                ui.close_menu();
-            }
-            if ui.button(RichText::new("Open").size(14.0)).clicked() {
-                if let Some(path) = FileDialog::new()
-                    .set_directory(
-                        std::env::current_dir().expect("Couldn't access runtime directory"),
+                ui.separator();
+                if ui
+                    .add_enabled(
+                        app.can_start_run(),
+                        Button::new(RichText::new("Start Run").size(14.0))
+                            .shortcut_text(ui.ctx().format_shortcut(&START_RUN_SHORTCUT)),
                     )
-                    .add_filter("YAML", &["yaml", "yml"])
-                    .pick_file()
+                    .clicked()
                 {
-                    match app.config.load(path) {
-                        Ok(()) => (),
-                        Err(e) => tracing::error!("Could not load Config: {e}"),
-                    }
+                    app.request_start_run();
+                    ui.close_menu();
+                }
+                if ui
+                    .add_enabled(
+                        app.can_stop_run(),
+                        Button::new(RichText::new("Stop Run").size(14.0))
+                            .shortcut_text(ui.ctx().format_shortcut(&STOP_RUN_SHORTCUT)),
+                    )
+                    .clicked()
+                {
+                    app.request_stop_run();
+                    ui.close_menu();
+                }
+                if ui
+                    .add_enabled(
+                        app.can_disconnect(),
+                        Button::new(RichText::new("Disconnect").size(14.0))
+                            .shortcut_text(ui.ctx().format_shortcut(&DISCONNECT_SHORTCUT)),
+                    )
+                    .on_disabled_hover_text("Stop the run before disconnecting")
+                    .clicked()
+                {
+                    app.disconnect();
+                    ui.close_menu();
+                }
+                if ui
+                    .add_enabled(
+                        app.embassy.is_connected() && app.status.is_system_running(),
+                        Button::new(
+                            RichText::new("Force Disconnect")
+                                .color(Color32::RED)
+                                .size(14.0),
+                        ),
+                    )
+                    .on_hover_text("Disconnect during a run, for when the DAQ is stuck")
+                    .clicked()
+                {
+                    app.request_force_disconnect();
+                    ui.close_menu();
+                }
+                ui.separator();
+                let mut show_run_table = app.run_table.is_some();
+                if ui
+                    .checkbox(&mut show_run_table, RichText::new("Run Table").size(14.0))
+                    .on_hover_text("Show the previous runs of the experiment")
+                    .clicked()
+                {
+                    app.run_table = show_run_table.then(|| RunTableView::load(&app.config));
+                    ui.close_menu();
+                }
+                if ui
+                    .button(RichText::new("Restore Config Backup...").size(14.0))
+                    .on_hover_text("Restore the ECC configuration of a run from its archive")
+                    .clicked()
+                {
+                    app.config_restore = Some(ConfigRestore::load(&app.config.experiment));
+                    ui.close_menu();
+                }
+                if ui
+                    .button(RichText::new("Setup Wizard...").size(14.0))
+                    .on_hover_text("Walk through the experiment, network, and directory setup")
+                    .clicked()
+                {
+                    app.setup_wizard = Some(SetupWizard::new(&app.config));
+                    ui.close_menu();
+                }
+            });
+            ui.menu_button(RichText::new("View").size(16.0), |ui| {
+                if ui
+                    .checkbox(
+                        &mut app.show_diagnostics,
+                        RichText::new("Diagnostics").size(14.0),
+                    )
+                    .clicked()
+                {
+                    ui.close_menu();
+                }
+                if ui
+                    .checkbox(&mut app.show_log, RichText::new("Log Console").size(14.0))
+                    .clicked()
+                {
+                    ui.close_menu();
+                }
+                if ui
+                    .button(RichText::new("Effective Config").size(14.0))
+                    .on_hover_text("The settings in use, compared against the config file")
+                    .clicked()
+                {
+                    app.show_effective_config();
+                    ui.close_menu();
+                }
+                if ui
+                    .checkbox(
+                        &mut app.config.expert_mode,
+                        RichText::new("Expert Mode").size(14.0),
+                    )
+                    .on_hover_text("Allow overriding safety checks")
+                    .clicked()
+                {
+                    ui.close_menu();
                 }
-                ui.close_menu();
+                ui.add_enabled(
+                    app.config.expert_mode,
+                    eframe::egui::Checkbox::new(
+                        &mut app.config.skip_pre_run_checklist,
+                        RichText::new("Skip Pre-run Checklist").size(14.0),
+                    ),
+                )
+                .on_disabled_hover_text("Requires Expert Mode");
+                ui.add_enabled(
+                    app.config.expert_mode,
+                    eframe::egui::Checkbox::new(
+                        &mut app.show_expert_console,
+                        RichText::new("Expert Console").size(14.0),
+                    ),
+                )
+                .on_disabled_hover_text("Requires Expert Mode");
+            });
+            if ui
+                .button(RichText::new("?").size(16.0))
+                .on_hover_text("ECC state machine help (F1)")
+                .clicked()
+            {
+                app.show_help = !app.show_help;
             }
+            ui.with_layout(
+                eframe::egui::Layout::right_to_left(eframe::egui::Align::Center),
+                |ui| {
+                    let matrix = FeatureMatrix::from_config(&app.config);
+                    for feature in matrix.features().iter().rev() {
+                        let (icon, color) = if feature.enabled {
+                            ("\u{25CF}", Color32::GREEN)
+                        } else {
+                            ("\u{25CB}", Color32::GRAY)
+                        };
+                        ui.label(RichText::new(format!("{icon} {}", feature.name)).color(color))
+                            .on_hover_text(&feature.detail);
+                    }
+                },
+            );
         });
 
         // Configuration
@@ -57,20 +230,96 @@ pub fn render_config_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
                 RichText::new(format!("Config File: {}", app.config.path.display())).size(16.0),
             );
             ui.label(RichText::new("Experiment").size(16.0));
-            ui.add(
-                eframe::egui::widgets::TextEdit::singleline(&mut app.config.experiment)
-                    .desired_width(100.0)
-                    .margin(eframe::egui::Margin::symmetric(4.0, 4.0)),
-            );
+            let run_locked = app.is_run_locked();
+            if ui
+                .add_enabled(
+                    !run_locked,
+                    eframe::egui::widgets::TextEdit::singleline(&mut app.config.experiment)
+                        .desired_width(100.0)
+                        .margin(eframe::egui::Margin::symmetric(4.0, 4.0)),
+                )
+                .on_disabled_hover_text("The experiment cannot be changed during a run")
+                .changed()
+            {
+                let experiment = app.config.experiment.clone();
+                let changed = app.config.set_experiment(&experiment);
+                app.notify_sanitized("Experiment", changed);
+            }
+            if !is_valid_experiment(&app.config.experiment) {
+                ui.label(RichText::new("Invalid name").color(Color32::YELLOW))
+                    .on_hover_text("The experiment name is empty or made only of dots, runs cannot be started");
+            }
+            ui.label(RichText::new("Operator").size(16.0));
+            if ui
+                .add(
+                    eframe::egui::widgets::TextEdit::singleline(&mut app.config.operator)
+                        .desired_width(100.0)
+                        .hint_text("Name")
+                        .margin(eframe::egui::Margin::symmetric(4.0, 4.0)),
+                )
+                .changed()
+            {
+                let operator = app.config.operator.clone();
+                let changed = app.config.set_operator(&operator);
+                app.notify_sanitized("Operator", changed);
+            }
             ui.label(RichText::new("Run Number").size(16.0));
-            ui.add(DragValue::new(&mut app.config.run_number).speed(1));
+            ui.add_enabled(
+                !run_locked,
+                DragValue::new(&mut app.config.run_number).speed(1),
+            )
+            .on_disabled_hover_text("The run number cannot be changed during a run");
+            if ui
+                .add_enabled(
+                    !run_locked
+                        && app.embassy.is_connected()
+                        && app.status.get_monitored_families().surveyor
+                        && app.run_number_suggestion.is_none(),
+                    Button::new("Suggest"),
+                )
+                .on_hover_text("Use the run after the last one found on the data routers")
+                .on_disabled_hover_text("Requires a connection to the data routers, outside of a run")
+                .clicked()
+            {
+                app.suggest_run_number();
+            }
+            if app.run_number_suggestion.is_some() {
+                ui.spinner();
+            }
+            ui.menu_button(
+                RichText::new(format!("Tags ({})", app.config.run_tags.len())).size(16.0),
+                |ui| {
+                    render_tag_selector(app, ui);
+                },
+            )
+            .response
+            .on_hover_text(tag_summary(app));
+            ComboBox::from_id_salt("Run_Tag")
+                .selected_text(RichText::new(app.config.run_tag.to_string()).size(16.0))
+                .show_ui(ui, |ui| {
+                    for tag in RunTag::ALL {
+                        ui.selectable_value(&mut app.config.run_tag, tag, tag.to_string());
+                    }
+                })
+                .response
+                .on_hover_text("The verdict on the current run, recorded in the run table");
             ui.label(RichText::new("Description").size(16.0));
-            ui.add(
-                eframe::egui::widgets::TextEdit::singleline(&mut app.config.description)
-                    .desired_width(f32::INFINITY)
-                    .margin(eframe::egui::Margin::symmetric(4.0, 4.0)),
-            );
+            if ui
+                .add(
+                    eframe::egui::widgets::TextEdit::singleline(&mut app.config.description)
+                        .desired_width(f32::INFINITY)
+                        .margin(eframe::egui::Margin::symmetric(4.0, 4.0)),
+                )
+                .changed()
+            {
+                let description = app.config.description.clone();
+                let changed = app.config.set_description(&description);
+                app.notify_sanitized("Description", changed);
+            }
         });
+        if let Some(notice) = &app.sanitize_notice {
+            ui.label(RichText::new(notice).size(14.0).color(Color32::YELLOW));
+        }
         // Connect buttons
         ui.separator();
 
@@ -80,9 +329,57 @@ pub fn render_config_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
                     .size(16.0)
                     .color(Color32::LIGHT_BLUE),
             );
+            ui.add_enabled_ui(!app.embassy.is_connected(), |ui| {
+                ui.checkbox(
+                    &mut app.config.families.ecc,
+                    RichText::new("ECC").size(16.0),
+                );
+                ui.checkbox(
+                    &mut app.config.families.surveyor,
+                    RichText::new("Data Routers").size(16.0),
+                );
+                ui.label(RichText::new("CoBos").size(16.0));
+                if ui
+                    .add(
+                        DragValue::new(&mut app.config.num_cobos)
+                            .speed(1)
+                            .range(1..=usize::MAX),
+                    )
+                    .changed()
+                {
+                    app.config.readiness = app.config.readiness.clamped(app.config.num_cobos);
+                }
+                ui.label(RichText::new("Poll Interval (s)").size(16.0));
+                ui.add(
+                    DragValue::new(&mut app.config.poll_interval_sec)
+                        .speed(1)
+                        .range(1..=60),
+                );
+                ComboBox::from_id_salt("Readiness_Mode")
+                    .selected_text(RichText::new(app.config.readiness.mode.to_string()).size(16.0))
+                    .show_ui(ui, |ui| {
+                        for mode in [ReadinessMode::Strict, ReadinessMode::Quorum] {
+                            ui.selectable_value(
+                                &mut app.config.readiness.mode,
+                                mode,
+                                mode.to_string(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text("Strict: every module must agree. Quorum: the MuTaNT and a quorum of the CoBos must agree.");
+                if app.config.readiness.mode == ReadinessMode::Quorum {
+                    ui.add(
+                        DragValue::new(&mut app.config.readiness.quorum_cobos)
+                            .speed(1)
+                            .range(1..=app.config.num_cobos.max(1))
+                            .suffix(" CoBos"),
+                    );
+                }
+            });
             if ui
                 .add_enabled(
-                    !app.embassy.is_connected(),
+                    !app.embassy.is_connected() && app.config.families.any(),
                     Button::new(
                         RichText::new("Connect")
                             .color(Color32::LIGHT_BLUE)
@@ -96,7 +393,7 @@ pub fn render_config_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
             }
             if ui
                 .add_enabled(
-                    app.embassy.is_connected(),
+                    app.can_disconnect(),
                     Button::new(
                         RichText::new("Disconnect")
                             .color(Color32::LIGHT_RED)
@@ -104,36 +401,147 @@ pub fn render_config_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
                     )
                     .min_size([100.0, 25.0].into()),
                 )
+                .on_disabled_hover_text(
+                    "Stop the run before disconnecting, or use File > Force Disconnect",
+                )
                 .clicked()
             {
                 app.disconnect();
             }
+            if let Some(regression) = &app.idle_regression {
+                ui.spinner();
+                ui.label(
+                    RichText::new(format!(
+                        "Regressing to Idle before disconnecting ({}/{} Idle, {} s)",
+                        regression.idle(&app.status),
+                        app.status.module_ids().len(),
+                        regression.start_time.elapsed().as_secs()
+                    ))
+                    .size(16.0),
+                );
+            }
+            ComboBox::from_id_salt("Disconnect_Policy")
+                .selected_text(RichText::new(app.config.disconnect_policy.to_string()).size(16.0))
+                .show_ui(ui, |ui| {
+                    for policy in [DisconnectPolicy::LeaveAsIs, DisconnectPolicy::RegressToIdle] {
+                        ui.selectable_value(
+                            &mut app.config.disconnect_policy,
+                            policy,
+                            policy.to_string(),
+                        );
+                    }
+                })
+                .response
+                .on_hover_text("What happens to the modules on disconnect");
             //Start/Stop
             ui.label(
                 RichText::new("Run Control")
                     .size(16.0)
                     .color(Color32::LIGHT_BLUE),
             );
+            ui.label(RichText::new("Auto-stop (min)").size(16.0))
+                .on_hover_text("Stop runs automatically after this many minutes, 0 to disable");
+            let mut auto_stop_minutes = app.config.auto_stop_minutes.unwrap_or(0);
             if ui
-                .add_enabled(
-                    app.status.is_system_ready(),
-                    Button::new(RichText::new("Start").color(Color32::GREEN).size(16.0))
-                        .min_size([100.0, 25.0].into()),
-                )
-                .clicked()
+                .add(DragValue::new(&mut auto_stop_minutes).speed(1).range(0..=1440))
+                .changed()
             {
-                app.start_run();
+                app.config.auto_stop_minutes = (auto_stop_minutes > 0).then_some(auto_stop_minutes);
             }
-
+            ui.label(RichText::new("Auto-cycle (GB)").size(16.0))
+                .on_hover_text("Stop the run and start the next one after this much data is written, 0 to disable");
+            let mut auto_cycle_gb = app.config.auto_cycle_gb.unwrap_or(0.0);
+            if ui
+                .add(DragValue::new(&mut auto_cycle_gb).speed(1.0).range(0.0..=100000.0))
+                .changed()
+            {
+                app.config.auto_cycle_gb = (auto_cycle_gb > 0.0).then_some(auto_cycle_gb);
+            }
+            ui.add_enabled(
+                !app.is_run_starting(),
+                eframe::egui::Checkbox::new(
+                    &mut app.config.reset_mutant_timestamps,
+                    RichText::new("Reset MuTaNT timestamps each run").size(16.0),
+                ),
+            )
+            .on_hover_text("Breakup and re-Configure the MuTaNT before starting the CoBos");
             if ui
                 .add_enabled(
-                    app.status.is_system_running(),
-                    Button::new(RichText::new("Stop").color(Color32::RED).size(16.0))
+                    app.can_start_run(),
+                    Button::new(RichText::new("Start").color(Color32::GREEN).size(16.0))
                         .min_size([100.0, 25.0].into()),
                 )
                 .clicked()
             {
-                app.stop_run();
+                app.request_start_run();
+            }
+            ui.horizontal(|ui| {
+                ui.add(
+                    DragValue::new(&mut app.countdown_seconds)
+                        .range(1..=3600)
+                        .suffix(" s"),
+                );
+                if ui
+                    .add_enabled(app.can_start_run(), Button::new("Start In"))
+                    .on_hover_text("Start the run after a countdown")
+                    .clicked()
+                {
+                    app.schedule_start_run(StartCountdown::in_seconds(app.countdown_seconds));
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.add(
+                    TextEdit::singleline(&mut app.countdown_time)
+                        .hint_text("HH:MM:SS")
+                        .desired_width(70.0),
+                );
+                if ui
+                    .add_enabled(app.can_start_run(), Button::new("Start At (UTC)"))
+                    .on_hover_text("Start the run at a time of day")
+                    .clicked()
+                {
+                    match StartCountdown::at_time(&app.countdown_time) {
+                        Ok(countdown) => app.schedule_start_run(countdown),
+                        Err(e) => app.notify_error(format!("Could not schedule the run: {e}")),
+                    }
+                }
+            });
+
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(
+                        app.can_stop_run(),
+                        Button::new(RichText::new("Stop").color(Color32::RED).size(16.0))
+                            .min_size([100.0, 25.0].into()),
+                    )
+                    .clicked()
+                {
+                    app.request_stop_run();
+                }
+                if ui
+                    .add_enabled(
+                        app.can_stop_run(),
+                        Button::new(RichText::new("Quick Stop").color(Color32::RED).size(16.0)),
+                    )
+                    .on_hover_text("Stop the DAQ now and record the run. Moving the .graw files and backing up the configuration are deferred until later.")
+                    .clicked()
+                {
+                    app.stop_run(StopReason::QuickStop);
+                }
+            });
+            if !app.config.deferred_tasks.is_empty() {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        RichText::new(format!(
+                            "\u{26A0} {} tasks were deferred by a quick stop",
+                            app.config.deferred_tasks.len()
+                        ))
+                        .color(Color32::YELLOW),
+                    );
+                    if ui.button("Show").clicked() {
+                        app.show_deferred_tasks = true;
+                    }
+                });
             }
 
             let mut run_duration = Duration::from_secs(0);
@@ -148,7 +556,241 @@ pub fn render_config_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
                 .size(16.0)
                 .color(Color32::LIGHT_BLUE),
             );
+            if let Some(livetime) = &app.livetime {
+                ui.label(
+                    RichText::new(format!("Livetime: {:.1}%", livetime.fraction() * 100.0))
+                        .size(16.0)
+                        .color(Color32::LIGHT_BLUE),
+                )
+                .on_hover_text(
+                    "Fraction of the run during which every module was Running",
+                );
+            }
+            if let Some(remaining) = app.auto_stop_remaining() {
+                ui.label(
+                    RichText::new(format!(
+                        "Auto-stop in: {}",
+                        pretty_ellapsed_time(remaining.as_secs())
+                    ))
+                    .size(16.0)
+                    .color(Color32::YELLOW),
+                );
+            }
+            if let Some((written, limit)) = app.auto_cycle_progress() {
+                ui.label(
+                    RichText::new(format!(
+                        "Written: {} / {}",
+                        human_bytes::human_bytes(written as f64),
+                        human_bytes::human_bytes(limit as f64)
+                    ))
+                    .size(16.0)
+                    .color(Color32::YELLOW),
+                );
+            } else if app.restart_pending {
+                ui.spinner();
+                ui.label(RichText::new("Cycling to the next run...").size(16.0));
+            }
+            if let Some(phase) = &app.run_start_phase {
+                ui.spinner();
+                ui.label(
+                    RichText::new(format!(
+                        "{phase}... ({} s)",
+                        app.run_start_phase_elapsed().as_secs()
+                    ))
+                    .size(16.0),
+                );
+                if let Some(reconfigure_time) = app.mutant_reconfigure_time {
+                    ui.label(
+                        RichText::new(format!(
+                            "MuTaNT re-configured in {} s",
+                            reconfigure_time.as_secs()
+                        ))
+                        .size(16.0),
+                    );
+                }
+            } else if let Some(stopping) = &app.stopping_run {
+                ui.spinner();
+                ui.label(
+                    RichText::new(format!(
+                        "{} of run {}... ({} s)",
+                        if stopping.backing_up {
+                            "Backing up the GET configuration"
+                        } else {
+                            "Moving .graw files"
+                        },
+                        stopping.run_number,
+                        stopping.since.elapsed().as_secs()
+                    ))
+                    .size(16.0),
+                );
+            } else if let Some(check) = &app.run_number_check {
+                ui.spinner();
+                ui.label(
+                    RichText::new(format!(
+                        "Checking run number {}... ({} s)",
+                        app.config.run_number,
+                        check.since.elapsed().as_secs()
+                    ))
+                    .size(16.0),
+                );
+            } else if let Some(running) = &app.deferred_run {
+                ui.spinner();
+                ui.label(
+                    RichText::new(format!(
+                        "Running the tasks deferred by the last quick stop, {} left... ({} s)",
+                        app.config.deferred_tasks.len(),
+                        running.since.elapsed().as_secs()
+                    ))
+                    .size(16.0),
+                );
+            } else if app.status.is_system_running() {
+                ui.label(RichText::new(format!("Run UUID: {}", app.run_id)).size(16.0));
+            }
         });
         ui.separator();
+        ui.horizontal(|ui| {
+            ui.label(
+                RichText::new("Run Sequence")
+                    .size(16.0)
+                    .color(Color32::LIGHT_BLUE),
+            );
+            let sequence_status = app.run_sequence.as_ref().map(|sequence| {
+                let remaining = match sequence.remaining() {
+                    Some(r) => format!(", {} left", pretty_ellapsed_time(r.as_secs())),
+                    None => String::default(),
+                };
+                format!(
+                    "Run {} of {} ({}{remaining})",
+                    (sequence.completed_runs + 1).min(sequence.total_runs),
+                    sequence.total_runs,
+                    sequence.phase
+                )
+            });
+            match sequence_status {
+                Some(status) => {
+                    ui.spinner();
+                    ui.label(RichText::new(status).size(16.0));
+                    if ui
+                        .button(RichText::new("Halt Sequence").color(Color32::RED).size(16.0))
+                        .on_hover_text("Stop sequencing, the current run is left running")
+                        .clicked()
+                    {
+                        app.halt_run_sequence(None);
+                    }
+                }
+                None => {
+                    ui.add(
+                        DragValue::new(&mut app.sequence_runs)
+                            .speed(1)
+                            .range(1..=1000)
+                            .suffix(" runs"),
+                    );
+                    ui.label(RichText::new("of").size(16.0));
+                    ui.add(
+                        DragValue::new(&mut app.sequence_minutes)
+                            .speed(1)
+                            .range(1..=1440)
+                            .suffix(" min"),
+                    );
+                    if ui
+                        .add_enabled(
+                            app.can_start_run(),
+                            Button::new(
+                                RichText::new("Start Sequence")
+                                    .color(Color32::GREEN)
+                                    .size(16.0),
+                            ),
+                        )
+                        .clicked()
+                    {
+                        app.start_run_sequence();
+                    }
+                }
+            }
+            if let Some(alert) = &app.sequence_alert {
+                ui.label(
+                    RichText::new(format!("\u{26A0} {alert}"))
+                        .size(16.0)
+                        .color(Color32::RED),
+                );
+                if ui.button(RichText::new("Dismiss").size(16.0)).clicked() {
+                    app.sequence_alert = None;
+                }
+            }
+        });
+        ui.separator();
+        if !app.changed_configs.is_empty() {
+            ui.horizontal(|ui| {
+                let files: Vec<String> = app
+                    .changed_configs
+                    .iter()
+                    .filter_map(|p| p.file_name())
+                    .map(|n| n.to_string_lossy().to_string())
+                    .collect();
+                ui.label(
+                    RichText::new(format!(
+                        "ECC config files changed ({}). The current configuration is stale, Breakup and re-Configure to apply the changes.",
+                        files.join(", ")
+                    ))
+                    .size(16.0)
+                    .color(Color32::YELLOW),
+                );
+                if let Some(phase) = &app.reconfigure_phase {
+                    ui.spinner();
+                    ui.label(
+                        RichText::new(format!(
+                            "{phase}... ({} s)",
+                            app.reconfigure_elapsed().as_secs()
+                        ))
+                        .size(16.0),
+                    );
+                } else {
+                    if ui
+                        .add_enabled(
+                            app.status.is_system_ready(),
+                            Button::new(RichText::new("Re-configure").size(16.0)),
+                        )
+                        .clicked()
+                    {
+                        app.reconfigure_system();
+                    }
+                    if ui.button(RichText::new("Dismiss").size(16.0)).clicked() {
+                        app.changed_configs.clear();
+                    }
+                }
+            });
+            ui.separator();
+        }
+        CollapsingHeader::new(RichText::new("Advanced").size(16.0))
+            .id_salt("Config_Advanced")
+            .show(ui, |ui| {
+                ui.add_enabled_ui(!app.is_run_locked(), |ui| {
+                    Grid::new("Config_Advanced_Grid")
+                        .num_columns(3)
+                        .show(ui, |ui| {
+                            ui.label(RichText::new("Shell").size(16.0));
+                            ui.text_edit_singleline(&mut app.config.shell.shell)
+                                .on_hover_text("The shell the scripts are run with, a path or a name on the PATH");
+                            match app.config.shell.shell_path() {
+                                Some(path) => ui.label(
+                                    RichText::new(path.display().to_string()).color(Color32::GREEN),
+                                ),
+                                None => ui.label(RichText::new("Not found").color(Color32::RED)),
+                            };
+                            ui.end_row();
+                            ui.label(RichText::new("Script Directory").size(16.0));
+                            let mut script_dir = app.config.shell.script_dir.display().to_string();
+                            if ui.text_edit_singleline(&mut script_dir).changed() {
+                                app.config.shell.script_dir = PathBuf::from(script_dir);
+                            }
+                            if app.config.shell.script_dir.is_dir() {
+                                ui.label(RichText::new("Found").color(Color32::GREEN));
+                            } else {
+                                ui.label(RichText::new("Missing").color(Color32::RED));
+                            }
+                            ui.end_row();
+                        });
+                });
+            });
     });
 }
@@ -0,0 +1,122 @@
+//! Restoring the ECC configuration of a previous run from its backup archive. The archive is
+//! verified against its manifest before anything is unpacked, and the operator must confirm,
+//! since the files in the config directory are overwritten.
+use super::app::EnvoyApp;
+use crate::command::config_archive::{list_archives, restore_archive};
+use crate::command::constants::CONFIG_DIR;
+use eframe::egui::{Button, Color32, ComboBox, RichText, Window};
+use std::path::{Path, PathBuf};
+
+/// The state of the restore window
+#[derive(Debug, Clone)]
+pub struct ConfigRestore {
+    archives: Vec<(i32, PathBuf)>,
+    selected: usize,
+    confirming: bool,
+}
+
+impl ConfigRestore {
+    /// Find the archives of the experiment, selecting the latest run
+    pub fn load(experiment: &str) -> Self {
+        let archives = list_archives(experiment);
+        Self {
+            selected: archives.len().saturating_sub(1),
+            archives,
+            confirming: false,
+        }
+    }
+}
+
+/// Render the config restore window, if it is open
+pub fn render_config_restore(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
+    let run_locked = app.is_run_locked();
+    let restore = match app.config_restore.as_mut() {
+        Some(restore) => restore,
+        None => return,
+    };
+    let mut open = true;
+    let mut confirmed: Option<(i32, PathBuf)> = None;
+    Window::new("Restore Config Backup")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            if restore.archives.is_empty() {
+                ui.label("No config archives were found for this experiment.");
+                return;
+            }
+            let selected_text = restore
+                .archives
+                .get(restore.selected)
+                .map(|(run_number, _)| format!("Run {run_number}"))
+                .unwrap_or_default();
+            ui.add_enabled_ui(!restore.confirming, |ui| {
+                ComboBox::from_label("Archive")
+                    .selected_text(selected_text)
+                    .show_ui(ui, |ui| {
+                        for (index, (run_number, _)) in restore.archives.iter().enumerate() {
+                            ui.selectable_value(
+                                &mut restore.selected,
+                                index,
+                                format!("Run {run_number}"),
+                            );
+                        }
+                    });
+            });
+            if run_locked {
+                ui.label(
+                    RichText::new("The configuration can't be restored during a run.")
+                        .color(Color32::LIGHT_RED),
+                );
+            }
+            if !restore.confirming {
+                if ui
+                    .add_enabled(!run_locked, Button::new(RichText::new("Restore").size(16.0)))
+                    .clicked()
+                {
+                    restore.confirming = true;
+                }
+                return;
+            }
+            ui.label(
+                RichText::new(format!(
+                    "The files in {CONFIG_DIR} will be overwritten with the archived configuration. Continue?"
+                ))
+                .color(Color32::YELLOW),
+            );
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(
+                        !run_locked,
+                        Button::new(RichText::new("Yes").color(Color32::RED).size(16.0)),
+                    )
+                    .clicked()
+                {
+                    confirmed = restore.archives.get(restore.selected).cloned();
+                    restore.confirming = false;
+                }
+                if ui.button(RichText::new("Cancel").size(16.0)).clicked() {
+                    restore.confirming = false;
+                }
+            });
+        });
+    if !open {
+        app.config_restore = None;
+    }
+    if let Some((run_number, archive)) = confirmed {
+        match restore_archive(&archive, Path::new(CONFIG_DIR)) {
+            Ok(restored) => {
+                tracing::warn!(
+                    "Restored {restored} config files of run {run_number} from {}",
+                    archive.display()
+                );
+                app.notify_info(format!(
+                    "Restored the configuration of run {run_number} ({restored} files)"
+                ));
+                app.check_config_files();
+            }
+            Err(e) => app.notify_error(format!(
+                "Could not restore the configuration of run {run_number}: {e}"
+            )),
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use super::app::EnvoyApp;
+use super::run_sequence::StopReason;
+use super::style::pretty_ellapsed_time;
+use eframe::egui::{Button, Color32, RichText, Window};
+use std::time::Instant;
+
+const SCRIPT_NAME: &str = "test_graw.sh";
+
+/// Render the tag checkboxes for the current run
+pub fn render_tag_selector(app: &mut EnvoyApp, ui: &mut eframe::egui::Ui) {
+    if app.config.tags.is_empty() {
+        ui.label("No tags defined, add some in the Run Log panel");
+    }
+    for tag in app.config.tags.iter() {
+        let mut selected = app.config.run_tags.contains(tag);
+        if ui
+            .checkbox(&mut selected, RichText::new(tag).size(14.0))
+            .changed()
+        {
+            if selected {
+                app.config.run_tags.insert(tag.clone());
+            } else {
+                app.config.run_tags.remove(tag);
+            }
+        }
+    }
+}
+
+/// A comma separated list of the current run tags
+pub fn tag_summary(app: &EnvoyApp) -> String {
+    if app.config.run_tags.is_empty() {
+        String::from("No tags chosen")
+    } else {
+        app.config
+            .run_tags
+            .iter()
+            .cloned()
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+/// An action which must be confirmed by the operator before it is performed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfirmAction {
+    StopRun,
+    ForceDisconnect,
+    /// Regress every module to Idle, then disconnect
+    RegressAndDisconnect,
+}
+
+impl std::fmt::Display for ConfirmAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::StopRun => write!(f, "Stop Run"),
+            Self::ForceDisconnect => write!(f, "Force Disconnect"),
+            Self::RegressAndDisconnect => write!(f, "Disconnect"),
+        }
+    }
+}
+
+/// The reason a run could not be started after the pre-start checks
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunNumberIssue {
+    /// Data routers are reporting a failing disk, with their ids
+    FailingDisks(Vec<usize>),
+    /// Data routers have too little free disk space, described for each router
+    LowDiskSpace(Vec<String>),
+    /// The run number was already used, with the paths of the existing run directories
+    InUse(Vec<String>),
+    /// The run number check script could not be executed
+    CheckFailed(String),
+    /// Tasks deferred by a quick stop could not be done, described for each task
+    DeferredTasks(Vec<String>),
+}
+
+impl std::fmt::Display for RunNumberIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InUse(paths) => {
+                write!(f, "the run number is already used ({})", paths.join(", "))
+            }
+            Self::CheckFailed(e) => write!(f, "the run number could not be checked: {e}"),
+            Self::LowDiskSpace(routers) => {
+                write!(f, "too little free disk space ({})", routers.join(", "))
+            }
+            Self::DeferredTasks(tasks) => write!(
+                f,
+                "the tasks deferred by a quick stop failed ({})",
+                tasks.join(", ")
+            ),
+            Self::FailingDisks(ids) => write!(
+                f,
+                "data routers {} report a failing disk",
+                ids.iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// Render the confirmation dialog for stopping a run, disconnecting during a run, or
+/// regressing the modules to Idle on disconnect
+pub fn render_confirm_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
+    let action = match app.pending_confirmation {
+        Some(action) => action,
+        None => return,
+    };
+    let still_valid = match action {
+        ConfirmAction::RegressAndDisconnect => app.can_disconnect(),
+        ConfirmAction::StopRun | ConfirmAction::ForceDisconnect => app.status.is_system_running(),
+    };
+    if !still_valid {
+        app.pending_confirmation = None;
+        return;
+    }
+    let run_duration = Instant::now() - app.run_start_time;
+    Window::new(format!("Confirm {action}"))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(eframe::egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            if action != ConfirmAction::RegressAndDisconnect {
+                ui.label(
+                    RichText::new(format!(
+                        "Run {} has been running for {} (hrs:mins:ss).",
+                        app.config.run_number,
+                        pretty_ellapsed_time(run_duration.as_secs())
+                    ))
+                    .size(16.0),
+                );
+            }
+            match action {
+                ConfirmAction::StopRun => {
+                    if app.config.run_tags.is_empty() {
+                        ui.label(
+                            RichText::new("No tags were chosen for this run. Tag it before stopping?")
+                                .size(16.0)
+                                .color(Color32::YELLOW),
+                        );
+                        render_tag_selector(app, ui);
+                    }
+                }
+                ConfirmAction::ForceDisconnect => {
+                    ui.label(
+                        RichText::new("Disconnecting will not stop the run, and the run will not be cataloged!")
+                            .size(16.0)
+                            .color(Color32::LIGHT_RED),
+                    );
+                }
+                ConfirmAction::RegressAndDisconnect => {
+                    ui.label(
+                        RichText::new(format!(
+                            "Every module will be regressed to Idle before disconnecting. This can take up to {} s per step.",
+                            app.config.disconnect_timeout_sec
+                        ))
+                        .size(16.0)
+                        .color(Color32::YELLOW),
+                    );
+                }
+            }
+            ui.separator();
+            if action == ConfirmAction::StopRun {
+                ui.checkbox(&mut app.skip_confirmation, "Don't ask again this session");
+            }
+            ui.horizontal(|ui| {
+                if ui
+                    .add(Button::new(
+                        RichText::new("Confirm").color(Color32::RED).size(16.0),
+                    ))
+                    .clicked()
+                {
+                    match action {
+                        ConfirmAction::StopRun => app.stop_run(StopReason::Manual),
+                        ConfirmAction::ForceDisconnect => app.force_disconnect(),
+                        ConfirmAction::RegressAndDisconnect => app.regress_and_disconnect(),
+                    }
+                }
+                if action == ConfirmAction::RegressAndDisconnect
+                    && ui
+                        .button(RichText::new("Disconnect Without Regressing").size(16.0))
+                        .clicked()
+                {
+                    app.force_disconnect();
+                }
+                if ui.button(RichText::new("Cancel").size(16.0)).clicked() {
+                    app.pending_confirmation = None;
+                }
+            });
+        });
+}
+
+/// Render the dialog explaining why a run could not be started
+pub fn render_run_number_issue(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
+    let issue = match &app.run_number_issue {
+        Some(issue) => issue.clone(),
+        None => return,
+    };
+    Window::new("Run Not Started")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(eframe::egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            match &issue {
+                RunNumberIssue::InUse(paths) => {
+                    ui.label(
+                        RichText::new(format!(
+                            "Run {} already exists! Either delete the extant data or change the run number.",
+                            app.config.run_number
+                        ))
+                        .size(16.0)
+                        .color(Color32::YELLOW),
+                    );
+                    for path in paths {
+                        ui.label(RichText::new(path).monospace());
+                    }
+                }
+                RunNumberIssue::CheckFailed(e) => {
+                    if app.config.run_check_script {
+                        ui.label(
+                            RichText::new(format!(
+                                "The run number check script ({SCRIPT_NAME}) could not be executed: {e}"
+                            ))
+                            .size(16.0)
+                            .color(Color32::LIGHT_RED),
+                        );
+                        ui.label(format!(
+                            "Check that {} is installed, the scripts directory ({}) is present, and the data routers are reachable over ssh.",
+                            app.config.shell.shell,
+                            app.config.shell.script_dir.display()
+                        ));
+                    } else {
+                        ui.label(
+                            RichText::new(format!("The run number could not be checked: {e}"))
+                                .size(16.0)
+                                .color(Color32::LIGHT_RED),
+                        );
+                        ui.label("Check that the data routers are reachable over ssh.");
+                    }
+                }
+                RunNumberIssue::FailingDisks(ids) => {
+                    ui.label(
+                        RichText::new("Data router disks are failing! Replace them before taking data.")
+                            .size(16.0)
+                            .color(Color32::LIGHT_RED),
+                    );
+                    let routers = app.status.get_surveyor_status_response();
+                    for id in ids {
+                        let details = routers
+                            .get(*id)
+                            .and_then(|router| router.disk_health.as_ref())
+                            .map(|health| health.details.clone())
+                            .unwrap_or_default();
+                        ui.label(format!("Data Router {id}: {details}"));
+                    }
+                }
+                RunNumberIssue::LowDiskSpace(routers) => {
+                    ui.label(
+                        RichText::new(format!(
+                            "Data routers are low on disk space! At least {} GB must be free, and at most {}% used.",
+                            app.config.min_free_disk_gb, app.config.disk_usage_limit_percent
+                        ))
+                        .size(16.0)
+                        .color(Color32::LIGHT_RED),
+                    );
+                    for router in routers {
+                        ui.label(router);
+                    }
+                }
+                RunNumberIssue::DeferredTasks(tasks) => {
+                    ui.label(
+                        RichText::new("The steps skipped by the last quick stop could not be done! They must be done before the next run.")
+                            .size(16.0)
+                            .color(Color32::LIGHT_RED),
+                    );
+                    for task in tasks {
+                        ui.label(task);
+                    }
+                    if ui.button("Open Deferred Tasks").clicked() {
+                        app.show_deferred_tasks = true;
+                    }
+                }
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                if matches!(
+                    issue,
+                    RunNumberIssue::CheckFailed(_)
+                        | RunNumberIssue::FailingDisks(_)
+                        | RunNumberIssue::LowDiskSpace(_)
+                ) && ui
+                        .add_enabled(
+                            app.config.expert_mode,
+                            Button::new(
+                                RichText::new("Proceed Anyway")
+                                    .color(Color32::RED)
+                                    .size(16.0),
+                            ),
+                        )
+                        .on_disabled_hover_text("Requires Expert Mode (View menu)")
+                        .clicked()
+                {
+                    if matches!(
+                        issue,
+                        RunNumberIssue::FailingDisks(_) | RunNumberIssue::LowDiskSpace(_)
+                    ) {
+                        tracing::warn!(
+                            "Starting run {} despite data router disk problems (expert override): {issue}",
+                            app.config.run_number
+                        );
+                        app.start_run_skipping_disk_checks();
+                    } else {
+                        tracing::warn!(
+                            "Starting run {} without validating the run number (expert override)",
+                            app.config.run_number
+                        );
+                        app.start_run_unchecked();
+                    }
+                }
+                if ui.button(RichText::new("Close").size(16.0)).clicked() {
+                    app.run_number_issue = None;
+                }
+            });
+        });
+}
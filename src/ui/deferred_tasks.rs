@@ -0,0 +1,136 @@
+//! The stop-run steps deferred by a quick stop. Moving the .graw files and backing up the
+//! configuration can take minutes, so a quick stop only stops the DAQ and records the run, and
+//! leaves the rest here. The tasks are kept in the config so that they survive a restart, and the
+//! next run can't start until they are done, since the next run would mix its files with the
+//! files of the quick stopped run.
+use super::app::EnvoyApp;
+use eframe::egui::{Button, Color32, Grid, RichText, Window};
+use serde::{Deserialize, Serialize};
+
+/// A stop-run step which can be deferred
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DeferredTaskKind {
+    MoveGrawFiles,
+    BackupConfig,
+}
+
+impl std::fmt::Display for DeferredTaskKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MoveGrawFiles => write!(f, "Move .graw files"),
+            Self::BackupConfig => write!(f, "Back up GET configuration"),
+        }
+    }
+}
+
+/// A deferred step of a quick stopped run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeferredTask {
+    pub kind: DeferredTaskKind,
+    pub experiment: String,
+    pub run_number: i32,
+}
+
+impl DeferredTask {
+    /// The steps deferred by a quick stop of a run, in the order they must run
+    pub fn for_run(experiment: &str, run_number: i32) -> Vec<Self> {
+        [
+            DeferredTaskKind::MoveGrawFiles,
+            DeferredTaskKind::BackupConfig,
+        ]
+        .into_iter()
+        .map(|kind| Self {
+            kind,
+            experiment: experiment.to_string(),
+            run_number,
+        })
+        .collect()
+    }
+}
+
+impl std::fmt::Display for DeferredTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} for {} run {}",
+            self.kind, self.experiment, self.run_number
+        )
+    }
+}
+
+/// Render the deferred tasks window, if it is open
+pub fn render_deferred_tasks(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
+    if !app.show_deferred_tasks {
+        return;
+    }
+    let run_locked = app.is_run_locked() || app.deferred_run.is_some();
+    let expert = app.config.expert_mode;
+    let mut open = true;
+    let mut run: Option<usize> = None;
+    let mut run_all = false;
+    let mut discard: Option<usize> = None;
+    Window::new("Deferred Tasks")
+        .open(&mut open)
+        .default_width(500.0)
+        .show(ctx, |ui| {
+            if app.config.deferred_tasks.is_empty() {
+                ui.label("No tasks were deferred.");
+                return;
+            }
+            ui.label(
+                RichText::new("These steps were skipped by a quick stop. The next run can't start until they are done.")
+                    .color(Color32::YELLOW),
+            );
+            if let Some(running) = &app.deferred_run {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!(
+                        "Running the tasks... ({} s)",
+                        running.since.elapsed().as_secs()
+                    ));
+                });
+            } else if run_locked {
+                ui.label(
+                    RichText::new("The tasks can't be run during a run.").color(Color32::LIGHT_RED),
+                );
+            }
+            Grid::new("Deferred_Tasks_Grid")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    for (index, task) in app.config.deferred_tasks.iter().enumerate() {
+                        ui.label(task.to_string());
+                        if ui.add_enabled(!run_locked, Button::new("Run")).clicked() {
+                            run = Some(index);
+                        }
+                        if ui
+                            .add_enabled(
+                                expert,
+                                Button::new(RichText::new("Discard").color(Color32::RED)),
+                            )
+                            .on_disabled_hover_text("Requires Expert Mode (View menu)")
+                            .clicked()
+                        {
+                            discard = Some(index);
+                        }
+                        ui.end_row();
+                    }
+                });
+            ui.separator();
+            if ui
+                .add_enabled(!run_locked, Button::new(RichText::new("Run All").size(16.0)))
+                .clicked()
+            {
+                run_all = true;
+            }
+        });
+    app.show_deferred_tasks = open;
+    if run_all {
+        let tasks = app.config.deferred_tasks.clone();
+        app.run_deferred_tasks(tasks);
+    } else if let Some(task) = run.and_then(|index| app.config.deferred_tasks.get(index)) {
+        app.run_deferred_tasks(vec![task.clone()]);
+    } else if let Some(index) = discard {
+        app.discard_deferred_task(index);
+    }
+}
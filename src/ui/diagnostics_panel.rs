@@ -0,0 +1,216 @@
+use super::app::EnvoyApp;
+use super::error_codes::ERROR_CODES;
+use super::features::FeatureMatrix;
+use eframe::egui::{CollapsingHeader, Color32, DragValue, Grid, RichText, ScrollArea, Window};
+
+/// Render the diagnostics window. Shows the async runtime statistics
+/// and the runtime configuration, which is applied at the next connect.
+pub fn render_diagnostics_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
+    let mut open = app.show_diagnostics;
+    Window::new("Diagnostics")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label(
+                RichText::new("Features")
+                    .color(Color32::LIGHT_BLUE)
+                    .size(16.0),
+            );
+            Grid::new("Diagnostics_Feature_Grid")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    let matrix = FeatureMatrix::from_config(&app.config);
+                    for feature in matrix.features() {
+                        ui.label(feature.name);
+                        ui.label(if feature.enabled {
+                            "Enabled"
+                        } else {
+                            "Disabled"
+                        });
+                        ui.label(&feature.detail);
+                        ui.end_row();
+                    }
+                });
+            ui.separator();
+            let stats = app.embassy.runtime_stats();
+            ui.label(
+                RichText::new("Async Runtime")
+                    .color(Color32::LIGHT_BLUE)
+                    .size(16.0),
+            );
+            Grid::new("Diagnostics_Runtime_Grid")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Worker Threads");
+                    ui.label(format!("{}", stats.workers));
+                    ui.end_row();
+                    ui.label("Active Tasks");
+                    ui.label(format!("{}", stats.alive_tasks));
+                    ui.end_row();
+                    ui.label("Injection Queue Depth");
+                    ui.label(format!("{}", stats.injection_queue_depth));
+                    ui.end_row();
+                });
+            ui.separator();
+            let poll_stats = app.embassy.poll_stats();
+            ui.label(
+                RichText::new("Embassy Polling")
+                    .color(Color32::LIGHT_BLUE)
+                    .size(16.0),
+            );
+            Grid::new("Diagnostics_Poll_Grid")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("Message Backlog");
+                    ui.label(format!("{}", poll_stats.backlog));
+                    ui.end_row();
+                    ui.label("Deferred Polls");
+                    ui.label(format!("{}", poll_stats.deferred_polls));
+                    ui.end_row();
+                    ui.label("Worst Handling Time");
+                    ui.label(format!("{:?}", poll_stats.worst_handle_time));
+                    ui.end_row();
+                    if let Ok(repaints) = app.repaints.lock() {
+                        let (requests, coalesced) = repaints.request_counts();
+                        ui.label("Frames (last minute)");
+                        ui.label(format!("{}", repaints.frames_per_minute()));
+                        ui.end_row();
+                        ui.label("Arrival Repaints");
+                        ui.label(format!("{requests} requested, {coalesced} coalesced"));
+                        ui.end_row();
+                    }
+                });
+            ui.separator();
+            ui.label(
+                RichText::new("Run Starts")
+                    .color(Color32::LIGHT_BLUE)
+                    .size(16.0),
+            );
+            match app.start_attempts.mean_starts_needed() {
+                Some(mean) => {
+                    ui.label(format!(
+                        "Starts needed: {mean:.2} on average, {} runs retried",
+                        app.start_attempts.retried_runs()
+                    ));
+                    app.start_attempts.plot(ui);
+                }
+                None => {
+                    ui.label("No runs were started this session.");
+                }
+            }
+            let (attempts, failure) = app.start_attempts.for_run(app.config.run_number);
+            if let Some(failure) = failure {
+                ui.label(
+                    RichText::new(format!(
+                        "Run {}: {attempts} start attempts, the last failure was at {failure}",
+                        app.config.run_number
+                    ))
+                    .color(Color32::YELLOW),
+                );
+            }
+            ui.separator();
+            ui.label(
+                RichText::new("Envoy Messages")
+                    .color(Color32::LIGHT_BLUE)
+                    .size(16.0),
+            );
+            ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                Grid::new("Diagnostics_Envoy_Grid")
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Envoy");
+                        ui.label("Messages");
+                        ui.label("Last Response");
+                        ui.label("Consecutive Failures");
+                        ui.end_row();
+                        let mutant = app.status.mutant_id();
+                        let ecc = app.status.get_ecc_message_stats().iter().enumerate().map(
+                            |(id, stats)| {
+                                let name = if id == mutant {
+                                    String::from("ECC MuTaNT")
+                                } else {
+                                    format!("ECC CoBo {id}")
+                                };
+                                (name, stats)
+                            },
+                        );
+                        let surveyor = app
+                            .status
+                            .get_surveyor_message_stats()
+                            .iter()
+                            .enumerate()
+                            .map(|(id, stats)| (format!("Data Router {id}"), stats));
+                        for (name, stats) in ecc.chain(surveyor) {
+                            ui.label(name);
+                            ui.label(format!("{}", stats.messages));
+                            ui.label(match stats.last_response {
+                                Some(time) => format!("{:.1} s ago", time.elapsed().as_secs_f64()),
+                                None => String::from("Never"),
+                            });
+                            let failures = RichText::new(format!("{}", stats.consecutive_failures));
+                            ui.label(if stats.consecutive_failures > 0 {
+                                failures.color(Color32::RED)
+                            } else {
+                                failures
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+            ui.separator();
+            ui.label(
+                RichText::new("Runtime Config (applied on connect)")
+                    .color(Color32::LIGHT_BLUE)
+                    .size(16.0),
+            );
+            ui.add_enabled_ui(!app.embassy.is_connected(), |ui| {
+                Grid::new("Diagnostics_Config_Grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Worker Threads");
+                        ui.add(
+                            DragValue::new(&mut app.config.runtime.worker_threads)
+                                .speed(1)
+                                .range(1..=64),
+                        );
+                        ui.end_row();
+                        ui.label("Thread Name");
+                        ui.text_edit_singleline(&mut app.config.runtime.thread_name);
+                        ui.end_row();
+                    });
+            });
+            ui.separator();
+            CollapsingHeader::new(
+                RichText::new("Error Codes")
+                    .color(Color32::LIGHT_BLUE)
+                    .size(16.0),
+            )
+            .show(ui, |ui| {
+                ScrollArea::vertical()
+                    .id_salt("Diagnostics_Error_Code_Scroll")
+                    .max_height(240.0)
+                    .show(ui, |ui| {
+                        Grid::new("Diagnostics_Error_Code_Grid")
+                            .num_columns(3)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                ui.label(RichText::new("Code").strong());
+                                ui.label(RichText::new("Error").strong());
+                                ui.label(RichText::new("Message").strong());
+                                ui.end_row();
+                                for entry in ERROR_CODES {
+                                    ui.monospace(entry.code);
+                                    ui.label(format!("{}::{}", entry.kind, entry.variant));
+                                    ui.label(entry.message);
+                                    ui.end_row();
+                                }
+                            });
+                    });
+            });
+        });
+    app.show_diagnostics = open;
+}
@@ -1,8 +1,14 @@
 use super::app::EnvoyApp;
-use crate::envoy::constants::MUTANT_ID;
 use crate::envoy::ecc_operation::ECCStatus;
-use crate::envoy::transition::{backward_transition_all, forward_transition_all, transition_ecc};
-use eframe::egui::{Button, Color32, RichText, SidePanel};
+use crate::envoy::reconcile::take_safe_action;
+use crate::envoy::transition::{
+    backward_transition_all, plan_forward_transition, plan_system_target, retry_failed_operation,
+    transition_ecc, transition_selected,
+};
+use eframe::egui::{Button, Color32, Grid, RichText, SidePanel, Window};
+
+/// The id of the ECC table, which must differ from the other tables in the central panel
+const ECC_TABLE_ID: &str = "ECC_Table";
 
 /// Render the ECC envoy control panel, the left side panel in the ui
 /// The ECC panel is one of the two panels that allow users to directly
@@ -15,53 +21,189 @@ pub fn render_ecc_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
                 .size(18.0),
         );
         let ecc_system_stat = app.status.get_system_ecc_status();
-        ui.label(
-            RichText::new(format!("System Status: {}", ecc_system_stat))
+        let in_flight = app.status.is_any_held() || app.forward_transition.is_some();
+        let locked = app.is_transition_locked();
+        ui.horizontal(|ui| {
+            ui.label(
+                RichText::new(format!("System Status: {}", ecc_system_stat))
+                    .size(16.0)
+                    .color(&ecc_system_stat),
+            );
+            if in_flight {
+                ui.spinner();
+            }
+            if app.status.is_network_lost() {
+                ui.label(
+                    RichText::new("Control network lost?")
+                        .size(16.0)
+                        .color(Color32::RED),
+                );
+            }
+        });
+        if let Some(transition) = app.forward_transition.as_ref() {
+            let total = transition.plan.operations.len();
+            let complete = transition.completed(&app.status);
+            ui.label(
+                RichText::new(format!(
+                    "Transition in progress ({complete}/{total} complete, stage {}/{})",
+                    transition.stage + 1,
+                    transition.plan.stages()
+                ))
                 .size(16.0)
-                .color(&ecc_system_stat),
-        );
+                .color(Color32::YELLOW),
+            );
+        } else if in_flight {
+            let total = app.status.module_ids().len();
+            let complete = total - app.status.number_held();
+            ui.label(
+                RichText::new(format!(
+                    "Transition in progress ({complete}/{total} complete)"
+                ))
+                .size(16.0)
+                .color(Color32::YELLOW),
+            );
+        }
+        let waived = app.status.waived_cobos();
+        if !waived.is_empty() {
+            let ids: Vec<String> = waived.iter().map(|id| id.to_string()).collect();
+            ui.label(
+                RichText::new(format!(
+                    "Quorum: waiving CoBo {} ({} of {} needed)",
+                    ids.join(", "),
+                    app.status.get_readiness_policy().quorum_cobos,
+                    app.status.cobo_ids().len()
+                ))
+                .size(16.0)
+                .color(Color32::GOLD),
+            );
+        }
+        if let Some(state) = app.external_state() {
+            ui.separator();
+            ui.label(
+                RichText::new("Unexpected system state")
+                    .size(16.0)
+                    .color(Color32::GOLD),
+            );
+            ui.label(RichText::new(state.description()).color(Color32::GOLD));
+            ui.horizontal(|ui| {
+                for action in state.safe_actions() {
+                    if ui.button(RichText::new(action.to_string()).size(16.0)).clicked() {
+                        tracing::warn!("Taking {action} to reconcile an externally induced state");
+                        if let Err(e) = take_safe_action(&mut app.embassy, &mut app.status, *action)
+                        {
+                            tracing::error!("Could not {action}: {e}");
+                        }
+                    }
+                }
+            });
+        }
         ui.separator();
         ui.horizontal(|ui| {
             ui.label(RichText::new("Regress system").size(16.0));
             if ui
                 .add_enabled(
-                    app.status.get_system_ecc_status().can_go_backward(),
+                    !locked && app.status.get_system_ecc_status().can_go_backward(),
                     Button::new(RichText::new("\u{25C0}").color(Color32::RED).size(16.0)),
                 )
                 .clicked()
             {
+                app.begin_transition_report();
                 backward_transition_all(&mut app.embassy, &mut app.status);
             }
             ui.label(RichText::new("Progress system").size(16.0));
-            if ui
+            let plan = plan_forward_transition(
+                &app.status.get_system_ecc_status(),
+                &app.status.ecc_snapshot(),
+            )
+            .ok();
+            let mut progress = ui.add_enabled(
+                !locked && app.status.get_system_ecc_status().can_go_forward(),
+                Button::new(RichText::new("\u{25B6}").color(Color32::GREEN).size(16.0)),
+            );
+            if let Some(plan) = plan.as_ref() {
+                progress = progress.on_hover_text(plan.to_string());
+            }
+            if progress.clicked() {
+                app.progress_system();
+            }
+            if let Some(plan) = plan {
+                if ui
+                    .small_button("Copy plan")
+                    .on_hover_text("Copy the operations of the next forward transition as JSON")
+                    .clicked()
+                {
+                    ui.ctx().copy_text(plan.to_json().to_string());
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            if let Some(target) = app.status.get_system_target() {
+                ui.spinner();
+                ui.label(RichText::new(format!("Bringing system to {target}...")).size(16.0));
+                if ui.button(RichText::new("Cancel").size(16.0)).clicked() {
+                    app.status.clear_ecc_targets();
+                }
+            } else if ui
                 .add_enabled(
-                    app.status.get_system_ecc_status().can_go_forward(),
-                    Button::new(RichText::new("\u{25B6}").color(Color32::GREEN).size(16.0)),
+                    !in_flight && !app.status.is_system_ready(),
+                    Button::new(
+                        RichText::new("Bring system to Ready")
+                            .color(Color32::LIGHT_GREEN)
+                            .size(16.0),
+                    ),
                 )
                 .clicked()
             {
-                match forward_transition_all(&mut app.embassy, &mut app.status) {
+                match plan_system_target(&mut app.status, ECCStatus::Ready) {
                     Ok(()) => (),
-                    Err(e) => tracing::error!(
-                        "An error occurred attempting to transition the system state: {}",
-                        e
-                    ),
+                    Err(e) => tracing::error!("Could not bring the system to Ready: {e}"),
                 }
             }
         });
         ui.separator();
+        ui.horizontal(|ui| {
+            let selected: Vec<usize> = app.ecc_selection.iter().copied().collect();
+            ui.label(RichText::new("Regress selected").size(16.0));
+            if ui
+                .add_enabled(
+                    !locked && !selected.is_empty(),
+                    Button::new(RichText::new("\u{25C0}").color(Color32::RED).size(16.0)),
+                )
+                .clicked()
+            {
+                transition_selected(&mut app.embassy, &mut app.status, &selected, false);
+            }
+            ui.label(RichText::new("Progress selected").size(16.0));
+            if ui
+                .add_enabled(
+                    !locked && !selected.is_empty(),
+                    Button::new(RichText::new("\u{25B6}").color(Color32::GREEN).size(16.0)),
+                )
+                .clicked()
+            {
+                transition_selected(&mut app.embassy, &mut app.status, &selected, true);
+            }
+        });
+        ui.separator();
 
         let mut forward_transitions: Vec<usize> = vec![];
         let mut backward_transitions: Vec<usize> = vec![];
+        let mut ready_targets: Vec<usize> = vec![];
+        let mut retries: Vec<usize> = vec![];
+        let mut dismissals: Vec<usize> = vec![];
 
-        ui.push_id(0, |ui| {
+        ui.push_id(ECC_TABLE_ID, |ui| {
             egui_extras::TableBuilder::new(ui)
                 .striped(true)
+                .column(egui_extras::Column::auto().at_least(20.0))
                 .column(egui_extras::Column::auto().at_least(150.0).resizable(true))
                 .column(egui_extras::Column::auto().at_least(100.0).resizable(true))
                 .column(egui_extras::Column::auto().at_least(50.0).resizable(true))
                 .column(egui_extras::Column::auto().at_least(50.0).resizable(true))
+                .column(egui_extras::Column::auto().at_least(50.0).resizable(true))
+                .column(egui_extras::Column::auto().at_least(50.0).resizable(true))
                 .header(40.0, |mut header| {
+                    header.col(|_| {});
                     header.col(|ui| {
                         ui.heading("Envoy");
                     });
@@ -74,33 +216,75 @@ pub fn render_ecc_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
                     header.col(|ui| {
                         ui.heading("Progress");
                     });
+                    header.col(|ui| {
+                        ui.heading("To Ready");
+                    });
+                    header.col(|ui| {
+                        ui.heading("Retry");
+                    });
                 })
                 .body(|body| {
                     let ecc_status = app.status.get_ecc_status_response();
                     body.rows(40.0, ecc_status.len(), |mut row| {
                         let ridx = row.index();
-                        let status = &ecc_status[ridx];
-                        let ecc_type = ECCStatus::from(status.state);
+                        let ecc_type = app.status.get_ecc_status(ridx);
                         row.col(|ui| {
-                            if ridx == MUTANT_ID {
-                                ui.label(
-                                    RichText::new(format!("ECC Envoy {} [MuTaNT]", ridx))
-                                        .color(Color32::LIGHT_GREEN),
-                                );
+                            let mut is_selected = app.ecc_selection.contains(&ridx);
+                            if ui.checkbox(&mut is_selected, "").changed() {
+                                if is_selected {
+                                    app.ecc_selection.insert(ridx);
+                                } else {
+                                    app.ecc_selection.remove(&ridx);
+                                }
+                            }
+                        });
+                        row.col(|ui| {
+                            let name = if ridx == app.status.mutant_id() {
+                                format!("ECC Envoy {} [MuTaNT]", ridx)
                             } else {
-                                ui.label(
-                                    RichText::new(format!("ECC Envoy {} [CoBo]", ridx))
-                                        .color(Color32::LIGHT_GREEN),
-                                );
+                                format!("ECC Envoy {} [CoBo]", ridx)
+                            };
+                            if ui
+                                .add(
+                                    Button::new(RichText::new(name).color(Color32::LIGHT_GREEN))
+                                        .frame(false),
+                                )
+                                .on_hover_text("Show module details")
+                                .clicked()
+                            {
+                                app.module_windows.insert(ridx);
                             }
                         });
                         row.col(|ui| {
-                            ui.label(RichText::new(format!("{}", ecc_type)).color(&ecc_type));
+                            let status_label = ui.label(
+                                RichText::new(format!("{}", ecc_type)).color(&ecc_type),
+                            );
+                            if waived.contains(&ridx) {
+                                ui.label(RichText::new("Waived").color(Color32::GOLD))
+                                    .on_hover_text("Disagrees with the system, waived by the quorum");
+                            }
+                            if let Some(record) = app.status.get_operation_record(ridx) {
+                                let ago = record.time.elapsed().as_secs();
+                                let detail = format!(
+                                    "Last operation ({ago} s ago)\nError code: {}\nMessage: {}\nText: {}",
+                                    record.response.error_code,
+                                    record.response.error_message,
+                                    record.response.text
+                                );
+                                if record.is_error() {
+                                    ui.label(RichText::new("\u{26A0}").color(Color32::RED))
+                                        .on_hover_text(
+                                            RichText::new(detail).color(Color32::RED),
+                                        );
+                                } else {
+                                    status_label.on_hover_text(detail);
+                                }
+                            }
                         });
                         row.col(|ui| {
                             if ui
                                 .add_enabled(
-                                    ecc_type.can_go_backward(),
+                                    !locked && ecc_type.can_go_backward(),
                                     Button::new(RichText::new("\u{25C0}").color(Color32::RED)),
                                 )
                                 .clicked()
@@ -111,7 +295,7 @@ pub fn render_ecc_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
                         row.col(|ui| {
                             if ui
                                 .add_enabled(
-                                    app.status.can_ecc_go_forward(ridx),
+                                    !locked && app.status.can_ecc_go_forward(ridx),
                                     Button::new(RichText::new("\u{25B6}").color(Color32::GREEN)),
                                 )
                                 .clicked()
@@ -119,6 +303,47 @@ pub fn render_ecc_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
                                 forward_transitions.push(ridx);
                             }
                         });
+                        row.col(|ui| {
+                            if app.status.get_ecc_target(ridx).is_some() {
+                                ui.spinner();
+                            } else if ui
+                                .add_enabled(
+                                    !locked && ecc_type.can_go_forward(),
+                                    Button::new(
+                                        RichText::new("\u{23E9}").color(Color32::LIGHT_GREEN),
+                                    ),
+                                )
+                                .on_hover_text("Progress this module until it is Ready")
+                                .clicked()
+                            {
+                                ready_targets.push(ridx);
+                            }
+                        });
+                        row.col(|ui| {
+                            if let Some(op) = app.status.get_failed_operation(ridx) {
+                                let retries_done = app.status.get_retry_count(ridx);
+                                if ui
+                                    .add_enabled(
+                                        !locked,
+                                        Button::new(
+                                            RichText::new(format!("\u{27F3} {retries_done}"))
+                                                .color(Color32::YELLOW),
+                                        ),
+                                    )
+                                    .on_hover_text(format!("Retry {op}"))
+                                    .clicked()
+                                {
+                                    retries.push(ridx);
+                                }
+                                if ui
+                                    .button(RichText::new("\u{2715}"))
+                                    .on_hover_text("Dismiss")
+                                    .clicked()
+                                {
+                                    dismissals.push(ridx);
+                                }
+                            }
+                        });
                     });
                 });
             ui.separator();
@@ -130,5 +355,69 @@ pub fn render_ecc_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
             backward_transitions,
             false,
         );
+        for id in ready_targets {
+            app.status.set_ecc_target(id, ECCStatus::Ready);
+        }
+        for id in retries {
+            match retry_failed_operation(&mut app.embassy, &mut app.status, id) {
+                Ok(()) => (),
+                Err(e) => tracing::error!("Could not retry the operation for module {id}: {e}"),
+            }
+        }
+        for id in dismissals {
+            app.status.dismiss_operation_error(id);
+        }
     });
 }
+
+/// Render the before/after table of the last bulk transition
+pub fn render_transition_report(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
+    let report = match &app.transition_report {
+        Some(r) => r,
+        None => return,
+    };
+    let mut open = true;
+    Window::new("Transition Report")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            Grid::new("Transition_Report_Grid")
+                .num_columns(5)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label(RichText::new("Module").strong());
+                    ui.label(RichText::new("Before").strong());
+                    ui.label(RichText::new("After").strong());
+                    ui.label(RichText::new("Duration").strong());
+                    ui.label(RichText::new("Error").strong());
+                    ui.end_row();
+                    for row in report.rows.iter() {
+                        let name = if row.id == app.status.mutant_id() {
+                            format!("{} [MuTaNT]", row.id)
+                        } else {
+                            format!("{} [CoBo]", row.id)
+                        };
+                        let moved = row.before != row.after;
+                        ui.label(RichText::new(name).color(if moved {
+                            Color32::LIGHT_GREEN
+                        } else {
+                            Color32::GRAY
+                        }));
+                        ui.label(RichText::new(row.before.to_string()).color(&row.before));
+                        ui.label(RichText::new(row.after.to_string()).color(&row.after));
+                        match row.duration {
+                            Some(d) => ui.label(format!("{:.1} s", d.as_secs_f64())),
+                            None => ui.label("-"),
+                        };
+                        match &row.error {
+                            Some(e) => ui.label(RichText::new(e).color(Color32::RED)),
+                            None => ui.label(""),
+                        };
+                        ui.end_row();
+                    }
+                });
+        });
+    if !open {
+        app.transition_report = None;
+    }
+}
@@ -1,28 +1,30 @@
-#[derive(Debug)]
-pub enum ConfigError {
-    FailedToParse(serde_yaml::Error),
-    BadIO(std::io::Error),
-}
-
-impl From<serde_yaml::Error> for ConfigError {
-    fn from(value: serde_yaml::Error) -> Self {
-        ConfigError::FailedToParse(value)
-    }
-}
+use crate::envoy::error::EmbassyError;
+use thiserror::Error;
 
-impl From<std::io::Error> for ConfigError {
-    fn from(value: std::io::Error) -> Self {
-        ConfigError::BadIO(value)
-    }
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("[CFG-001] Config failed to parse: {0}")]
+    FailedToParse(#[from] serde_yaml::Error),
+    #[error("[CFG-002] Config failed IO: {0}")]
+    BadIO(#[from] std::io::Error),
+    #[error("[CFG-003] Run metadata failed to serialize as JSON: {0}")]
+    FailedToSerialize(#[from] serde_json::Error),
+    #[error("[CFG-004] Run log database error: {0}")]
+    FailedDatabase(#[from] rusqlite::Error),
 }
 
-impl std::fmt::Display for ConfigError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::BadIO(e) => write!(f, "Config failed IO: {e}"),
-            Self::FailedToParse(e) => write!(f, "Config failed to parse: {e}"),
-        }
-    }
+#[derive(Debug, Error)]
+pub enum PlanError {
+    #[error("[PLN-001] Usage: attpc_envoy plan [--json] [--config <path>] ({0})")]
+    BadArguments(String),
+    #[error("[PLN-002] No config was given and the setup wizard has not saved one")]
+    NoConfig,
+    #[error("[PLN-003] Could not load the config: {0}")]
+    BadConfig(#[from] ConfigError),
+    #[error("[PLN-004] The ECC envoys are disabled in the config")]
+    EccDisabled,
+    #[error("[PLN-005] Embassy failed: {0}")]
+    BadEmbassy(#[from] EmbassyError),
+    #[error("[PLN-006] ECC modules {0} did not report a status within {1} s")]
+    NotReported(String, u64),
 }
-
-impl std::error::Error for ConfigError {}
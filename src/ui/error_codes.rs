@@ -0,0 +1,51 @@
+//! The table of error codes, generated at build time from the error enums.
+
+/// A tagged error variant
+#[derive(Debug, Clone, Copy)]
+pub struct ErrorCode {
+    pub code: &'static str,
+    pub kind: &'static str,
+    pub variant: &'static str,
+    pub message: &'static str,
+}
+
+/// Every error code, in declaration order
+pub const ERROR_CODES: &[ErrorCode] = include!(concat!(env!("OUT_DIR"), "/error_codes.rs"));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn codes_are_unique_and_well_formed() {
+        let mut seen = HashSet::new();
+        for entry in ERROR_CODES {
+            let (prefix, number) = entry.code.split_once('-').unwrap();
+            assert_eq!(prefix.len(), 3, "{}", entry.code);
+            assert!(prefix.chars().all(|c| c.is_ascii_uppercase()));
+            assert_eq!(number.len(), 3, "{}", entry.code);
+            assert!(number.chars().all(|c| c.is_ascii_digit()));
+            assert!(seen.insert(entry.code), "duplicate code {}", entry.code);
+        }
+    }
+
+    #[test]
+    fn table_matches_the_enums() {
+        let code = |variant: &str, kind: &str| {
+            ERROR_CODES
+                .iter()
+                .find(|e| e.kind == kind && e.variant == variant)
+                .map(|e| e.code)
+        };
+        assert_eq!(code("BadRequest", "EnvoyError"), Some("ENV-001"));
+        assert_eq!(code("FailedDatabase", "ConfigError"), Some("CFG-004"));
+        assert!(ERROR_CODES.iter().any(|e| e.kind == "CommandError"));
+        assert!(ERROR_CODES.iter().all(|e| !e.variant.is_empty()));
+        let rendered = crate::ui::error::ConfigError::FailedToSerialize(
+            serde_json::from_str::<u8>("x").unwrap_err(),
+        )
+        .to_string();
+        assert!(rendered.starts_with("[CFG-003]"));
+    }
+}
@@ -0,0 +1,140 @@
+use super::app::EnvoyApp;
+use eframe::egui::{CollapsingHeader, Color32, RichText, TopBottomPanel};
+use std::collections::VecDeque;
+use time::OffsetDateTime;
+
+/// The maximum number of errors kept, the oldest are dropped first
+const ERROR_LOG_CAPACITY: usize = 500;
+
+/// The id of the error log table
+const ERROR_LOG_TABLE_ID: &str = "Error_Log_Table";
+
+/// A failure recorded during the session
+#[derive(Debug, Clone)]
+pub struct ErrorEntry {
+    pub time: OffsetDateTime,
+    /// The module the error came from, if it came from a specific module
+    pub module: Option<usize>,
+    pub message: String,
+}
+
+impl ErrorEntry {
+    /// The time of the error as a UTC timestamp
+    pub fn timestamp(&self) -> String {
+        format!(
+            "{} {:02}:{:02}:{:02} UTC",
+            self.time.date(),
+            self.time.hour(),
+            self.time.minute(),
+            self.time.second()
+        )
+    }
+}
+
+impl std::fmt::Display for ErrorEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.module {
+            Some(id) => write!(f, "[{}] Module {id}: {}", self.timestamp(), self.message),
+            None => write!(f, "[{}] {}", self.timestamp(), self.message),
+        }
+    }
+}
+
+/// Capped history of the errors seen during the session
+#[derive(Debug, Default)]
+pub struct ErrorLog {
+    entries: VecDeque<ErrorEntry>,
+}
+
+impl ErrorLog {
+    pub fn push(&mut self, module: Option<usize>, message: String) {
+        if self.entries.len() == ERROR_LOG_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(ErrorEntry {
+            time: OffsetDateTime::now_utc(),
+            module,
+            message,
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The entry at an index counting from the newest
+    pub fn newest(&self, index: usize) -> Option<&ErrorEntry> {
+        self.entries.iter().rev().nth(index)
+    }
+}
+
+/// Render the error history, a collapsible panel at the bottom of the UI
+pub fn render_error_log_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
+    TopBottomPanel::bottom("Error_Log_Panel").show(ctx, |ui| {
+        CollapsingHeader::new(
+            RichText::new(format!("Error History ({})", app.error_log.len()))
+                .color(Color32::LIGHT_BLUE)
+                .size(18.0),
+        )
+        .id_salt("Error_Log_Header")
+        .show(ui, |ui| {
+            if ui.button(RichText::new("Clear").size(16.0)).clicked() {
+                app.error_log.clear();
+            }
+            ui.push_id(ERROR_LOG_TABLE_ID, |ui| {
+                egui_extras::TableBuilder::new(ui)
+                    .striped(true)
+                    .max_scroll_height(200.0)
+                    .column(egui_extras::Column::auto().at_least(200.0))
+                    .column(egui_extras::Column::auto().at_least(60.0))
+                    .column(egui_extras::Column::remainder().at_least(300.0))
+                    .column(egui_extras::Column::auto().at_least(40.0))
+                    .header(25.0, |mut header| {
+                        header.col(|ui| {
+                            ui.strong("Time");
+                        });
+                        header.col(|ui| {
+                            ui.strong("Module");
+                        });
+                        header.col(|ui| {
+                            ui.strong("Message");
+                        });
+                        header.col(|_| {});
+                    })
+                    .body(|body| {
+                        body.rows(20.0, app.error_log.len(), |mut row| {
+                            let entry = match app.error_log.newest(row.index()) {
+                                Some(e) => e,
+                                None => return,
+                            };
+                            row.col(|ui| {
+                                ui.label(entry.timestamp());
+                            });
+                            row.col(|ui| {
+                                match entry.module {
+                                    Some(id) => ui.label(id.to_string()),
+                                    None => ui.label("-"),
+                                };
+                            });
+                            row.col(|ui| {
+                                ui.label(RichText::new(&entry.message).color(Color32::LIGHT_RED));
+                            });
+                            row.col(|ui| {
+                                if ui
+                                    .small_button("\u{1F4CB}")
+                                    .on_hover_text("Copy to clipboard")
+                                    .clicked()
+                                {
+                                    ui.ctx().copy_text(entry.to_string());
+                                }
+                            });
+                        });
+                    });
+            });
+        });
+    });
+}
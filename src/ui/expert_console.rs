@@ -0,0 +1,242 @@
+//! The expert console, for running one of the scripts with custom arguments or a one-off shell
+//! command against a data router without leaving the app. Every command is recorded in the log,
+//! so the audit trail is kept. Only available in expert mode, and not during a run unless the
+//! expert explicitly allows it.
+use super::app::EnvoyApp;
+use crate::command::bash_command::{run_shell, CommandName, ShellConfig};
+use eframe::egui::{Button, Color32, ComboBox, Key, RichText, ScrollArea, TextEdit, Window};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+
+/// The number of commands kept in the history
+const MAX_HISTORY: usize = 100;
+
+/// A command run from the console and its result
+#[derive(Debug, Clone)]
+pub struct ConsoleEntry {
+    pub command: String,
+    pub success: bool,
+    pub output: String,
+}
+
+/// The console state
+#[derive(Debug, Default)]
+pub struct ExpertConsole {
+    input: String,
+    history: Vec<ConsoleEntry>,
+    /// The position when stepping back through the history with the arrow keys
+    history_cursor: Option<usize>,
+    module: usize,
+    allow_during_run: bool,
+    running: Option<Receiver<ConsoleEntry>>,
+}
+
+impl ExpertConsole {
+    /// Run a command on a background thread, so that the UI does not freeze
+    fn submit(&mut self, command: String, shell: ShellConfig, timeout: Duration) {
+        tracing::warn!("Expert console: {command}");
+        let (tx, rx) = channel();
+        std::thread::spawn(move || {
+            let entry = match run_shell(&command, &shell, timeout) {
+                Ok(output) => ConsoleEntry {
+                    success: output.success(),
+                    output: match output.timed_out {
+                        Some(timeout) => format!(
+                            "{}{}[killed after {} s]",
+                            output.stdout,
+                            output.stderr,
+                            timeout.as_secs()
+                        ),
+                        None => format!("{}{}", output.stdout, output.stderr),
+                    },
+                    command,
+                },
+                Err(e) => ConsoleEntry {
+                    success: false,
+                    output: format!("Could not execute the command: {e}"),
+                    command,
+                },
+            };
+            let _ = tx.send(entry);
+        });
+        self.running = Some(rx);
+        self.history_cursor = None;
+    }
+
+    /// Collect the result of the running command, if it finished
+    fn poll(&mut self) {
+        let entry = match self.running.as_ref().map(|rx| rx.try_recv()) {
+            Some(Ok(entry)) => entry,
+            Some(Err(std::sync::mpsc::TryRecvError::Disconnected)) => {
+                self.running = None;
+                return;
+            }
+            _ => return,
+        };
+        tracing::warn!(
+            "Expert console command finished ({}): {}",
+            if entry.success { "success" } else { "failure" },
+            entry.command
+        );
+        self.running = None;
+        self.history.push(entry);
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+    }
+
+    /// Step through the history of commands with the arrow keys
+    fn recall(&mut self, older: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+        let last = self.history.len() - 1;
+        self.history_cursor = match (self.history_cursor, older) {
+            (None, true) => Some(last),
+            (None, false) => None,
+            (Some(cursor), true) => Some(cursor.saturating_sub(1)),
+            (Some(cursor), false) if cursor < last => Some(cursor + 1),
+            (Some(_), false) => None,
+        };
+        self.input = match self.history_cursor {
+            Some(cursor) => self.history[cursor].command.clone(),
+            None => String::new(),
+        };
+    }
+}
+
+/// The argument templates for the selected data router
+fn templates(app: &EnvoyApp, module: usize) -> Vec<(&'static str, String)> {
+    let (address, location) = match app.status.get_surveyor_status_response().get(module) {
+        Some(router) => (router.address.clone(), router.location.clone()),
+        None => return vec![],
+    };
+    let experiment = &app.config.experiment;
+    let run = app.config.run_number;
+    let shell = &app.config.shell;
+    let script = |name: &str| format!("{} {}", shell.shell, shell.script_path(name).display());
+    vec![
+        ("ssh", format!("ssh {address} ls {location}")),
+        (
+            "test_graw.sh",
+            format!(
+                "{} {address} {location} {experiment} {run}",
+                script("test_graw.sh")
+            ),
+        ),
+        (
+            "move_graw.sh",
+            format!(
+                "{} {address} {location} {experiment} {run}",
+                script("move_graw.sh")
+            ),
+        ),
+        (
+            "list_runs.sh",
+            format!(
+                "{} {address} {location} {experiment}",
+                script("list_runs.sh")
+            ),
+        ),
+    ]
+}
+
+/// Render the expert console, if it is open
+pub fn render_expert_console(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
+    if !app.show_expert_console {
+        return;
+    }
+    if !app.config.expert_mode {
+        app.show_expert_console = false;
+        return;
+    }
+    app.expert_console.poll();
+    let run_locked = app.is_run_locked();
+    let templates = templates(app, app.expert_console.module);
+    let number_of_routers = app.status.get_surveyor_status_response().len();
+    let timeout = app.config.command_timeout(CommandName::Shell);
+    let shell = app.config.shell.clone();
+    let console = &mut app.expert_console;
+    let mut open = true;
+    Window::new("Expert Console")
+        .open(&mut open)
+        .default_width(600.0)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ComboBox::from_id_salt("Expert_Console_Module")
+                    .selected_text(format!("Data Router {}", console.module))
+                    .show_ui(ui, |ui| {
+                        for id in 0..number_of_routers {
+                            ui.selectable_value(
+                                &mut console.module,
+                                id,
+                                format!("Data Router {id}"),
+                            );
+                        }
+                    });
+                for (name, template) in templates {
+                    if ui
+                        .button(name)
+                        .on_hover_text("Fill in the command for the selected data router")
+                        .clicked()
+                    {
+                        console.input = template;
+                    }
+                }
+            });
+            if run_locked {
+                ui.checkbox(
+                    &mut console.allow_during_run,
+                    RichText::new("Allow commands during the run").color(Color32::GOLD),
+                );
+            }
+            let enabled = console.running.is_none() && (!run_locked || console.allow_during_run);
+            ui.horizontal(|ui| {
+                let response = ui.add_enabled(
+                    enabled,
+                    TextEdit::singleline(&mut console.input)
+                        .code_editor()
+                        .desired_width(480.0)
+                        .hint_text("Shell command"),
+                );
+                if response.has_focus() {
+                    if ui.input(|i| i.key_pressed(Key::ArrowUp)) {
+                        console.recall(true);
+                    } else if ui.input(|i| i.key_pressed(Key::ArrowDown)) {
+                        console.recall(false);
+                    }
+                }
+                let entered = response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                let clicked = ui.add_enabled(enabled, Button::new("Run")).clicked();
+                if (entered || clicked) && enabled && !console.input.trim().is_empty() {
+                    let command = std::mem::take(&mut console.input);
+                    console.submit(command.trim().to_string(), shell.clone(), timeout);
+                }
+                if console.running.is_some() {
+                    ui.spinner();
+                }
+            });
+            ui.separator();
+            ScrollArea::vertical()
+                .max_height(400.0)
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    for entry in console.history.iter() {
+                        let color = if entry.success {
+                            Color32::LIGHT_GREEN
+                        } else {
+                            Color32::LIGHT_RED
+                        };
+                        ui.label(
+                            RichText::new(format!("$ {}", entry.command))
+                                .monospace()
+                                .color(color),
+                        );
+                        if !entry.output.is_empty() {
+                            ui.label(RichText::new(entry.output.trim_end()).monospace());
+                        }
+                    }
+                });
+        });
+    app.show_expert_console = open;
+}
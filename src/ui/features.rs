@@ -0,0 +1,187 @@
+use super::config::Config;
+use super::run_log_store::RunLogBackend;
+use crate::command::config_archive::BackupFormat;
+use crate::envoy::status_manager::ReadinessMode;
+
+/// An optional subsystem of the envoy
+#[derive(Debug, Clone)]
+pub struct Feature {
+    pub name: &'static str,
+    pub enabled: bool,
+    pub detail: String,
+}
+
+/// The set of optional subsystems and whether they are enabled. Always derived
+/// from the same Config fields the subsystems read, so it can't fall out of sync.
+#[derive(Debug, Clone)]
+pub struct FeatureMatrix {
+    features: Vec<Feature>,
+}
+
+impl FeatureMatrix {
+    /// Assemble the feature matrix from a Config
+    pub fn from_config(config: &Config) -> Self {
+        let features = vec![
+            Feature {
+                name: "ECC",
+                enabled: config.families.ecc,
+                detail: format!("{} CoBos + MuTaNT", config.num_cobos),
+            },
+            Feature {
+                name: "Data Routers",
+                enabled: config.families.surveyor,
+                detail: format!("{} data routers", config.num_cobos),
+            },
+            Feature {
+                name: "Quorum Readiness",
+                enabled: config.readiness.mode == ReadinessMode::Quorum && config.families.ecc,
+                detail: format!(
+                    "The system needs the MuTaNT and {} of {} CoBos to agree",
+                    config.readiness.clamped(config.num_cobos).quorum_cobos,
+                    config.num_cobos
+                ),
+            },
+            Feature {
+                name: "Config Watcher",
+                enabled: config.watch_configs && config.families.ecc,
+                detail: String::from("Watches the ECC config files for changes"),
+            },
+            Feature {
+                name: "SQLite Run Log",
+                enabled: config.run_log_backend == RunLogBackend::Sqlite,
+                detail: config.table_path().display().to_string(),
+            },
+            Feature {
+                name: "Config Archives",
+                enabled: config.backup_format != BackupFormat::Directory,
+                detail: format!("Config backups are kept as: {}", config.backup_format),
+            },
+            Feature {
+                name: "Auto-Stop",
+                enabled: config.auto_stop_duration().is_some(),
+                detail: format!(
+                    "Runs stop after {} minutes",
+                    config.auto_stop_minutes.unwrap_or_default()
+                ),
+            },
+            Feature {
+                name: "Auto-Cycle",
+                enabled: config.auto_cycle_bytes().is_some(),
+                detail: format!(
+                    "Runs are cycled after {} GB are written",
+                    config.auto_cycle_gb.unwrap_or_default()
+                ),
+            },
+            Feature {
+                name: "Expert Mode",
+                enabled: config.expert_mode,
+                detail: if config.skip_pre_run_checklist {
+                    String::from(
+                        "Safety checks can be overridden, the pre-run checklist is skipped",
+                    )
+                } else {
+                    String::from("Safety checks can be overridden")
+                },
+            },
+            Feature {
+                name: "Run Summary",
+                enabled: config.write_run_summary,
+                detail: String::from("A markdown summary is written when each run stops"),
+            },
+            Feature {
+                name: "Quick Stop",
+                enabled: true,
+                detail: format!(
+                    "{} deferred tasks are waiting to be done",
+                    config.deferred_tasks.len()
+                ),
+            },
+        ];
+        Self { features }
+    }
+
+    /// All of the features in the matrix
+    pub fn features(&self) -> &[Feature] {
+        &self.features
+    }
+}
+
+impl std::fmt::Display for FeatureMatrix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let entries: Vec<String> = self
+            .features
+            .iter()
+            .map(|feat| format!("{}={}", feat.name, if feat.enabled { "on" } else { "off" }))
+            .collect();
+        write!(f, "{}", entries.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_enabled(config: &Config, name: &str) -> bool {
+        FeatureMatrix::from_config(config)
+            .features()
+            .iter()
+            .find(|feature| feature.name == name)
+            .unwrap_or_else(|| panic!("{name} is not in the feature matrix"))
+            .enabled
+    }
+
+    /// A config change which toggles a feature
+    type Toggle = fn(&mut Config);
+
+    /// Every feature with the config change which toggles it. Quick stop is always available.
+    fn toggles() -> Vec<(&'static str, Toggle)> {
+        vec![
+            ("ECC", |c| c.families.ecc = !c.families.ecc),
+            ("Data Routers", |c| {
+                c.families.surveyor = !c.families.surveyor
+            }),
+            ("Quorum Readiness", |c| {
+                c.readiness.mode = ReadinessMode::Quorum
+            }),
+            ("Config Watcher", |c| c.watch_configs = !c.watch_configs),
+            ("SQLite Run Log", |c| {
+                c.run_log_backend = RunLogBackend::Sqlite
+            }),
+            ("Config Archives", |c| {
+                c.backup_format = BackupFormat::Archive
+            }),
+            ("Auto-Stop", |c| c.auto_stop_minutes = Some(30)),
+            ("Auto-Cycle", |c| c.auto_cycle_gb = Some(5.0)),
+            ("Expert Mode", |c| c.expert_mode = true),
+            ("Run Summary", |c| {
+                c.write_run_summary = !c.write_run_summary
+            }),
+        ]
+    }
+
+    #[test]
+    fn features_follow_the_config() {
+        for (name, toggle) in toggles() {
+            let mut config = Config::new();
+            let before = is_enabled(&config, name);
+            toggle(&mut config);
+            assert_ne!(
+                is_enabled(&config, name),
+                before,
+                "{name} did not follow the config"
+            );
+        }
+        assert!(is_enabled(&Config::new(), "Quick Stop"));
+    }
+
+    #[test]
+    fn every_feature_is_covered() {
+        let matrix = FeatureMatrix::from_config(&Config::new());
+        let mut names: Vec<&str> = matrix.features().iter().map(|f| f.name).collect();
+        let mut covered: Vec<&str> = toggles().iter().map(|(name, _)| *name).collect();
+        covered.push("Quick Stop");
+        names.sort();
+        covered.sort();
+        assert_eq!(names, covered);
+    }
+}
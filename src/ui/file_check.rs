@@ -0,0 +1,106 @@
+//! The consistency check of the .graw file counts when a run stops. Every CoBo should write
+//! about the same number of files, and a data router with no files most likely lost its data link.
+use super::app::EnvoyApp;
+use crate::envoy::surveyor_envoy::SurveyorResponse;
+use eframe::egui::{Color32, RichText, Window};
+
+/// A data router whose file count deviates from the others
+#[derive(Debug, Clone)]
+pub struct SuspectRouter {
+    pub id: usize,
+    pub files: i32,
+}
+
+/// The result of the file count check of a run, shown to the operator when there are suspects
+#[derive(Debug, Clone)]
+pub struct FileCountWarning {
+    pub run_number: i32,
+    pub median: i32,
+    pub suspects: Vec<SuspectRouter>,
+}
+
+impl FileCountWarning {
+    /// Check the file counts of the data routers against the median count. A data router is
+    /// suspect if it has no files, or if its count differs from the median by more than the factor.
+    pub fn check(run_number: i32, routers: &[SurveyorResponse], factor: f64) -> Option<Self> {
+        let mut counts: Vec<i32> = routers.iter().map(|router| router.files).collect();
+        if counts.is_empty() {
+            return None;
+        }
+        counts.sort_unstable();
+        let median = counts[counts.len() / 2];
+        let suspects: Vec<SuspectRouter> = routers
+            .iter()
+            .enumerate()
+            .filter(|(_, router)| {
+                let files = router.files as f64;
+                router.files <= 0
+                    || files * factor < median as f64
+                    || files > median as f64 * factor
+            })
+            .map(|(id, router)| SuspectRouter {
+                id,
+                files: router.files,
+            })
+            .collect();
+        (!suspects.is_empty()).then_some(Self {
+            run_number,
+            median,
+            suspects,
+        })
+    }
+
+    /// The flag written to the run log
+    pub fn flag(&self) -> String {
+        let suspects: Vec<String> = self
+            .suspects
+            .iter()
+            .map(|suspect| format!("{} ({} files)", suspect.id, suspect.files))
+            .collect();
+        format!("Suspect: {}", suspects.join(", "))
+    }
+}
+
+/// Render the file count warning, if the last run had suspect data routers
+pub fn render_file_count_warning(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
+    let warning = match &app.file_count_warning {
+        Some(warning) => warning,
+        None => return,
+    };
+    let mut dismiss = false;
+    Window::new(format!("Run {} File Counts", warning.run_number))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(eframe::egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.label(
+                RichText::new(format!(
+                    "Some data routers wrote an unusual number of files (median {}). Their data links may have been broken.",
+                    warning.median
+                ))
+                .size(16.0)
+                .color(Color32::YELLOW),
+            );
+            for suspect in warning.suspects.iter() {
+                let color = if suspect.files <= 0 {
+                    Color32::RED
+                } else {
+                    Color32::YELLOW
+                };
+                ui.label(
+                    RichText::new(format!(
+                        "Data Router {}: {} files",
+                        suspect.id, suspect.files
+                    ))
+                    .color(color),
+                );
+            }
+            ui.separator();
+            if ui.button(RichText::new("OK").size(16.0)).clicked() {
+                dismiss = true;
+            }
+        });
+    if dismiss {
+        app.file_count_warning = None;
+    }
+}
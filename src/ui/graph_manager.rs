@@ -1,6 +1,7 @@
-use crate::envoy::constants::NUMBER_OF_MODULES;
 use crate::envoy::surveyor_envoy::SurveyorResponse;
-use egui_plot::Line;
+use crate::envoy::surveyor_status::SurveyorStatus;
+use eframe::egui::Color32;
+use egui_plot::{Line, LineStyle};
 use std::{
     collections::VecDeque,
     time::{Duration, Instant},
@@ -14,6 +15,8 @@ struct RateGraph {
     points: VecDeque<f64>,
     max_points: usize,
     name: String,
+    /// Is the module flagged by the watchdogs, drawn dashed and red
+    problem: bool,
 }
 
 impl RateGraph {
@@ -25,6 +28,7 @@ impl RateGraph {
             points: VecDeque::with_capacity(*max_points),
             max_points: *max_points,
             name: String::from(name),
+            problem: false,
         }
     }
 
@@ -36,16 +40,31 @@ impl RateGraph {
         self.points.push_back(rate);
     }
 
-    /// Convert the data to a egui_plot::Line.
+    /// Convert the data to a egui_plot::Line. Problem modules are drawn dashed and red, with
+    /// a warning glyph in the legend.
     pub fn get_points_to_draw(&self, times: &VecDeque<f64>) -> Line {
-        Line::new(
+        let line = Line::new(
             times
                 .iter()
                 .zip(self.points.iter())
                 .map(|(time, rate)| [*time, *rate])
                 .collect::<Vec<[f64; 2]>>(),
-        )
-        .name(&self.name)
+        );
+        let line = line.name(self.legend_name());
+        if self.problem {
+            line.color(Color32::RED).style(LineStyle::dashed_loose())
+        } else {
+            line
+        }
+    }
+
+    /// The legend entry, with a warning glyph for problem modules
+    fn legend_name(&self) -> String {
+        if self.problem {
+            format!("\u{26A0} {}", self.name)
+        } else {
+            self.name.clone()
+        }
     }
 
     /// Reset the graph, deleting all points
@@ -60,6 +79,9 @@ impl RateGraph {
     }
 }
 
+/// Default ceiling for data rates (MB/s); anything above this is treated as bad data
+pub const DEFAULT_RATE_CEILING: f64 = 1000.0;
+
 /// Structure used to manage RateGraphs for the UI. Acts in observer-like role, reading a list of messages
 /// from the StatusManager and trasmitting relevant data to the graph of interest.
 #[derive(Debug)]
@@ -70,15 +92,14 @@ pub struct GraphManager {
     update_interval: Duration,
     last_update_time: Instant,
     start_time: Instant,
+    rate_ceiling: f64,
+    warned_bad_rate: bool,
 }
 
 impl GraphManager {
-    /// Create a new manager
-    pub fn new(max_points: usize, time_step_seconds: u64) -> Self {
-        let mut graphs: Vec<RateGraph> = vec![];
-        for i in 0..(NUMBER_OF_MODULES - 1) {
-            graphs.push(RateGraph::new(&format!("envoy_{i}"), &max_points));
-        }
+    /// Create a new manager with a graph for each CoBo
+    pub fn new(max_points: usize, time_step_seconds: u64, number_of_cobos: usize) -> Self {
+        let graphs = Self::create_graphs(number_of_cobos, &max_points);
         let right_now = Instant::now();
         Self {
             graphs,
@@ -87,6 +108,46 @@ impl GraphManager {
             update_interval: Duration::from_secs(time_step_seconds),
             last_update_time: right_now,
             start_time: right_now,
+            rate_ceiling: DEFAULT_RATE_CEILING,
+            warned_bad_rate: false,
+        }
+    }
+
+    /// Create a graph for each CoBo
+    fn create_graphs(number_of_cobos: usize, max_points: &usize) -> Vec<RateGraph> {
+        (0..number_of_cobos)
+            .map(|i| RateGraph::new(&format!("envoy_{i}"), max_points))
+            .collect()
+    }
+
+    /// Change the number of CoBos being graphed. This also resets the graphs.
+    pub fn set_number_of_cobos(&mut self, number_of_cobos: usize) {
+        self.graphs = Self::create_graphs(number_of_cobos, &self.max_points);
+        self.reset();
+    }
+
+    /// Change the ceiling above which rates are clamped
+    pub fn set_rate_ceiling(&mut self, ceiling: f64) {
+        self.rate_ceiling = ceiling;
+    }
+
+    /// Sanitize a rate before it is graphed. Non-finite and negative rates (i.e. from a
+    /// zero time step or a reset disk counter) become zero, and absurd rates are clamped
+    /// to the ceiling, so that one bad sample can't break the plot autoscale.
+    fn sanitize_rate(&mut self, id: usize, rate: f64) -> f64 {
+        if rate.is_finite() && (0.0..=self.rate_ceiling).contains(&rate) {
+            return rate;
+        }
+        if !self.warned_bad_rate {
+            tracing::warn!(
+                "Recieved a bad data rate {rate} for envoy_{id}, bad rates will not be graphed"
+            );
+            self.warned_bad_rate = true;
+        }
+        if rate.is_finite() && rate > self.rate_ceiling {
+            self.rate_ceiling
+        } else {
+            0.0
         }
     }
 
@@ -104,12 +165,21 @@ impl GraphManager {
         }
         self.time_points.push_back(ellapsed_time.as_secs_f64());
         for (id, status) in statuses.iter().enumerate() {
+            let rate = self.sanitize_rate(id, status.data_rate);
             if let Some(graph) = self.graphs.get_mut(id) {
-                graph.add_point(status.data_rate);
+                graph.add_point(rate);
             }
         }
     }
 
+    /// Flag the modules the watchdogs consider unhealthy (i.e. stale, or not writing while the
+    /// others are). Modules missing from the flags are healthy.
+    pub fn set_problems(&mut self, problems: &[bool]) {
+        for (id, graph) in self.graphs.iter_mut().enumerate() {
+            graph.problem = problems.get(id).copied().unwrap_or(false);
+        }
+    }
+
     /// Get all of the graphs as egui_plot::Lines
     pub fn get_line_graphs(&self) -> Vec<egui_plot::Line> {
         self.graphs
@@ -118,11 +188,19 @@ impl GraphManager {
             .collect()
     }
 
+    /// Get the graph of a single CoBo as an egui_plot::Line
+    pub fn get_line_graph(&self, id: usize) -> Option<egui_plot::Line> {
+        self.graphs
+            .get(id)
+            .map(|g| g.get_points_to_draw(&self.time_points))
+    }
+
     /// Reset all of the graphs, dumping their points
     pub fn reset(&mut self) {
         self.start_time = Instant::now();
         self.last_update_time = self.start_time;
         self.time_points.clear();
+        self.warned_bad_rate = false;
         for graph in self.graphs.iter_mut() {
             graph.reset();
         }
@@ -140,3 +218,83 @@ impl GraphManager {
         &self.max_points
     }
 }
+
+/// The health flags of the data routers for set_problems: a router is flagged when it is not
+/// online, or when the stalled-rate alarm is raised for it
+pub fn router_problems(
+    routers: &[SurveyorResponse],
+    is_alarmed: impl Fn(usize) -> bool,
+) -> Vec<bool> {
+    routers
+        .iter()
+        .enumerate()
+        .map(|(id, router)| {
+            !matches!(SurveyorStatus::from(router.state), SurveyorStatus::Online) || is_alarmed(id)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bad_rates_are_sanitized() {
+        let mut graphs = GraphManager::new(10, 1, 1);
+        graphs.set_rate_ceiling(100.0);
+        assert_eq!(graphs.sanitize_rate(0, 12.5), 12.5);
+        assert_eq!(graphs.sanitize_rate(0, f64::NAN), 0.0);
+        assert_eq!(graphs.sanitize_rate(0, f64::INFINITY), 0.0);
+        assert_eq!(graphs.sanitize_rate(0, f64::NEG_INFINITY), 0.0);
+        assert_eq!(graphs.sanitize_rate(0, -3.0), 0.0);
+        assert_eq!(graphs.sanitize_rate(0, 1.0e9), 100.0);
+    }
+
+    #[test]
+    fn graphs_only_hold_finite_points() {
+        let mut graphs = GraphManager::new(10, 1, 3);
+        let statuses: Vec<SurveyorResponse> = [f64::NAN, f64::INFINITY, 5.0]
+            .into_iter()
+            .map(|data_rate| SurveyorResponse {
+                data_rate,
+                ..Default::default()
+            })
+            .collect();
+        graphs.update(&statuses);
+        for graph in graphs.graphs.iter() {
+            assert!(graph.points.iter().all(|p| p.is_finite()));
+        }
+        assert_eq!(graphs.graphs[2].points.back(), Some(&5.0));
+    }
+
+    fn router(state: i32) -> SurveyorResponse {
+        SurveyorResponse {
+            state,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn offline_and_alarmed_routers_are_problems() {
+        let routers = [router(1), router(0), router(1), router(2)];
+        assert_eq!(
+            router_problems(&routers, |id| id == 2),
+            vec![false, true, true, true]
+        );
+        assert!(router_problems(&[], |_| true).is_empty());
+    }
+
+    #[test]
+    fn problem_modules_are_marked_in_the_legend() {
+        let mut graphs = GraphManager::new(10, 1, 3);
+        graphs.set_problems(&[false, true]);
+        let legend: Vec<String> = graphs.graphs.iter().map(|g| g.legend_name()).collect();
+        assert!(!legend[0].starts_with('\u{26A0}'));
+        assert!(legend[1].starts_with('\u{26A0}'));
+        // Modules without a flag are healthy
+        assert!(!legend[2].starts_with('\u{26A0}'));
+
+        graphs.set_problems(&[]);
+        assert!(graphs.graphs.iter().all(|g| !g.problem));
+    }
+}
@@ -0,0 +1,67 @@
+use super::app::EnvoyApp;
+use crate::envoy::ecc_operation::{ECCOperation, ECCStatus};
+use eframe::egui::{Color32, Grid, Key, RichText, Window};
+
+/// Render the help overlay (toggled with F1). The contents are generated from the
+/// same state machine definitions used by the transition code, so they can't drift.
+pub fn render_help_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
+    if ctx.input(|i| i.key_pressed(Key::F1)) {
+        app.show_help = !app.show_help;
+    }
+    let mut open = app.show_help;
+    Window::new("ECC State Machine Help")
+        .open(&mut open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label(
+                RichText::new("States")
+                    .color(Color32::LIGHT_BLUE)
+                    .size(16.0),
+            );
+            Grid::new("Help_State_Grid")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label(RichText::new("Status").strong());
+                    ui.label(RichText::new("Progress").strong());
+                    ui.label(RichText::new("Regress").strong());
+                    ui.end_row();
+                    for status in ECCStatus::SERVER_STATES.iter() {
+                        ui.label(RichText::new(status.to_string()).color(status));
+                        ui.label(operation_label(status.get_forward_operation()));
+                        ui.label(operation_label(status.get_backward_operation()));
+                        ui.end_row();
+                    }
+                });
+            ui.separator();
+            ui.label(
+                RichText::new("Operations")
+                    .color(Color32::LIGHT_BLUE)
+                    .size(16.0),
+            );
+            Grid::new("Help_Operation_Grid")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label(RichText::new("Operation").strong());
+                    ui.label(RichText::new("Description").strong());
+                    ui.label(RichText::new("System Order").strong());
+                    ui.end_row();
+                    for operation in ECCOperation::TRANSITIONS.iter() {
+                        ui.label(operation.to_string());
+                        ui.label(operation.description());
+                        ui.label(operation.module_order().to_string());
+                        ui.end_row();
+                    }
+                });
+        });
+    app.show_help = open;
+}
+
+/// Label for an operation in the state table
+fn operation_label(operation: ECCOperation) -> String {
+    match operation {
+        ECCOperation::Invalid => String::from("-"),
+        op => op.to_string(),
+    }
+}
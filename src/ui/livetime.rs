@@ -0,0 +1,119 @@
+use crate::envoy::ecc_operation::ECCStatus;
+use crate::envoy::status_manager::StatusManager;
+use std::time::{Duration, Instant};
+
+/// Accumulates the time during a run that modules spent degraded (not Running). The time with
+/// any module degraded is tracked separately from the per-module totals, so that overlapping
+/// degradations are only counted once towards the livetime.
+#[derive(Debug, Clone)]
+pub struct RunLivetime {
+    module_degraded: Vec<Duration>,
+    any_degraded: Duration,
+    active: Duration,
+    last_sample: Option<Instant>,
+}
+
+impl RunLivetime {
+    pub fn new(number_of_modules: usize) -> Self {
+        Self {
+            module_degraded: vec![Duration::ZERO; number_of_modules],
+            any_degraded: Duration::ZERO,
+            active: Duration::ZERO,
+            last_sample: None,
+        }
+    }
+
+    /// Sample the module states, attributing the time since the last sample to the current states
+    pub fn sample(&mut self, status: &StatusManager) {
+        self.sample_at(status, Instant::now());
+    }
+
+    fn sample_at(&mut self, status: &StatusManager, now: Instant) {
+        let elapsed = match self.last_sample.replace(now) {
+            Some(last) => now - last,
+            None => return,
+        };
+        self.active += elapsed;
+        let mut any = false;
+        for id in status.module_ids() {
+            if status.get_ecc_status(id) != ECCStatus::Running {
+                any = true;
+                if let Some(total) = self.module_degraded.get_mut(id) {
+                    *total += elapsed;
+                }
+            }
+        }
+        if any {
+            self.any_degraded += elapsed;
+        }
+    }
+
+    /// The fraction of the run during which every module was Running
+    pub fn fraction(&self) -> f64 {
+        if self.active.is_zero() {
+            return 1.0;
+        }
+        1.0 - self.any_degraded.as_secs_f64() / self.active.as_secs_f64()
+    }
+
+    /// The time each module spent degraded, for the modules which were degraded at some point
+    pub fn degraded_modules(&self) -> Vec<(usize, Duration)> {
+        self.module_degraded
+            .iter()
+            .enumerate()
+            .filter(|(_, total)| !total.is_zero())
+            .map(|(id, total)| (id, *total))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::envoy::testing::{set_statuses, status_manager};
+    use ECCStatus::{Ready, Running};
+
+    /// Sample a scripted sequence of (seconds since the start, module statuses)
+    fn run_script(script: &[(u64, &[ECCStatus])]) -> RunLivetime {
+        let start = Instant::now();
+        let mut status = status_manager(script[0].1);
+        let mut livetime = RunLivetime::new(script[0].1.len());
+        for (seconds, statuses) in script {
+            // The state reported at a sample is attributed to the following interval
+            livetime.sample_at(&status, start + Duration::from_secs(*seconds));
+            set_statuses(&mut status, statuses);
+        }
+        livetime
+    }
+
+    #[test]
+    fn fully_running_run_is_fully_live() {
+        let livetime = run_script(&[(0, &[Running, Running]), (10, &[Running, Running])]);
+        assert_eq!(livetime.fraction(), 1.0);
+        assert!(livetime.degraded_modules().is_empty());
+    }
+
+    #[test]
+    fn overlapping_degradations_count_once() {
+        let livetime = run_script(&[
+            (0, &[Running, Running, Running]),
+            (10, &[Ready, Running, Running]),
+            (20, &[Ready, Ready, Running]),
+            (30, &[Running, Ready, Running]),
+            (40, &[Running, Running, Running]),
+            (100, &[Running, Running, Running]),
+        ]);
+        // Module 0 from 10 s to 30 s and module 1 from 20 s to 40 s: 30 s with any degraded
+        assert!((livetime.fraction() - 0.7).abs() < 1e-9);
+        assert_eq!(
+            livetime.degraded_modules(),
+            vec![(0, Duration::from_secs(20)), (1, Duration::from_secs(20))]
+        );
+    }
+
+    #[test]
+    fn empty_run_is_fully_live() {
+        let livetime = RunLivetime::new(2);
+        assert_eq!(livetime.fraction(), 1.0);
+    }
+}
@@ -0,0 +1,159 @@
+//! The in-app log console. A tracing Layer forwards formatted events over a bounded channel
+//! to the console owned by the app, so that operators can see the log without a terminal.
+use eframe::egui::{Color32, ComboBox, RichText, ScrollArea, SidePanel};
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::Layer;
+
+/// Capacity of the channel between the layer and the console. Lines are dropped if the
+/// console falls this far behind.
+const LOG_CHANNEL_CAPACITY: usize = 1024;
+/// The number of lines kept by the console
+const MAX_LOG_LINES: usize = 1000;
+const LOG_LEVELS: [Level; 5] = [
+    Level::ERROR,
+    Level::WARN,
+    Level::INFO,
+    Level::DEBUG,
+    Level::TRACE,
+];
+
+/// A formatted log event
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub level: Level,
+    pub text: String,
+}
+
+/// Collects the message and fields of an event into a single line
+struct LineVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for LineVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.fields, " {}={value:?}", field.name());
+        }
+    }
+}
+
+/// A tracing Layer which forwards events to the log console
+pub struct LogConsoleLayer {
+    tx: SyncSender<LogLine>,
+}
+
+impl<S: Subscriber> Layer<S> for LogConsoleLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = LineVisitor {
+            message: String::new(),
+            fields: String::new(),
+        };
+        event.record(&mut visitor);
+        let metadata = event.metadata();
+        let text = format!(
+            "{} {:>5} {}{}",
+            time::OffsetDateTime::now_utc().time(),
+            metadata.level(),
+            visitor.message,
+            visitor.fields
+        );
+        // Never block the logging thread, drop the line if the console is behind
+        let _ = self.tx.try_send(LogLine {
+            level: *metadata.level(),
+            text,
+        });
+    }
+}
+
+/// The lines recieved from the LogConsoleLayer and the console view state
+#[derive(Debug)]
+pub struct LogConsole {
+    rx: Receiver<LogLine>,
+    lines: VecDeque<LogLine>,
+    pub filter: Level,
+    pub paused: bool,
+}
+
+impl LogConsole {
+    /// Create a console and the layer which feeds it
+    pub fn new() -> (Self, LogConsoleLayer) {
+        let (tx, rx) = sync_channel(LOG_CHANNEL_CAPACITY);
+        (
+            Self {
+                rx,
+                lines: VecDeque::with_capacity(MAX_LOG_LINES),
+                filter: Level::INFO,
+                paused: false,
+            },
+            LogConsoleLayer { tx },
+        )
+    }
+
+    /// Move any new lines from the layer into the console
+    pub fn drain(&mut self) {
+        while let Ok(line) = self.rx.try_recv() {
+            if self.lines.len() == MAX_LOG_LINES {
+                self.lines.pop_front();
+            }
+            self.lines.push_back(line);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+}
+
+fn level_color(level: &Level) -> Color32 {
+    match *level {
+        Level::ERROR => Color32::RED,
+        Level::WARN => Color32::YELLOW,
+        Level::INFO => Color32::LIGHT_GRAY,
+        _ => Color32::GRAY,
+    }
+}
+
+/// Render the log console, a right side panel which can be hidden from the View menu
+pub fn render_log_panel(console: &mut LogConsole, show: bool, ctx: &eframe::egui::Context) {
+    console.drain();
+    SidePanel::right("Log_Panel")
+        .resizable(true)
+        .default_width(400.0)
+        .show_animated(ctx, show, |ui| {
+            ui.label(RichText::new("Log").size(18.0).color(Color32::LIGHT_BLUE));
+            ui.horizontal(|ui| {
+                ComboBox::from_id_salt("Log_Level_Filter")
+                    .selected_text(console.filter.to_string())
+                    .show_ui(ui, |ui| {
+                        for level in LOG_LEVELS {
+                            ui.selectable_value(&mut console.filter, level, level.to_string());
+                        }
+                    });
+                ui.checkbox(&mut console.paused, "Pause scrolling");
+                if ui.button("Clear").clicked() {
+                    console.clear();
+                }
+            });
+            ui.separator();
+            ScrollArea::vertical()
+                .auto_shrink([false, false])
+                .stick_to_bottom(!console.paused)
+                .show(ui, |ui| {
+                    for line in console.lines.iter().filter(|l| l.level <= console.filter) {
+                        ui.label(
+                            RichText::new(&line.text)
+                                .monospace()
+                                .color(level_color(&line.level)),
+                        );
+                    }
+                });
+        });
+}
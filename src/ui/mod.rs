@@ -3,10 +3,39 @@
 pub mod app;
 mod config;
 mod config_panel;
+mod config_restore;
+mod confirm_panel;
+mod deferred_tasks;
+mod diagnostics_panel;
 mod ecc_panel;
+mod effective_config;
 mod error;
+mod error_codes;
+mod error_log;
+mod expert_console;
+mod features;
+mod file_check;
 mod graph_manager;
 mod graph_panel;
+mod help_panel;
+mod livetime;
+pub mod log_console;
+mod module_panel;
+mod notifications;
+pub mod plan_cli;
+mod pre_run_checklist;
+mod rate_alarm;
+mod repaint;
 mod router_panel;
+pub mod run_log_file;
 mod run_log_panel;
+mod run_log_store;
+mod run_metadata;
+mod run_sequence;
+mod run_summary;
+mod run_table_panel;
+mod sanitize;
+pub mod setup_wizard;
+mod start_attempts;
+mod start_countdown;
 mod style;
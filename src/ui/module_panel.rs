@@ -0,0 +1,249 @@
+use super::app::EnvoyApp;
+use crate::envoy::ecc_envoy::ECCConfig;
+use crate::envoy::surveyor_envoy::SurveyorConfig;
+use crate::envoy::transition::{retry_failed_operation, transition_ecc};
+use eframe::egui::{Button, Color32, Grid, Id, RichText, Window};
+
+/// Render a detail window for each module the operator has opened. Each window is keyed by
+/// the module id so that several can be open at once.
+pub fn render_module_windows(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
+    let ids: Vec<usize> = app.module_windows.iter().copied().collect();
+    for id in ids {
+        if id > app.status.mutant_id() {
+            app.module_windows.remove(&id);
+            continue;
+        }
+        let mut open = true;
+        Window::new(module_name(app, id))
+            .id(Id::new(("Module_Detail", id)))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| render_module_detail(app, ui, id));
+        if !open {
+            app.module_windows.remove(&id);
+        }
+    }
+}
+
+/// The display name of a module
+fn module_name(app: &EnvoyApp, id: usize) -> String {
+    if id == app.status.mutant_id() {
+        format!("Module {id} [MuTaNT]")
+    } else {
+        format!("Module {id} [CoBo]")
+    }
+}
+
+fn render_module_detail(app: &mut EnvoyApp, ui: &mut eframe::egui::Ui, id: usize) {
+    let is_mutant = id == app.status.mutant_id();
+    let status = app.status.get_ecc_status(id);
+    ui.label(RichText::new("ECC").color(Color32::LIGHT_BLUE).size(16.0));
+    Grid::new(("Module_ECC_Grid", id))
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Status");
+            ui.label(RichText::new(status.to_string()).color(&status));
+            ui.end_row();
+            ui.label("Target");
+            match app.status.get_ecc_target(id) {
+                Some(target) => ui.label(target.to_string()),
+                None => ui.label("-"),
+            };
+            ui.end_row();
+            ui.label("Last Operation");
+            match app.status.get_last_operation(id) {
+                Some(op) if app.status.is_ecc_held(id) => ui.label(format!("{op} (in progress)")),
+                Some(op) => ui.label(op.to_string()),
+                None => ui.label("-"),
+            };
+            ui.end_row();
+            if let Some(record) = app.status.get_operation_record(id) {
+                ui.label("Response");
+                let text = format!(
+                    "Error code {}: {} ({} s ago)",
+                    record.response.error_code,
+                    record.response.error_message,
+                    record.time.elapsed().as_secs()
+                );
+                if record.is_error() {
+                    ui.label(RichText::new(text).color(Color32::RED));
+                } else {
+                    ui.label(text);
+                }
+                ui.end_row();
+            }
+            if let Some(latency) = app.status.get_operation_latency(id) {
+                ui.label("Latency");
+                ui.label(format!("{:.2} s", latency.as_secs_f64()));
+                ui.end_row();
+            }
+            ui.label("Retries");
+            ui.label(app.status.get_retry_count(id).to_string());
+            ui.end_row();
+        });
+
+    let expected = app.config.expected_asads.get(&id).copied();
+    if let Some(summary) = app.status.get_asad_summary(id) {
+        ui.separator();
+        ui.label(RichText::new("AsAds").color(Color32::LIGHT_BLUE).size(16.0));
+        let list = |ids: &std::collections::BTreeSet<usize>| {
+            if ids.is_empty() {
+                String::from("-")
+            } else {
+                ids.iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            }
+        };
+        Grid::new(("Module_AsAd_Grid", id))
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Active");
+                let active = format!("{} ({})", summary.active.len(), list(&summary.active));
+                match expected.and_then(|expected| summary.discrepancy(expected)) {
+                    Some(discrepancy) => ui
+                        .label(RichText::new(active).color(Color32::YELLOW))
+                        .on_hover_text(discrepancy),
+                    None => ui.label(active),
+                };
+                ui.end_row();
+                ui.label("Masked");
+                ui.label(list(&summary.masked));
+                ui.end_row();
+                if let Some(channels) = summary.masked_channels {
+                    ui.label("Masked Channels");
+                    ui.label(channels.to_string());
+                    ui.end_row();
+                }
+                if let Some(expected) = expected {
+                    ui.label("Expected");
+                    ui.label(expected.to_string());
+                    ui.end_row();
+                }
+            });
+    }
+
+    ui.separator();
+    ui.label(
+        RichText::new("Network")
+            .color(Color32::LIGHT_BLUE)
+            .size(16.0),
+    );
+    let address = if is_mutant {
+        Ok(app.config.network.mutant.as_str())
+    } else {
+        app.config.network.cobo_address(id)
+    };
+    Grid::new(("Module_Network_Grid", id))
+        .num_columns(2)
+        .show(ui, |ui| match address {
+            Ok(address) => {
+                ui.label("Address");
+                ui.label(address);
+                ui.end_row();
+                ui.label("ECC URL");
+                ui.label(ECCConfig::url(address));
+                ui.end_row();
+                ui.label("Data Link Protocol");
+                ui.label(if is_mutant {
+                    app.config.network.protocol.to_string()
+                } else {
+                    app.config.network.cobo_protocol(id).to_string()
+                });
+                ui.end_row();
+                if !is_mutant {
+                    ui.label("Surveyor URL");
+                    ui.label(SurveyorConfig::url(address));
+                    ui.end_row();
+                }
+            }
+            Err(e) => {
+                ui.label("Address");
+                ui.label(RichText::new(e.to_string()).color(Color32::RED));
+                ui.end_row();
+            }
+        });
+
+    if let Some(surveyor) = app.status.get_surveyor_status_response().get(id) {
+        if !is_mutant {
+            ui.separator();
+            ui.label(
+                RichText::new("Data Router")
+                    .color(Color32::LIGHT_BLUE)
+                    .size(16.0),
+            );
+            let surveyor_status = app.status.get_surveyor_status(id);
+            Grid::new(("Module_Router_Grid", id))
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Status");
+                    ui.label(RichText::new(surveyor_status.to_string()).color(&surveyor_status));
+                    ui.end_row();
+                    ui.label("Location");
+                    ui.label(&surveyor.location);
+                    ui.end_row();
+                    ui.label("Disk Status");
+                    ui.label(&surveyor.disk_status);
+                    ui.end_row();
+                    ui.label("Files");
+                    ui.label(surveyor.files.to_string());
+                    ui.end_row();
+                    ui.label("Bytes Written");
+                    ui.label(human_bytes::human_bytes(surveyor.bytes_used as f64));
+                    ui.end_row();
+                    ui.label("Data Rate");
+                    ui.label(format!("{:.2} MB/s", surveyor.data_rate));
+                    ui.end_row();
+                    ui.label("%Disk Used");
+                    ui.label(&surveyor.percent_used);
+                    ui.end_row();
+                });
+            if let Some(line) = app.graphs.get_line_graph(id) {
+                egui_plot::Plot::new(("Module_Rate_Plot", id))
+                    .height(80.0)
+                    .width(300.0)
+                    .show_axes([false, true])
+                    .allow_drag(false)
+                    .allow_zoom(false)
+                    .allow_scroll(false)
+                    .show(ui, |plot_ui| plot_ui.line(line));
+            }
+        }
+    }
+
+    ui.separator();
+    ui.horizontal(|ui| {
+        let enabled = app.embassy.is_connected() && !app.is_transition_locked();
+        if ui
+            .add_enabled(
+                enabled && status.can_go_backward(),
+                Button::new(RichText::new("\u{25C0} Regress").color(Color32::RED)),
+            )
+            .clicked()
+        {
+            transition_ecc(&mut app.embassy, &mut app.status, vec![id], false);
+        }
+        if ui
+            .add_enabled(
+                enabled && app.status.can_ecc_go_forward(id),
+                Button::new(RichText::new("Progress \u{25B6}").color(Color32::GREEN)),
+            )
+            .clicked()
+        {
+            transition_ecc(&mut app.embassy, &mut app.status, vec![id], true);
+        }
+        if ui
+            .add_enabled(
+                enabled && app.status.get_failed_operation(id).is_some(),
+                Button::new(RichText::new("\u{27F3} Retry Last Operation").color(Color32::YELLOW)),
+            )
+            .clicked()
+        {
+            match retry_failed_operation(&mut app.embassy, &mut app.status, id) {
+                Ok(()) => (),
+                Err(e) => tracing::error!("Could not retry the operation for module {id}: {e}"),
+            }
+        }
+    });
+}
@@ -0,0 +1,259 @@
+use eframe::egui::{Align2, CollapsingHeader, Color32, RichText, Window};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How long an info toast is shown before it expires
+const INFO_TOAST_LIFETIME: Duration = Duration::from_secs(5);
+/// The maximum number of toasts kept. The oldest info or warning is dropped first, errors are
+/// only hidden (and counted) once every toast is an error.
+const MAX_TOASTS: usize = 8;
+/// Identical ECC errors from different modules within this many seconds share a toast
+pub const DEFAULT_ERROR_GROUP_WINDOW_SEC: u64 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToastLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A message shown to the operator in the notification overlay
+#[derive(Debug, Clone)]
+pub struct Toast {
+    pub level: ToastLevel,
+    pub message: String,
+    pub created: Instant,
+    /// The ECC error code shared by the modules of a grouped error
+    pub group: Option<i32>,
+    pub modules: Vec<usize>,
+}
+
+/// A small queue of toasts. Errors and warnings stay until dismissed, infos expire on their own.
+#[derive(Debug, Default)]
+pub struct Notifications {
+    toasts: VecDeque<Toast>,
+    /// The number of errors pushed out of the overlay by newer errors
+    hidden_errors: usize,
+}
+
+impl Notifications {
+    pub fn push(&mut self, level: ToastLevel, message: String) {
+        if self.toasts.len() >= MAX_TOASTS {
+            match self
+                .toasts
+                .iter()
+                .position(|toast| toast.level != ToastLevel::Error)
+            {
+                Some(index) => {
+                    self.toasts.remove(index);
+                }
+                // Every toast is an error, the new message is already in the log
+                None if level != ToastLevel::Error => return,
+                None => {
+                    self.toasts.pop_front();
+                    self.hidden_errors += 1;
+                }
+            }
+        }
+        self.toasts.push_back(Toast {
+            level,
+            message,
+            created: Instant::now(),
+            group: None,
+            modules: vec![],
+        });
+    }
+
+    /// Show an ECC error from a module. If another module reported the same error code within
+    /// the window, the error is added to that toast instead (i.e. "ECC error 112 on 12 modules").
+    pub fn push_module_error(
+        &mut self,
+        code: i32,
+        module: usize,
+        message: String,
+        window: Duration,
+    ) {
+        if let Some(toast) = self
+            .toasts
+            .iter_mut()
+            .rev()
+            .find(|toast| toast.group == Some(code) && toast.created.elapsed() <= window)
+        {
+            if !toast.modules.contains(&module) {
+                toast.modules.push(module);
+            }
+            toast.message = format!("ECC error {code} on {} modules", toast.modules.len());
+            return;
+        }
+        self.push(ToastLevel::Error, format!("Module {module}: {message}"));
+        if let Some(toast) = self.toasts.back_mut() {
+            toast.group = Some(code);
+            toast.modules.push(module);
+        }
+    }
+
+    /// Remove expired info toasts
+    pub fn expire(&mut self) {
+        self.toasts.retain(|toast| {
+            toast.level != ToastLevel::Info || toast.created.elapsed() < INFO_TOAST_LIFETIME
+        });
+    }
+
+    pub fn dismiss(&mut self, index: usize) {
+        self.toasts.remove(index);
+    }
+
+    pub fn clear(&mut self) {
+        self.toasts.clear();
+        self.hidden_errors = 0;
+    }
+
+    /// The number of older errors which no longer fit in the overlay
+    pub fn hidden_errors(&self) -> usize {
+        self.hidden_errors
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.toasts.is_empty()
+    }
+
+    pub fn toasts(&self) -> &VecDeque<Toast> {
+        &self.toasts
+    }
+}
+
+/// Render the toasts in the bottom right corner, newest on top
+pub fn render_notifications(notifications: &mut Notifications, ctx: &eframe::egui::Context) {
+    notifications.expire();
+    if notifications.is_empty() {
+        return;
+    }
+    let mut dismissed = None;
+    let mut clear = false;
+    Window::new("Notifications")
+        .title_bar(false)
+        .resizable(false)
+        .anchor(Align2::RIGHT_BOTTOM, [-10.0, -10.0])
+        .show(ctx, |ui| {
+            for (index, toast) in notifications.toasts().iter().enumerate().rev() {
+                ui.horizontal(|ui| {
+                    let (icon, color) = match toast.level {
+                        ToastLevel::Info => ("\u{2139}", Color32::LIGHT_BLUE),
+                        ToastLevel::Warning => ("\u{26A0}", Color32::YELLOW),
+                        ToastLevel::Error => ("\u{26A0}", Color32::RED),
+                    };
+                    ui.label(RichText::new(format!("{icon} {}", toast.message)).color(color));
+                    if toast.level != ToastLevel::Info && ui.small_button("\u{2715}").clicked() {
+                        dismissed = Some(index);
+                    }
+                });
+                if toast.modules.len() > 1 {
+                    CollapsingHeader::new("Modules")
+                        .id_salt(("Toast_Modules", index))
+                        .show(ui, |ui| {
+                            let mut modules = toast.modules.clone();
+                            modules.sort();
+                            ui.label(
+                                modules
+                                    .iter()
+                                    .map(|id| id.to_string())
+                                    .collect::<Vec<String>>()
+                                    .join(", "),
+                            );
+                        });
+                }
+            }
+            if notifications.hidden_errors() > 0 {
+                ui.label(
+                    RichText::new(format!(
+                        "{} older errors are only in the error log",
+                        notifications.hidden_errors()
+                    ))
+                    .color(Color32::RED),
+                );
+            }
+            if notifications.toasts().len() > 1 && ui.small_button("Dismiss all").clicked() {
+                clear = true;
+            }
+        });
+    if clear {
+        notifications.clear();
+    } else if let Some(index) = dismissed {
+        notifications.dismiss(index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels(notifications: &Notifications) -> Vec<ToastLevel> {
+        notifications
+            .toasts()
+            .iter()
+            .map(|toast| toast.level)
+            .collect()
+    }
+
+    #[test]
+    fn infos_and_warnings_are_evicted_before_errors() {
+        let mut notifications = Notifications::default();
+        notifications.push(ToastLevel::Error, String::from("first error"));
+        for index in 0..MAX_TOASTS - 1 {
+            notifications.push(ToastLevel::Info, format!("info {index}"));
+        }
+        notifications.push(ToastLevel::Warning, String::from("warning"));
+        assert_eq!(notifications.toasts().len(), MAX_TOASTS);
+        assert_eq!(notifications.toasts()[0].message, "first error");
+        assert_eq!(notifications.toasts()[1].message, "info 1");
+        assert_eq!(levels(&notifications).last(), Some(&ToastLevel::Warning));
+        assert_eq!(notifications.hidden_errors(), 0);
+    }
+
+    #[test]
+    fn errors_are_never_evicted_silently() {
+        let mut notifications = Notifications::default();
+        for index in 0..MAX_TOASTS {
+            notifications.push(ToastLevel::Error, format!("error {index}"));
+        }
+        notifications.push(ToastLevel::Info, String::from("info"));
+        assert_eq!(levels(&notifications), vec![ToastLevel::Error; MAX_TOASTS]);
+        assert_eq!(notifications.hidden_errors(), 0);
+
+        notifications.push(ToastLevel::Error, String::from("newest"));
+        assert_eq!(notifications.toasts().len(), MAX_TOASTS);
+        assert_eq!(notifications.toasts()[0].message, "error 1");
+        assert_eq!(notifications.toasts().back().unwrap().message, "newest");
+        assert_eq!(notifications.hidden_errors(), 1);
+
+        notifications.clear();
+        assert_eq!(notifications.hidden_errors(), 0);
+    }
+
+    #[test]
+    fn identical_module_errors_share_a_toast() {
+        let mut notifications = Notifications::default();
+        let window = Duration::from_secs(5);
+        for module in 0..12 {
+            notifications.push_module_error(112, module, String::from("clock lost"), window);
+        }
+        // A repeat from the same module is not counted twice
+        notifications.push_module_error(112, 3, String::from("clock lost"), window);
+        notifications.push_module_error(7, 4, String::from("other"), window);
+        assert_eq!(notifications.toasts().len(), 2);
+        let grouped = &notifications.toasts()[0];
+        assert_eq!(grouped.message, "ECC error 112 on 12 modules");
+        assert_eq!(grouped.modules, (0..12).collect::<Vec<usize>>());
+        assert_eq!(notifications.toasts()[1].message, "Module 4: other");
+    }
+
+    #[test]
+    fn module_errors_outside_the_window_are_not_grouped() {
+        let mut notifications = Notifications::default();
+        notifications.push_module_error(112, 0, String::from("clock lost"), Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(5));
+        notifications.push_module_error(112, 1, String::from("clock lost"), Duration::ZERO);
+        assert_eq!(notifications.toasts().len(), 2);
+        assert_eq!(notifications.toasts()[1].message, "Module 1: clock lost");
+    }
+}
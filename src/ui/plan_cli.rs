@@ -0,0 +1,181 @@
+//! The headless `plan` subcommand. Connects to the ECC servers of a saved config, waits for
+//! every module to report its status, and prints the operations the next forward transition
+//! of the system would send, without sending any of them.
+use super::config::Config;
+use super::error::PlanError;
+use super::setup_wizard::saved_config_path;
+use crate::envoy::embassy::Embassy;
+use crate::envoy::runtime::RuntimeConfig;
+use crate::envoy::status_manager::StatusManager;
+use crate::envoy::transition::{plan_forward_transition, poll_embassy, TransitionPlan};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use tokio::runtime::Runtime;
+
+/// How often the statuses are polled while waiting for the modules to report
+const POLL_PERIOD: Duration = Duration::from_millis(50);
+
+/// The options of the plan subcommand
+#[derive(Debug, Default, PartialEq)]
+pub struct PlanArgs {
+    pub json: bool,
+    pub config: Option<PathBuf>,
+}
+
+impl PlanArgs {
+    /// Parse the arguments following `plan`
+    pub fn parse(args: &[String]) -> Result<Self, PlanError> {
+        let mut parsed = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--json" => parsed.json = true,
+                "--config" => match args.next() {
+                    Some(path) => parsed.config = Some(PathBuf::from(path)),
+                    None => {
+                        return Err(PlanError::BadArguments(String::from(
+                            "--config needs a path",
+                        )))
+                    }
+                },
+                other => return Err(PlanError::BadArguments(format!("unknown argument {other}"))),
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Run the plan subcommand, printing the plan to stdout. Returns the process exit code. The
+/// runtime was built from runtime_config, and is rebuilt if the planned config differs.
+pub fn run_plan_command(args: &[String], runtime: Runtime, runtime_config: RuntimeConfig) -> i32 {
+    let result = PlanArgs::parse(args).and_then(|args| {
+        let plan = plan_from_servers(&args, runtime, runtime_config)?;
+        if args.json {
+            println!("{:#}", plan.to_json());
+        } else {
+            println!("{plan}");
+        }
+        Ok(())
+    });
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("{e}");
+            1
+        }
+    }
+}
+
+/// Connect to the servers of the config, wait for every ECC module to report, and plan the
+/// forward transition from the reported statuses
+fn plan_from_servers(
+    args: &PlanArgs,
+    runtime: Runtime,
+    runtime_config: RuntimeConfig,
+) -> Result<TransitionPlan, PlanError> {
+    let path = args
+        .config
+        .clone()
+        .or_else(saved_config_path)
+        .ok_or(PlanError::NoConfig)?;
+    let mut config = Config::new();
+    config.load(path)?;
+    if !config.families.ecc {
+        return Err(PlanError::EccDisabled);
+    }
+    let num_cobos = config.num_cobos.max(1);
+    let poll_interval = config.poll_interval_sec.max(1);
+    let mut embassy = Embassy::new(runtime, runtime_config);
+    embassy.apply_runtime_config(&config.runtime)?;
+    embassy.startup(
+        &config.experiment,
+        num_cobos,
+        &config.network,
+        &config.families,
+        Duration::from_secs(poll_interval),
+    )?;
+    let mut status = StatusManager::new(num_cobos);
+    status.set_monitored_families(&config.families);
+    status.set_readiness_policy(config.readiness);
+
+    // Give every envoy a couple of polls to answer
+    let timeout = Duration::from_secs(2 * poll_interval + 5);
+    let started = Instant::now();
+    let waited = loop {
+        if let Err(e) = poll_embassy(&mut embassy, &mut status) {
+            break Err(PlanError::from(e));
+        }
+        let silent: Vec<String> = status
+            .get_ecc_message_stats()
+            .iter()
+            .enumerate()
+            .filter(|(_, stats)| stats.messages == 0)
+            .map(|(id, _)| id.to_string())
+            .collect();
+        if silent.is_empty() {
+            break Ok(());
+        }
+        if started.elapsed() > timeout {
+            break Err(PlanError::NotReported(silent.join(", "), timeout.as_secs()));
+        }
+        std::thread::sleep(POLL_PERIOD);
+    };
+    let plan = waited.and_then(|()| {
+        Ok(plan_forward_transition(
+            &status.get_system_ecc_status(),
+            &status.ecc_snapshot(),
+        )?)
+    });
+    if let Err(e) = embassy.shutdown() {
+        tracing::error!("Could not shut down the embassy after planning: {e}");
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn arguments_are_parsed() {
+        assert_eq!(PlanArgs::parse(&[]).unwrap(), PlanArgs::default());
+        assert_eq!(
+            PlanArgs::parse(&args(&["--config", "exp.yml", "--json"])).unwrap(),
+            PlanArgs {
+                json: true,
+                config: Some(PathBuf::from("exp.yml")),
+            }
+        );
+    }
+
+    #[test]
+    fn bad_arguments_are_rejected() {
+        assert!(matches!(
+            PlanArgs::parse(&args(&["--config"])),
+            Err(PlanError::BadArguments(_))
+        ));
+        assert!(matches!(
+            PlanArgs::parse(&args(&["--yaml"])),
+            Err(PlanError::BadArguments(_))
+        ));
+    }
+
+    #[test]
+    fn missing_config_fails() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let missing = PlanArgs {
+            json: true,
+            config: Some(PathBuf::from("/nonexistent/envoy/config.yml")),
+        };
+        assert!(matches!(
+            plan_from_servers(&missing, runtime, RuntimeConfig::default()),
+            Err(PlanError::BadConfig(_))
+        ));
+    }
+}
@@ -0,0 +1,184 @@
+//! The checklist shown when the operator starts a run. Hard checks must pass before the run
+//! can be started, soft checks can be overridden.
+use super::app::EnvoyApp;
+use super::config::{Config, DEFAULT_DESCRIPTION};
+use crate::command::config_check::XcfgIssue;
+use crate::envoy::status_manager::StatusManager;
+use crate::envoy::surveyor_status::SurveyorStatus;
+use eframe::egui::{Button, Color32, Grid, RichText, Window};
+
+/// One line of the checklist
+#[derive(Debug, Clone)]
+pub struct CheckItem {
+    pub name: &'static str,
+    pub passed: bool,
+    /// Hard checks can't be overridden
+    pub hard: bool,
+    pub detail: String,
+}
+
+/// Compute the checklist from the current status and config, and the last check of the ECC
+/// configuration files
+pub fn compute_checklist(
+    config: &Config,
+    status: &StatusManager,
+    config_file_issues: &[XcfgIssue],
+) -> Vec<CheckItem> {
+    let mut checks = vec![];
+    let system = status.get_system_ecc_status();
+    checks.push(CheckItem {
+        name: "All modules Ready",
+        passed: status.is_system_ready(),
+        hard: true,
+        detail: format!("The system is {system}"),
+    });
+
+    if status.get_monitored_families().surveyor {
+        let offline: Vec<String> = (0..status.get_surveyor_status_response().len())
+            .filter(|id| !matches!(status.get_surveyor_status(*id), SurveyorStatus::Online))
+            .map(|id| id.to_string())
+            .collect();
+        checks.push(CheckItem {
+            name: "All data routers Online",
+            passed: offline.is_empty(),
+            hard: true,
+            detail: if offline.is_empty() {
+                String::new()
+            } else {
+                format!("Not online: {}", offline.join(", "))
+            },
+        });
+
+        let full = config.disk_space_issues(status.get_surveyor_status_response());
+        checks.push(CheckItem {
+            name: "Enough free disk space",
+            passed: full.is_empty(),
+            hard: true,
+            detail: full.join(", "),
+        });
+    }
+
+    if !config.expected_asads.is_empty() {
+        let mismatched: Vec<String> = config
+            .expected_asads
+            .iter()
+            .filter_map(|(id, expected)| match status.get_asad_summary(*id) {
+                Some(summary) => summary
+                    .discrepancy(*expected)
+                    .map(|discrepancy| format!("module {id}: {discrepancy}")),
+                None => Some(format!("module {id}: no AsAd information")),
+            })
+            .collect();
+        checks.push(CheckItem {
+            name: "AsAds as expected",
+            passed: mismatched.is_empty(),
+            hard: false,
+            detail: mismatched.join(", "),
+        });
+    }
+
+    if status.get_monitored_families().ecc {
+        checks.push(CheckItem {
+            name: "ECC config files valid",
+            passed: config_file_issues.is_empty(),
+            hard: false,
+            detail: config_file_issues
+                .iter()
+                .map(|issue| issue.to_string())
+                .collect::<Vec<String>>()
+                .join(", "),
+        });
+    }
+
+    let description = config.description.trim();
+    checks.push(CheckItem {
+        name: "Description written",
+        passed: !description.is_empty() && description != DEFAULT_DESCRIPTION,
+        hard: false,
+        detail: String::from("The description is still the default"),
+    });
+
+    let empty: Vec<&str> = config
+        .required_fields
+        .iter()
+        .filter(|field| {
+            config
+                .fields
+                .get(*field)
+                .is_none_or(|value| value.trim().is_empty())
+        })
+        .map(String::as_str)
+        .collect();
+    checks.push(CheckItem {
+        name: "Required fields filled",
+        passed: empty.is_empty(),
+        hard: false,
+        detail: format!("Empty: {}", empty.join(", ")),
+    });
+    checks
+}
+
+/// Render the pre-run checklist, if it was requested
+pub fn render_pre_run_checklist(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
+    let mut override_soft = match app.pre_run_checklist {
+        Some(override_soft) => override_soft,
+        None => return,
+    };
+    let checks = compute_checklist(&app.config, &app.status, &app.config_file_issues);
+    let hard_passed = checks.iter().filter(|c| c.hard).all(|c| c.passed);
+    let soft_passed = checks.iter().filter(|c| !c.hard).all(|c| c.passed);
+    let mut start = false;
+    let mut cancel = false;
+    Window::new(format!("Start Run {}", app.config.run_number))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(eframe::egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            Grid::new("Pre_Run_Checklist_Grid")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    for check in checks.iter() {
+                        let (icon, color) = match (check.passed, check.hard) {
+                            (true, _) => ("\u{2714}", Color32::GREEN),
+                            (false, true) => ("\u{2716}", Color32::RED),
+                            (false, false) => ("\u{26A0}", Color32::YELLOW),
+                        };
+                        ui.label(RichText::new(icon).color(color).size(16.0));
+                        ui.label(RichText::new(check.name).size(16.0));
+                        if check.passed {
+                            ui.label("");
+                        } else {
+                            ui.label(RichText::new(&check.detail).color(color));
+                        }
+                        ui.end_row();
+                    }
+                });
+            ui.separator();
+            if !soft_passed {
+                ui.checkbox(&mut override_soft, "Start anyway, ignoring the warnings");
+            }
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(
+                        hard_passed && (soft_passed || override_soft),
+                        Button::new(RichText::new("Confirm").color(Color32::GREEN).size(16.0)),
+                    )
+                    .clicked()
+                {
+                    start = true;
+                }
+                if ui.button(RichText::new("Cancel").size(16.0)).clicked() {
+                    cancel = true;
+                }
+            });
+        });
+    if start {
+        app.pre_run_checklist = None;
+        app.start_run();
+    } else if cancel {
+        app.pre_run_checklist = None;
+    } else {
+        app.pre_run_checklist = Some(override_soft);
+    }
+}
@@ -0,0 +1,77 @@
+use crate::envoy::surveyor_envoy::SurveyorResponse;
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+/// Data rate (MB/s) at or below which a data router is considered to not be writing
+const STALLED_RATE_MB: f64 = 0.001;
+
+/// A change in the alarm state of a data router
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateAlarmChange {
+    Raised(usize),
+    Cleared(usize),
+}
+
+/// Watches the data rates during a run for data routers which stop writing while the others
+/// keep going. A data router is alarmed once it has been stalled for the grace period. Nothing
+/// is alarmed during the first grace period of the run, while the buffers fill.
+#[derive(Debug, Clone)]
+pub struct RateAlarm {
+    grace: Duration,
+    run_start: Instant,
+    stalled_since: Vec<Option<Instant>>,
+    alarmed: BTreeSet<usize>,
+}
+
+impl RateAlarm {
+    pub fn new(grace: Duration) -> Self {
+        Self {
+            grace,
+            run_start: Instant::now(),
+            stalled_since: vec![],
+            alarmed: BTreeSet::new(),
+        }
+    }
+
+    /// Sample the data rates, returning the alarms which were raised or cleared
+    pub fn sample(&mut self, routers: &[SurveyorResponse]) -> Vec<RateAlarmChange> {
+        let now = Instant::now();
+        let mut changes = vec![];
+        if now - self.run_start < self.grace {
+            return changes;
+        }
+        self.stalled_since.resize(routers.len(), None);
+        let any_writing = routers
+            .iter()
+            .any(|router| router.data_rate > STALLED_RATE_MB);
+        for (id, router) in routers.iter().enumerate() {
+            if router.data_rate > STALLED_RATE_MB {
+                self.stalled_since[id] = None;
+                if self.alarmed.remove(&id) {
+                    changes.push(RateAlarmChange::Cleared(id));
+                }
+                continue;
+            }
+            //With nothing writing a stall can't be told apart from no beam
+            if !any_writing {
+                self.stalled_since[id] = None;
+                continue;
+            }
+            let since = *self.stalled_since[id].get_or_insert(now);
+            if now - since >= self.grace && self.alarmed.insert(id) {
+                changes.push(RateAlarmChange::Raised(id));
+            }
+        }
+        changes
+    }
+
+    /// Is the data router alarmed
+    pub fn is_alarmed(&self, id: usize) -> bool {
+        self.alarmed.contains(&id)
+    }
+
+    /// The alarmed data routers
+    pub fn alarmed(&self) -> &BTreeSet<usize> {
+        &self.alarmed
+    }
+}
@@ -0,0 +1,138 @@
+//! Coalescing of the repaints requested when messages arrive from the envoys. A burst of
+//! messages (i.e. every envoy reporting at once) should cause one repaint, not one per message.
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Repaint requests within this long of a pending repaint are merged into it
+pub const REPAINT_COALESCE_WINDOW: Duration = Duration::from_millis(50);
+/// The span over which frames are counted for the diagnostics
+const FRAME_COUNT_SPAN: Duration = Duration::from_secs(60);
+
+/// Tracks the earliest pending repaint deadline, and counts frames for the diagnostics
+#[derive(Debug)]
+pub struct RepaintScheduler {
+    window: Duration,
+    pending: Option<Instant>,
+    requests: u64,
+    coalesced: u64,
+    frames: VecDeque<Instant>,
+}
+
+impl RepaintScheduler {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            pending: None,
+            requests: 0,
+            coalesced: 0,
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// Request a repaint. Returns the delay to schedule the repaint after, or None if the
+    /// request was merged into a repaint which is already pending.
+    pub fn request(&mut self, now: Instant) -> Option<Duration> {
+        self.requests += 1;
+        match self.pending {
+            Some(deadline) if deadline >= now => {
+                self.coalesced += 1;
+                None
+            }
+            _ => {
+                self.pending = Some(now + self.window);
+                Some(self.window)
+            }
+        }
+    }
+
+    /// Record a frame. A pending repaint whose deadline has passed is complete.
+    pub fn frame(&mut self, now: Instant) {
+        if self.pending.is_some_and(|deadline| deadline <= now) {
+            self.pending = None;
+        }
+        self.frames.push_back(now);
+        while self
+            .frames
+            .front()
+            .is_some_and(|time| now.duration_since(*time) > FRAME_COUNT_SPAN)
+        {
+            self.frames.pop_front();
+        }
+    }
+
+    /// The number of frames drawn in the last minute
+    pub fn frames_per_minute(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// The total number of repaint requests, and how many of them were merged into another
+    pub fn request_counts(&self) -> (u64, u64) {
+        (self.requests, self.coalesced)
+    }
+}
+
+/// Create the hook the embassy calls when a message arrives, which requests a coalesced repaint
+pub fn repaint_hook(
+    scheduler: Arc<Mutex<RepaintScheduler>>,
+    ctx: eframe::egui::Context,
+) -> impl Fn() + Send + Sync + 'static {
+    move || {
+        let delay = match scheduler.lock() {
+            Ok(mut scheduler) => scheduler.request(Instant::now()),
+            Err(_) => return,
+        };
+        if let Some(delay) = delay {
+            ctx.request_repaint_after(delay);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_millis(50);
+
+    #[test]
+    fn burst_is_coalesced_into_one_repaint() {
+        let mut scheduler = RepaintScheduler::new(WINDOW);
+        let start = Instant::now();
+        assert_eq!(scheduler.request(start), Some(WINDOW));
+        for ms in 1..23 {
+            assert_eq!(scheduler.request(start + Duration::from_millis(ms)), None);
+        }
+        assert_eq!(scheduler.request_counts(), (23, 22));
+    }
+
+    #[test]
+    fn request_after_the_repaint_schedules_another() {
+        let mut scheduler = RepaintScheduler::new(WINDOW);
+        let start = Instant::now();
+        scheduler.request(start);
+        scheduler.frame(start + WINDOW);
+        assert_eq!(scheduler.request(start + WINDOW), Some(WINDOW));
+        // A deadline which passed without a frame does not swallow requests either
+        assert_eq!(scheduler.request(start + 3 * WINDOW), Some(WINDOW));
+        assert_eq!(scheduler.request_counts(), (3, 0));
+    }
+
+    #[test]
+    fn early_frame_keeps_the_repaint_pending() {
+        let mut scheduler = RepaintScheduler::new(WINDOW);
+        let start = Instant::now();
+        scheduler.request(start);
+        scheduler.frame(start + WINDOW / 2);
+        assert_eq!(scheduler.request(start + WINDOW / 2), None);
+    }
+
+    #[test]
+    fn frames_are_counted_over_the_last_minute() {
+        let mut scheduler = RepaintScheduler::new(WINDOW);
+        let start = Instant::now();
+        for second in 0..90 {
+            scheduler.frame(start + Duration::from_secs(second));
+        }
+        assert_eq!(scheduler.frames_per_minute(), 61);
+    }
+}
@@ -1,6 +1,9 @@
 use super::app::EnvoyApp;
-use crate::envoy::surveyor_status::{SurveyorDiskStatus, SurveyorStatus};
-use eframe::egui::{CentralPanel, Color32, RichText};
+use crate::envoy::surveyor_status::{DiskHealthState, SurveyorDiskStatus};
+use eframe::egui::{Button, CentralPanel, Color32, RichText};
+
+/// The id of the data router table, which must differ from the other tables in the central panel
+const DATA_ROUTER_TABLE_ID: &str = "Data_Router_Table";
 
 /// Render the panel displaying data router status, this is the central panel in the UI
 pub fn render_data_router_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
@@ -16,10 +19,38 @@ pub fn render_data_router_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context)
                 .color(&surv_system_stat)
                 .size(16.0),
         );
+        if !app.status.is_system_running() && app.status.get_monitored_families().surveyor {
+            let low = app
+                .config
+                .disk_space_issues(app.status.get_surveyor_status_response());
+            if !low.is_empty() {
+                ui.label(
+                    RichText::new(format!(
+                        "Low disk space, runs will not start: {}",
+                        low.join(", ")
+                    ))
+                    .size(16.0)
+                    .color(Color32::GOLD),
+                );
+            }
+        }
+        if let Some(alarm) = app.rate_alarm.as_ref() {
+            if !alarm.alarmed().is_empty() {
+                let ids: Vec<String> = alarm.alarmed().iter().map(|id| id.to_string()).collect();
+                ui.label(
+                    RichText::new(format!(
+                        "No data from Data Router {} while the others are writing!",
+                        ids.join(", ")
+                    ))
+                    .size(18.0)
+                    .color(Color32::RED),
+                );
+            }
+        }
         ui.separator();
         ui.label(RichText::new("Status Board").size(16.0));
         ui.separator();
-        ui.push_id(1, |ui| {
+        ui.push_id(DATA_ROUTER_TABLE_ID, |ui| {
             egui_extras::TableBuilder::new(ui)
                 .striped(true)
                 .column(egui_extras::Column::auto().at_least(90.0).resizable(true))
@@ -31,6 +62,7 @@ pub fn render_data_router_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context)
                 .column(egui_extras::Column::auto().at_least(140.0).resizable(true))
                 .column(egui_extras::Column::auto().at_least(100.0).resizable(true))
                 .column(egui_extras::Column::auto().at_least(100.0).resizable(true))
+                .column(egui_extras::Column::auto().at_least(60.0).resizable(true))
                 .header(40.0, |mut header| {
                     header.col(|ui| {
                         ui.heading("Envoy");
@@ -59,6 +91,9 @@ pub fn render_data_router_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context)
                     header.col(|ui| {
                         ui.heading("Disk Size");
                     });
+                    header.col(|ui| {
+                        ui.heading("Health");
+                    });
                 })
                 .body(|body| {
                     let surveyor_status = app.status.get_surveyor_status_response();
@@ -67,13 +102,22 @@ pub fn render_data_router_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context)
                         let status = &surveyor_status[ridx];
                         let disk_stat = SurveyorDiskStatus::from(status.disk_status.as_str());
                         row.col(|ui| {
-                            ui.label(
-                                RichText::new(format!("Data Router {}", ridx))
-                                    .color(Color32::LIGHT_GREEN),
-                            );
+                            if ui
+                                .add(
+                                    Button::new(
+                                        RichText::new(format!("Data Router {}", ridx))
+                                            .color(Color32::LIGHT_GREEN),
+                                    )
+                                    .frame(false),
+                                )
+                                .on_hover_text("Show module details")
+                                .clicked()
+                            {
+                                app.module_windows.insert(ridx);
+                            }
                         });
                         row.col(|ui| {
-                            let surv_type = SurveyorStatus::from(status.state);
+                            let surv_type = app.status.get_surveyor_status(ridx);
                             ui.label(RichText::new(format!("{}", surv_type)).color(&surv_type));
                         });
                         row.col(|ui| {
@@ -91,7 +135,13 @@ pub fn render_data_router_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context)
                             )));
                         });
                         row.col(|ui| {
-                            ui.label(RichText::new(format!("{:.3}", status.data_rate)));
+                            let rate = RichText::new(format!("{:.3}", status.data_rate));
+                            if app.rate_alarm.as_ref().is_some_and(|a| a.is_alarmed(ridx)) {
+                                ui.label(rate.color(Color32::RED).strong())
+                                    .on_hover_text("This data router stopped writing");
+                            } else {
+                                ui.label(rate);
+                            }
                         });
                         row.col(|ui| {
                             ui.label(RichText::new(status.percent_used.clone()));
@@ -101,6 +151,26 @@ pub fn render_data_router_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context)
                                 status.disk_space as f64,
                             )));
                         });
+                        row.col(|ui| match &status.disk_health {
+                            Some(health) => {
+                                let icon = match health.state {
+                                    DiskHealthState::Healthy => "✔",
+                                    DiskHealthState::Warning => "⚠",
+                                    DiskHealthState::Failing => "✖",
+                                };
+                                let hover = if health.details.is_empty() {
+                                    health.state.to_string()
+                                } else {
+                                    format!("{}: {}", health.state, health.details)
+                                };
+                                ui.label(RichText::new(icon).size(18.0).color(&health.state))
+                                    .on_hover_text(hover);
+                            }
+                            None => {
+                                ui.label(RichText::new("–").color(Color32::LIGHT_GRAY))
+                                    .on_hover_text("Disk health is not reported by this surveyor");
+                            }
+                        });
                     })
                 });
         });
@@ -0,0 +1,98 @@
+//! Per-run log files. The RunLogFile is a tracing writer whose destination is switched by the
+//! app: logs/<experiment>/run_<NNNN>.log while a run is in progress and logs/<experiment>/idle.log
+//! between runs, so that the log of a failed run can be found after the fact.
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tracing_subscriber::fmt::MakeWriter;
+
+const RUN_LOG_DIRECTORY: &str = "logs";
+const IDLE_LOG_NAME: &str = "idle.log";
+
+/// A handle to the current log file. Clones share the same file, one is given to the tracing
+/// subscriber and one is kept by the app to switch files.
+#[derive(Debug, Clone, Default)]
+pub struct RunLogFile {
+    file: Arc<Mutex<Option<File>>>,
+}
+
+impl RunLogFile {
+    /// Direct the log to the file for the given run
+    pub fn open_run(&self, experiment: &str, run_number: i32) {
+        self.switch(experiment, &format!("run_{run_number:04}.log"));
+    }
+
+    /// Direct the log to the between-runs file of the experiment
+    pub fn open_idle(&self, experiment: &str) {
+        self.switch(experiment, IDLE_LOG_NAME);
+    }
+
+    /// Flush and close the current file and open the new one. If the new file cannot be
+    /// opened only a warning is issued; the terminal and daily logs are unaffected.
+    fn switch(&self, experiment: &str, name: &str) {
+        let path = PathBuf::from(RUN_LOG_DIRECTORY).join(experiment);
+        let opened = std::fs::create_dir_all(&path).and_then(|_| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path.join(name))
+        });
+        let (new_file, error) = match opened {
+            Ok(file) => (Some(file), None),
+            Err(e) => (None, Some(e)),
+        };
+        // Never log while holding the lock, the subscriber writes through it
+        let previous = match self.file.lock() {
+            Ok(mut guard) => std::mem::replace(&mut *guard, new_file),
+            Err(_) => None,
+        };
+        if let Some(mut previous) = previous {
+            let _ = previous.flush();
+        }
+        match error {
+            None => tracing::info!("Logging to {}", path.join(name).display()),
+            Some(e) => tracing::warn!(
+                "Could not open log file {}, it will not be written: {e}",
+                path.join(name).display()
+            ),
+        }
+    }
+}
+
+/// Writes a single event to the current file, if there is one
+pub struct RunLogWriter {
+    file: Arc<Mutex<Option<File>>>,
+}
+
+impl Write for RunLogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self.file.lock() {
+            Ok(mut guard) => match guard.as_mut() {
+                Some(file) => file.write(buf),
+                None => Ok(buf.len()),
+            },
+            Err(_) => Ok(buf.len()),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.file.lock() {
+            Ok(mut guard) => match guard.as_mut() {
+                Some(file) => file.flush(),
+                None => Ok(()),
+            },
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+impl<'a> MakeWriter<'a> for RunLogFile {
+    type Writer = RunLogWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RunLogWriter {
+            file: self.file.clone(),
+        }
+    }
+}
@@ -16,12 +16,43 @@ pub fn render_run_log_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
             ui.text_edit_singleline(&mut app.new_field_name);
         });
         ui.separator();
+        ui.label(RichText::new("Run Tags").size(16.0));
+        ui.horizontal(|ui| {
+            if ui.button(RichText::new("Add Tag").size(16.0)).clicked() {
+                app.config.add_tag(&app.new_tag_name);
+                app.new_tag_name.clear();
+            }
+            ui.text_edit_singleline(&mut app.new_tag_name);
+        });
+        let mut removed = None;
+        ui.horizontal_wrapped(|ui| {
+            for tag in app.config.tags.iter() {
+                if ui
+                    .button(format!("{tag} \u{2715}"))
+                    .on_hover_text("Remove this tag")
+                    .clicked()
+                {
+                    removed = Some(tag.clone());
+                }
+            }
+        });
+        if let Some(tag) = removed {
+            app.config.remove_tag(&tag);
+        }
+        ui.separator();
         Grid::new("Runlog Grid").num_columns(2).show(ui, |ui| {
+            let mut edited = None;
             for (field, value) in app.config.fields.iter_mut() {
                 ui.label(RichText::new(field).size(16.0));
-                ui.text_edit_singleline(value);
+                if ui.text_edit_singleline(value).changed() {
+                    edited = Some((field.clone(), value.clone()));
+                }
                 ui.end_row();
             }
+            if let Some((field, value)) = edited {
+                let changed = app.config.set_field(&field, &value);
+                app.notify_sanitized(&field, changed);
+            }
         });
     });
 }
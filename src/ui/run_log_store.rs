@@ -0,0 +1,558 @@
+//! Storage for the run log, the record of every run of an experiment. The CSV table is the
+//! default; the SQLite database tolerates several instances of the app writing at once and
+//! updates a run which is recorded twice instead of duplicating it.
+use super::error::ConfigError;
+use super::sanitize::{csv_row, parse_csv};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The directory the run logs are kept in, relative to the working directory
+const TABLE_DIRECTORY: &str = "tables/";
+
+/// Columns added to the runs table after it was first released, which older databases lack
+const SQLITE_ADDED_COLUMNS: [(&str, &str); 5] = [
+    ("run_tag", "TEXT NOT NULL DEFAULT 'Good'"),
+    ("operator", "TEXT NOT NULL DEFAULT ''"),
+    ("file_check", "TEXT NOT NULL DEFAULT ''"),
+    ("start_attempts", "INTEGER NOT NULL DEFAULT 0"),
+    ("start_failure", "TEXT NOT NULL DEFAULT ''"),
+];
+
+/// How long a SQLite write waits for another writer to finish before failing
+const SQLITE_BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The columns of the run log which precede the user fields
+const TABLE_COLUMNS: [&str; 12] = [
+    "Run",
+    "Run UUID",
+    "Note",
+    "Operator",
+    "Tags",
+    "Run Tag",
+    "Duration",
+    "Livetime",
+    "Stop Reason",
+    "File Check",
+    "Start Attempts",
+    "Start Failure",
+];
+
+/// Where the run log is stored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum RunLogBackend {
+    #[default]
+    Csv,
+    Sqlite,
+}
+
+/// The operator's verdict on a run, so that junk runs can be marked while they are fresh in memory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum RunTag {
+    #[default]
+    Good,
+    Junk,
+    Calibration,
+    Test,
+}
+
+impl std::fmt::Display for RunTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Good => write!(f, "Good"),
+            Self::Junk => write!(f, "Junk"),
+            Self::Calibration => write!(f, "Calibration"),
+            Self::Test => write!(f, "Test"),
+        }
+    }
+}
+
+impl RunTag {
+    pub const ALL: [RunTag; 4] = [Self::Good, Self::Junk, Self::Calibration, Self::Test];
+
+    /// Parse a run tag as it is written in the run log
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|tag| tag.to_string() == name)
+    }
+}
+
+/// A run as it is recorded in the run log
+#[derive(Debug, Clone)]
+pub struct RunRecord {
+    pub run_number: i32,
+    pub run_uuid: String,
+    pub note: String,
+    pub operator: String,
+    pub tags: String,
+    pub run_tag: RunTag,
+    pub duration_sec: u64,
+    pub livetime: Option<f64>,
+    pub stop_reason: String,
+    /// The result of the file count check, flagging suspect data routers
+    pub file_check: String,
+    /// The attempts it took to start the run, 0 if unknown
+    pub start_attempts: u32,
+    /// The step and reason the last failed start attempt of the run failed at
+    pub start_failure: String,
+    pub fields: BTreeMap<String, String>,
+}
+
+impl RunRecord {
+    /// The values of the record in table order, followed by the fields
+    fn values(&self) -> Vec<String> {
+        let mut values = vec![
+            self.run_number.to_string(),
+            self.run_uuid.clone(),
+            self.note.clone(),
+            self.operator.clone(),
+            self.tags.clone(),
+            self.run_tag.to_string(),
+            self.duration_sec.to_string(),
+            self.livetime.map(|l| format!("{l:.4}")).unwrap_or_default(),
+            self.stop_reason.clone(),
+            self.file_check.clone(),
+            self.start_attempts.to_string(),
+            self.start_failure.clone(),
+        ];
+        values.extend(self.fields.values().cloned());
+        values
+    }
+}
+
+/// A place the run log of an experiment can be kept
+pub trait RunLogStore {
+    /// The file the run log of the experiment is kept in
+    fn path(&self, experiment: &str) -> PathBuf;
+
+    /// Record a run in the run log of the experiment
+    fn write_run(&mut self, experiment: &str, run: &RunRecord) -> Result<(), ConfigError>;
+
+    /// Read the run log of the experiment as a table, in the order the runs were recorded.
+    /// The first record is the header.
+    fn read_runs(&self, experiment: &str) -> Result<Vec<Vec<String>>, ConfigError>;
+}
+
+/// The run log as a CSV table, tables/<experiment>.csv
+#[derive(Debug, Clone, Default)]
+pub struct CsvRunLog {
+    /// The table path and header which were last validated this session
+    validated_table: Option<(PathBuf, Vec<String>)>,
+}
+
+impl CsvRunLog {
+    /// Compose the header of the table from the fields of a run
+    fn header(run: &RunRecord) -> Vec<String> {
+        let mut header: Vec<String> = TABLE_COLUMNS.iter().map(|c| c.to_string()).collect();
+        header.extend(run.fields.keys().cloned());
+        header
+    }
+
+    /// Make sure the table exists with the given header. The table header is only validated
+    /// once per session (or when the fields change), and the table is only rewritten if the
+    /// existing header does not match.
+    fn validate_table(
+        &mut self,
+        table_path: &Path,
+        header: Vec<String>,
+    ) -> Result<(), ConfigError> {
+        if let Some((path, validated_header)) = &self.validated_table {
+            if path == table_path && *validated_header == header && table_path.exists() {
+                return Ok(());
+            }
+        }
+
+        if let Some(table_dir) = table_path.parent().filter(|dir| !dir.exists()) {
+            std::fs::create_dir_all(table_dir)?;
+        }
+
+        if !table_path.exists() {
+            let mut file = File::create(table_path)?;
+            file.write_all(format!("{}\n", csv_row(&header)).as_bytes())?;
+        } else {
+            let records = parse_csv(&std::fs::read_to_string(table_path)?);
+            if let Some(old_header) = records.first().filter(|old| **old != header) {
+                tracing::info!("Config table header changed, reformatting the table...");
+                let records: Vec<Vec<String>> = records[1..]
+                    .iter()
+                    .map(|record| Self::migrate_record(old_header, &header, record))
+                    .collect();
+                Self::rewrite_table(table_path, &header, &records)?;
+            }
+        }
+
+        self.validated_table = Some((table_path.to_path_buf(), header));
+        Ok(())
+    }
+
+    /// Move the values of a record written with an old header into the columns of the new
+    /// header, by column name. Columns which are new are left empty.
+    fn migrate_record(old_header: &[String], header: &[String], record: &[String]) -> Vec<String> {
+        header
+            .iter()
+            .map(|name| {
+                old_header
+                    .iter()
+                    .position(|old| old == name)
+                    .and_then(|column| record.get(column))
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    /// Rewrite the table with a new header. The table is written to a temporary file which then replaces
+    /// the original, so that a failure part way through never truncates the existing table.
+    fn rewrite_table(
+        table_path: &Path,
+        header: &[String],
+        records: &[Vec<String>],
+    ) -> Result<(), ConfigError> {
+        let temp_path = table_path.with_extension("csv.tmp");
+        let mut file = File::create(&temp_path)?;
+        file.write_all(format!("{}\n", csv_row(header)).as_bytes())?;
+        for record in records {
+            file.write_all(format!("{}\n", csv_row(record)).as_bytes())?;
+        }
+        file.sync_all()?;
+        std::fs::rename(&temp_path, table_path)?;
+        Ok(())
+    }
+}
+
+impl RunLogStore for CsvRunLog {
+    fn path(&self, experiment: &str) -> PathBuf {
+        PathBuf::from(TABLE_DIRECTORY).join(format!("{experiment}.csv"))
+    }
+
+    fn write_run(&mut self, experiment: &str, run: &RunRecord) -> Result<(), ConfigError> {
+        let path = self.path(experiment);
+        self.validate_table(&path, Self::header(run))?;
+        let mut file = std::fs::OpenOptions::new().append(true).open(path)?;
+        file.write_all(format!("{}\n", csv_row(&run.values())).as_bytes())?;
+        Ok(())
+    }
+
+    fn read_runs(&self, experiment: &str) -> Result<Vec<Vec<String>>, ConfigError> {
+        Ok(parse_csv(&std::fs::read_to_string(self.path(experiment))?))
+    }
+}
+
+/// The run log as a SQLite database, <directory>/<experiment>.db, with a runs table keyed on the
+/// run number. The user fields are stored as a JSON object, as they change between runs.
+#[derive(Debug, Clone)]
+pub struct SqliteRunLog {
+    directory: PathBuf,
+}
+
+impl Default for SqliteRunLog {
+    fn default() -> Self {
+        Self::new(TABLE_DIRECTORY)
+    }
+}
+
+impl SqliteRunLog {
+    /// Keep the databases in the given directory, which is created on the first write
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    /// Open the database, creating it and the runs table if needed
+    fn open(path: &Path) -> Result<Connection, ConfigError> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let connection = Connection::open(path)?;
+        connection.busy_timeout(SQLITE_BUSY_TIMEOUT)?;
+        connection.execute(
+            "CREATE TABLE IF NOT EXISTS runs (
+                run_number INTEGER PRIMARY KEY,
+                run_uuid TEXT NOT NULL,
+                note TEXT NOT NULL,
+                operator TEXT NOT NULL DEFAULT '',
+                tags TEXT NOT NULL,
+                run_tag TEXT NOT NULL DEFAULT 'Good',
+                duration_sec INTEGER NOT NULL,
+                livetime REAL,
+                stop_reason TEXT NOT NULL,
+                file_check TEXT NOT NULL DEFAULT '',
+                start_attempts INTEGER NOT NULL DEFAULT 0,
+                start_failure TEXT NOT NULL DEFAULT '',
+                fields TEXT NOT NULL,
+                recorded_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )?;
+        for (column, definition) in SQLITE_ADDED_COLUMNS {
+            let exists: bool = connection.query_row(
+                "SELECT COUNT(*) FROM pragma_table_info('runs') WHERE name = ?1",
+                params![column],
+                |row| row.get(0),
+            )?;
+            if !exists {
+                connection.execute(
+                    &format!("ALTER TABLE runs ADD COLUMN {column} {definition}"),
+                    [],
+                )?;
+            }
+        }
+        Ok(connection)
+    }
+}
+
+impl RunLogStore for SqliteRunLog {
+    fn path(&self, experiment: &str) -> PathBuf {
+        self.directory.join(format!("{experiment}.db"))
+    }
+
+    fn write_run(&mut self, experiment: &str, run: &RunRecord) -> Result<(), ConfigError> {
+        let connection = Self::open(&self.path(experiment))?;
+        let existing: Option<String> = connection
+            .query_row(
+                "SELECT run_uuid FROM runs WHERE run_number = ?1",
+                params![run.run_number],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if let Some(uuid) = existing {
+            tracing::warn!(
+                "Run {} (UUID {uuid}) is already in the run log, updating it",
+                run.run_number
+            );
+        }
+        connection.execute(
+            "INSERT INTO runs (run_number, run_uuid, note, operator, tags, run_tag, duration_sec, livetime, stop_reason, file_check, start_attempts, start_failure, fields)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+            ON CONFLICT(run_number) DO UPDATE SET
+                run_uuid = excluded.run_uuid,
+                note = excluded.note,
+                operator = excluded.operator,
+                tags = excluded.tags,
+                run_tag = excluded.run_tag,
+                duration_sec = excluded.duration_sec,
+                livetime = excluded.livetime,
+                stop_reason = excluded.stop_reason,
+                file_check = excluded.file_check,
+                start_attempts = excluded.start_attempts,
+                start_failure = excluded.start_failure,
+                fields = excluded.fields,
+                recorded_at = CURRENT_TIMESTAMP",
+            params![
+                run.run_number,
+                run.run_uuid,
+                run.note,
+                run.operator,
+                run.tags,
+                run.run_tag.to_string(),
+                run.duration_sec as i64,
+                run.livetime,
+                run.stop_reason,
+                run.file_check,
+                run.start_attempts,
+                run.start_failure,
+                serde_json::to_string(&run.fields)?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn read_runs(&self, experiment: &str) -> Result<Vec<Vec<String>>, ConfigError> {
+        let path = self.path(experiment);
+        if !path.exists() {
+            return Err(std::io::Error::from(std::io::ErrorKind::NotFound).into());
+        }
+        let connection = Self::open(&path)?;
+        let mut statement = connection.prepare(
+            "SELECT run_number, run_uuid, note, operator, tags, run_tag, duration_sec, livetime, stop_reason, file_check, start_attempts, start_failure, fields
+            FROM runs ORDER BY recorded_at, run_number",
+        )?;
+        let mut runs = vec![];
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let fields: String = row.get(12)?;
+            let run_tag: String = row.get(5)?;
+            runs.push(RunRecord {
+                run_number: row.get(0)?,
+                run_uuid: row.get(1)?,
+                note: row.get(2)?,
+                operator: row.get(3)?,
+                tags: row.get(4)?,
+                run_tag: RunTag::from_name(&run_tag).unwrap_or_default(),
+                duration_sec: row.get::<_, i64>(6)?.max(0) as u64,
+                livetime: row.get(7)?,
+                stop_reason: row.get(8)?,
+                file_check: row.get(9)?,
+                start_attempts: row.get(10)?,
+                start_failure: row.get(11)?,
+                fields: serde_json::from_str(&fields)?,
+            });
+        }
+
+        // The fields may differ between runs, show all of them
+        let field_names: BTreeSet<String> = runs
+            .iter()
+            .flat_map(|run| run.fields.keys().cloned())
+            .collect();
+        let mut header: Vec<String> = TABLE_COLUMNS.iter().map(|c| c.to_string()).collect();
+        header.extend(field_names.iter().cloned());
+        let mut table = vec![header];
+        for run in runs {
+            let mut values = run.values();
+            values.truncate(TABLE_COLUMNS.len());
+            values.extend(
+                field_names
+                    .iter()
+                    .map(|name| run.fields.get(name).cloned().unwrap_or_default()),
+            );
+            table.push(values);
+        }
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(run_number: i32, fields: &[(&str, &str)]) -> RunRecord {
+        RunRecord {
+            run_number,
+            run_uuid: format!("uuid-{run_number}"),
+            note: String::from("note"),
+            operator: String::from("op"),
+            tags: String::new(),
+            run_tag: RunTag::Good,
+            duration_sec: 60,
+            livetime: None,
+            stop_reason: String::from("Operator"),
+            file_check: String::new(),
+            start_attempts: 1,
+            start_failure: String::new(),
+            fields: fields
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn new_table_gets_the_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tables").join("exp.csv");
+        let run = record(1, &[("Gas", "H2")]);
+        let mut log = CsvRunLog::default();
+        log.validate_table(&path, CsvRunLog::header(&run)).unwrap();
+        let records = parse_csv(&std::fs::read_to_string(&path).unwrap());
+        assert_eq!(records, vec![CsvRunLog::header(&run)]);
+    }
+
+    #[test]
+    fn validated_header_is_not_checked_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("exp.csv");
+        let header = CsvRunLog::header(&record(1, &[]));
+        let mut log = CsvRunLog::default();
+        log.validate_table(&path, header.clone()).unwrap();
+        std::fs::write(&path, "Stale\n").unwrap();
+        log.validate_table(&path, header).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "Stale\n");
+    }
+
+    #[test]
+    fn header_change_rewrites_the_table_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("exp.csv");
+        std::fs::write(&path, "Run,Note,Gas\n1,first,H2\n").unwrap();
+        let header = strings(&["Run", "Gas", "Pressure"]);
+        CsvRunLog::default()
+            .validate_table(&path, header.clone())
+            .unwrap();
+        let records = parse_csv(&std::fs::read_to_string(&path).unwrap());
+        assert_eq!(records, vec![header, strings(&["1", "H2", ""])]);
+        assert!(!path.with_extension("csv.tmp").exists());
+    }
+
+    #[test]
+    fn records_are_migrated_by_column_name() {
+        let old_header = strings(&["Run", "Note", "Duration", "Gas"]);
+        let header = strings(&["Run", "Run UUID", "Note", "Duration", "Gas"]);
+        let migrated =
+            CsvRunLog::migrate_record(&old_header, &header, &strings(&["4", "n", "60", "H2"]));
+        assert_eq!(migrated, strings(&["4", "", "n", "60", "H2"]));
+    }
+
+    #[test]
+    fn sqlite_updates_a_repeated_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = SqliteRunLog::new(dir.path().join("tables"));
+        log.write_run("exp", &record(1, &[("Gas", "H2")])).unwrap();
+        log.write_run("exp", &record(2, &[])).unwrap();
+        let mut rerun = record(1, &[("Gas", "D2")]);
+        rerun.note = String::from("rerecorded");
+        log.write_run("exp", &rerun).unwrap();
+
+        let table = log.read_runs("exp").unwrap();
+        assert_eq!(table.len(), 3);
+        let runs: Vec<&Vec<String>> = table[1..].iter().filter(|row| row[0] == "1").collect();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0][2], "rerecorded");
+        assert_eq!(runs[0].last().unwrap(), "D2");
+    }
+
+    #[test]
+    fn sqlite_adds_the_missing_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = SqliteRunLog::new(dir.path());
+        let old = Connection::open(log.path("exp")).unwrap();
+        old.execute(
+            "CREATE TABLE runs (
+                run_number INTEGER PRIMARY KEY,
+                run_uuid TEXT NOT NULL,
+                note TEXT NOT NULL,
+                tags TEXT NOT NULL,
+                duration_sec INTEGER NOT NULL,
+                livetime REAL,
+                stop_reason TEXT NOT NULL,
+                fields TEXT NOT NULL,
+                recorded_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+            [],
+        )
+        .unwrap();
+        old.execute(
+            "INSERT INTO runs (run_number, run_uuid, note, tags, duration_sec, stop_reason, fields)
+            VALUES (7, 'uuid-7', 'old', '', 30, 'Operator', '{}')",
+            [],
+        )
+        .unwrap();
+        drop(old);
+
+        let mut log = log;
+        log.write_run("exp", &record(8, &[])).unwrap();
+        let table = log.read_runs("exp").unwrap();
+        assert_eq!(table.len(), 3);
+        let old_run = &table[1];
+        assert_eq!(old_run[0], "7");
+        assert_eq!(old_run[3], "");
+        assert_eq!(old_run[5], "Good");
+        assert_eq!(old_run[10], "0");
+        assert_eq!(table[2][0], "8");
+        assert_eq!(table[2][3], "op");
+    }
+
+    #[test]
+    fn short_records_are_padded() {
+        let old_header = strings(&["Run", "Note", "Gas"]);
+        let migrated = CsvRunLog::migrate_record(&old_header, &old_header, &strings(&["4"]));
+        assert_eq!(migrated, strings(&["4", "", ""]));
+    }
+}
@@ -0,0 +1,231 @@
+//! Structured run metadata, written as runs/<experiment>/run_<NNNN>.json when a run stops so
+//! that the analysis pipeline does not have to parse the CSV table.
+use super::config::Config;
+use super::effective_config::effective_config_yaml;
+use super::error::ConfigError;
+use super::run_log_store::RunTag;
+use super::run_sequence::StopReason;
+use crate::envoy::status_manager::StatusManager;
+use crate::envoy::surveyor_envoy::SurveyorResponse;
+use crate::envoy::surveyor_status::DiskHealth;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+const RUN_METADATA_DIRECTORY: &str = "runs";
+
+/// The directory holding the metadata of the runs of an experiment, created if needed
+pub fn metadata_directory(experiment: &str) -> Result<PathBuf, ConfigError> {
+    let dir = PathBuf::from(RUN_METADATA_DIRECTORY).join(experiment);
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Write the effective configuration of the current run (see effective_config) to
+/// runs/<experiment>/run_<NNNN>.effective-config.yaml, returning the path written
+pub fn write_effective_config(config: &Config) -> Result<PathBuf, ConfigError> {
+    let path = metadata_directory(&config.experiment)?.join(format!(
+        "run_{:04}.effective-config.yaml",
+        config.run_number
+    ));
+    std::fs::write(&path, effective_config_yaml(config)?)?;
+    Ok(path)
+}
+
+/// A UTC timestamp in ISO 8601 format
+fn iso_timestamp(time: &OffsetDateTime) -> String {
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        time.date(),
+        time.hour(),
+        time.minute(),
+        time.second()
+    )
+}
+
+/// The final file and byte counts of a data router
+#[derive(Debug, Serialize)]
+pub struct RouterMetadata {
+    pub id: usize,
+    pub address: String,
+    pub location: String,
+    pub files: i32,
+    pub bytes_used: u64,
+    pub disk_health: Option<DiskHealth>,
+    /// The average data rate (MB/s) over the run, None if it is not known
+    pub average_rate: Option<f64>,
+}
+
+/// The average data rate (MB/s) of each data router over a run, from the bytes written since
+/// the run started. None for the routers without a starting value, or if the run had no duration.
+pub fn average_rates(
+    routers: &[SurveyorResponse],
+    start_bytes: &[u64],
+    duration: Duration,
+) -> Vec<Option<f64>> {
+    routers
+        .iter()
+        .enumerate()
+        .map(|(id, router)| {
+            let start = start_bytes.get(id)?;
+            (!duration.is_zero()).then(|| {
+                router.bytes_used.saturating_sub(*start) as f64 * 1.0e-6 / duration.as_secs_f64()
+            })
+        })
+        .collect()
+}
+
+/// The quorum readiness policy a run was started under
+#[derive(Debug, Serialize)]
+pub struct QuorumMetadata {
+    pub quorum_cobos: usize,
+    /// The CoBos which were waived when the run started
+    pub waived_cobos: Vec<usize>,
+}
+
+/// The final ECC state of a module
+#[derive(Debug, Serialize)]
+pub struct ModuleMetadata {
+    pub id: usize,
+    pub is_mutant: bool,
+    pub state: String,
+}
+
+/// Everything known about a run when it stops
+#[derive(Debug, Serialize)]
+pub struct RunMetadata {
+    pub experiment: String,
+    pub run_number: i32,
+    pub run_uuid: String,
+    pub description: String,
+    pub operator: String,
+    pub tags: Vec<String>,
+    pub run_tag: RunTag,
+    /// When the run was scheduled to start, if it was started by a countdown
+    pub scheduled_start_time: Option<String>,
+    pub start_time: String,
+    pub stop_time: String,
+    pub duration_sec: u64,
+    pub livetime: Option<f64>,
+    pub stop_reason: String,
+    pub fields: BTreeMap<String, String>,
+    pub routers: Vec<RouterMetadata>,
+    pub modules: Vec<ModuleMetadata>,
+    /// Set if the run was started under the quorum readiness policy
+    pub quorum: Option<QuorumMetadata>,
+    /// Where the GET configuration of the run was backed up, if it was
+    pub config_backup: Option<String>,
+    /// The attempts it took to start the run
+    pub start_attempts: u32,
+    /// The step and reason the last failed start attempt failed at, if any failed
+    pub last_start_failure: Option<String>,
+    /// How long (s) re-configuring the MuTaNT to reset the timestamps took, if it was reset
+    pub mutant_reconfigure_sec: Option<f64>,
+}
+
+impl RunMetadata {
+    /// Collect the metadata of the run from the config and the final status of the envoys
+    pub fn new(
+        config: &Config,
+        run_id: &Uuid,
+        start_time: &OffsetDateTime,
+        scheduled_start_time: Option<&OffsetDateTime>,
+        livetime: Option<f64>,
+        stop_reason: StopReason,
+        status: &StatusManager,
+    ) -> Self {
+        let stop_time = OffsetDateTime::now_utc();
+        Self {
+            experiment: config.experiment.clone(),
+            run_number: config.run_number,
+            run_uuid: run_id.to_string(),
+            description: stop_reason.annotate(&config.description),
+            operator: config.operator.clone(),
+            tags: config.run_tags.iter().cloned().collect(),
+            run_tag: config.run_tag,
+            scheduled_start_time: scheduled_start_time.map(iso_timestamp),
+            start_time: iso_timestamp(start_time),
+            stop_time: iso_timestamp(&stop_time),
+            duration_sec: (stop_time - *start_time).whole_seconds().max(0) as u64,
+            livetime,
+            stop_reason: stop_reason.to_string(),
+            fields: config.fields.clone(),
+            routers: status
+                .get_surveyor_status_response()
+                .iter()
+                .enumerate()
+                .map(|(id, router)| RouterMetadata {
+                    id,
+                    address: router.address.clone(),
+                    location: router.location.clone(),
+                    files: router.files,
+                    bytes_used: router.bytes_used,
+                    disk_health: router.disk_health.clone(),
+                    average_rate: None,
+                })
+                .collect(),
+            modules: status
+                .module_ids()
+                .into_iter()
+                .map(|id| ModuleMetadata {
+                    id,
+                    is_mutant: id == status.mutant_id(),
+                    state: status.get_ecc_status(id).to_string(),
+                })
+                .collect(),
+            quorum: None,
+            config_backup: None,
+            start_attempts: 0,
+            last_start_failure: None,
+            mutant_reconfigure_sec: None,
+        }
+    }
+
+    /// Write the metadata to runs/<experiment>/run_<NNNN>.json, returning the path written
+    pub fn write(&self) -> Result<PathBuf, ConfigError> {
+        let path =
+            metadata_directory(&self.experiment)?.join(format!("run_{:04}.json", self.run_number));
+        let file = std::fs::File::create(&path)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router(bytes_used: u64) -> SurveyorResponse {
+        SurveyorResponse {
+            bytes_used,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn average_rate_is_the_bytes_written_over_the_run() {
+        let routers = [router(5_000_000_000), router(1_000_000_000), router(0)];
+        let rates = average_rates(
+            &routers,
+            &[1_000_000_000, 1_000_000_000],
+            Duration::from_secs(100),
+        );
+        assert_eq!(rates, vec![Some(40.0), Some(0.0), None]);
+    }
+
+    #[test]
+    fn average_rate_is_unknown_without_a_duration() {
+        let rates = average_rates(&[router(10)], &[0], Duration::ZERO);
+        assert_eq!(rates, vec![None]);
+    }
+
+    #[test]
+    fn average_rate_ignores_a_shrinking_disk() {
+        // i.e. the .graw files were moved out of the watched directory
+        let rates = average_rates(&[router(10)], &[20], Duration::from_secs(1));
+        assert_eq!(rates, vec![Some(0.0)]);
+    }
+}
@@ -0,0 +1,98 @@
+use std::time::{Duration, Instant};
+
+/// Why a run was stopped, recorded in the run table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopReason {
+    Manual,
+    AutoStop,
+    Sequence,
+    AutoCycle,
+    /// Stopped because the data router with this id ran out of space
+    DiskFull(usize),
+    QuickStop,
+}
+
+impl std::fmt::Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Manual => write!(f, "Manual"),
+            Self::AutoStop => write!(f, "Auto-stop"),
+            Self::Sequence => write!(f, "Sequence"),
+            Self::AutoCycle => write!(f, "Auto-cycled"),
+            Self::DiskFull(_) => write!(f, "Disk full"),
+            Self::QuickStop => write!(f, "Quick stop"),
+        }
+    }
+}
+
+impl StopReason {
+    /// The note appended to the run description in the run log and metadata, if the reason
+    /// needs more than the stop reason column
+    pub fn note(&self) -> Option<String> {
+        match self {
+            Self::DiskFull(id) => Some(format!("[auto-stopped: disk full on router {id}]")),
+            _ => None,
+        }
+    }
+
+    /// The run description with the note of the stop reason appended
+    pub fn annotate(&self, description: &str) -> String {
+        match self.note() {
+            Some(note) => format!("{description} {note}"),
+            None => description.to_string(),
+        }
+    }
+}
+
+/// Where a run sequence is in its cycle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SequencePhase {
+    /// Waiting for the system to be Ready to start the next run
+    WaitingForReady,
+    /// A run start was requested, waiting for it to complete. Holds the number of runs
+    /// started by the app when the request was made.
+    Starting(usize),
+    /// A run is in progress. Whether the system has been seen Running yet is tracked, as
+    /// the MuTaNT reports Running shortly after the start sequence completes.
+    Running { since: Instant, confirmed: bool },
+}
+
+impl std::fmt::Display for SequencePhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WaitingForReady => write!(f, "Waiting for Ready"),
+            Self::Starting(_) => write!(f, "Starting"),
+            Self::Running { .. } => write!(f, "Running"),
+        }
+    }
+}
+
+/// A sequence of fixed length runs taken back to back
+#[derive(Debug, Clone)]
+pub struct RunSequence {
+    pub total_runs: usize,
+    pub run_length: Duration,
+    pub completed_runs: usize,
+    pub phase: SequencePhase,
+}
+
+impl RunSequence {
+    pub fn new(total_runs: usize, run_length: Duration) -> Self {
+        Self {
+            total_runs,
+            run_length,
+            completed_runs: 0,
+            phase: SequencePhase::WaitingForReady,
+        }
+    }
+
+    /// Time left in the current run of the sequence
+    pub fn remaining(&self) -> Option<Duration> {
+        match self.phase {
+            SequencePhase::Running { since, .. } => {
+                Some(self.run_length.saturating_sub(since.elapsed()))
+            }
+            _ => None,
+        }
+    }
+}
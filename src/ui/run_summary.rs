@@ -0,0 +1,96 @@
+//! A short human-readable summary of a run, written as runs/<experiment>/run_<NNNN>.md when
+//! the run stops, for pasting into the logbook.
+use super::error::ConfigError;
+use super::run_log_store::RunRecord;
+use super::run_metadata::metadata_directory;
+use super::style::pretty_ellapsed_time;
+use crate::envoy::surveyor_envoy::SurveyorResponse;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+/// Make a value safe to put in a markdown table cell
+fn table_cell(value: &str) -> String {
+    value.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+/// Compose the summary from the run log record of the run, the final data router statuses,
+/// and the average data rate of each data router over the run.
+fn compose_run_summary(
+    experiment: &str,
+    run: &RunRecord,
+    routers: &[SurveyorResponse],
+    average_rates: &[Option<f64>],
+) -> String {
+    let mut summary = String::new();
+    let _ = writeln!(summary, "# {experiment} Run {:04}\n", run.run_number);
+    let _ = writeln!(
+        summary,
+        "- Duration: {}",
+        pretty_ellapsed_time(run.duration_sec)
+    );
+    let _ = writeln!(summary, "- Description: {}", run.note.replace('\n', " "));
+    let _ = writeln!(summary, "- Operator: {}", run.operator);
+    let _ = writeln!(summary, "- Run Tag: {}", run.run_tag);
+    if !run.tags.is_empty() {
+        let _ = writeln!(summary, "- Tags: {}", run.tags);
+    }
+    if let Some(livetime) = run.livetime {
+        let _ = writeln!(summary, "- Livetime: {:.2}%", livetime * 100.0);
+    }
+    let _ = writeln!(summary, "- Stop Reason: {}", run.stop_reason);
+    let _ = writeln!(summary, "- Run UUID: {}", run.run_uuid);
+
+    let _ = writeln!(summary, "\n## Fields\n");
+    let _ = writeln!(summary, "| Field | Value |");
+    let _ = writeln!(summary, "| --- | --- |");
+    for (name, value) in run.fields.iter() {
+        let _ = writeln!(summary, "| {} | {} |", table_cell(name), table_cell(value));
+    }
+
+    let _ = writeln!(summary, "\n## Data Routers\n");
+    let _ = writeln!(
+        summary,
+        "| Router | Address | Files | Data | Average Rate (MB/s) |"
+    );
+    let _ = writeln!(summary, "| --- | --- | --- | --- | --- |");
+    let mut total_files = 0;
+    let mut total_bytes = 0;
+    for (id, router) in routers.iter().enumerate() {
+        let rate = average_rates
+            .get(id)
+            .copied()
+            .flatten()
+            .map(|rate| format!("{rate:.2}"))
+            .unwrap_or(String::from("N/A"));
+        let _ = writeln!(
+            summary,
+            "| {id} | {} | {} | {} | {rate} |",
+            table_cell(&router.address),
+            router.files,
+            human_bytes::human_bytes(router.bytes_used as f64)
+        );
+        total_files += router.files;
+        total_bytes += router.bytes_used;
+    }
+    let _ = writeln!(
+        summary,
+        "| Total | | {total_files} | {} | |",
+        human_bytes::human_bytes(total_bytes as f64)
+    );
+    summary
+}
+
+/// Write the summary to runs/<experiment>/run_<NNNN>.md, returning the path written
+pub fn write_run_summary(
+    experiment: &str,
+    run: &RunRecord,
+    routers: &[SurveyorResponse],
+    average_rates: &[Option<f64>],
+) -> Result<PathBuf, ConfigError> {
+    let path = metadata_directory(experiment)?.join(format!("run_{:04}.md", run.run_number));
+    std::fs::write(
+        &path,
+        compose_run_summary(experiment, run, routers, average_rates),
+    )?;
+    Ok(path)
+}
@@ -0,0 +1,140 @@
+use super::app::EnvoyApp;
+use super::config::Config;
+use super::run_log_store::RunTag;
+use super::sanitize::csv_row;
+use eframe::egui::{Color32, RichText, Window};
+use egui_extras::{Column, TableBuilder};
+
+/// The contents of the run table, as last read from disk
+#[derive(Debug, Default)]
+pub struct RunTableView {
+    pub header: Vec<String>,
+    /// The runs, most recent first
+    pub rows: Vec<Vec<String>>,
+    /// Why the table could not be read, if it could not
+    pub error: Option<String>,
+    /// The column holding the run tag, absent in tables written before runs were tagged
+    pub tag_column: Option<usize>,
+}
+
+/// The color of the rows with a run tag. Good runs are not highlighted.
+fn run_tag_color(tag: RunTag) -> Option<Color32> {
+    match tag {
+        RunTag::Good => None,
+        RunTag::Junk => Some(Color32::LIGHT_RED),
+        RunTag::Calibration => Some(Color32::LIGHT_BLUE),
+        RunTag::Test => Some(Color32::YELLOW),
+    }
+}
+
+impl RunTableView {
+    /// Read the run table of the configured experiment. A missing or unreadable file gives
+    /// an empty view with the error.
+    pub fn load(config: &Config) -> Self {
+        match config.read_table() {
+            Ok(mut records) => {
+                if records.is_empty() {
+                    return Self {
+                        error: Some(String::from("The run table is empty")),
+                        ..Default::default()
+                    };
+                }
+                let header = records.remove(0);
+                records.reverse();
+                Self {
+                    tag_column: header.iter().position(|name| name == "Run Tag"),
+                    header,
+                    rows: records,
+                    error: None,
+                }
+            }
+            Err(e) => Self {
+                error: Some(format!(
+                    "Could not read {}: {e}",
+                    config.table_path().display()
+                )),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Render the run table viewer, showing the previous runs of the experiment
+pub fn render_run_table_panel(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
+    let view = match &app.run_table {
+        Some(view) => view,
+        None => return,
+    };
+    let mut open = true;
+    let mut refresh = false;
+    Window::new("Run Table")
+        .open(&mut open)
+        .default_width(900.0)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "{} ({} runs)",
+                    app.config.table_path().display(),
+                    view.rows.len()
+                ));
+                if ui.button("Refresh").clicked() {
+                    refresh = true;
+                }
+            });
+            if let Some(error) = &view.error {
+                ui.label(RichText::new(error).color(Color32::RED));
+                return;
+            }
+            ui.separator();
+            let n_columns = view.header.len();
+            TableBuilder::new(ui)
+                .striped(true)
+                .max_scroll_height(600.0)
+                .column(Column::auto())
+                .columns(Column::auto().at_least(50.0).resizable(true), n_columns)
+                .header(30.0, |mut header| {
+                    header.col(|_| {});
+                    for name in view.header.iter() {
+                        header.col(|ui| {
+                            ui.strong(name);
+                        });
+                    }
+                })
+                .body(|body| {
+                    body.rows(24.0, view.rows.len(), |mut row| {
+                        let record = &view.rows[row.index()];
+                        let color = view
+                            .tag_column
+                            .and_then(|column| record.get(column))
+                            .and_then(|tag| RunTag::from_name(tag))
+                            .and_then(run_tag_color);
+                        row.col(|ui| {
+                            if ui
+                                .small_button("Copy")
+                                .on_hover_text("Copy the row to the clipboard")
+                                .clicked()
+                            {
+                                ui.ctx().copy_text(csv_row(record));
+                            }
+                        });
+                        // Malformed rows may have too few or too many values
+                        for column in 0..n_columns {
+                            row.col(|ui| {
+                                let text = RichText::new(
+                                    record.get(column).map(String::as_str).unwrap_or(""),
+                                );
+                                ui.label(match color {
+                                    Some(color) => text.color(color),
+                                    None => text,
+                                });
+                            });
+                        }
+                    });
+                });
+        });
+    if !open {
+        app.run_table = None;
+    } else if refresh {
+        app.run_table = Some(RunTableView::load(&app.config));
+    }
+}
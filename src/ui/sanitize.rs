@@ -0,0 +1,201 @@
+//! Sanitization of the operator entered text which ends up in the run table, shell arguments,
+//! and file paths. Text is NFC normalized, control characters are removed, and the length is
+//! limited. The experiment name is additionally restricted to characters which are safe in paths
+//! and remote shell commands.
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+const DEFAULT_MAX_EXPERIMENT_LENGTH: usize = 64;
+const DEFAULT_MAX_DESCRIPTION_LENGTH: usize = 256;
+const DEFAULT_MAX_FIELD_LENGTH: usize = 64;
+
+/// The maximum lengths (in characters) of the operator entered text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextLimits {
+    pub max_experiment_length: usize,
+    pub max_description_length: usize,
+    pub max_field_length: usize,
+}
+
+impl Default for TextLimits {
+    fn default() -> Self {
+        Self {
+            max_experiment_length: DEFAULT_MAX_EXPERIMENT_LENGTH,
+            max_description_length: DEFAULT_MAX_DESCRIPTION_LENGTH,
+            max_field_length: DEFAULT_MAX_FIELD_LENGTH,
+        }
+    }
+}
+
+/// Normalize the text, remove control characters, and limit the length.
+/// Returns the sanitized text and whether it was changed.
+pub fn sanitize_text(text: &str, max_length: usize) -> (String, bool) {
+    let sanitized: String = text
+        .nfc()
+        .filter(|c| !c.is_control())
+        .take(max_length)
+        .collect();
+    let changed = sanitized != text;
+    (sanitized, changed)
+}
+
+/// Sanitize an experiment name. Anything other than ASCII letters, digits, '-', '_', and '.'
+/// is replaced with '_', as the name is used in paths and remote shell commands.
+pub fn sanitize_experiment(text: &str, max_length: usize) -> (String, bool) {
+    let (sanitized, _) = sanitize_text(text, max_length);
+    let sanitized: String = sanitized
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let changed = sanitized != text;
+    (sanitized, changed)
+}
+
+/// Is the (sanitized) experiment name usable as a directory name. Empty names and names made
+/// only of dots ("." and "..") would point at the parent directories.
+pub fn is_valid_experiment(name: &str) -> bool {
+    !name.chars().all(|c| c == '.')
+}
+
+/// Quote a value for the CSV run table if it contains a delimiter, quote, or line break (RFC 4180)
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Join values into a row of the CSV run table, quoting each as needed
+pub fn csv_row<S: AsRef<str>>(values: &[S]) -> String {
+    values
+        .iter()
+        .map(|value| csv_field(value.as_ref()))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Parse CSV text into records using the same rules as csv_field: quoted fields may contain
+/// delimiters, doubled quotes, and line breaks. Both LF and CRLF line endings are accepted.
+pub fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(c);
+            }
+            continue;
+        }
+        match c {
+            '"' => in_quotes = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => (),
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    // The last record may not end with a line break
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn dot_experiment_names_are_rejected() {
+        for name in ["", ".", "..", "..."] {
+            let (sanitized, _) = sanitize_experiment(name, 64);
+            assert!(!is_valid_experiment(&sanitized), "{name:?} was accepted");
+        }
+        assert!(is_valid_experiment("e20009"));
+        assert!(is_valid_experiment(".hidden"));
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_as_needed() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(
+            csv_row(&["run", "gas, 300 torr", ""]),
+            "run,\"gas, 300 torr\","
+        );
+    }
+
+    #[test]
+    fn csv_parsing_follows_the_quoting() {
+        let text = "run,description\r\n1,\"gas, \"\"P10\"\"\"\n2,\"two\nlines\"";
+        assert_eq!(
+            parse_csv(text),
+            vec![
+                vec!["run", "description"],
+                vec!["1", "gas, \"P10\""],
+                vec!["2", "two\nlines"],
+            ]
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn sanitized_text_is_clean_and_stable(text in any::<String>(), max in 0usize..300) {
+            let (sanitized, _) = sanitize_text(&text, max);
+            prop_assert!(sanitized.chars().count() <= max);
+            prop_assert!(!sanitized.chars().any(char::is_control));
+            prop_assert_eq!(sanitize_text(&sanitized, max), (sanitized.clone(), false));
+        }
+
+        #[test]
+        fn accepted_experiments_are_single_path_components(text in any::<String>()) {
+            let (experiment, _) = sanitize_experiment(&text, 64);
+            prop_assume!(is_valid_experiment(&experiment));
+            let run_dir = std::path::Path::new("/data").join(&experiment).join("run_0001");
+            prop_assert_eq!(
+                run_dir.parent().and_then(|dir| dir.file_name()),
+                Some(std::ffi::OsStr::new(&experiment))
+            );
+            // Safe to pass unquoted to a remote shell
+            prop_assert!(experiment
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')));
+        }
+
+        #[test]
+        fn csv_fields_survive_a_round_trip(values in prop::collection::vec(any::<String>(), 1..6)) {
+            let values: Vec<String> = values
+                .iter()
+                .map(|value| sanitize_text(value, DEFAULT_MAX_DESCRIPTION_LENGTH).0)
+                .collect();
+            // A row of a single empty field is written as an empty line
+            prop_assume!(values != vec![String::new()]);
+            let text = format!("{}\n", csv_row(&values));
+            prop_assert_eq!(parse_csv(&text), vec![values]);
+        }
+    }
+}
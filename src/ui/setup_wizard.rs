@@ -0,0 +1,394 @@
+//! The setup wizard, shown on the first launch (when there is no setup state file) and from the
+//! File menu. Walks through the experiment, the network, and the directories the scripts use,
+//! and writes a validated Config at the end.
+use super::app::EnvoyApp;
+use super::config::Config;
+use super::sanitize::{is_valid_experiment, sanitize_experiment};
+use crate::command::constants::{BACKUP_CONFIG_DIR, CONFIG_DIR};
+use crate::envoy::ecc_envoy::probe_ecc_server;
+use eframe::egui::{Button, Color32, DragValue, Grid, RichText, ScrollArea, TextEdit, Window};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+
+/// Written when the wizard completes. Its absence marks the first launch.
+const SETUP_STATE_FILE: &str = "envoy_state.yml";
+const PROBE_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// What the app remembers about the setup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SetupState {
+    config_path: PathBuf,
+}
+
+/// Has the setup wizard never been completed
+pub fn is_first_launch() -> bool {
+    !Path::new(SETUP_STATE_FILE).exists()
+}
+
+/// The config saved by the setup wizard, if the wizard has been completed
+pub fn saved_config_path() -> Option<PathBuf> {
+    let yaml = std::fs::read_to_string(SETUP_STATE_FILE).ok()?;
+    match serde_yaml::from_str::<SetupState>(&yaml) {
+        Ok(state) => Some(state.config_path),
+        Err(e) => {
+            tracing::warn!("Could not read {SETUP_STATE_FILE}: {e}");
+            None
+        }
+    }
+}
+
+/// Load the config saved by the setup wizard, or the default config if there is none or it
+/// could not be read
+pub fn load_saved_config() -> Config {
+    let mut config = Config::new();
+    if let Some(path) = saved_config_path() {
+        if let Err(e) = config.load(path.clone()) {
+            tracing::warn!("Could not load the config {}: {e}", path.display());
+        }
+    }
+    config
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WizardStep {
+    Experiment,
+    Network,
+    Directories,
+    Review,
+}
+
+impl WizardStep {
+    fn next(self) -> Self {
+        match self {
+            Self::Experiment => Self::Network,
+            Self::Network => Self::Directories,
+            Self::Directories | Self::Review => Self::Review,
+        }
+    }
+
+    fn previous(self) -> Self {
+        match self {
+            Self::Experiment | Self::Network => Self::Experiment,
+            Self::Directories => Self::Network,
+            Self::Review => Self::Directories,
+        }
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            Self::Experiment => "1/4 Experiment",
+            Self::Network => "2/4 Network",
+            Self::Directories => "3/4 Directories",
+            Self::Review => "4/4 Review",
+        }
+    }
+}
+
+/// The result of probing an ECC server address
+#[derive(Debug, Clone)]
+enum ProbeResult {
+    Pending,
+    Reachable,
+    Unreachable(String),
+}
+
+/// The state of the wizard. Edits are made to a draft Config which only replaces the app
+/// config when the wizard is finished.
+#[derive(Debug)]
+pub struct SetupWizard {
+    step: WizardStep,
+    draft: Config,
+    config_path: String,
+    probes: BTreeMap<String, ProbeResult>,
+    probe_rx: Option<Receiver<(String, Result<(), String>)>>,
+}
+
+impl SetupWizard {
+    /// Start the wizard from the current config
+    pub fn new(config: &Config) -> Self {
+        let config_path = if config.path.exists() {
+            config.path.display().to_string()
+        } else {
+            format!("{}.yml", config.experiment)
+        };
+        Self {
+            step: WizardStep::Experiment,
+            draft: config.clone(),
+            config_path,
+            probes: BTreeMap::new(),
+            probe_rx: None,
+        }
+    }
+
+    /// The ECC server addresses of the draft, MuTaNT first
+    fn addresses(&self) -> Vec<String> {
+        let mut addresses = vec![self.draft.network.mutant.clone()];
+        addresses.extend(
+            (0..self.draft.num_cobos).filter_map(|id| self.draft.network.cobos.get(&id).cloned()),
+        );
+        addresses
+    }
+
+    /// Probe every ECC server address on a background thread
+    fn start_probes(&mut self) {
+        let addresses = self.addresses();
+        let (tx, rx) = channel();
+        self.probes.clear();
+        for address in addresses.iter() {
+            self.probes.insert(address.clone(), ProbeResult::Pending);
+        }
+        std::thread::spawn(move || {
+            for address in addresses {
+                let result = probe_ecc_server(&address, PROBE_TIMEOUT).map_err(|e| e.to_string());
+                if tx.send((address, result)).is_err() {
+                    break;
+                }
+            }
+        });
+        self.probe_rx = Some(rx);
+    }
+
+    /// Collect any finished probes
+    fn poll_probes(&mut self) {
+        if let Some(rx) = &self.probe_rx {
+            while let Ok((address, result)) = rx.try_recv() {
+                self.probes.insert(
+                    address,
+                    match result {
+                        Ok(()) => ProbeResult::Reachable,
+                        Err(e) => ProbeResult::Unreachable(e),
+                    },
+                );
+            }
+        }
+    }
+
+    /// The problems which prevent the draft from being used
+    fn validate(&self) -> Vec<String> {
+        let mut problems = vec![];
+        let (experiment, _) = sanitize_experiment(
+            &self.draft.experiment,
+            self.draft.text_limits.max_experiment_length,
+        );
+        if !is_valid_experiment(&experiment) {
+            problems.push(String::from(
+                "The experiment name is empty or made only of dots",
+            ));
+        }
+        if !self.draft.families.any() {
+            problems.push(String::from("Neither ECC nor the data routers are enabled"));
+        }
+        if self.draft.network.mutant.trim().is_empty() {
+            problems.push(String::from("The MuTaNT has no address"));
+        }
+        for id in 0..self.draft.num_cobos {
+            match self.draft.network.cobos.get(&id) {
+                Some(address) if !address.trim().is_empty() => (),
+                _ => problems.push(format!("CoBo {id} has no address")),
+            }
+        }
+        if self.config_path.trim().is_empty() {
+            problems.push(String::from("No path was given for the config file"));
+        }
+        problems
+    }
+
+    fn render_experiment(&mut self, ui: &mut eframe::egui::Ui) {
+        ui.label("The experiment name must match the name used for the ECC configuration files.");
+        Grid::new("Setup_Experiment_Grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Experiment");
+                ui.text_edit_singleline(&mut self.draft.experiment);
+                ui.end_row();
+                ui.label("Run Number");
+                ui.add(DragValue::new(&mut self.draft.run_number).range(0..=i32::MAX));
+                ui.end_row();
+                ui.label("Description");
+                ui.text_edit_singleline(&mut self.draft.description);
+                ui.end_row();
+            });
+    }
+
+    fn render_network(&mut self, ui: &mut eframe::egui::Ui) {
+        self.poll_probes();
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.draft.families.ecc, "ECC");
+            ui.checkbox(&mut self.draft.families.surveyor, "Data Routers");
+            ui.label("CoBos");
+            ui.add(DragValue::new(&mut self.draft.num_cobos).range(1..=usize::MAX));
+        });
+        self.draft.network.ensure_cobos(self.draft.num_cobos);
+        ui.checkbox(
+            &mut self.draft.watch_configs,
+            "Watch the ECC config files for changes",
+        );
+        ui.separator();
+        let probes = &self.probes;
+        let probe_label = |ui: &mut eframe::egui::Ui, address: &String| match probes.get(address) {
+            Some(ProbeResult::Pending) => {
+                ui.spinner();
+            }
+            Some(ProbeResult::Reachable) => {
+                ui.label(RichText::new("Reachable").color(Color32::GREEN));
+            }
+            Some(ProbeResult::Unreachable(e)) => {
+                ui.label(RichText::new("Unreachable").color(Color32::RED))
+                    .on_hover_text(e);
+            }
+            None => {
+                ui.label("");
+            }
+        };
+        ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+            Grid::new("Setup_Network_Grid")
+                .num_columns(3)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("MuTaNT");
+                    ui.add(TextEdit::singleline(&mut self.draft.network.mutant));
+                    probe_label(ui, &self.draft.network.mutant);
+                    ui.end_row();
+                    for id in 0..self.draft.num_cobos {
+                        if let Some(address) = self.draft.network.cobos.get_mut(&id) {
+                            ui.label(format!("CoBo {id}"));
+                            ui.add(TextEdit::singleline(address));
+                            probe_label(ui, address);
+                            ui.end_row();
+                        }
+                    }
+                });
+        });
+        if ui
+            .button("Probe")
+            .on_hover_text("Check that an ECC server is listening at each address")
+            .clicked()
+        {
+            self.start_probes();
+        }
+    }
+
+    fn render_directories(&self, ui: &mut eframe::egui::Ui) {
+        ui.label("These directories are used by the run scripts and must exist on this machine.");
+        Grid::new("Setup_Directories_Grid")
+            .num_columns(3)
+            .striped(true)
+            .show(ui, |ui| {
+                let script_dir = self.draft.shell.script_dir.display().to_string();
+                for (name, dir) in [
+                    ("Scripts", script_dir.as_str()),
+                    ("ECC Configs", CONFIG_DIR),
+                    ("Config Backups", BACKUP_CONFIG_DIR),
+                ] {
+                    ui.label(name);
+                    ui.label(dir);
+                    if Path::new(dir).is_dir() {
+                        ui.label(RichText::new("Found").color(Color32::GREEN));
+                    } else {
+                        ui.label(RichText::new("Missing").color(Color32::RED));
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+
+    fn render_review(&mut self, ui: &mut eframe::egui::Ui) {
+        Grid::new("Setup_Review_Grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Experiment");
+                ui.label(&self.draft.experiment);
+                ui.end_row();
+                ui.label("Modules");
+                ui.label(format!("{} CoBos + MuTaNT", self.draft.num_cobos));
+                ui.end_row();
+                ui.label("Save config to");
+                ui.text_edit_singleline(&mut self.config_path);
+                ui.end_row();
+            });
+        for problem in self.validate() {
+            ui.label(RichText::new(problem).color(Color32::RED));
+        }
+    }
+}
+
+/// Sanitize the draft, save it, and record that the setup was completed
+fn finish_setup(wizard: &mut SetupWizard) -> Result<Config, String> {
+    let mut config = wizard.draft.clone();
+    config.sanitize();
+    config.path = PathBuf::from(wizard.config_path.trim());
+    config
+        .save()
+        .map_err(|e| format!("Could not save the config: {e}"))?;
+    let state = SetupState {
+        config_path: config.path.clone(),
+    };
+    let yaml = serde_yaml::to_string(&state).map_err(|e| e.to_string())?;
+    std::fs::write(SETUP_STATE_FILE, yaml)
+        .map_err(|e| format!("Could not write {SETUP_STATE_FILE}: {e}"))?;
+    Ok(config)
+}
+
+/// Render the setup wizard, if it is open
+pub fn render_setup_wizard(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
+    let connected = app.embassy.is_connected();
+    let wizard = match app.setup_wizard.as_mut() {
+        Some(wizard) => wizard,
+        None => return,
+    };
+    let mut open = true;
+    let mut finished = None;
+    Window::new("Setup")
+        .open(&mut open)
+        .collapsible(false)
+        .default_width(500.0)
+        .show(ctx, |ui| {
+            ui.label(
+                RichText::new(wizard.step.title())
+                    .color(Color32::LIGHT_BLUE)
+                    .size(16.0),
+            );
+            ui.separator();
+            match wizard.step {
+                WizardStep::Experiment => wizard.render_experiment(ui),
+                WizardStep::Network => wizard.render_network(ui),
+                WizardStep::Directories => wizard.render_directories(ui),
+                WizardStep::Review => wizard.render_review(ui),
+            }
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(wizard.step != WizardStep::Experiment, Button::new("Back"))
+                    .clicked()
+                {
+                    wizard.step = wizard.step.previous();
+                }
+                if wizard.step == WizardStep::Review {
+                    let valid = wizard.validate().is_empty();
+                    if ui
+                        .add_enabled(valid && !connected, Button::new("Finish"))
+                        .on_disabled_hover_text("Fix the problems above, and disconnect first")
+                        .clicked()
+                    {
+                        finished = Some(finish_setup(wizard));
+                    }
+                } else if ui.button("Next").clicked() {
+                    wizard.step = wizard.step.next();
+                }
+            });
+        });
+    match finished {
+        Some(Ok(config)) => {
+            tracing::info!("Setup complete, config saved to {}", config.path.display());
+            app.config = config;
+            app.setup_wizard = None;
+            app.open_idle_log();
+        }
+        Some(Err(e)) => app.notify_error(e),
+        None if !open => app.setup_wizard = None,
+        None => (),
+    }
+}
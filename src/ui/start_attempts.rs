@@ -0,0 +1,177 @@
+//! The number of attempts it took to start each run. A start which needs retries is usually
+//! not noticed once the run is going, so the attempts and the step the last failed one stopped
+//! at are recorded with the run, and the attempts needed by recent runs are kept for the
+//! diagnostics window to expose systemic flakiness.
+use eframe::egui::Ui;
+use egui_plot::{Bar, BarChart, Plot};
+
+/// The number of started runs kept in the history
+const MAX_HISTORY: usize = 50;
+
+/// A failed start attempt, and the step it failed at
+#[derive(Debug, Clone, PartialEq)]
+pub struct StartFailure {
+    pub step: String,
+    pub reason: String,
+}
+
+impl std::fmt::Display for StartFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.step, self.reason)
+    }
+}
+
+/// The start attempts of the current run number, and the attempts needed by the runs started
+/// this session
+#[derive(Debug, Clone, Default)]
+pub struct StartAttempts {
+    run_number: Option<i32>,
+    attempts: u32,
+    last_failure: Option<StartFailure>,
+    /// The run number and the attempts needed, oldest first
+    history: Vec<(i32, u32)>,
+}
+
+impl StartAttempts {
+    /// Count an attempt to start the run. The count restarts when the run number changes.
+    pub fn begin(&mut self, run_number: i32) {
+        if self.run_number != Some(run_number) {
+            self.run_number = Some(run_number);
+            self.attempts = 0;
+            self.last_failure = None;
+        }
+        self.attempts += 1;
+    }
+
+    /// Record that the current attempt failed at a step of the start sequence
+    pub fn fail(&mut self, step: &str, reason: &str) {
+        tracing::warn!(
+            "Start attempt {} of run {} failed at {step}",
+            self.attempts,
+            self.run_number.unwrap_or_default()
+        );
+        self.last_failure = Some(StartFailure {
+            step: step.to_string(),
+            reason: reason.to_string(),
+        });
+    }
+
+    /// Record that the current attempt started the run
+    pub fn started(&mut self) {
+        if let Some(run_number) = self.run_number {
+            if self.attempts > 1 {
+                tracing::warn!(
+                    "Run {run_number} needed {} attempts to start",
+                    self.attempts
+                );
+            }
+            self.history.push((run_number, self.attempts));
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+        }
+    }
+
+    /// The attempts made to start the run and the last failure, if any were made
+    pub fn for_run(&self, run_number: i32) -> (u32, Option<&StartFailure>) {
+        if self.run_number == Some(run_number) {
+            (self.attempts, self.last_failure.as_ref())
+        } else {
+            (0, None)
+        }
+    }
+
+    /// The mean number of attempts needed by the runs started this session
+    pub fn mean_starts_needed(&self) -> Option<f64> {
+        if self.history.is_empty() {
+            return None;
+        }
+        let total: u32 = self.history.iter().map(|(_, attempts)| attempts).sum();
+        Some(total as f64 / self.history.len() as f64)
+    }
+
+    /// The number of runs started this session which needed a retry
+    pub fn retried_runs(&self) -> usize {
+        self.history
+            .iter()
+            .filter(|(_, attempts)| *attempts > 1)
+            .count()
+    }
+
+    /// Plot the attempts needed by each run started this session
+    pub fn plot(&self, ui: &mut Ui) {
+        let bars: Vec<Bar> = self
+            .history
+            .iter()
+            .map(|(run_number, attempts)| {
+                Bar::new(*run_number as f64, *attempts as f64).name(format!("Run {run_number}"))
+            })
+            .collect();
+        Plot::new("Starts_Needed_Plot")
+            .height(80.0)
+            .width(300.0)
+            .show_axes([true, true])
+            .allow_drag(false)
+            .allow_zoom(false)
+            .allow_scroll(false)
+            .show(ui, |plot_ui| {
+                plot_ui.bar_chart(BarChart::new(bars).name("Starts needed"))
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_attempts_keep_the_last_failure_step() {
+        let mut attempts = StartAttempts::default();
+        attempts.begin(12);
+        attempts.fail("Breakup MuTaNT", "error code 4");
+        attempts.begin(12);
+        attempts.fail("Start CoBos", "CoBo 3 is in ErrorStat");
+        let (count, failure) = attempts.for_run(12);
+        assert_eq!(count, 2);
+        assert_eq!(
+            failure.map(|failure| failure.to_string()),
+            Some(String::from("Start CoBos: CoBo 3 is in ErrorStat"))
+        );
+        // Nothing has started yet
+        assert_eq!(attempts.mean_starts_needed(), None);
+
+        attempts.begin(12);
+        attempts.started();
+        assert_eq!(attempts.for_run(12).0, 3);
+        assert_eq!(attempts.retried_runs(), 1);
+        assert_eq!(attempts.mean_starts_needed(), Some(3.0));
+    }
+
+    #[test]
+    fn count_restarts_with_a_new_run_number() {
+        let mut attempts = StartAttempts::default();
+        attempts.begin(1);
+        attempts.fail("Configure MuTaNT", "timed out");
+        attempts.begin(2);
+        attempts.started();
+        assert_eq!(attempts.for_run(2), (1, None));
+        assert_eq!(attempts.for_run(1), (0, None));
+        assert_eq!(attempts.retried_runs(), 0);
+    }
+
+    #[test]
+    fn history_is_limited() {
+        let mut attempts = StartAttempts::default();
+        for run_number in 0..(MAX_HISTORY as i32 + 10) {
+            attempts.begin(run_number);
+            if run_number < 10 {
+                attempts.begin(run_number);
+            }
+            attempts.started();
+        }
+        // The retried runs were the oldest, and have been dropped
+        assert_eq!(attempts.history.len(), MAX_HISTORY);
+        assert_eq!(attempts.retried_runs(), 0);
+        assert_eq!(attempts.mean_starts_needed(), Some(1.0));
+    }
+}
@@ -0,0 +1,104 @@
+//! The optional countdown before a run is started, for starting the DAQ at a coordinated time
+//! (i.e. when the beam gate opens). The countdown is scheduled on the wall clock, so when the run
+//! starts does not depend on how often the UI repaints.
+use super::app::EnvoyApp;
+use eframe::egui::{Align2, Color32, RichText, Window};
+use std::time::Duration;
+use time::{OffsetDateTime, Time};
+
+/// How often the UI repaints while a countdown is shown
+pub const COUNTDOWN_REPAINT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A run start scheduled for a wall-clock time
+#[derive(Debug, Clone, Copy)]
+pub struct StartCountdown {
+    pub target: OffsetDateTime,
+}
+
+impl StartCountdown {
+    /// Schedule the start a number of seconds from now
+    pub fn in_seconds(seconds: u64) -> Self {
+        Self {
+            target: OffsetDateTime::now_utc() + Duration::from_secs(seconds),
+        }
+    }
+
+    /// Schedule the start at a UTC time of day given as HH:MM or HH:MM:SS. Times which have
+    /// already passed today are scheduled for tomorrow.
+    pub fn at_time(text: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = text.trim().split(':').collect();
+        if !(2..=3).contains(&parts.len()) {
+            return Err(format!("{text} is not a time of the form HH:MM[:SS]"));
+        }
+        let mut values = [0u8; 3];
+        for (value, part) in values.iter_mut().zip(parts.iter()) {
+            *value = part
+                .trim()
+                .parse()
+                .map_err(|_| format!("{text} is not a time of the form HH:MM[:SS]"))?;
+        }
+        let time = Time::from_hms(values[0], values[1], values[2]).map_err(|e| e.to_string())?;
+        let now = OffsetDateTime::now_utc();
+        let mut target = now.replace_time(time);
+        if target <= now {
+            target += time::Duration::days(1);
+        }
+        Ok(Self { target })
+    }
+
+    /// The time left until the start, zero once it is due
+    pub fn remaining(&self) -> Duration {
+        (self.target - OffsetDateTime::now_utc())
+            .try_into()
+            .unwrap_or(Duration::ZERO)
+    }
+
+    /// Has the scheduled start time been reached
+    pub fn is_due(&self) -> bool {
+        OffsetDateTime::now_utc() >= self.target
+    }
+}
+
+/// Render the countdown, if a run start is scheduled
+pub fn render_start_countdown(app: &mut EnvoyApp, ctx: &eframe::egui::Context) {
+    let countdown = match app.start_countdown {
+        Some(countdown) => countdown,
+        None => return,
+    };
+    let remaining = countdown.remaining();
+    let mut abort = false;
+    Window::new(format!("Start Run {}", app.config.run_number))
+        .collapsible(false)
+        .resizable(false)
+        .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.label(
+                    RichText::new(format!(
+                        "{:02}:{:02}.{}",
+                        remaining.as_secs() / 60,
+                        remaining.as_secs() % 60,
+                        remaining.subsec_millis() / 100
+                    ))
+                    .size(64.0)
+                    .color(Color32::GOLD),
+                );
+                ui.label(format!(
+                    "Starting at {:02}:{:02}:{:02} UTC",
+                    countdown.target.hour(),
+                    countdown.target.minute(),
+                    countdown.target.second()
+                ));
+                ui.separator();
+                if ui
+                    .button(RichText::new("Abort").color(Color32::RED).size(24.0))
+                    .clicked()
+                {
+                    abort = true;
+                }
+            });
+        });
+    if abort {
+        app.abort_start_countdown();
+    }
+}
@@ -1,5 +1,5 @@
 use crate::envoy::ecc_operation::ECCStatus;
-use crate::envoy::surveyor_status::{SurveyorDiskStatus, SurveyorStatus};
+use crate::envoy::surveyor_status::{DiskHealthState, SurveyorDiskStatus, SurveyorStatus};
 use eframe::egui::Color32;
 
 impl From<&ECCStatus> for Color32 {
@@ -12,6 +12,8 @@ impl From<&ECCStatus> for Color32 {
             ECCStatus::Prepared => Color32::LIGHT_BLUE,
             ECCStatus::Ready => Color32::LIGHT_GREEN,
             ECCStatus::Running => Color32::GREEN,
+            ECCStatus::NotMonitored => Color32::GRAY,
+            ECCStatus::Unreachable => Color32::from_rgb(255, 140, 0),
             _ => Color32::RED,
         }
     }
@@ -22,6 +24,7 @@ impl From<&SurveyorStatus> for Color32 {
         match value {
             SurveyorStatus::Offline => Color32::GOLD,
             SurveyorStatus::Online => Color32::GREEN,
+            SurveyorStatus::NotMonitored => Color32::GRAY,
             _ => Color32::RED,
         }
     }
@@ -37,10 +40,20 @@ impl From<&SurveyorDiskStatus> for Color32 {
     }
 }
 
+impl From<&DiskHealthState> for Color32 {
+    fn from(value: &DiskHealthState) -> Color32 {
+        match value {
+            DiskHealthState::Healthy => Color32::GREEN,
+            DiskHealthState::Warning => Color32::GOLD,
+            DiskHealthState::Failing => Color32::RED,
+        }
+    }
+}
+
 pub fn pretty_ellapsed_time(seconds: u64) -> String {
-    let hrs = ((seconds as f64) / 3600.0).floor() as u64;
+    let hrs = seconds / 3600;
     let mut remainder = seconds - hrs * 3600;
-    let mins = ((seconds as f64) / 60.0).floor() as u64;
+    let mins = remainder / 60;
     remainder -= mins * 60;
     format!("{hrs:02}:{mins:02}:{remainder:02}")
 }